@@ -29,8 +29,8 @@ use tokio::sync::broadcast;
 use warp::{Filter, http::Response};
 use warp::filters::ws::Message;
 
-use milli::{FacetCondition, Index, MatchingWords, obkv_to_json, SearchResult, UpdateStore};
-use milli::facet::FacetValue;
+use milli::{AscDesc, Checksummed, FacetCondition, IndexOptions, MatchingWords, obkv_to_json, SearchResult, UpdatePriority, UpdateStore};
+use milli::facet::{FacetType, FacetValue};
 use milli::update::{IndexDocumentsMethod, Setting, UpdateBuilder, UpdateFormat};
 use milli::update::UpdateIndexingStep::*;
 
@@ -45,7 +45,7 @@ pub struct Opt {
     database: PathBuf,
 
     /// The maximum size the database can take on disk. It is recommended to specify
-    /// the whole disk space (value must be a multiple of a page size).
+    /// the whole disk space (value is rounded up to a multiple of a page size).
     #[structopt(long = "db-size", default_value = "100 GiB")]
     database_size: Byte,
 
@@ -142,17 +142,15 @@ impl<'a, A: AsRef<[u8]>> Highlighter<'a, A> {
             Value::Number(number) => Value::Number(number),
             Value::String(old_string) => {
                 let mut string = String::new();
-                let analyzed = self.analyzer.analyze(&old_string);
-                for (word, token) in analyzed.reconstruct() {
-                    if token.is_word() {
-                        let to_highlight = matching_words.matches(token.text());
-                        if to_highlight { string.push_str("<mark>") }
-                        string.push_str(word);
-                        if to_highlight { string.push_str("</mark>") }
-                    } else {
-                        string.push_str(word);
-                    }
+                let mut last = 0;
+                for (start, len) in matching_words.matching_bytes(&self.analyzer, &old_string) {
+                    string.push_str(&old_string[last..start]);
+                    string.push_str("<mark>");
+                    string.push_str(&old_string[start..start + len]);
+                    string.push_str("</mark>");
+                    last = start + len;
                 }
+                string.push_str(&old_string[last..]);
                 Value::String(string)
             }
             Value::Array(values) => {
@@ -184,6 +182,23 @@ impl<'a, A: AsRef<[u8]>> Highlighter<'a, A> {
     }
 }
 
+/// Parses the sort expressions of a query, an invalid expression is a `400 Bad Request`.
+fn parse_sort(
+    sortable_fields: &HashMap<String, FacetType>,
+    sort: &[String],
+) -> Result<Vec<AscDesc>, Response<String>>
+{
+    sort.iter()
+        .map(|s| AscDesc::from_str(sortable_fields, s))
+        .collect::<anyhow::Result<_>>()
+        .map_err(|e| {
+            Response::builder()
+                .status(400)
+                .body(format!("Invalid sort expression: {}", e))
+                .unwrap()
+        })
+}
+
 #[derive(Template)]
 #[template(path = "index.html")]
 struct IndexTemplate {
@@ -228,6 +243,22 @@ enum UpdateMeta {
     ClearDocuments,
     Settings(Settings),
     Facets(Facets),
+    WordsCompaction,
+    RenameField(RenameField),
+}
+
+impl UpdateMeta {
+    /// The settings are applied first and the words compaction last, the updates that
+    /// add, clear or rename the documents content are applied in the order they were sent.
+    fn priority(&self) -> UpdatePriority {
+        match self {
+            UpdateMeta::Settings(_) | UpdateMeta::Facets(_) => UpdatePriority::High,
+            UpdateMeta::DocumentsAddition { .. }
+            | UpdateMeta::ClearDocuments
+            | UpdateMeta::RenameField(_) => UpdatePriority::Normal,
+            UpdateMeta::WordsCompaction => UpdatePriority::Low,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -272,6 +303,13 @@ struct Facets {
     min_level_size: Option<NonZeroUsize>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenameField {
+    from: String,
+    to: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
@@ -299,7 +337,7 @@ async fn main() -> anyhow::Result<()> {
         .init()?;
 
     create_dir_all(&opt.database)?;
-    let mut options = EnvOpenOptions::new();
+    let mut options = IndexOptions::new();
     options.map_size(opt.database_size.get_bytes() as usize);
 
     // Setup the global thread pool
@@ -308,7 +346,7 @@ async fn main() -> anyhow::Result<()> {
     GLOBAL_THREAD_POOL.set(pool).unwrap();
 
     // Open the LMDB database.
-    let index = Index::new(options, &opt.database)?;
+    let index = options.open(&opt.database)?;
 
     // Setup the LMDB based update database.
     let mut update_store_options = EnvOpenOptions::new();
@@ -387,7 +425,7 @@ async fn main() -> anyhow::Result<()> {
                     });
 
                     match result {
-                        Ok(_) => wtxn.commit().map_err(Into::into),
+                        Ok(_) => index_cloned.commit_update(wtxn).map(drop).map_err(Into::into),
                         Err(e) => Err(e)
                     }
                 }
@@ -397,7 +435,7 @@ async fn main() -> anyhow::Result<()> {
                     let builder = update_builder.clear_documents(&mut wtxn, &index_cloned);
 
                     match builder.execute() {
-                        Ok(_count) => wtxn.commit().map_err(Into::into),
+                        Ok(_count) => index_cloned.commit_update(wtxn).map(drop).map_err(Into::into),
                         Err(e) => Err(e)
                     }
                 }
@@ -429,7 +467,10 @@ async fn main() -> anyhow::Result<()> {
 
                     // We transpose the settings JSON struct into a real setting update.
                     match settings.criteria {
-                        Setting::Set(criteria) => builder.set_criteria(criteria),
+                        Setting::Set(criteria) => {
+                            let criteria = criteria.iter().map(|c| c.parse()).collect::<anyhow::Result<_>>()?;
+                            builder.set_criteria(criteria);
+                        },
                         Setting::Reset => builder.reset_criteria(),
                         Setting::NotSet => ()
                     }
@@ -467,7 +508,12 @@ async fn main() -> anyhow::Result<()> {
                     });
 
                     match result {
-                        Ok(_count) => wtxn.commit().map_err(Into::into),
+                        Ok(diff) => {
+                            for warning in diff.warnings {
+                                log::warn!("{}", warning);
+                            }
+                            index_cloned.commit_update(wtxn).map(drop).map_err(Into::into)
+                        },
                         Err(e) => Err(e)
                     }
                 }
@@ -482,7 +528,27 @@ async fn main() -> anyhow::Result<()> {
                         builder.min_level_size(value);
                     }
                     match builder.execute() {
-                        Ok(()) => wtxn.commit().map_err(Into::into),
+                        Ok(()) => index_cloned.commit_update(wtxn).map(drop).map_err(Into::into),
+                        Err(e) => Err(e)
+                    }
+                }
+                UpdateMeta::WordsCompaction => {
+                    // We must use the write transaction of the update here.
+                    let mut wtxn = index_cloned.write_txn()?;
+                    let builder = update_builder.words_compaction(&mut wtxn, &index_cloned);
+
+                    match builder.execute() {
+                        Ok(_result) => index_cloned.commit_update(wtxn).map(drop).map_err(Into::into),
+                        Err(e) => Err(e)
+                    }
+                }
+                UpdateMeta::RenameField(rename) => {
+                    // We must use the write transaction of the update here.
+                    let mut wtxn = index_cloned.write_txn()?;
+                    let builder = update_builder.rename_field(&mut wtxn, &index_cloned);
+
+                    match builder.execute(&rename.from, &rename.to) {
+                        Ok(()) => index_cloned.commit_update(wtxn).map(drop).map_err(Into::into),
                         Err(e) => Err(e)
                     }
                 }
@@ -537,15 +603,15 @@ async fn main() -> anyhow::Result<()> {
             let mut updates = update_store.iter_metas(|processed, aborted, pending| {
                 let mut updates = Vec::<UpdateStatus<_, UpdateMetaProgress, _>>::new();
                 for result in processed {
-                    let (uid, meta) = result?;
+                    let (uid, Checksummed { meta, .. }) = result?;
                     updates.push(UpdateStatus::Processed { update_id: uid.get(), meta });
                 }
                 for result in aborted {
-                    let (uid, meta) = result?;
+                    let (uid, Checksummed { meta, .. }) = result?;
                     updates.push(UpdateStatus::Aborted { update_id: uid.get(), meta });
                 }
                 for result in pending {
-                    let (uid, meta) = result?;
+                    let (uid, Checksummed { meta, .. }) = result?;
                     updates.push(UpdateStatus::Pending { update_id: uid.get(), meta });
                 }
                 Ok(updates)
@@ -664,6 +730,7 @@ async fn main() -> anyhow::Result<()> {
         filters: Option<String>,
         facet_filters: Option<Vec<UntaggedEither<Vec<String>, String>>>,
         facet_distribution: Option<bool>,
+        sort: Option<Vec<String>>,
     }
 
     #[derive(Debug, Serialize)]
@@ -716,7 +783,16 @@ async fn main() -> anyhow::Result<()> {
                 search.facet_condition(condition);
             }
 
-            let SearchResult { matching_words, candidates, documents_ids } = search.execute().unwrap();
+            // The sort is applied at the position of the sort ranking rule.
+            if let Some(sort) = query.sort {
+                let sortable_fields = index.indexed_facet_fields(&rtxn).unwrap();
+                match parse_sort(&sortable_fields, &sort) {
+                    Ok(sort) => search.sort_by(sort),
+                    Err(response) => return Ok(response),
+                };
+            }
+
+            let SearchResult { matching_words, candidates, documents_ids, .. } = search.execute().unwrap();
 
             let number_of_candidates = candidates.len();
             let facets = if query.facet_distribution == Some(true) {
@@ -826,7 +902,7 @@ async fn main() -> anyhow::Result<()> {
         };
 
         let meta = UpdateMeta::DocumentsAddition { method, format, encoding };
-        let update_id = update_store.register_update(&meta, &mmap[..]).unwrap();
+        let update_id = update_store.register_update_with_priority(&meta, &mmap[..], meta.priority()).unwrap();
         let _ = update_status_sender.send(UpdateStatus::Pending { update_id, meta });
         eprintln!("update {} registered", update_id);
 
@@ -870,7 +946,7 @@ async fn main() -> anyhow::Result<()> {
         .and(warp::path!("clear-documents"))
         .map(move || {
             let meta = UpdateMeta::ClearDocuments;
-            let update_id = update_store_cloned.register_update(&meta, &[]).unwrap();
+            let update_id = update_store_cloned.register_update_with_priority(&meta, &[], meta.priority()).unwrap();
             let _ = update_status_sender_cloned.send(UpdateStatus::Pending { update_id, meta });
             eprintln!("update {} registered", update_id);
             Ok(warp::reply())
@@ -883,7 +959,7 @@ async fn main() -> anyhow::Result<()> {
         .and(warp::body::json())
         .map(move |settings: Settings| {
             let meta = UpdateMeta::Settings(settings);
-            let update_id = update_store_cloned.register_update(&meta, &[]).unwrap();
+            let update_id = update_store_cloned.register_update_with_priority(&meta, &[], meta.priority()).unwrap();
             let _ = update_status_sender_cloned.send(UpdateStatus::Pending { update_id, meta });
             eprintln!("update {} registered", update_id);
             Ok(warp::reply())
@@ -896,7 +972,32 @@ async fn main() -> anyhow::Result<()> {
         .and(warp::body::json())
         .map(move |levels: Facets| {
             let meta = UpdateMeta::Facets(levels);
-            let update_id = update_store_cloned.register_update(&meta, &[]).unwrap();
+            let update_id = update_store_cloned.register_update_with_priority(&meta, &[], meta.priority()).unwrap();
+            let _ = update_status_sender_cloned.send(UpdateStatus::Pending { update_id, meta });
+            eprintln!("update {} registered", update_id);
+            warp::reply()
+        });
+
+    let update_store_cloned = update_store.clone();
+    let update_status_sender_cloned = update_status_sender.clone();
+    let words_compaction_route = warp::filters::method::post()
+        .and(warp::path!("compact-words"))
+        .map(move || {
+            let meta = UpdateMeta::WordsCompaction;
+            let update_id = update_store_cloned.register_update_with_priority(&meta, &[], meta.priority()).unwrap();
+            let _ = update_status_sender_cloned.send(UpdateStatus::Pending { update_id, meta });
+            eprintln!("update {} registered", update_id);
+            warp::reply()
+        });
+
+    let update_store_cloned = update_store.clone();
+    let update_status_sender_cloned = update_status_sender.clone();
+    let rename_field_route = warp::filters::method::post()
+        .and(warp::path!("rename-field"))
+        .and(warp::body::json())
+        .map(move |rename: RenameField| {
+            let meta = UpdateMeta::RenameField(rename);
+            let update_id = update_store_cloned.register_update_with_priority(&meta, &[], meta.priority()).unwrap();
             let _ = update_status_sender_cloned.send(UpdateStatus::Pending { update_id, meta });
             eprintln!("update {} registered", update_id);
             warp::reply()
@@ -976,6 +1077,8 @@ async fn main() -> anyhow::Result<()> {
         .or(clearing_route)
         .or(change_settings_route)
         .or(change_facet_levels_route)
+        .or(words_compaction_route)
+        .or(rename_field_route)
         .or(update_ws_route);
 
     let addr = SocketAddr::from_str(&opt.http_listen_addr)?;
@@ -988,9 +1091,24 @@ mod tests {
     use maplit::{btreeset,hashmap};
     use serde_test::{assert_tokens, Token};
 
+    use milli::{AscDesc, Member};
+    use milli::facet::FacetType;
     use milli::update::Setting;
 
-    use crate::Settings;
+    use crate::{Settings, parse_sort};
+
+    #[test]
+    fn invalid_sort_is_a_bad_request() {
+        let sortable_fields = hashmap! { "price".to_string() => FacetType::Number };
+
+        let sort = parse_sort(&sortable_fields, &["asc(price)".to_string()]).unwrap();
+        assert_eq!(sort, vec![AscDesc::Asc(Member::Field("price".to_string()))]);
+
+        let response = parse_sort(&sortable_fields, &["asc(unknown)".to_string()]).unwrap_err();
+        assert_eq!(response.status(), 400);
+        let response = parse_sort(&sortable_fields, &["price:up".to_string()]).unwrap_err();
+        assert_eq!(response.status(), 400);
+    }
 
     #[test]
     fn serde_settings_set() {
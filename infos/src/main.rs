@@ -4,8 +4,7 @@ use std::{str, io, fmt};
 
 use anyhow::Context;
 use byte_unit::Byte;
-use heed::EnvOpenOptions;
-use milli::{Index, TreeLevel};
+use milli::{Index, IndexOptions, TreeLevel};
 use structopt::StructOpt;
 
 use Command::*;
@@ -22,8 +21,11 @@ const WORD_PAIR_PROXIMITY_DOCIDS_DB_NAME: &str = "word-pair-proximity-docids";
 const WORD_PREFIX_PAIR_PROXIMITY_DOCIDS_DB_NAME: &str = "word-prefix-pair-proximity-docids";
 const WORD_LEVEL_POSITION_DOCIDS_DB_NAME: &str = "word-level-position-docids";
 const WORD_PREFIX_LEVEL_POSITION_DOCIDS_DB_NAME: &str = "word-prefix-level-position-docids";
+const FIELD_ID_WORD_COUNT_DOCIDS_DB_NAME: &str = "field-id-word-count-docids";
+const FIELD_ID_ORIGINAL_WORD_DOCIDS_DB_NAME: &str = "field-id-original-word-docids";
 const FACET_FIELD_ID_VALUE_DOCIDS_DB_NAME: &str = "facet-field-id-value-docids";
 const FIELD_ID_DOCID_FACET_VALUES_DB_NAME: &str = "field-id-docid-facet-values";
+const FACET_FIELD_ID_DOCID_COUNT_DB_NAME: &str = "facet-field-id-docid-count";
 const DOCUMENTS_DB_NAME: &str = "documents";
 
 const ALL_DATABASE_NAMES: &[&str] = &[
@@ -35,8 +37,11 @@ const ALL_DATABASE_NAMES: &[&str] = &[
     WORD_PREFIX_PAIR_PROXIMITY_DOCIDS_DB_NAME,
     WORD_LEVEL_POSITION_DOCIDS_DB_NAME,
     WORD_PREFIX_LEVEL_POSITION_DOCIDS_DB_NAME,
+    FIELD_ID_WORD_COUNT_DOCIDS_DB_NAME,
+    FIELD_ID_ORIGINAL_WORD_DOCIDS_DB_NAME,
     FACET_FIELD_ID_VALUE_DOCIDS_DB_NAME,
     FIELD_ID_DOCID_FACET_VALUES_DB_NAME,
+    FACET_FIELD_ID_DOCID_COUNT_DB_NAME,
     DOCUMENTS_DB_NAME,
 ];
 
@@ -57,7 +62,7 @@ pub struct Opt {
     database: PathBuf,
 
     /// The maximum size the database can take on disk. It is recommended to specify
-    /// the whole disk space (value must be a multiple of a page size).
+    /// the whole disk space (value is rounded up to a multiple of a page size).
     #[structopt(long = "db-size", default_value = "100 GiB")]
     database_size: Byte,
 
@@ -194,6 +199,26 @@ enum Command {
         word2: String,
     },
 
+    /// Outputs a CSV with the words that appear the most often close to the given word.
+    ///
+    /// `documents_count` defines the number of documents where both words are close to each other.
+    WordCooccurrences {
+        /// The maximum number of words to return.
+        #[structopt(long, default_value = "10")]
+        limit: usize,
+
+        /// The word to display the co-occurring words of.
+        word: String,
+    },
+
+    /// Outputs the number of documents that contain the given words one after the other.
+    ///
+    /// The words are lowercased, like the indexed ones.
+    PhraseDocumentCount {
+        /// The words of the phrase, in order.
+        words: Vec<String>,
+    },
+
     /// Outputs the words FST to standard output.
     ///
     /// One can use the FST binary helper to dissect and analyze it,
@@ -224,7 +249,7 @@ fn main() -> anyhow::Result<()> {
         .timestamp(stderrlog::Timestamp::Off)
         .init()?;
 
-    let mut options = EnvOpenOptions::new();
+    let mut options = IndexOptions::new();
     options.map_size(opt.database_size.get_bytes() as usize);
 
     // Return an error if the database does not exist.
@@ -233,7 +258,7 @@ fn main() -> anyhow::Result<()> {
     }
 
     // Open the LMDB database.
-    let index = Index::new(options, opt.database)?;
+    let index = options.open(opt.database)?;
     let rtxn = index.read_txn()?;
 
     match opt.command {
@@ -265,6 +290,8 @@ fn main() -> anyhow::Result<()> {
         WordPairProximitiesDocids { full_display, word1, word2 } => {
             word_pair_proximities_docids(&index, &rtxn, !full_display, word1, word2)
         },
+        WordCooccurrences { limit, word } => word_cooccurrences(&index, &rtxn, limit, word),
+        PhraseDocumentCount { words } => phrase_document_count(&index, &rtxn, words),
         ExportWordsFst => export_words_fst(&index, &rtxn),
         ExportWordsPrefixFst => export_words_prefix_fst(&index, &rtxn),
         ExportDocuments { internal_documents_ids } => {
@@ -310,16 +337,16 @@ where
     DC: heed::BytesDecode<'txn>,
 {
     use milli::facet::FacetType;
-    use milli::heed_codec::facet::{FacetValueStringCodec, FacetLevelValueF64Codec};
+    use milli::heed_codec::facet::{FacetValueStringCodec, FacetStringValueDocidsCodec, FacetLevelValueF64Codec};
 
     let iter = db.prefix_iter(&rtxn, &[field_id])?;
     match facet_type {
-        FacetType::String => {
-            let iter = iter.remap_key_type::<FacetValueStringCodec>()
-                .map(move |r| r.map(|((_, key), value)| (string_fn(key), value)));
+        FacetType::String | FacetType::Hierarchy => {
+            let iter = iter.remap_types::<FacetValueStringCodec, FacetStringValueDocidsCodec<DC>>()
+                .map(move |r| r.map(|((_, key), (_original, value))| (string_fn(key), value)));
             Ok(Box::new(iter) as Box<dyn Iterator<Item=_>>)
         },
-        FacetType::Number => {
+        FacetType::Number | FacetType::Date => {
             let iter = iter.remap_key_type::<FacetLevelValueF64Codec>()
                 .map(move |r| r.map(|((_, level, left, right), value)| {
                     (float_fn(level, left, right), value)
@@ -354,7 +381,9 @@ fn biggest_value_sizes(index: &Index, rtxn: &heed::RoTxn, limit: usize) -> anyho
         word_prefix_level_position_docids,
         facet_field_id_value_docids,
         field_id_docid_facet_values: _,
-        documents
+        facet_field_id_docid_count: _,
+        documents,
+        ..
     } = index;
 
     let main_name = "main";
@@ -435,7 +464,7 @@ fn biggest_value_sizes(index: &Index, rtxn: &heed::RoTxn, limit: usize) -> anyho
             if heap.len() > limit { heap.pop(); }
         }
 
-        let faceted_fields = index.faceted_fields_ids(rtxn)?;
+        let faceted_fields = index.indexed_facet_fields_ids(rtxn)?;
         let fields_ids_map = index.fields_ids_map(rtxn)?;
         for (field_id, field_type) in faceted_fields {
             let facet_name = fields_ids_map.name(field_id).unwrap();
@@ -538,7 +567,7 @@ fn words_prefixes_docids(
 
 fn facet_values_docids(index: &Index, rtxn: &heed::RoTxn, debug: bool, field_name: String) -> anyhow::Result<()> {
     let fields_ids_map = index.fields_ids_map(&rtxn)?;
-    let faceted_fields = index.faceted_fields_ids(&rtxn)?;
+    let faceted_fields = index.indexed_facet_fields_ids(&rtxn)?;
 
     let field_id = fields_ids_map.id(&field_name)
         .with_context(|| format!("field {} not found", field_name))?;
@@ -685,14 +714,30 @@ fn docids_words_positions(
 }
 
 fn facet_stats(index: &Index, rtxn: &heed::RoTxn, field_name: String) -> anyhow::Result<()> {
+    use milli::facet::FacetType;
+
     let fields_ids_map = index.fields_ids_map(&rtxn)?;
-    let faceted_fields = index.faceted_fields_ids(&rtxn)?;
+    let faceted_fields = index.indexed_facet_fields_ids(&rtxn)?;
 
     let field_id = fields_ids_map.id(&field_name)
         .with_context(|| format!("field {} not found", field_name))?;
     let field_type = faceted_fields.get(&field_id)
         .with_context(|| format!("field {} is not faceted", field_name))?;
 
+    println!("The database {:?} facet stats", field_name);
+
+    if *field_type == FacetType::Number || *field_type == FacetType::Date {
+        let levels = index.facet_levels(rtxn, field_id)?;
+        println!("\tnumber of levels: {}", levels.len());
+        for stats in levels {
+            println!(
+                "\tnumber of groups at level {}: {} (average group size: {:.02}, max documents: {})",
+                stats.level, stats.entries, stats.average_group_size, stats.max_documents,
+            );
+        }
+        return Ok(());
+    }
+
     let db = index.facet_field_id_value_docids;
     let iter = facet_values_iter(
         rtxn,
@@ -703,8 +748,6 @@ fn facet_stats(index: &Index, rtxn: &heed::RoTxn, field_name: String) -> anyhow:
         |level, _left, _right| level,
     )?;
 
-    println!("The database {:?} facet stats", field_name);
-
     let mut level_size = 0;
     let mut current_level = None;
     for result in iter {
@@ -852,9 +895,13 @@ fn size_of_databases(index: &Index, rtxn: &heed::RoTxn, names: Vec<String>) -> a
         word_prefix_pair_proximity_docids,
         word_level_position_docids,
         word_prefix_level_position_docids,
+        field_id_word_count_docids,
+        field_id_original_word_docids,
         facet_field_id_value_docids,
         field_id_docid_facet_values,
+        facet_field_id_docid_count,
         documents,
+        ..
     } = index;
 
     let names = if names.is_empty() {
@@ -873,8 +920,11 @@ fn size_of_databases(index: &Index, rtxn: &heed::RoTxn, names: Vec<String>) -> a
             WORD_PREFIX_PAIR_PROXIMITY_DOCIDS_DB_NAME => word_prefix_pair_proximity_docids.as_polymorph(),
             WORD_LEVEL_POSITION_DOCIDS_DB_NAME => word_level_position_docids.as_polymorph(),
             WORD_PREFIX_LEVEL_POSITION_DOCIDS_DB_NAME => word_prefix_level_position_docids.as_polymorph(),
+            FIELD_ID_WORD_COUNT_DOCIDS_DB_NAME => field_id_word_count_docids.as_polymorph(),
+            FIELD_ID_ORIGINAL_WORD_DOCIDS_DB_NAME => field_id_original_word_docids.as_polymorph(),
             FACET_FIELD_ID_VALUE_DOCIDS_DB_NAME => facet_field_id_value_docids.as_polymorph(),
             FIELD_ID_DOCID_FACET_VALUES_DB_NAME => field_id_docid_facet_values.as_polymorph(),
+            FACET_FIELD_ID_DOCID_COUNT_DB_NAME => facet_field_id_docid_count.as_polymorph(),
             DOCUMENTS_DB_NAME => documents.as_polymorph(),
             unknown => anyhow::bail!("unknown database {:?}", unknown),
         };
@@ -980,6 +1030,32 @@ fn database_stats(index: &Index, rtxn: &heed::RoTxn, name: &str) -> anyhow::Resu
     }
 }
 
+fn word_cooccurrences(index: &Index, rtxn: &heed::RoTxn, limit: usize, word: String) -> anyhow::Result<()> {
+    let stdout = io::stdout();
+    let mut wtr = csv::Writer::from_writer(stdout.lock());
+    wtr.write_record(&["word", "cooccurring_word", "documents_count"])?;
+
+    for (cooccurring_word, count) in index.word_cooccurrences(rtxn, &word, limit)? {
+        wtr.write_record(&[word.as_str(), cooccurring_word, &count.to_string()])?;
+    }
+
+    Ok(wtr.flush()?)
+}
+
+fn phrase_document_count(index: &Index, rtxn: &heed::RoTxn, words: Vec<String>) -> anyhow::Result<()> {
+    let words: Vec<_> = words.iter().map(|w| w.to_lowercase()).collect();
+    let words: Vec<_> = words.iter().map(String::as_str).collect();
+
+    let stdout = io::stdout();
+    let mut wtr = csv::Writer::from_writer(stdout.lock());
+    wtr.write_record(&["phrase", "documents_count"])?;
+
+    let count = index.phrase_document_count(rtxn, &words)?;
+    wtr.write_record(&[words.join(" "), count.to_string()])?;
+
+    Ok(wtr.flush()?)
+}
+
 fn word_pair_proximities_docids(
     index: &Index,
     rtxn: &heed::RoTxn,
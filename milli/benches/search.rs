@@ -1,7 +1,6 @@
 use std::time::Duration;
 
-use heed::EnvOpenOptions;
-use milli::Index;
+use milli::IndexOptions;
 use criterion::{criterion_group, criterion_main, BenchmarkId};
 
 fn bench_search(c: &mut criterion::Criterion) {
@@ -11,10 +10,10 @@ fn bench_search(c: &mut criterion::Criterion) {
         "minogue kylie live",
     ];
 
-    let mut options = EnvOpenOptions::new();
+    let mut options = IndexOptions::new();
     options.map_size(100 * 1024 * 1024 * 1024); // 100 GB
     options.max_readers(10);
-    let index = Index::new(options, database).unwrap();
+    let index = options.open(database).unwrap();
 
     let mut group = c.benchmark_group("search");
     group.sample_size(10);
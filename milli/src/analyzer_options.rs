@@ -0,0 +1,302 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+/// The options that tune how the texts are analyzed, they are stored in the index
+/// and applied identically to the documents at indexing time and to the queries.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AnalyzerOptions {
+    /// Strips the leading zeros and the thousands separators of the numbers,
+    /// "007" is indexed as "7" and "1,000" as "1000", the documents are kept untouched.
+    pub normalize_numbers: bool,
+    /// Splits the camelCase and snake_case identifiers into the words they are made of,
+    /// the words are indexed at the position of the identifier which is also indexed.
+    pub split_identifiers: bool,
+    /// Defines what is done with the emojis and the other symbols.
+    pub symbols: SymbolsPolicy,
+}
+
+/// How the emojis and the other symbols, that the analyzer doesn't consider as words, are handled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SymbolsPolicy {
+    /// The symbols are ignored by the analyzer, neither indexed nor searchable.
+    Strip,
+    /// Every symbol is indexed as a word made of its code point, "👍" is indexed as "u1f44d".
+    Index,
+    /// The symbols are replaced by the words of their alias, the ones without an alias are stripped.
+    Alias(BTreeMap<String, String>),
+}
+
+impl Default for SymbolsPolicy {
+    fn default() -> SymbolsPolicy {
+        SymbolsPolicy::Strip
+    }
+}
+
+impl AnalyzerOptions {
+    /// Returns the text that must be given to the analyzer.
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let text = if self.normalize_numbers {
+            normalize_numbers(text)
+        } else {
+            Cow::Borrowed(text)
+        };
+
+        match replace_symbols(&text, &self.symbols) {
+            Some(replaced) => Cow::Owned(replaced),
+            None => text,
+        }
+    }
+
+    /// Returns the lowercased words of the given word, as it is written in the original text,
+    /// when the identifiers splitting is enabled and it is a camelCase or snake_case identifier.
+    pub fn identifier_words(&self, original: &str) -> Vec<String> {
+        if !self.split_identifiers {
+            return Vec::new();
+        }
+
+        match split_identifier(original).as_slice() {
+            [] | [_] => Vec::new(),
+            words => words.iter().map(|w| w.to_lowercase()).collect(),
+        }
+    }
+}
+
+/// Returns the text where the symbols are replaced according to the policy,
+/// or `None` if the symbols are stripped or the text doesn't contain any.
+fn replace_symbols(text: &str, policy: &SymbolsPolicy) -> Option<String> {
+    if *policy == SymbolsPolicy::Strip || !text.contains(is_symbol) {
+        return None;
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(is_symbol) {
+        output.push_str(&rest[..start]);
+
+        // A symbol is followed by its modifiers and the symbols it is joined with (e.g. a flag).
+        let end = rest[start..].char_indices()
+            .find(|(_, c)| !is_symbol(*c) && !is_symbol_modifier(*c))
+            .map_or(rest.len(), |(i, _)| start + i);
+        let symbols = &rest[start..end];
+
+        output.push(' ');
+        match policy {
+            SymbolsPolicy::Strip => (),
+            SymbolsPolicy::Index => {
+                for c in symbols.chars().filter(|c| is_symbol(*c)) {
+                    let _ = write!(output, "u{:x} ", c as u32);
+                }
+            },
+            SymbolsPolicy::Alias(aliases) => match aliases.get(symbols) {
+                Some(alias) => {
+                    output.push_str(alias);
+                    output.push(' ');
+                },
+                None => {
+                    for c in symbols.chars().filter(|c| is_symbol(*c)) {
+                        if let Some(alias) = aliases.get(c.encode_utf8(&mut [0; 4]) as &str) {
+                            output.push_str(alias);
+                            output.push(' ');
+                        }
+                    }
+                },
+            },
+        }
+
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    Some(output)
+}
+
+/// Returns `true` for the emojis and the pictographic symbols.
+fn is_symbol(c: char) -> bool {
+    !is_symbol_modifier(c) && matches!(c as u32,
+        0x00A9 | 0x00AE | 0x203C | 0x2049 | 0x2122 | 0x2139
+        | 0x2190..=0x21FF // arrows
+        | 0x2300..=0x23FF // miscellaneous technical
+        | 0x25A0..=0x27BF // geometric shapes, miscellaneous symbols and dingbats
+        | 0x2900..=0x297F // supplemental arrows
+        | 0x2B00..=0x2BFF // miscellaneous symbols and arrows
+        | 0x3030 | 0x303D | 0x3297 | 0x3299
+        | 0x1F000..=0x1FAFF // emojis, pictographs and flags
+    )
+}
+
+/// Returns `true` for the characters that modify the preceding symbol, the skin tones,
+/// the variation selectors, the zero width joiner and the keycap.
+fn is_symbol_modifier(c: char) -> bool {
+    matches!(c as u32, 0x1F3FB..=0x1F3FF | 0xFE0E | 0xFE0F | 0x200D | 0x20E3)
+}
+
+/// Splits an identifier on its underscores and its case changes, the uppercase
+/// acronyms are kept together (e.g. "parseHTTPRequest" gives "parse", "HTTP" and "Request").
+fn split_identifier(identifier: &str) -> Vec<&str> {
+    let chars: Vec<_> = identifier.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start = 0;
+
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        if c == '_' {
+            if start < pos {
+                words.push(&identifier[start..pos]);
+            }
+            start = pos + 1;
+            continue;
+        }
+
+        if let Some(&(_, prev)) = i.checked_sub(1).and_then(|i| chars.get(i)) {
+            let next_is_lowercase = chars.get(i + 1).map_or(false, |&(_, next)| next.is_lowercase());
+            let boundary = c.is_uppercase()
+                && (prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_is_lowercase));
+            if boundary && start < pos {
+                words.push(&identifier[start..pos]);
+                start = pos;
+            }
+        }
+    }
+
+    if start < identifier.len() {
+        words.push(&identifier[start..]);
+    }
+
+    words
+}
+
+/// Rewrites the numbers of the text without their leading zeros and thousands separators,
+/// the digits that follow a dot or a letter are left untouched as they are either the
+/// fractional part of a number or part of a word (e.g. "1.05", "v02").
+fn normalize_numbers(text: &str) -> Cow<str> {
+    let mut output = String::new();
+    // The position in the text up to which the output is written.
+    let mut written = 0;
+    let mut chars = text.char_indices().peekable();
+    let mut previous = None;
+
+    while let Some((start, c)) = chars.next() {
+        let attached = previous.map_or(false, |p: char| p.is_alphanumeric() || p == '.');
+        previous = Some(c);
+        if !c.is_ascii_digit() || attached {
+            continue;
+        }
+
+        // We find the end of the number, digits separated by single commas or underscores.
+        let mut end = start + 1;
+        while let Some(&(i, c)) = chars.peek() {
+            let separated = (c == ',' || c == '_') && text[i + 1..].starts_with(|c: char| c.is_ascii_digit());
+            if c.is_ascii_digit() || separated {
+                chars.next();
+                previous = Some(c);
+                end = i + 1;
+            } else {
+                break;
+            }
+        }
+
+        // A number directly followed by a letter is part of a word.
+        if text[end..].starts_with(char::is_alphabetic) {
+            continue;
+        }
+
+        let number = &text[start..end];
+        let normalized = normalize_number(number);
+        if normalized != number {
+            output.push_str(&text[written..start]);
+            output.push_str(&normalized);
+            written = end;
+        }
+    }
+
+    if written == 0 {
+        Cow::Borrowed(text)
+    } else {
+        output.push_str(&text[written..]);
+        Cow::Owned(output)
+    }
+}
+
+/// Normalizes a number made of digits groups separated by commas or underscores, the separators
+/// are only removed when they are thousands separators, a list of numbers is kept as is.
+fn normalize_number(number: &str) -> String {
+    let groups: Vec<_> = number.split(|c: char| c == ',' || c == '_').collect();
+    let uses_one_separator = !(number.contains(',') && number.contains('_'));
+    let thousands = groups.len() > 1
+        && uses_one_separator
+        && groups[0].len() <= 3
+        && groups[1..].iter().all(|g| g.len() == 3);
+
+    if thousands {
+        strip_leading_zeros(&groups.concat()).to_string()
+    } else {
+        let mut output = String::with_capacity(number.len());
+        let mut groups = groups.into_iter();
+        for separator in number.matches(|c: char| c == ',' || c == '_') {
+            output.push_str(strip_leading_zeros(groups.next().unwrap()));
+            output.push_str(separator);
+        }
+        output.push_str(strip_leading_zeros(groups.next().unwrap()));
+        output
+    }
+}
+
+fn strip_leading_zeros(digits: &str) -> &str {
+    match digits.trim_start_matches('0') {
+        "" => "0",
+        trimmed => trimmed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::FromIterator;
+
+    use super::*;
+
+    #[test]
+    fn numbers_normalization() {
+        assert_eq!(normalize_numbers("agent 007"), "agent 7");
+        assert_eq!(normalize_numbers("1,000 or 10_000_000 dollars"), "1000 or 10000000 dollars");
+        assert_eq!(normalize_numbers("000 and 0"), "0 and 0");
+        assert_eq!(normalize_numbers("1,000.050"), "1000.050");
+        assert_eq!(normalize_numbers("1,2,03"), "1,2,3");
+        assert_eq!(normalize_numbers("v02 and 007bond"), "v02 and 007bond");
+        assert!(matches!(normalize_numbers("nothing 12 to normalize"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn identifiers_splitting() {
+        assert_eq!(split_identifier("getUserName"), vec!["get", "User", "Name"]);
+        assert_eq!(split_identifier("parseHTTPRequest"), vec!["parse", "HTTP", "Request"]);
+        assert_eq!(split_identifier("__snake_case_"), vec!["snake", "case"]);
+        assert_eq!(split_identifier("utf8Decoder"), vec!["utf8", "Decoder"]);
+        assert_eq!(split_identifier("word"), vec!["word"]);
+
+        let options = AnalyzerOptions { split_identifiers: true, ..AnalyzerOptions::default() };
+        assert_eq!(options.identifier_words("HTTP_Server"), vec!["http", "server"]);
+        assert!(options.identifier_words("Server").is_empty());
+        assert!(AnalyzerOptions::default().identifier_words("getUser").is_empty());
+    }
+
+    #[test]
+    fn symbols_replacement() {
+        let text = "I \u{2764}\u{FE0F} rust\u{1F44D}\u{1F3FD}!";
+        assert_eq!(replace_symbols(text, &SymbolsPolicy::Strip), None);
+        assert_eq!(replace_symbols("no symbols", &SymbolsPolicy::Index), None);
+
+        let replaced = replace_symbols(text, &SymbolsPolicy::Index).unwrap();
+        assert_eq!(replaced, "I  u2764  rust u1f44d !");
+
+        let aliases = BTreeMap::from_iter(vec![
+            ("\u{2764}".to_string(), "love".to_string()),
+            ("\u{1F44D}\u{1F3FD}".to_string(), "thumbs up".to_string()),
+        ]);
+        let replaced = replace_symbols(text, &SymbolsPolicy::Alias(aliases)).unwrap();
+        assert_eq!(replaced, "I  love  rust thumbs up !");
+    }
+}
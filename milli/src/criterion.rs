@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
 
 use anyhow::{Context, bail};
 use regex::Regex;
@@ -12,7 +13,15 @@ static ASC_DESC_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r#"(asc|desc)\(([\w_-]+)\)"#).unwrap()
 });
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+static GEO_ASC_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^geo:asc\(\s*(-?\d+(?:\.\d+)?)\s*,\s*(-?\d+(?:\.\d+)?)\s*\)$"#).unwrap()
+});
+
+static GEO_POINT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(asc|desc)\(_geoPoint\(\s*(-?\d+(?:\.\d+)?)\s*,\s*(-?\d+(?:\.\d+)?)\s*\)\)"#).unwrap()
+});
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum Criterion {
     /// Sorted by decreasing number of matched query terms.
     /// Query words at the front of an attribute is considered better than if it was at the back.
@@ -22,32 +31,68 @@ pub enum Criterion {
     /// Sorted by increasing distance between matched query terms.
     Proximity,
     /// Documents with quey words contained in more important
-    /// attributes are considred better.
+    /// attributes are considred better, the importance of the
+    /// attributes is the order of the searchable attributes.
     Attribute,
+    /// Sorted by the sort expressions given at query time, if any.
+    Sort,
     /// Sorted by the similarity of the matched words with the query words.
     Exactness,
-    /// Sorted by the increasing value of the field specified.
+    /// Sorted by decreasing number of occurrences of the query words,
+    /// it is not part of the default criteria.
+    Frequency,
+    /// Sorted by the increasing value of the field specified, the strings are sorted
+    /// lexicographically and the documents without any value for the field come last.
     Asc(String),
-    /// Sorted by the decreasing value of the field specified.
+    /// Sorted by the decreasing value of the field specified,
+    /// the documents without any value for the field come last.
     Desc(String),
+    /// Sorted by the increasing distance of the documents `_geo` to the latitude and longitude specified.
+    GeoAsc([f64; 2]),
 }
 
 impl Criterion {
-    pub fn from_str(faceted_attributes: &HashMap<String, FacetType>, txt: &str) -> anyhow::Result<Criterion> {
+    /// Returns the field of the asc and desc criteria.
+    pub fn field_name(&self) -> Option<&str> {
+        match self {
+            Criterion::Asc(field) | Criterion::Desc(field) => Some(field),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a criterion, the settings update checks that the
+/// field of the asc and desc criteria is sortable.
+impl FromStr for Criterion {
+    type Err = anyhow::Error;
+
+    fn from_str(txt: &str) -> anyhow::Result<Criterion> {
         match txt {
             "words" => Ok(Criterion::Words),
             "typo" => Ok(Criterion::Typo),
             "proximity" => Ok(Criterion::Proximity),
             "attribute" => Ok(Criterion::Attribute),
+            "sort" => Ok(Criterion::Sort),
             "exactness" => Ok(Criterion::Exactness),
+            "frequency" => Ok(Criterion::Frequency),
+            text if text.starts_with("geo:") => {
+                let caps = GEO_ASC_REGEX.captures(text).with_context(|| format!("unknown criterion name: {}", text))?;
+                let lat: f64 = caps.get(1).unwrap().as_str().parse()?;
+                let lng: f64 = caps.get(2).unwrap().as_str().parse()?;
+                if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lng) {
+                    bail!("invalid geo point: latitude or longitude out of range: {}", text);
+                }
+                Ok(Criterion::GeoAsc([lat, lng]))
+            },
+            text if GEO_POINT_REGEX.is_match(text) => {
+                bail!("Can't use {:?} as a criterion, use geo:asc(lat, lng) to rank by distance instead.", text)
+            },
             text => {
                 let caps = ASC_DESC_REGEX.captures(text).with_context(|| format!("unknown criterion name: {}", text))?;
-                let order = caps.get(1).unwrap().as_str();
-                let field_name = caps.get(2).unwrap().as_str();
-                faceted_attributes.get(field_name).with_context(|| format!("Can't use {:?} as a criterion as it isn't a faceted field.", field_name))?;
-                match order {
-                    "asc" => Ok(Criterion::Asc(field_name.to_string())),
-                    "desc" => Ok(Criterion::Desc(field_name.to_string())),
+                let field_name = caps.get(2).unwrap().as_str().to_string();
+                match caps.get(1).unwrap().as_str() {
+                    "asc" => Ok(Criterion::Asc(field_name)),
+                    "desc" => Ok(Criterion::Desc(field_name)),
                     otherwise => bail!("unknown criterion name: {}", otherwise),
                 }
             },
@@ -55,16 +100,121 @@ impl Criterion {
     }
 }
 
+/// The member on which a sort expression applies.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum Member {
+    /// A faceted field, sorted by its values.
+    Field(String),
+    /// A latitude and a longitude, sorted by the distance of the documents `_geo` to it.
+    Geo([f64; 2]),
+}
+
+impl fmt::Display for Member {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Member::Field(field) => f.write_str(field),
+            Member::Geo([lat, lng]) => write!(f, "_geoPoint({}, {})", lat, lng),
+        }
+    }
+}
+
+/// A sort expression that can be given at query time.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AscDesc {
+    /// Sorted by the increasing value of the member specified.
+    Asc(Member),
+    /// Sorted by the decreasing value of the member specified.
+    Desc(Member),
+}
+
+impl AscDesc {
+    pub fn from_str(sortable_attributes: &HashMap<String, FacetType>, txt: &str) -> anyhow::Result<AscDesc> {
+        let (order, member) = match GEO_POINT_REGEX.captures(txt) {
+            Some(caps) => {
+                let lat: f64 = caps.get(2).unwrap().as_str().parse()?;
+                let lng: f64 = caps.get(3).unwrap().as_str().parse()?;
+                if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lng) {
+                    bail!("invalid geo point: latitude or longitude out of range: {}", txt);
+                }
+                (caps.get(1).unwrap().as_str(), Member::Geo([lat, lng]))
+            },
+            None => {
+                let caps = ASC_DESC_REGEX.captures(txt).with_context(|| format!("unknown criterion name: {}", txt))?;
+                let field_name = caps.get(2).unwrap().as_str();
+                sortable_attributes.get(field_name).with_context(|| format!("Can't use {:?} as a criterion as it isn't a sortable or faceted field.", field_name))?;
+                (caps.get(1).unwrap().as_str(), Member::Field(field_name.to_string()))
+            },
+        };
+
+        match order {
+            "asc" => Ok(AscDesc::Asc(member)),
+            "desc" => Ok(AscDesc::Desc(member)),
+            otherwise => bail!("unknown criterion name: {}", otherwise),
+        }
+    }
+
+    pub fn member(&self) -> &Member {
+        match self {
+            AscDesc::Asc(member) => member,
+            AscDesc::Desc(member) => member,
+        }
+    }
+}
+
+/// How the attribute criterion computes the buckets of documents.
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub enum AttributeRanking {
+    /// Uses the exhaustive ranking when there are few candidates, the approximate one otherwise.
+    Auto,
+    /// Computes the exact attribute score of every candidate, slower on large sets of candidates.
+    Exhaustive,
+    /// Iterates over the words positions levels, faster but the buckets are approximated.
+    Approximate,
+}
+
+impl Default for AttributeRanking {
+    fn default() -> AttributeRanking {
+        AttributeRanking::Auto
+    }
+}
+
 pub fn default_criteria() -> Vec<Criterion> {
     vec![
         Criterion::Words,
         Criterion::Typo,
         Criterion::Proximity,
         Criterion::Attribute,
+        Criterion::Sort,
         Criterion::Exactness,
     ]
 }
 
+/// Checks that the ranking rules are not used more than once, that a field is not sorted in both
+/// orders and returns the warnings about the orders that make the ranking slower.
+pub fn validate_criteria(criteria: &[Criterion]) -> anyhow::Result<Vec<String>> {
+    for (i, criterion) in criteria.iter().enumerate() {
+        if criteria[..i].contains(criterion) {
+            bail!("the {} criterion is defined more than once", criterion);
+        }
+        if let Criterion::Asc(field) = criterion {
+            if criteria.contains(&Criterion::Desc(field.clone())) {
+                bail!("the {:?} field can't be sorted both in ascending and descending order", field);
+            }
+        }
+    }
+
+    let mut warnings = Vec::new();
+    let position = |name: Criterion| criteria.iter().position(|c| *c == name);
+    if let (Some(words), Some(proximity)) = (position(Criterion::Words), position(Criterion::Proximity)) {
+        if proximity < words {
+            warnings.push("the proximity criterion is placed before the words criterion, \
+                this makes the ranking slower".to_string());
+        }
+    }
+
+    Ok(warnings)
+}
+
 impl fmt::Display for Criterion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Criterion::*;
@@ -74,9 +224,21 @@ impl fmt::Display for Criterion {
             Typo            => f.write_str("typo"),
             Proximity       => f.write_str("proximity"),
             Attribute       => f.write_str("attribute"),
+            Sort            => f.write_str("sort"),
             Exactness       => f.write_str("exactness"),
+            Frequency       => f.write_str("frequency"),
             Asc(attr)       => write!(f, "asc({})", attr),
             Desc(attr)      => write!(f, "desc({})", attr),
+            GeoAsc([lat, lng]) => write!(f, "geo:asc({}, {})", lat, lng),
+        }
+    }
+}
+
+impl fmt::Display for AscDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AscDesc::Asc(member)  => write!(f, "asc({})", member),
+            AscDesc::Desc(member) => write!(f, "desc({})", member),
         }
     }
 }
@@ -0,0 +1,402 @@
+use std::io;
+
+use anyhow::bail;
+use heed::PolyDatabase;
+use heed::types::ByteSlice;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{FacetCondition, FieldId, Index};
+
+/// The bytes that start a raw database dump, followed by the version of the format.
+const DUMP_MAGIC: &[u8; 8] = b"MILLIKV\x01";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ExportFormat {
+    /// The documents are written as a **comma seperated** CSV with headers on the first line.
+    Csv,
+    /// The documents are written as a JSON stream with a document on each line.
+    JsonStream,
+}
+
+/// The LMDB databases of an index that can be dumped and loaded with their raw content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DatabaseName {
+    Main,
+    WordDocids,
+    WordPrefixDocids,
+    DocidWordPositions,
+    WordPairProximityDocids,
+    WordPrefixPairProximityDocids,
+    WordLevelPositionDocids,
+    WordPrefixLevelPositionDocids,
+    FieldIdWordCountDocids,
+    FieldIdOriginalWordDocids,
+    FacetFieldIdValueDocids,
+    FieldIdDocidFacetValues,
+    FacetFieldIdDocidCount,
+    Documents,
+}
+
+impl DatabaseName {
+    /// All the databases of an index, in the order they are created.
+    pub const ALL: [DatabaseName; 14] = [
+        DatabaseName::Main,
+        DatabaseName::WordDocids,
+        DatabaseName::WordPrefixDocids,
+        DatabaseName::DocidWordPositions,
+        DatabaseName::WordPairProximityDocids,
+        DatabaseName::WordPrefixPairProximityDocids,
+        DatabaseName::WordLevelPositionDocids,
+        DatabaseName::WordPrefixLevelPositionDocids,
+        DatabaseName::FieldIdWordCountDocids,
+        DatabaseName::FieldIdOriginalWordDocids,
+        DatabaseName::FacetFieldIdValueDocids,
+        DatabaseName::FieldIdDocidFacetValues,
+        DatabaseName::FacetFieldIdDocidCount,
+        DatabaseName::Documents,
+    ];
+
+    /// The name of the database in the LMDB environment.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DatabaseName::Main => "main",
+            DatabaseName::WordDocids => "word-docids",
+            DatabaseName::WordPrefixDocids => "word-prefix-docids",
+            DatabaseName::DocidWordPositions => "docid-word-positions",
+            DatabaseName::WordPairProximityDocids => "word-pair-proximity-docids",
+            DatabaseName::WordPrefixPairProximityDocids => "word-prefix-pair-proximity-docids",
+            DatabaseName::WordLevelPositionDocids => "word-level-position-docids",
+            DatabaseName::WordPrefixLevelPositionDocids => "word-prefix-level-position-docids",
+            DatabaseName::FieldIdWordCountDocids => "field-id-word-count-docids",
+            DatabaseName::FieldIdOriginalWordDocids => "field-id-original-word-docids",
+            DatabaseName::FacetFieldIdValueDocids => "facet-field-id-value-docids",
+            DatabaseName::FieldIdDocidFacetValues => "field-id-docid-facet-values",
+            DatabaseName::FacetFieldIdDocidCount => "facet-field-id-docid-count",
+            DatabaseName::Documents => "documents",
+        }
+    }
+
+    fn database(&self, index: &Index) -> PolyDatabase {
+        match self {
+            DatabaseName::Main => index.main,
+            DatabaseName::WordDocids => *index.word_docids.as_polymorph(),
+            DatabaseName::WordPrefixDocids => *index.word_prefix_docids.as_polymorph(),
+            DatabaseName::DocidWordPositions => *index.docid_word_positions.as_polymorph(),
+            DatabaseName::WordPairProximityDocids => *index.word_pair_proximity_docids.as_polymorph(),
+            DatabaseName::WordPrefixPairProximityDocids => *index.word_prefix_pair_proximity_docids.as_polymorph(),
+            DatabaseName::WordLevelPositionDocids => *index.word_level_position_docids.as_polymorph(),
+            DatabaseName::WordPrefixLevelPositionDocids => *index.word_prefix_level_position_docids.as_polymorph(),
+            DatabaseName::FieldIdWordCountDocids => *index.field_id_word_count_docids.as_polymorph(),
+            DatabaseName::FieldIdOriginalWordDocids => *index.field_id_original_word_docids.as_polymorph(),
+            DatabaseName::FacetFieldIdValueDocids => *index.facet_field_id_value_docids.as_polymorph(),
+            DatabaseName::FieldIdDocidFacetValues => *index.field_id_docid_facet_values.as_polymorph(),
+            DatabaseName::FacetFieldIdDocidCount => *index.facet_field_id_docid_count.as_polymorph(),
+            DatabaseName::Documents => *index.documents.as_polymorph(),
+        }
+    }
+}
+
+/// The disk usage of one database of an index.
+///
+/// The sizes are the ones of the raw keys and values, they don't include
+/// the LMDB pages overhead nor the free pages that are kept for reuse.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatabaseSize {
+    /// The number of entries in the database.
+    pub entries: u64,
+    /// The total length of the keys, in bytes.
+    pub keys_size: u64,
+    /// The total length of the values, in bytes.
+    pub values_size: u64,
+}
+
+impl DatabaseSize {
+    /// The total length of the keys and values, in bytes.
+    pub fn total_size(&self) -> u64 {
+        self.keys_size + self.values_size
+    }
+}
+
+fn write_frame<W: io::Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    writer.write_all(bytes)
+}
+
+/// Reads a length prefixed frame, returns `false` if the reader was already at its end.
+fn read_frame<R: io::Read>(reader: &mut R, buffer: &mut Vec<u8>) -> io::Result<bool> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+        Err(e) => return Err(e),
+    }
+
+    buffer.clear();
+    buffer.resize(u32::from_be_bytes(length) as usize, 0);
+    reader.read_exact(buffer)?;
+    Ok(true)
+}
+
+impl Index {
+    /// Returns the number of entries and the size of the keys and values of one database
+    /// of the index, every entry of the database is read to compute it.
+    pub fn database_size(&self, rtxn: &heed::RoTxn, name: DatabaseName) -> heed::Result<DatabaseSize> {
+        let mut size = DatabaseSize::default();
+        for result in name.database(self).iter::<_, ByteSlice, ByteSlice>(rtxn)? {
+            let (key, value) = result?;
+            size.entries += 1;
+            size.keys_size += key.len() as u64;
+            size.values_size += value.len() as u64;
+        }
+        Ok(size)
+    }
+
+    /// Returns the disk usage of every database of the index, the postings, the prefix
+    /// postings, the facets and the documents, to see which ones take the most space.
+    pub fn database_sizes(&self, rtxn: &heed::RoTxn) -> heed::Result<Vec<(DatabaseName, DatabaseSize)>> {
+        DatabaseName::ALL.iter()
+            .map(|&name| self.database_size(rtxn, name).map(|size| (name, size)))
+            .collect()
+    }
+
+    /// Streams the raw keys and values of one database of the index into the given writer,
+    /// so that it can be shared and loaded into another index without the whole environment.
+    /// Returns the number of dumped entries.
+    ///
+    /// The dump starts with a magic header and the name of the database, each entry
+    /// is then written as its key and its value, both prefixed by their big endian u32 length.
+    pub fn dump_database<W: io::Write>(
+        &self,
+        rtxn: &heed::RoTxn,
+        name: DatabaseName,
+        writer: W,
+    ) -> anyhow::Result<u64>
+    {
+        let mut writer = io::BufWriter::new(writer);
+        io::Write::write_all(&mut writer, DUMP_MAGIC)?;
+        write_frame(&mut writer, name.as_str().as_bytes())?;
+
+        let mut count = 0;
+        for result in name.database(self).iter::<_, ByteSlice, ByteSlice>(rtxn)? {
+            let (key, value) = result?;
+            write_frame(&mut writer, key)?;
+            write_frame(&mut writer, value)?;
+            count += 1;
+        }
+
+        io::Write::flush(&mut writer)?;
+        Ok(count)
+    }
+
+    /// Replaces the content of one database of the index by the entries of a dump
+    /// written by `dump_database`. Returns the number of loaded entries.
+    ///
+    /// The other databases are left untouched, it is the responsibility of the caller
+    /// to only load dumps that are consistent with the rest of the index.
+    pub fn load_database<R: io::Read>(
+        &self,
+        wtxn: &mut heed::RwTxn,
+        name: DatabaseName,
+        reader: R,
+    ) -> anyhow::Result<u64>
+    {
+        let mut reader = io::BufReader::new(reader);
+        let mut magic = [0; 8];
+        io::Read::read_exact(&mut reader, &mut magic)?;
+        if &magic != DUMP_MAGIC {
+            bail!("invalid database dump header");
+        }
+
+        let mut buffer = Vec::new();
+        if !read_frame(&mut reader, &mut buffer)? || buffer != name.as_str().as_bytes() {
+            bail!("the dump is not a dump of the {:?} database", name.as_str());
+        }
+
+        let database = name.database(self);
+        database.clear(wtxn)?;
+
+        let mut key = Vec::new();
+        let mut count = 0;
+        while read_frame(&mut reader, &mut key)? {
+            if !read_frame(&mut reader, &mut buffer)? {
+                bail!("the dump ends with a key without a value");
+            }
+            database.put::<_, ByteSlice, ByteSlice>(wtxn, &key, &buffer)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Writes the documents of the index, ordered by internal document id, into the given
+    /// writer in the requested format. Only the documents matching the filter are exported
+    /// when one is specified. Returns the number of exported documents.
+    ///
+    /// CSV headers are the fields of the fields ids map in field id order, string values
+    /// are written as is and other values are written as JSON, missing values are left empty.
+    pub fn export_documents<W: io::Write>(
+        &self,
+        rtxn: &heed::RoTxn,
+        writer: W,
+        format: ExportFormat,
+        filter: Option<&FacetCondition>,
+    ) -> anyhow::Result<u64>
+    {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let fields_ids: Vec<FieldId> = fields_ids_map.iter().map(|(id, _)| id).collect();
+        let filtered_ids = match filter {
+            Some(condition) => Some(condition.evaluate(rtxn, self)?),
+            None => None,
+        };
+
+        let mut count = 0;
+        match format {
+            ExportFormat::Csv => {
+                let mut csv = csv::Writer::from_writer(writer);
+                csv.write_record(fields_ids_map.iter().map(|(_, name)| name))?;
+
+                let mut record = Vec::with_capacity(fields_ids.len());
+                for result in self.documents.iter(rtxn)? {
+                    let (docid, obkv) = result?;
+                    if filtered_ids.as_ref().map_or(false, |ids| !ids.contains(docid.get())) {
+                        continue;
+                    }
+
+                    record.clear();
+                    for id in &fields_ids {
+                        let field = match obkv.get(*id) {
+                            Some(value) => match serde_json::from_slice(value)? {
+                                Value::String(string) => string,
+                                Value::Null => String::new(),
+                                otherwise => otherwise.to_string(),
+                            },
+                            None => String::new(),
+                        };
+                        record.push(field);
+                    }
+
+                    csv.write_record(&record)?;
+                    count += 1;
+                }
+
+                csv.flush()?;
+            },
+            ExportFormat::JsonStream => {
+                let mut writer = io::BufWriter::new(writer);
+                for result in self.documents.iter(rtxn)? {
+                    let (docid, obkv) = result?;
+                    if filtered_ids.as_ref().map_or(false, |ids| !ids.contains(docid.get())) {
+                        continue;
+                    }
+
+                    let object = crate::obkv_to_json(&fields_ids, &fields_ids_map, obkv)?;
+                    serde_json::to_writer(&mut writer, &object)?;
+                    io::Write::write_all(&mut writer, b"\n")?;
+                    count += 1;
+                }
+
+                io::Write::flush(&mut writer)?;
+            },
+        }
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, UpdateFormat};
+
+    fn create_index() -> TempIndex {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": 1, "name": "kevin", "age": 21 },
+            { "id": 2, "name": "bob" },
+            { "id": 3, "name": "kevina", "age": 35 }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        index
+    }
+
+    #[test]
+    fn export_csv() {
+        let index = create_index();
+        let rtxn = index.read_txn().unwrap();
+
+        let mut output = Vec::new();
+        let count = index.export_documents(&rtxn, &mut output, ExportFormat::Csv, None).unwrap();
+        assert_eq!(count, 3);
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "id,name,age\n1,kevin,21\n2,bob,\n3,kevina,35\n");
+    }
+
+    #[test]
+    fn export_json_stream() {
+        let index = create_index();
+        let rtxn = index.read_txn().unwrap();
+
+        let mut output = Vec::new();
+        let count = index.export_documents(&rtxn, &mut output, ExportFormat::JsonStream, None).unwrap();
+        assert_eq!(count, 3);
+
+        let output = String::from_utf8(output).unwrap();
+        let expected = concat!(
+            r#"{"id":1,"name":"kevin","age":21}"#, "\n",
+            r#"{"id":2,"name":"bob"}"#, "\n",
+            r#"{"id":3,"name":"kevina","age":35}"#, "\n",
+        );
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn dump_and_load_database() {
+        let index = create_index();
+        let rtxn = index.read_txn().unwrap();
+
+        let mut dump = Vec::new();
+        let count = index.dump_database(&rtxn, DatabaseName::WordDocids, &mut dump).unwrap();
+        assert_eq!(count, index.word_docids.len(&rtxn).unwrap() as u64);
+
+        let other = TempIndex::new();
+
+        // A dump can only be loaded into the database it was dumped from.
+        let mut wtxn = other.write_txn().unwrap();
+        assert!(other.load_database(&mut wtxn, DatabaseName::Documents, &dump[..]).is_err());
+        assert_eq!(other.load_database(&mut wtxn, DatabaseName::WordDocids, &dump[..]).unwrap(), count);
+        wtxn.commit().unwrap();
+
+        let other_rtxn = other.read_txn().unwrap();
+        let entries: Vec<_> = index.word_docids.iter(&rtxn).unwrap().map(Result::unwrap).collect();
+        let other_entries: Vec<_> = other.word_docids.iter(&other_rtxn).unwrap().map(Result::unwrap).collect();
+        assert_eq!(entries, other_entries);
+    }
+
+    #[test]
+    fn database_sizes() {
+        let index = create_index();
+        let rtxn = index.read_txn().unwrap();
+
+        let sizes = index.database_sizes(&rtxn).unwrap();
+        assert_eq!(sizes.len(), DatabaseName::ALL.len());
+
+        let (_, documents) = sizes.iter().find(|(name, _)| *name == DatabaseName::Documents).unwrap();
+        assert_eq!(documents.entries, 3);
+        assert_eq!(documents.keys_size, 3 * 4);
+        assert!(documents.values_size > 0);
+
+        let word_docids = index.database_size(&rtxn, DatabaseName::WordDocids).unwrap();
+        assert_eq!(word_docids.entries, index.word_docids.len(&rtxn).unwrap() as u64);
+        assert_eq!(word_docids.total_size(), word_docids.keys_size + word_docids.values_size);
+    }
+}
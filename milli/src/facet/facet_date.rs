@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::DateTime;
+
+/// A date facet value, the number of seconds since the UNIX epoch, parsed from an RFC3339
+/// date (e.g. `2021-03-01T00:00:00Z`) or directly from a number of seconds.
+///
+/// The dates are stored in the number facet databases, every realistic timestamp is exactly
+/// represented by an `f64`, the facet levels of the dates are therefore built, and the
+/// dates sorted, like the numbers.
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub struct FacetDate(pub i64);
+
+impl FacetDate {
+    pub fn timestamp(&self) -> i64 {
+        self.0
+    }
+}
+
+impl From<FacetDate> for f64 {
+    fn from(date: FacetDate) -> f64 {
+        date.0 as f64
+    }
+}
+
+impl FromStr for FacetDate {
+    type Err = InvalidFacetDate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.parse::<i64>() {
+            Ok(timestamp) => Ok(FacetDate(timestamp)),
+            Err(_) => match DateTime::parse_from_rfc3339(s) {
+                Ok(date) => Ok(FacetDate(date.timestamp())),
+                Err(_) => Err(InvalidFacetDate(s.to_string())),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidFacetDate(String);
+
+impl fmt::Display for InvalidFacetDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid date {:?}, must be an RFC3339 date or a number of seconds since the epoch", self.0)
+    }
+}
+
+impl Error for InvalidFacetDate { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dates() {
+        assert_eq!("2021-03-01T00:00:00Z".parse(), Ok(FacetDate(1614556800)));
+        assert_eq!("2021-03-01T01:00:00+01:00".parse(), Ok(FacetDate(1614556800)));
+        assert_eq!(" 1614556800 ".parse(), Ok(FacetDate(1614556800)));
+        assert_eq!("-86400".parse(), Ok(FacetDate(-86400)));
+        assert!("2021-03-01".parse::<FacetDate>().is_err());
+        assert!("yesterday".parse::<FacetDate>().is_err());
+    }
+}
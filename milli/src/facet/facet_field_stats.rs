@@ -0,0 +1,23 @@
+use serde::{Serialize, Deserialize};
+
+/// Approximate statistics about the values of a faceted field, they are computed when the
+/// facets are built and are used to estimate the number of documents a filter matches.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetFieldStats {
+    /// The number of distinct values of the field, deleting documents doesn't update it.
+    pub distinct_values: u64,
+    /// The number of documents that have at least one value for the field.
+    pub documents_count: u64,
+}
+
+impl FacetFieldStats {
+    /// The estimated number of documents that have a given value of the field,
+    /// the documents are assumed to be evenly distributed among the values.
+    pub fn documents_by_value(&self) -> f64 {
+        if self.distinct_values == 0 {
+            0.0
+        } else {
+            self.documents_count as f64 / self.distinct_values as f64
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use serde::Serialize;
+
+use super::FacetNormalization;
+
+/// The separator between the levels of a hierarchical facet value, e.g. `Books > Fantasy`.
+pub const HIERARCHY_SEPARATOR: &str = " > ";
+
+/// Normalizes every level of a hierarchical path and joins them back with the separator,
+/// the levels are always trimmed and the empty ones are ignored.
+pub fn normalize_path(path: &str, normalization: FacetNormalization) -> String {
+    let mut output = String::new();
+    for level in path.split(HIERARCHY_SEPARATOR.trim()) {
+        let level = normalization.normalize(level);
+        let level = level.trim();
+        if !level.is_empty() {
+            if !output.is_empty() {
+                output.push_str(HIERARCHY_SEPARATOR);
+            }
+            output.push_str(level);
+        }
+    }
+    output
+}
+
+/// Returns the paths of every level of a normalized hierarchical path,
+/// from the root level to the full path itself.
+pub fn path_levels(path: &str) -> impl Iterator<Item=&str> {
+    let parents = path.match_indices(HIERARCHY_SEPARATOR).map(move |(i, _)| &path[..i]);
+    parents.chain(Some(path).filter(|p| !p.is_empty()))
+}
+
+/// The number of documents of a level of a hierarchical facet and of its sub-levels.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FacetHierarchyNode {
+    /// The last level of the path in its original form, e.g. `Fantasy` for `books > fantasy`.
+    pub value: String,
+    /// The full normalized path of this level.
+    pub path: String,
+    pub count: u64,
+    pub children: Vec<FacetHierarchyNode>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_and_split_paths() {
+        let path = normalize_path("  Books>Fantasy >  Epic  ", FacetNormalization::default());
+        assert_eq!(path, "books > fantasy > epic");
+
+        let path = normalize_path("Books > > Fantasy", FacetNormalization::none());
+        assert_eq!(path, "Books > Fantasy");
+
+        let levels: Vec<_> = path_levels("books > fantasy > epic").collect();
+        assert_eq!(levels, vec!["books", "books > fantasy", "books > fantasy > epic"]);
+
+        assert_eq!(path_levels("").count(), 0);
+    }
+}
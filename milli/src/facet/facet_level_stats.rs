@@ -0,0 +1,14 @@
+use serde::{Serialize, Deserialize};
+
+/// Statistics about a level of the number facet values of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FacetLevelStats {
+    /// The level these statistics are about, the level 0 contains the facet values themselves.
+    pub level: u8,
+    /// The number of entries in this level, a value or a group of values.
+    pub entries: u64,
+    /// The average number of entries of the level below grouped into an entry of this level.
+    pub average_group_size: f64,
+    /// The biggest number of documents ids associated with a single entry of this level.
+    pub max_documents: u64,
+}
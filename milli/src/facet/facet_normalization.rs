@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+
+use serde::{Serialize, Deserialize};
+use unicode_normalization::UnicodeNormalization;
+
+/// How the values of a string faceted field are normalized, the same normalization is applied
+/// to the values of the documents and to the values of the filters. By default the values are
+/// lowercased and trimmed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct FacetNormalization {
+    /// Lowercases the values, makes the filters case-insensitive.
+    pub lowercase: bool,
+    /// Applies the unicode NFKC normalization, e.g. "ﬁ" becomes "fi" and "①" becomes "1".
+    pub unicode_nfkc: bool,
+    /// Removes the leading and trailing whitespaces of the values.
+    pub trim: bool,
+}
+
+impl FacetNormalization {
+    /// Keeps the values exactly as they are written in the documents.
+    pub fn none() -> FacetNormalization {
+        FacetNormalization { lowercase: false, unicode_nfkc: false, trim: false }
+    }
+
+    pub fn normalize<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let mut value = Cow::Borrowed(value);
+        if self.unicode_nfkc {
+            value = Cow::Owned(value.nfkc().collect());
+        }
+        if self.lowercase {
+            let lowercased = value.to_lowercase();
+            if lowercased != value {
+                value = Cow::Owned(lowercased);
+            }
+        }
+        if self.trim {
+            value = match value {
+                Cow::Borrowed(value) => Cow::Borrowed(value.trim()),
+                Cow::Owned(value) => Cow::Owned(value.trim().to_string()),
+            };
+        }
+        value
+    }
+}
+
+impl Default for FacetNormalization {
+    fn default() -> FacetNormalization {
+        FacetNormalization { lowercase: true, unicode_nfkc: false, trim: true }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        let default = FacetNormalization::default();
+        assert_eq!(default.normalize("  Hard Rock "), "hard rock");
+        assert!(matches!(default.normalize("rock"), Cow::Borrowed("rock")));
+
+        let nfkc = FacetNormalization { unicode_nfkc: true, ..default };
+        assert_eq!(default.normalize("ﬁve"), "ﬁve");
+        assert_eq!(nfkc.normalize("ﬁve\u{3000}"), "five");
+
+        assert_eq!(FacetNormalization::none().normalize(" Rock "), " Rock ");
+    }
+}
@@ -9,6 +9,11 @@ use serde::{Serialize, Deserialize};
 pub enum FacetType {
     String,
     Number,
+    /// The dates are stored and compared as their number of seconds since the epoch.
+    Date,
+    /// The values are paths like `Books > Fantasy`, every level of the path is indexed
+    /// as a string value so that the documents can be filtered on any of them.
+    Hierarchy,
 }
 
 impl fmt::Display for FacetType {
@@ -16,6 +21,8 @@ impl fmt::Display for FacetType {
         match self {
             FacetType::String => f.write_str("string"),
             FacetType::Number => f.write_str("number"),
+            FacetType::Date => f.write_str("date"),
+            FacetType::Hierarchy => f.write_str("hierarchy"),
         }
     }
 }
@@ -28,6 +35,10 @@ impl FromStr for FacetType {
             Ok(FacetType::String)
         } else if s.trim().eq_ignore_ascii_case("number") {
             Ok(FacetType::Number)
+        } else if s.trim().eq_ignore_ascii_case("date") {
+            Ok(FacetType::Date)
+        } else if s.trim().eq_ignore_ascii_case("hierarchy") {
+            Ok(FacetType::Hierarchy)
         } else {
             Err(InvalidFacetType)
         }
@@ -39,7 +50,7 @@ pub struct InvalidFacetType;
 
 impl fmt::Display for InvalidFacetType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(r#"Invalid facet type, must be "string" or "number""#)
+        f.write_str(r#"Invalid facet type, must be "string", "number", "date" or "hierarchy""#)
     }
 }
 
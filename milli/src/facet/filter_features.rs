@@ -0,0 +1,28 @@
+use serde::{Serialize, Deserialize};
+
+/// The filter features that are enabled on a faceted field, all of them are enabled by default.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct FilterFeatures {
+    /// Allows filtering with the `=` and `!=` operators.
+    pub equality: bool,
+    /// Allows filtering with the `<`, `<=`, `>`, `>=` and `TO` operators,
+    /// the facet levels of number fields are only computed when enabled.
+    pub comparison: bool,
+    /// Allows searching for the facet values of this field.
+    pub facet_search: bool,
+}
+
+impl FilterFeatures {
+    /// Only allows filtering with the `=` and `!=` operators.
+    pub fn equality_only() -> FilterFeatures {
+        FilterFeatures { equality: true, comparison: false, facet_search: false }
+    }
+}
+
+impl Default for FilterFeatures {
+    fn default() -> FilterFeatures {
+        FilterFeatures { equality: true, comparison: true, facet_search: true }
+    }
+}
@@ -1,6 +1,18 @@
+mod facet_date;
+mod facet_field_stats;
+mod facet_hierarchy;
+mod facet_level_stats;
+mod facet_normalization;
 mod facet_type;
 mod facet_value;
+mod filter_features;
 pub mod value_encoding;
 
+pub use self::facet_date::{FacetDate, InvalidFacetDate};
+pub use self::facet_field_stats::FacetFieldStats;
+pub use self::facet_hierarchy::{FacetHierarchyNode, HIERARCHY_SEPARATOR, normalize_path, path_levels};
+pub use self::facet_level_stats::FacetLevelStats;
+pub use self::facet_normalization::FacetNormalization;
 pub use self::facet_type::FacetType;
 pub use self::facet_value::FacetValue;
+pub use self::filter_features::FilterFeatures;
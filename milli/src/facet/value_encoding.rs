@@ -13,6 +13,16 @@ pub fn f64_into_bytes(float: f64) -> Option<[u8; 8]> {
     None
 }
 
+#[inline]
+pub fn i64_into_bytes(int: i64) -> [u8; 8] {
+    xor_first_bit(int.to_be_bytes())
+}
+
+#[inline]
+pub fn i64_from_bytes(bytes: [u8; 8]) -> i64 {
+    i64::from_be_bytes(xor_first_bit(bytes))
+}
+
 #[inline]
 fn xor_first_bit(mut x: [u8; 8]) -> [u8; 8] {
     x[0] ^= 0x80;
@@ -45,4 +55,14 @@ mod tests {
         let vec: Vec<_> = [a, b, c, d, e].iter().cloned().map(f64_into_bytes).collect();
         assert!(is_sorted(&vec), "{:?}", vec);
     }
+
+    #[test]
+    fn ordered_i64_bytes() {
+        let values = [i64::MIN, -13, -1, 0, 1, 43, i64::MAX];
+        let vec: Vec<_> = values.iter().cloned().map(i64_into_bytes).collect();
+        assert!(is_sorted(&vec), "{:?}", vec);
+
+        let back: Vec<_> = vec.into_iter().map(i64_from_bytes).collect();
+        assert_eq!(back, values);
+    }
 }
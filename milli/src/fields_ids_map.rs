@@ -61,6 +61,18 @@ impl FieldsIdsMap {
         }
     }
 
+    /// Renames a field while keeping its field id, returns the id of the renamed field,
+    /// `None` if the old name is unknown or if the new name is already used by another field.
+    pub fn rename(&mut self, old_name: &str, new_name: &str) -> Option<FieldId> {
+        if self.names_ids.contains_key(new_name) {
+            return None;
+        }
+        let id = self.names_ids.remove(old_name)?;
+        self.names_ids.insert(new_name.to_owned(), id);
+        self.ids_names.insert(id, new_name.to_owned());
+        Some(id)
+    }
+
     /// Iterate over the ids and names in the ids order.
     pub fn iter(&self) -> impl Iterator<Item=(FieldId, &str)> {
         self.ids_names.iter().map(|(id, name)| (*id, name.as_str()))
@@ -111,5 +123,14 @@ mod tests {
         assert_eq!(iter.next(), Some((2, "description")));
         assert_eq!(iter.next(), Some((3, "title")));
         assert_eq!(iter.next(), None);
+        drop(iter);
+
+        assert_eq!(map.rename("title", "name"), Some(3));
+        assert_eq!(map.rename("id", "description"), None);
+        assert_eq!(map.rename("date", "day"), None);
+        assert_eq!(map.id("title"), None);
+        assert_eq!(map.id("name"), Some(3));
+        assert_eq!(map.name(3), Some("name"));
+        assert_eq!(map.insert("title"), Some(4));
     }
 }
@@ -0,0 +1,191 @@
+use anyhow::{bail, Context};
+use rstar::AABB;
+use serde_json::Value;
+
+use crate::{DocumentId, FieldId};
+
+/// The reserved field name that contains the `{ "lat": .., "lng": .. }` object of a document.
+pub const GEO_FIELD_NAME: &str = "_geo";
+
+/// The mean radius of the earth, in meters.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// A point stored in the geo R-tree, it is positioned on the surface of a sphere
+/// using cartesian coordinates and keeps the original latitude and longitude.
+pub type GeoPoint = rstar::primitives::PointWithData<(DocumentId, [f64; 2]), [f64; 3]>;
+
+/// Converts a latitude and a longitude, in degrees, into cartesian coordinates on a unit sphere.
+///
+/// The euclidean distance between two converted points grows with the distance
+/// on the surface of the earth, it can therefore be used by the R-tree to order points.
+pub fn lat_lng_to_xyz(coord: &[f64; 2]) -> [f64; 3] {
+    let [lat, lng] = coord;
+    let (lat, lng) = (lat.to_radians(), lng.to_radians());
+    [lat.cos() * lng.cos(), lat.cos() * lng.sin(), lat.sin()]
+}
+
+/// Converts a distance in meters on the surface of the earth into the squared euclidean
+/// distance between two points converted with `lat_lng_to_xyz`, it can be used to query the R-tree.
+pub(crate) fn meters_to_squared_chord(meters: f64) -> f64 {
+    // The angle between two points can't be more than half a turn.
+    let angle = (meters / EARTH_RADIUS).min(std::f64::consts::PI);
+    (2.0 * (angle / 2.0).sin()).powi(2)
+}
+
+/// Returns the cartesian envelopes of the R-tree that contain every point whose latitude is
+/// in `lat_range` and longitude in `lng_range`. The longitudes can go past 180 degrees when
+/// the range crosses the antimeridian, the range is then split into two envelopes.
+pub(crate) fn lat_lng_envelopes(lat_range: [f64; 2], lng_range: [f64; 2]) -> Vec<AABB<[f64; 3]>> {
+    let [min_lng, max_lng] = lng_range;
+    if max_lng - min_lng >= 360.0 {
+        return vec![lat_lng_envelope(lat_range, [-180.0, 180.0])];
+    }
+
+    // We bring the minimum longitude back into the [-180, 180) range.
+    let shift = ((min_lng + 180.0) / 360.0).floor() * 360.0;
+    let (min_lng, max_lng) = (min_lng - shift, max_lng - shift);
+    if max_lng <= 180.0 {
+        vec![lat_lng_envelope(lat_range, [min_lng, max_lng])]
+    } else {
+        vec![
+            lat_lng_envelope(lat_range, [min_lng, 180.0]),
+            lat_lng_envelope(lat_range, [-180.0, max_lng - 360.0]),
+        ]
+    }
+}
+
+/// Returns the cartesian envelope of the points in the given ranges, the longitudes must not wrap.
+fn lat_lng_envelope(lat_range: [f64; 2], lng_range: [f64; 2]) -> AABB<[f64; 3]> {
+    // The extremes of the coordinates are reached on the corners of the ranges
+    // or on the equator and the meridians where the cosines and sines peak.
+    let contains = |[min, max]: [f64; 2], value: f64| min <= value && value <= max;
+    let mut lats = vec![lat_range[0], lat_range[1]];
+    lats.extend([0.0].iter().filter(|lat| contains(lat_range, **lat)));
+    let mut lngs = vec![lng_range[0], lng_range[1]];
+    lngs.extend([-90.0, 0.0, 90.0].iter().filter(|lng| contains(lng_range, **lng)));
+
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for lat in &lats {
+        for lng in &lngs {
+            let point = lat_lng_to_xyz(&[*lat, *lng]);
+            for i in 0..3 {
+                min[i] = min[i].min(point[i]);
+                max[i] = max[i].max(point[i]);
+            }
+        }
+    }
+
+    // We enlarge the envelope a little to avoid missing the points on its borders.
+    const MARGIN: f64 = 1e-9;
+    AABB::from_corners(
+        [min[0] - MARGIN, min[1] - MARGIN, min[2] - MARGIN],
+        [max[0] + MARGIN, max[1] + MARGIN, max[2] + MARGIN],
+    )
+}
+
+/// Returns the distance, in meters, between two points given as latitudes and longitudes.
+pub fn distance_between_two_points(a: &[f64; 2], b: &[f64; 2]) -> f64 {
+    let (a_lat, a_lng) = (a[0].to_radians(), a[1].to_radians());
+    let (b_lat, b_lng) = (b[0].to_radians(), b[1].to_radians());
+
+    let d_lat = b_lat - a_lat;
+    let d_lng = b_lng - a_lng;
+    let h = (d_lat / 2.0).sin().powi(2) + a_lat.cos() * b_lat.cos() * (d_lng / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// Extracts the latitude and the longitude of the `_geo` field of the given document.
+pub(crate) fn extract_geo_point(obkv: &obkv::KvReader, geo_field_id: FieldId) -> anyhow::Result<Option<[f64; 2]>> {
+    fn extract_coord(object: &serde_json::Map<String, Value>, name: &str) -> anyhow::Result<f64> {
+        match object.get(name).with_context(|| format!("missing {:?} in the {} field", name, GEO_FIELD_NAME))? {
+            Value::Number(number) => number.as_f64().with_context(|| format!("invalid {:?} coordinate", name)),
+            Value::String(string) => string.trim().parse().with_context(|| format!("invalid {:?} coordinate", name)),
+            otherwise => bail!("invalid {:?} coordinate, expecting a number found {}", name, otherwise),
+        }
+    }
+
+    let value = match obkv.get(geo_field_id) {
+        Some(value) => serde_json::from_slice(value)?,
+        None => return Ok(None),
+    };
+
+    match value {
+        Value::Null => Ok(None),
+        Value::Object(object) => {
+            let lat = extract_coord(&object, "lat")?;
+            let lng = extract_coord(&object, "lng")?;
+            if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lng) {
+                bail!("invalid {} field, coordinates out of range: [{}, {}]", GEO_FIELD_NAME, lat, lng);
+            }
+            Ok(Some([lat, lng]))
+        },
+        otherwise => bail!("invalid {} field, expecting an object found {}", GEO_FIELD_NAME, otherwise),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstar::Envelope;
+
+    use super::*;
+
+    #[test]
+    fn distance_between_paris_and_london() {
+        let paris = [48.8566, 2.3522];
+        let london = [51.5074, -0.1278];
+        let distance = distance_between_two_points(&paris, &london);
+        assert!((distance - 343_500.0).abs() < 1_000.0, "{}", distance);
+        assert_eq!(distance_between_two_points(&paris, &paris), 0.0);
+    }
+
+    #[test]
+    fn squared_chord_of_a_distance() {
+        let paris = [48.8566, 2.3522];
+        let london = [51.5074, -0.1278];
+        let squared = lat_lng_to_xyz(&paris).iter()
+            .zip(&lat_lng_to_xyz(&london))
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f64>();
+        let chord = meters_to_squared_chord(distance_between_two_points(&paris, &london));
+        assert!((squared - chord).abs() < 1e-9, "{} {}", squared, chord);
+
+        // The opposite point of the globe is at the maximum distance.
+        assert!((meters_to_squared_chord(1e9) - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn envelopes_contain_the_ranges() {
+        let inside = |envelopes: &[AABB<[f64; 3]>], lat, lng| {
+            let point = lat_lng_to_xyz(&[lat, lng]);
+            envelopes.iter().any(|envelope| envelope.contains_point(&point))
+        };
+
+        let envelopes = lat_lng_envelopes([-10.0, 10.0], [-100.0, 100.0]);
+        assert_eq!(envelopes.len(), 1);
+        assert!(inside(&envelopes, 0.0, 0.0));
+        assert!(inside(&envelopes, 10.0, 90.0));
+        assert!(inside(&envelopes, -10.0, -100.0));
+        assert!(!inside(&envelopes, 0.0, 180.0));
+        assert!(!inside(&envelopes, 45.0, 0.0));
+
+        // This range crosses the antimeridian.
+        let envelopes = lat_lng_envelopes([-20.0, -10.0], [170.0, 190.0]);
+        assert_eq!(envelopes.len(), 2);
+        assert!(inside(&envelopes, -17.7134, 178.0650));
+        assert!(inside(&envelopes, -13.7590, -172.1046));
+        assert!(!inside(&envelopes, -15.0, 0.0));
+    }
+
+    #[test]
+    fn xyz_preserves_ordering() {
+        let paris = lat_lng_to_xyz(&[48.8566, 2.3522]);
+        let london = lat_lng_to_xyz(&[51.5074, -0.1278]);
+        let tokyo = lat_lng_to_xyz(&[35.6762, 139.6503]);
+        let squared = |a: &[f64; 3], b: &[f64; 3]| {
+            a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum::<f64>()
+        };
+        assert!(squared(&paris, &london) < squared(&paris, &tokyo));
+    }
+}
@@ -0,0 +1,207 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use crate::facet::value_encoding::{f64_into_bytes, i64_from_bytes, i64_into_bytes};
+use crate::FieldId;
+
+/// The codec of the number facet levels keys, the `(FieldId, level, left, right)` tuple.
+///
+/// The keys are laid out so that LMDB orders them by field id, level and then by left bound:
+///
+/// ```text
+/// level 0:     [field id][level][ordered left][raw left]
+/// other level: [field id][level][ordered left][ordered right][raw left][raw right]
+/// ```
+///
+/// The ordered bytes are a big-endian representation of the bounds that sorts like the numbers
+/// themselves, the raw bytes are only there to read the bounds back when the ordered bytes
+/// can't be decoded, they are empty for the integers. The right bound of the level 0 is
+/// not stored as it is always equal to the left bound.
+pub struct FacetLevelValueCodec<T>(PhantomData<T>);
+
+/// The codec of the float facet levels keys.
+pub type FacetLevelValueF64Codec = FacetLevelValueCodec<f64>;
+
+/// The codec of the integer facet levels keys.
+pub type FacetLevelValueI64Codec = FacetLevelValueCodec<i64>;
+
+/// A number that can be used as a bound of the facet levels keys.
+pub trait FacetLevelBound: Sized + Copy + 'static {
+    /// The length of the globally ordered bytes.
+    const ORDERED_LEN: usize;
+    /// The length of the raw bytes, used to read the number back.
+    const RAW_LEN: usize;
+
+    fn write_ordered(&self, output: &mut Vec<u8>) -> Option<()>;
+
+    fn write_raw(&self, output: &mut Vec<u8>);
+
+    fn read(ordered: &[u8], raw: &[u8]) -> Option<Self>;
+}
+
+impl FacetLevelBound for f64 {
+    const ORDERED_LEN: usize = 8;
+    const RAW_LEN: usize = 8;
+
+    fn write_ordered(&self, output: &mut Vec<u8>) -> Option<()> {
+        output.extend_from_slice(&f64_into_bytes(*self)?);
+        Some(())
+    }
+
+    fn write_raw(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn read(_ordered: &[u8], raw: &[u8]) -> Option<Self> {
+        raw.try_into().ok().map(f64::from_be_bytes)
+    }
+}
+
+impl FacetLevelBound for i64 {
+    const ORDERED_LEN: usize = 8;
+    const RAW_LEN: usize = 0;
+
+    fn write_ordered(&self, output: &mut Vec<u8>) -> Option<()> {
+        output.extend_from_slice(&i64_into_bytes(*self));
+        Some(())
+    }
+
+    fn write_raw(&self, _output: &mut Vec<u8>) { }
+
+    fn read(ordered: &[u8], _raw: &[u8]) -> Option<Self> {
+        ordered.try_into().ok().map(i64_from_bytes)
+    }
+}
+
+impl<'a, T: FacetLevelBound> heed::BytesDecode<'a> for FacetLevelValueCodec<T> {
+    type DItem = (FieldId, u8, T, T);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        let (field_id, bytes) = bytes.split_first()?;
+        let (level, bytes) = bytes.split_first()?;
+        let (olen, rlen) = (T::ORDERED_LEN, T::RAW_LEN);
+
+        let (left, right) = if *level != 0 {
+            if bytes.len() != 2 * (olen + rlen) { return None }
+            let (ordered, raw) = bytes.split_at(2 * olen);
+            let left = T::read(&ordered[..olen], &raw[..rlen])?;
+            let right = T::read(&ordered[olen..], &raw[rlen..])?;
+            (left, right)
+        } else {
+            if bytes.len() != olen + rlen { return None }
+            let (ordered, raw) = bytes.split_at(olen);
+            let left = T::read(ordered, raw)?;
+            (left, left)
+        };
+
+        Some((*field_id, *level, left, right))
+    }
+}
+
+impl<T: FacetLevelBound> heed::BytesEncode<'_> for FacetLevelValueCodec<T> {
+    type EItem = (FieldId, u8, T, T);
+
+    fn bytes_encode((field_id, level, left, right): &Self::EItem) -> Option<Cow<[u8]>> {
+        let mut bytes = Vec::with_capacity(2 + 2 * (T::ORDERED_LEN + T::RAW_LEN));
+        bytes.push(*field_id);
+        bytes.push(*level);
+
+        if *level != 0 {
+            // Write the globally ordered bounds.
+            left.write_ordered(&mut bytes)?;
+            right.write_ordered(&mut bytes)?;
+            // Then the raw bounds just to be able to read them back.
+            left.write_raw(&mut bytes);
+            right.write_raw(&mut bytes);
+        } else {
+            left.write_ordered(&mut bytes)?;
+            left.write_raw(&mut bytes);
+        }
+
+        Some(Cow::Owned(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::{BytesEncode, BytesDecode};
+    use super::*;
+
+    #[test]
+    fn globally_ordered_f64() {
+        let bytes = FacetLevelValueF64Codec::bytes_encode(&(3, 0, 32.0, 0.0)).unwrap();
+        let (name, level, left, right) = FacetLevelValueF64Codec::bytes_decode(&bytes).unwrap();
+        assert_eq!((name, level, left, right), (3, 0, 32.0, 32.0));
+
+        let bytes = FacetLevelValueF64Codec::bytes_encode(&(3, 1, -32.0, 32.0)).unwrap();
+        let (name, level, left, right) = FacetLevelValueF64Codec::bytes_decode(&bytes).unwrap();
+        assert_eq!((name, level, left, right), (3, 1, -32.0, 32.0));
+    }
+
+    #[test]
+    fn f64_byte_layout() {
+        let bytes = FacetLevelValueF64Codec::bytes_encode(&(3, 0, 1.5, 1.5)).unwrap();
+        assert_eq!(bytes.len(), 2 + 16);
+        assert_eq!(&bytes[..2], &[3, 0]);
+        assert_eq!(&bytes[2..10], &f64_into_bytes(1.5).unwrap());
+        assert_eq!(&bytes[10..], &1.5f64.to_be_bytes());
+
+        let bytes = FacetLevelValueF64Codec::bytes_encode(&(3, 2, -1.0, 1.5)).unwrap();
+        assert_eq!(bytes.len(), 2 + 32);
+        assert_eq!(&bytes[2..10], &f64_into_bytes(-1.0).unwrap());
+        assert_eq!(&bytes[10..18], &f64_into_bytes(1.5).unwrap());
+        assert_eq!(&bytes[18..26], &(-1.0f64).to_be_bytes());
+        assert_eq!(&bytes[26..], &1.5f64.to_be_bytes());
+    }
+
+    #[test]
+    fn globally_ordered_i64() {
+        let bytes = FacetLevelValueI64Codec::bytes_encode(&(3, 0, -32, 0)).unwrap();
+        assert_eq!(bytes.len(), 2 + 8);
+        let (name, level, left, right) = FacetLevelValueI64Codec::bytes_decode(&bytes).unwrap();
+        assert_eq!((name, level, left, right), (3, 0, -32, -32));
+
+        let bytes = FacetLevelValueI64Codec::bytes_encode(&(3, 1, i64::MIN, i64::MAX)).unwrap();
+        assert_eq!(bytes.len(), 2 + 16);
+        let (name, level, left, right) = FacetLevelValueI64Codec::bytes_decode(&bytes).unwrap();
+        assert_eq!((name, level, left, right), (3, 1, i64::MIN, i64::MAX));
+    }
+
+    #[test]
+    fn keys_ordering() {
+        fn encode_all<T: FacetLevelBound>(keys: &[(FieldId, u8, T, T)]) -> Vec<Vec<u8>> {
+            keys.iter().map(|k| FacetLevelValueCodec::<T>::bytes_encode(k).unwrap().into_owned()).collect()
+        }
+
+        // The keys are ordered by field id, level and then left bound.
+        let keys = encode_all(&[
+            (1, 0, -1000.5, -1000.5),
+            (1, 0, -0.5, -0.5),
+            (1, 0, 0.0, 0.0),
+            (1, 0, 42.0, 42.0),
+            (1, 1, -1000.5, 0.0),
+            (1, 1, 42.0, 50.0),
+            (2, 0, -3.0, -3.0),
+        ]);
+        assert!(keys.windows(2).all(|w| w[0] < w[1]), "{:?}", keys);
+
+        let keys = encode_all(&[
+            (1, 0, i64::MIN, i64::MIN),
+            (1, 0, -1, -1),
+            (1, 0, 0, 0),
+            (1, 0, 1 << 40, 1 << 40),
+            (1, 1, -20, 0),
+            (1, 1, 5, 10),
+            (2, 0, -3, -3),
+        ]);
+        assert!(keys.windows(2).all(|w| w[0] < w[1]), "{:?}", keys);
+    }
+
+    #[test]
+    fn invalid_keys() {
+        assert!(FacetLevelValueF64Codec::bytes_encode(&(1, 0, f64::NAN, f64::NAN)).is_none());
+        assert!(FacetLevelValueF64Codec::bytes_decode(&[1, 0, 0, 0]).is_none());
+        assert!(FacetLevelValueI64Codec::bytes_decode(&[1, 1, 0, 0, 0, 0, 0, 0, 0, 0]).is_none());
+    }
+}
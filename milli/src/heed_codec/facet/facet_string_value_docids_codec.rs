@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::marker::PhantomData;
+use std::str;
+
+/// Encodes the original form of a facet string value, as it is written in the documents,
+/// in front of the documents ids that are associated with its normalized form.
+///
+/// The original value is prefixed by its length encoded as a big endian `u16`.
+pub struct FacetStringValueDocidsCodec<C>(PhantomData<C>);
+
+impl<'a, C> heed::BytesDecode<'a> for FacetStringValueDocidsCodec<C>
+where C: heed::BytesDecode<'a>,
+{
+    type DItem = (&'a str, C::DItem);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        let (length_bytes, bytes) = try_split_at(bytes, 2)?;
+        let length = length_bytes.try_into().map(u16::from_be_bytes).ok()?;
+        let (original_bytes, bytes) = try_split_at(bytes, length as usize)?;
+        let original = str::from_utf8(original_bytes).ok()?;
+        let docids = C::bytes_decode(bytes)?;
+        Some((original, docids))
+    }
+}
+
+impl<'a, C> heed::BytesEncode<'a> for FacetStringValueDocidsCodec<C>
+where C: heed::BytesEncode<'a>,
+{
+    type EItem = (&'a str, C::EItem);
+
+    fn bytes_encode((original, docids): &Self::EItem) -> Option<Cow<[u8]>> {
+        let length: u16 = original.len().try_into().ok()?;
+        let docids_bytes = C::bytes_encode(docids)?;
+
+        let mut bytes = Vec::with_capacity(2 + original.len() + docids_bytes.len());
+        bytes.extend_from_slice(&length.to_be_bytes());
+        bytes.extend_from_slice(original.as_bytes());
+        bytes.extend_from_slice(&docids_bytes);
+        Some(Cow::Owned(bytes))
+    }
+}
+
+fn try_split_at(slice: &[u8], mid: usize) -> Option<(&[u8], &[u8])> {
+    if mid <= slice.len() {
+        Some(slice.split_at(mid))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::{BytesDecode, BytesEncode};
+    use roaring::RoaringBitmap;
+
+    use crate::CboRoaringBitmapCodec;
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let docids: RoaringBitmap = (0..100).collect();
+        let bytes = FacetStringValueDocidsCodec::<CboRoaringBitmapCodec>::bytes_encode(&("New York", docids.clone())).unwrap();
+        let (original, decoded) = FacetStringValueDocidsCodec::<CboRoaringBitmapCodec>::bytes_decode(&bytes).unwrap();
+        assert_eq!(original, "New York");
+        assert_eq!(decoded, docids);
+
+        assert!(FacetStringValueDocidsCodec::<CboRoaringBitmapCodec>::bytes_decode(&[0, 10, b'a']).is_none());
+    }
+}
@@ -0,0 +1,27 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+
+use crate::{FieldId, DocumentId};
+
+pub struct FieldDocIdCodec;
+
+impl<'a> heed::BytesDecode<'a> for FieldDocIdCodec {
+    type DItem = (FieldId, DocumentId);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        let (field_id, bytes) = bytes.split_first()?;
+        let document_id = bytes.try_into().map(u32::from_be_bytes).ok()?;
+        Some((*field_id, document_id))
+    }
+}
+
+impl<'a> heed::BytesEncode<'a> for FieldDocIdCodec {
+    type EItem = (FieldId, DocumentId);
+
+    fn bytes_encode((field_id, document_id): &Self::EItem) -> Option<Cow<[u8]>> {
+        let mut bytes = Vec::with_capacity(1 + 4);
+        bytes.push(*field_id);
+        bytes.extend_from_slice(&document_id.to_be_bytes());
+        Some(Cow::Owned(bytes))
+    }
+}
@@ -1,9 +1,14 @@
-mod facet_level_value_f64_codec;
+mod facet_level_value_codec;
+mod facet_string_value_docids_codec;
 mod facet_value_string_codec;
+mod field_doc_id_codec;
 mod field_doc_id_facet_f64_codec;
 mod field_doc_id_facet_string_codec;
 
-pub use self::facet_level_value_f64_codec::FacetLevelValueF64Codec;
+pub use self::facet_level_value_codec::{FacetLevelBound, FacetLevelValueCodec};
+pub use self::facet_level_value_codec::{FacetLevelValueF64Codec, FacetLevelValueI64Codec};
+pub use self::facet_string_value_docids_codec::FacetStringValueDocidsCodec;
 pub use self::facet_value_string_codec::FacetValueStringCodec;
+pub use self::field_doc_id_codec::FieldDocIdCodec;
 pub use self::field_doc_id_facet_f64_codec::FieldDocIdFacetF64Codec;
 pub use self::field_doc_id_facet_string_codec::FieldDocIdFacetStringCodec;
@@ -0,0 +1,27 @@
+use std::borrow::Cow;
+use std::str;
+
+use crate::FieldId;
+
+pub struct FieldIdWordCodec;
+
+impl<'a> heed::BytesDecode<'a> for FieldIdWordCodec {
+    type DItem = (FieldId, &'a str);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        let (field_id, bytes) = bytes.split_first()?;
+        let word = str::from_utf8(bytes).ok()?;
+        Some((*field_id, word))
+    }
+}
+
+impl<'a> heed::BytesEncode<'a> for FieldIdWordCodec {
+    type EItem = (FieldId, &'a str);
+
+    fn bytes_encode((field_id, word): &Self::EItem) -> Option<Cow<[u8]>> {
+        let mut bytes = Vec::with_capacity(word.len() + 1);
+        bytes.push(*field_id);
+        bytes.extend_from_slice(word.as_bytes());
+        Some(Cow::Owned(bytes))
+    }
+}
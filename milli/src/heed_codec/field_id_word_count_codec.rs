@@ -0,0 +1,24 @@
+use std::borrow::Cow;
+
+use crate::FieldId;
+
+pub struct FieldIdWordCountCodec;
+
+impl<'a> heed::BytesDecode<'a> for FieldIdWordCountCodec {
+    type DItem = (FieldId, u8);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        match bytes {
+            [field_id, word_count] => Some((*field_id, *word_count)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> heed::BytesEncode<'a> for FieldIdWordCountCodec {
+    type EItem = (FieldId, u8);
+
+    fn bytes_encode((field_id, word_count): &Self::EItem) -> Option<Cow<[u8]>> {
+        Some(Cow::Owned(vec![*field_id, *word_count]))
+    }
+}
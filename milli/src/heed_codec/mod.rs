@@ -1,4 +1,6 @@
 mod beu32_str_codec;
+mod field_id_word_codec;
+mod field_id_word_count_codec;
 mod obkv_codec;
 mod roaring_bitmap;
 mod roaring_bitmap_length;
@@ -7,8 +9,12 @@ mod str_str_u8_codec;
 pub mod facet;
 
 pub use self::beu32_str_codec::BEU32StrCodec;
+pub use self::field_id_word_codec::FieldIdWordCodec;
+pub use self::field_id_word_count_codec::FieldIdWordCountCodec;
 pub use self::obkv_codec::ObkvCodec;
 pub use self::roaring_bitmap::{BoRoaringBitmapCodec, CboRoaringBitmapCodec, RoaringBitmapCodec};
+pub use self::roaring_bitmap::{RoaringBitmapFormat, TaggedRoaringBitmapCodec};
 pub use self::roaring_bitmap_length::{BoRoaringBitmapLenCodec, CboRoaringBitmapLenCodec, RoaringBitmapLenCodec};
+pub use self::roaring_bitmap_length::TaggedRoaringBitmapLenCodec;
 pub use self::str_level_position_codec::StrLevelPositionCodec;
 pub use self::str_str_u8_codec::StrStrU8Codec;
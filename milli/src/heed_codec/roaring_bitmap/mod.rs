@@ -1,7 +1,9 @@
 mod bo_roaring_bitmap_codec;
 pub mod cbo_roaring_bitmap_codec;
 mod roaring_bitmap_codec;
+mod tagged_roaring_bitmap_codec;
 
 pub use self::bo_roaring_bitmap_codec::BoRoaringBitmapCodec;
 pub use self::cbo_roaring_bitmap_codec::CboRoaringBitmapCodec;
 pub use self::roaring_bitmap_codec::RoaringBitmapCodec;
+pub use self::tagged_roaring_bitmap_codec::{RoaringBitmapFormat, TaggedRoaringBitmapCodec};
@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+use std::mem::size_of;
+
+use byteorder::{NativeEndian, ReadBytesExt, WriteBytesExt};
+use roaring::RoaringBitmap;
+
+use super::cbo_roaring_bitmap_codec::THRESHOLD;
+
+/// The first byte of a bitmap serialized with the classic roaring format
+/// (without or with run containers), used to detect the untagged values.
+const LEGACY_COOKIE_BYTES: [u8; 2] = [0x3A, 0x3B];
+
+/// The format used to serialize a bitmap, written as a one-byte tag
+/// in front of the bitmap bytes so that different formats can coexist.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RoaringBitmapFormat {
+    /// The classic roaring serialization format.
+    Classic = 1,
+    /// The integers directly written one after the other in native endian.
+    ByteOrder = 2,
+    /// The roaring serialization format with run containers.
+    RunOptimized = 3,
+}
+
+impl RoaringBitmapFormat {
+    pub fn tag(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_tag(tag: u8) -> Option<RoaringBitmapFormat> {
+        match tag {
+            1 => Some(RoaringBitmapFormat::Classic),
+            2 => Some(RoaringBitmapFormat::ByteOrder),
+            3 => Some(RoaringBitmapFormat::RunOptimized),
+            _ => None,
+        }
+    }
+}
+
+/// A codec that prefixes the bitmap bytes with a `RoaringBitmapFormat` tag.
+///
+/// It also decodes the untagged values written by the `RoaringBitmapCodec`, those can
+/// be detected by the roaring cookie and are migrated the next time they are written.
+pub struct TaggedRoaringBitmapCodec;
+
+impl TaggedRoaringBitmapCodec {
+    /// Returns the format of the given bytes and the bitmap bytes without the tag.
+    pub fn split_format(bytes: &[u8]) -> Option<(RoaringBitmapFormat, &[u8])> {
+        let (&first, tail) = bytes.split_first()?;
+        if LEGACY_COOKIE_BYTES.contains(&first) {
+            Some((RoaringBitmapFormat::Classic, bytes))
+        } else {
+            RoaringBitmapFormat::from_tag(first).map(|format| (format, tail))
+        }
+    }
+
+    pub fn serialize_into(roaring: &RoaringBitmap, vec: &mut Vec<u8>) -> Option<()> {
+        if roaring.len() <= THRESHOLD as u64 {
+            vec.push(RoaringBitmapFormat::ByteOrder.tag());
+            for integer in roaring {
+                vec.write_u32::<NativeEndian>(integer).ok()?;
+            }
+        } else {
+            vec.push(RoaringBitmapFormat::Classic.tag());
+            roaring.serialize_into(vec).ok()?;
+        }
+        Some(())
+    }
+}
+
+impl heed::BytesDecode<'_> for TaggedRoaringBitmapCodec {
+    type DItem = RoaringBitmap;
+
+    fn bytes_decode(bytes: &[u8]) -> Option<Self::DItem> {
+        match Self::split_format(bytes)? {
+            (RoaringBitmapFormat::ByteOrder, mut bytes) => {
+                let mut bitmap = RoaringBitmap::new();
+                while let Ok(integer) = bytes.read_u32::<NativeEndian>() {
+                    bitmap.insert(integer);
+                }
+                Some(bitmap)
+            },
+            // The roaring crate is able to read both of the roaring formats.
+            (RoaringBitmapFormat::Classic, bytes) | (RoaringBitmapFormat::RunOptimized, bytes) => {
+                RoaringBitmap::deserialize_from(bytes).ok()
+            },
+        }
+    }
+}
+
+impl heed::BytesEncode<'_> for TaggedRoaringBitmapCodec {
+    type EItem = RoaringBitmap;
+
+    fn bytes_encode(item: &Self::EItem) -> Option<Cow<[u8]>> {
+        let size = if item.len() <= THRESHOLD as u64 {
+            item.len() as usize * size_of::<u32>()
+        } else {
+            item.serialized_size()
+        };
+        let mut vec = Vec::with_capacity(size + 1);
+        Self::serialize_into(item, &mut vec)?;
+        Some(Cow::Owned(vec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::FromIterator;
+    use heed::{BytesEncode, BytesDecode};
+
+    use crate::heed_codec::RoaringBitmapCodec;
+    use super::*;
+
+    #[test]
+    fn tagged_encoding_decoding() {
+        let small = RoaringBitmap::from_iter(0..THRESHOLD as u32);
+        let bytes = TaggedRoaringBitmapCodec::bytes_encode(&small).unwrap();
+        assert_eq!(bytes[0], RoaringBitmapFormat::ByteOrder.tag());
+        assert_eq!(TaggedRoaringBitmapCodec::bytes_decode(&bytes).unwrap(), small);
+
+        let large = RoaringBitmap::from_iter(0..1000);
+        let bytes = TaggedRoaringBitmapCodec::bytes_encode(&large).unwrap();
+        assert_eq!(bytes[0], RoaringBitmapFormat::Classic.tag());
+        assert_eq!(TaggedRoaringBitmapCodec::bytes_decode(&bytes).unwrap(), large);
+    }
+
+    #[test]
+    fn decode_untagged_values() {
+        for bitmap in vec![RoaringBitmap::new(), RoaringBitmap::from_iter(0..3)] {
+            let bytes = RoaringBitmapCodec::bytes_encode(&bitmap).unwrap();
+            assert_eq!(TaggedRoaringBitmapCodec::bytes_decode(&bytes).unwrap(), bitmap);
+        }
+    }
+
+    #[test]
+    fn unknown_tag() {
+        assert!(TaggedRoaringBitmapCodec::bytes_decode(&[42, 0, 0, 0, 0]).is_none());
+        assert!(TaggedRoaringBitmapCodec::bytes_decode(&[]).is_none());
+    }
+}
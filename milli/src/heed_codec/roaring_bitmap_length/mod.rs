@@ -1,7 +1,9 @@
 mod bo_roaring_bitmap_len_codec;
 mod cbo_roaring_bitmap_len_codec;
 mod roaring_bitmap_len_codec;
+mod tagged_roaring_bitmap_len_codec;
 
 pub use self::bo_roaring_bitmap_len_codec::BoRoaringBitmapLenCodec;
 pub use self::cbo_roaring_bitmap_len_codec::CboRoaringBitmapLenCodec;
 pub use self::roaring_bitmap_len_codec::RoaringBitmapLenCodec;
+pub use self::tagged_roaring_bitmap_len_codec::TaggedRoaringBitmapLenCodec;
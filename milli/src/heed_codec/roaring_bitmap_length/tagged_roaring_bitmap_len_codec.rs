@@ -0,0 +1,23 @@
+use std::mem;
+
+use heed::BytesDecode;
+
+use crate::heed_codec::{RoaringBitmapFormat, TaggedRoaringBitmapCodec};
+use super::RoaringBitmapLenCodec;
+
+pub struct TaggedRoaringBitmapLenCodec;
+
+impl heed::BytesDecode<'_> for TaggedRoaringBitmapLenCodec {
+    type DItem = u64;
+
+    fn bytes_decode(bytes: &[u8]) -> Option<Self::DItem> {
+        match TaggedRoaringBitmapCodec::split_format(bytes)? {
+            (RoaringBitmapFormat::ByteOrder, bytes) => {
+                Some((bytes.len() / mem::size_of::<u32>()) as u64)
+            },
+            (RoaringBitmapFormat::Classic, bytes) | (RoaringBitmapFormat::RunOptimized, bytes) => {
+                RoaringBitmapLenCodec::bytes_decode(bytes)
+            },
+        }
+    }
+}
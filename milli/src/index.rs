@@ -1,41 +1,77 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::num::NonZeroUsize;
 use std::path::Path;
 
 use anyhow::Context;
 use chrono::{DateTime, Utc};
+use fst::{IntoStreamer, Streamer};
 use heed::{Database, PolyDatabase, RoTxn, RwTxn};
 use heed::types::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use roaring::RoaringBitmap;
+use rstar::RTree;
 
-use crate::{Criterion, default_criteria, FacetDistribution, FieldsDistribution, Search};
-use crate::{BEU32, DocumentId, ExternalDocumentsIds, FieldId};
+use crate::{AnalyzerOptions, AttributeRanking, Criterion, default_criteria, FacetDistribution, FacetStats, FieldsDistribution, FuzzyWordsIter, Search, WordDocidsIter};
+use crate::{BEU32, DocumentId, ExternalDocumentsIds, FieldId, GeoPoint, TermsMatchingStrategy};
 use crate::{
-    BEU32StrCodec, BoRoaringBitmapCodec, CboRoaringBitmapCodec,
-    ObkvCodec, RoaringBitmapCodec, RoaringBitmapLenCodec, StrLevelPositionCodec, StrStrU8Codec,
+    BEU32StrCodec, BoRoaringBitmapCodec, CboRoaringBitmapCodec, CboRoaringBitmapLenCodec,
+    FieldIdWordCodec, FieldIdWordCountCodec, ObkvCodec, RoaringBitmapCodec, RoaringBitmapLenCodec, StrLevelPositionCodec,
+    StrStrU8Codec, TaggedRoaringBitmapCodec, TaggedRoaringBitmapLenCodec,
 };
-use crate::facet::FacetType;
+use crate::facet::{FacetFieldStats, FacetLevelStats, FacetNormalization, FacetType, FilterFeatures};
+use crate::search::build_dfa;
+use crate::heed_codec::facet::{FacetLevelValueF64Codec, FacetStringValueDocidsCodec, FacetValueStringCodec, FieldDocIdCodec};
 use crate::fields_ids_map::FieldsIdsMap;
+use crate::update::{UpdateHooks, UpdateOutcome};
+use crate::writer_lock::{LockedRwTxn, WriterLock};
 
+pub const ANALYZER_OPTIONS_KEY: &str = "analyzer-options";
+pub const ATTRIBUTE_RANKING_KEY: &str = "attribute-ranking";
 pub const CRITERIA_KEY: &str = "criteria";
 pub const DISPLAYED_FIELDS_KEY: &str = "displayed-fields";
 pub const DISTINCT_ATTRIBUTE_KEY: &str = "distinct-attribute-key";
+pub const DISTINCT_ATTRIBUTES_KEY: &str = "distinct-attributes";
 pub const DOCUMENTS_IDS_KEY: &str = "documents-ids";
 pub const FACETED_DOCUMENTS_IDS_PREFIX: &str = "faceted-documents-ids";
+pub const EXACT_ATTRIBUTES_KEY: &str = "exact-attributes";
+pub const EXACT_WORDS_KEY: &str = "exact-words";
+pub const EXACTNESS_DATABASES_KEY: &str = "exactness-databases";
 pub const FACETED_FIELDS_KEY: &str = "faceted-fields";
+pub const FACET_FIELD_STATS_PREFIX: &str = "facet-field-stats";
+pub const FACET_LEVEL_SIZES_KEY: &str = "facet-level-sizes";
+pub const FACET_NORMALIZATIONS_KEY: &str = "facet-normalizations";
+pub const FACET_STRING_ORIGINALS_KEY: &str = "facet-string-originals";
 pub const FIELDS_IDS_MAP_KEY: &str = "fields-ids-map";
 pub const FIELDS_DISTRIBUTION_KEY: &str = "fields-distribution";
+pub const FILTERABLE_FEATURES_KEY: &str = "filterable-features";
+pub const FIELDS_MAX_INDEXED_TOKENS_KEY: &str = "fields-max-indexed-tokens";
+pub const GEO_FACETED_DOCUMENTS_IDS_KEY: &str = "geo-faceted-documents-ids";
+pub const GEO_RTREE_KEY: &str = "geo-rtree";
+pub const MIN_WORD_SIZE_FOR_ONE_TYPO_KEY: &str = "min-word-size-for-one-typo";
+pub const MIN_WORD_SIZE_FOR_TWO_TYPOS_KEY: &str = "min-word-size-for-two-typos";
+pub const PREFIX_DATABASES_ENABLED_KEY: &str = "prefix-databases-enabled";
+pub const PROXIMITY_ENABLED_KEY: &str = "proximity-enabled";
 pub const PRIMARY_KEY_KEY: &str = "primary-key";
 pub const SEARCHABLE_FIELDS_KEY: &str = "searchable-fields";
+pub const SORTABLE_FIELDS_KEY: &str = "sortable-fields";
+pub const TYPO_TOLERANCE_ENABLED_KEY: &str = "typo-tolerance-enabled";
 pub const HARD_EXTERNAL_DOCUMENTS_IDS_KEY: &str = "hard-external-documents-ids";
 pub const SOFT_EXTERNAL_DOCUMENTS_IDS_KEY: &str = "soft-external-documents-ids";
 pub const WORDS_FST_KEY: &str = "words-fst";
 pub const STOP_WORDS_KEY: &str = "stop-words";
 pub const SYNONYMS_KEY: &str = "synonyms";
+pub const TERMS_MATCHING_STRATEGY_KEY: &str = "terms-matching-strategy";
 pub const WORDS_PREFIXES_FST_KEY: &str = "words-prefixes-fst";
 const CREATED_AT_KEY: &str = "created-at";
 const UPDATED_AT_KEY: &str = "updated-at";
 
+/// The query words that are shorter than this number of characters are matched exactly.
+pub const DEFAULT_MIN_WORD_SIZE_FOR_ONE_TYPO: u8 = 5;
+/// The query words that are shorter than this number of characters are matched with at most one typo.
+pub const DEFAULT_MIN_WORD_SIZE_FOR_TWO_TYPOS: u8 = 9;
+
 #[derive(Clone)]
 pub struct Index {
     /// The LMDB environment which this index is associated with.
@@ -56,17 +92,28 @@ pub struct Index {
     pub word_level_position_docids: Database<StrLevelPositionCodec, CboRoaringBitmapCodec>,
     /// Maps the level positions of a word prefix with all the docids where this prefix appears.
     pub word_prefix_level_position_docids: Database<StrLevelPositionCodec, CboRoaringBitmapCodec>,
+    /// Maps a field id and a number of words with the docids whose field contains exactly this number of words.
+    pub field_id_word_count_docids: Database<FieldIdWordCountCodec, CboRoaringBitmapCodec>,
+    /// Maps a field id and a word, as it is written in the documents, with the docids whose
+    /// field contains it. The words are not lowercased and keep their diacritics.
+    pub field_id_original_word_docids: Database<FieldIdWordCodec, CboRoaringBitmapCodec>,
     /// Maps the facet field id and the globally ordered value with the docids that corresponds to it.
     pub facet_field_id_value_docids: Database<ByteSlice, CboRoaringBitmapCodec>,
     /// Maps the document id, the facet field id and the globally ordered value.
     pub field_id_docid_facet_values: Database<ByteSlice, Unit>,
+    /// Maps the facet field id and the document id to the number of values of this field in the document.
+    pub facet_field_id_docid_count: Database<FieldDocIdCodec, OwnedType<BEU32>>,
     /// Maps the document id to the document as an obkv store.
     pub documents: Database<OwnedType<BEU32>, ObkvCodec>,
+    /// The functions called with the outcome of the updates once committed.
+    pub(crate) update_hooks: UpdateHooks,
+    /// Ensures that a single writer uses `try_write_txn` at a time.
+    pub(crate) writer_lock: WriterLock,
 }
 
 impl Index {
     pub fn new<P: AsRef<Path>>(mut options: heed::EnvOpenOptions, path: P) -> anyhow::Result<Index> {
-        options.max_dbs(11);
+        options.max_dbs(14);
 
         let env = options.open(path)?;
         let main = env.create_poly_database(Some("main"))?;
@@ -77,8 +124,11 @@ impl Index {
         let word_prefix_pair_proximity_docids = env.create_database(Some("word-prefix-pair-proximity-docids"))?;
         let word_level_position_docids = env.create_database(Some("word-level-position-docids"))?;
         let word_prefix_level_position_docids = env.create_database(Some("word-prefix-level-position-docids"))?;
+        let field_id_word_count_docids = env.create_database(Some("field-id-word-count-docids"))?;
+        let field_id_original_word_docids = env.create_database(Some("field-id-original-word-docids"))?;
         let facet_field_id_value_docids = env.create_database(Some("facet-field-id-value-docids"))?;
         let field_id_docid_facet_values = env.create_database(Some("field-id-docid-facet-values"))?;
+        let facet_field_id_docid_count = env.create_database(Some("facet-field-id-docid-count"))?;
         let documents = env.create_database(Some("documents"))?;
 
         {
@@ -102,15 +152,23 @@ impl Index {
             word_prefix_pair_proximity_docids,
             word_level_position_docids,
             word_prefix_level_position_docids,
+            field_id_word_count_docids,
+            field_id_original_word_docids,
             facet_field_id_value_docids,
             field_id_docid_facet_values,
+            facet_field_id_docid_count,
             documents,
+            update_hooks: UpdateHooks::default(),
+            writer_lock: WriterLock::default(),
         })
     }
 
     /// Create a write transaction to be able to write into the index.
     pub fn write_txn(&self) -> heed::Result<RwTxn> {
-        self.env.write_txn()
+        let wtxn = self.env.write_txn()?;
+        // The outcome recorded by the updates of an aborted transaction must not be notified.
+        self.update_hooks.take_pending();
+        Ok(wtxn)
     }
 
     /// Create a read transaction to be able to read the index.
@@ -118,6 +176,45 @@ impl Index {
         self.env.read_txn()
     }
 
+    /// Create a write transaction that fails fast with a `WriterLockError` when another
+    /// write transaction created with this method is in progress, instead of waiting for it.
+    ///
+    /// Only the transactions created with this method are checked, the ones created
+    /// with `write_txn` still wait on the LMDB writer lock.
+    pub fn try_write_txn(&self) -> anyhow::Result<LockedRwTxn> {
+        let guard = self.writer_lock.try_lock(self.path())?;
+        let wtxn = self.write_txn()?;
+        Ok(LockedRwTxn::new(wtxn, guard))
+    }
+
+    /// Enables an advisory lock file in the index directory that is held by the write
+    /// transactions created with `try_write_txn`, other processes fail to write while it exists.
+    ///
+    /// The file is not removed if the process crashes during a write, it must then be removed by hand.
+    pub fn set_writer_lock_file(&self, enabled: bool) {
+        self.writer_lock.set_lock_file(enabled);
+    }
+
+    /// Registers a function that is called with the outcome of every update
+    /// committed with `commit_update`, it is called once the changes are visible.
+    pub fn on_update_committed<F>(&self, hook: F)
+    where F: Fn(&UpdateOutcome) + Send + Sync + 'static,
+    {
+        self.update_hooks.register(hook);
+    }
+
+    /// Commits the write transaction of an update and calls the registered hooks with the
+    /// outcome recorded by the documents additions, deletions and settings updates executed
+    /// in this transaction, only if the commit succeeded and one of these was executed.
+    pub fn commit_update(&self, wtxn: RwTxn) -> heed::Result<Option<UpdateOutcome>> {
+        wtxn.commit()?;
+        let outcome = self.update_hooks.take_pending();
+        if let Some(outcome) = &outcome {
+            self.update_hooks.notify(outcome);
+        }
+        Ok(outcome)
+    }
+
     /// Returns the canonicalized path where the heed `Env` of this `Index` lives.
     pub fn path(&self) -> &Path {
         self.env.path()
@@ -136,17 +233,17 @@ impl Index {
 
     /// Writes the documents ids that corresponds to the user-ids-documents-ids FST.
     pub fn put_documents_ids(&self, wtxn: &mut RwTxn, docids: &RoaringBitmap) -> heed::Result<()> {
-        self.main.put::<_, Str, RoaringBitmapCodec>(wtxn, DOCUMENTS_IDS_KEY, docids)
+        self.main.put::<_, Str, TaggedRoaringBitmapCodec>(wtxn, DOCUMENTS_IDS_KEY, docids)
     }
 
     /// Returns the internal documents ids.
     pub fn documents_ids(&self, rtxn: &RoTxn) -> heed::Result<RoaringBitmap> {
-        Ok(self.main.get::<_, Str, RoaringBitmapCodec>(rtxn, DOCUMENTS_IDS_KEY)?.unwrap_or_default())
+        Ok(self.main.get::<_, Str, TaggedRoaringBitmapCodec>(rtxn, DOCUMENTS_IDS_KEY)?.unwrap_or_default())
     }
 
     /// Returns the number of documents indexed in the database.
     pub fn number_of_documents(&self, rtxn: &RoTxn) -> anyhow::Result<u64> {
-        let count = self.main.get::<_, Str, RoaringBitmapLenCodec>(rtxn, DOCUMENTS_IDS_KEY)?;
+        let count = self.main.get::<_, Str, TaggedRoaringBitmapLenCodec>(rtxn, DOCUMENTS_IDS_KEY)?;
         Ok(count.unwrap_or_default())
     }
 
@@ -201,6 +298,29 @@ impl Index {
         Ok(ExternalDocumentsIds::new(hard, soft))
     }
 
+    /// Returns the internal document id associated with the given external id, if any.
+    pub fn external_to_internal_id(&self, rtxn: &RoTxn, external_id: &str) -> anyhow::Result<Option<DocumentId>> {
+        let external_documents_ids = self.external_documents_ids(rtxn)?;
+        Ok(external_documents_ids.get(external_id))
+    }
+
+    /// Returns the internal documents ids associated with the given external ids,
+    /// the external ids that are not part of the index are ignored.
+    pub fn external_to_internal_ids<I, A>(&self, rtxn: &RoTxn, external_ids: I) -> anyhow::Result<RoaringBitmap>
+    where
+        I: IntoIterator<Item=A>,
+        A: AsRef<str>,
+    {
+        let external_documents_ids = self.external_documents_ids(rtxn)?;
+        let mut docids = RoaringBitmap::new();
+        for external_id in external_ids {
+            if let Some(docid) = external_documents_ids.get(external_id.as_ref()) {
+                docids.insert(docid);
+            }
+        }
+        Ok(docids)
+    }
+
     /* fields ids map */
 
     /// Writes the fields ids map which associate the documents keys with an internal field id
@@ -259,6 +379,34 @@ impl Index {
         Ok(ids)
     }
 
+    /* exact attributes */
+
+    /// Writes the attributes on which the typo tolerance is disabled.
+    pub fn put_exact_attributes(&self, wtxn: &mut RwTxn, fields: &[&str]) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeBincode<&[&str]>>(wtxn, EXACT_ATTRIBUTES_KEY, &fields)
+    }
+
+    pub fn delete_exact_attributes(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, EXACT_ATTRIBUTES_KEY)
+    }
+
+    /// Returns the attributes on which the typo tolerance is disabled, the words
+    /// that only appear in these attributes are never matched with typos.
+    pub fn exact_attributes<'t>(&self, rtxn: &'t RoTxn) -> heed::Result<Vec<&'t str>> {
+        Ok(self.main.get::<_, Str, SerdeBincode<Vec<&'t str>>>(rtxn, EXACT_ATTRIBUTES_KEY)?.unwrap_or_default())
+    }
+
+    /// Same as `exact_attributes`, but returns the sorted ids instead.
+    pub fn exact_attributes_ids(&self, rtxn: &RoTxn) -> heed::Result<Vec<FieldId>> {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let mut ids: Vec<_> = self.exact_attributes(rtxn)?
+            .into_iter()
+            .filter_map(|name| fields_ids_map.id(name))
+            .collect();
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
     /* searchable fields */
 
     /// Writes the searchable fields, when this list is specified, only these are indexed.
@@ -331,6 +479,198 @@ impl Index {
         Ok(faceted_fields)
     }
 
+    /* sortable fields */
+
+    /// Writes the sortable fields associated with their facet type.
+    pub fn put_sortable_fields(&self, wtxn: &mut RwTxn, fields_types: &HashMap<String, FacetType>) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, SORTABLE_FIELDS_KEY, fields_types)
+    }
+
+    /// Deletes the sortable fields associated with their facet type.
+    pub fn delete_sortable_fields(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, SORTABLE_FIELDS_KEY)
+    }
+
+    /// Returns the sortable fields names associated with their facet type.
+    pub fn sortable_fields(&self, rtxn: &RoTxn) -> heed::Result<HashMap<String, FacetType>> {
+        Ok(self.main.get::<_, Str, SerdeJson<_>>(rtxn, SORTABLE_FIELDS_KEY)?.unwrap_or_default())
+    }
+
+    /// Returns the fields whose values are stored in the facet databases, the faceted
+    /// fields and the sortable fields, associated with their facet type.
+    pub fn indexed_facet_fields(&self, rtxn: &RoTxn) -> heed::Result<HashMap<String, FacetType>> {
+        let mut fields = self.sortable_fields(rtxn)?;
+        fields.extend(self.faceted_fields(rtxn)?);
+        Ok(fields)
+    }
+
+    /// Same as `indexed_facet_fields`, but returns ids instead.
+    pub fn indexed_facet_fields_ids(&self, rtxn: &RoTxn) -> heed::Result<HashMap<FieldId, FacetType>> {
+        let fields = self.indexed_facet_fields(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let fields = fields
+            .iter()
+            .map(|(k, v)| {
+                let kid = fields_ids_map
+                    .id(k)
+                    .ok_or_else(|| format!("{:?} should be present in the field id map", k))
+                    .expect("corrupted data: ");
+                (kid, *v)
+            })
+            .collect();
+        Ok(fields)
+    }
+
+    /* max indexed tokens */
+
+    /// Writes the maximum number of words indexed for each of the given fields.
+    pub fn put_max_indexed_tokens(&self, wtxn: &mut RwTxn, fields: &HashMap<String, usize>) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, FIELDS_MAX_INDEXED_TOKENS_KEY, fields)
+    }
+
+    pub fn delete_max_indexed_tokens(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, FIELDS_MAX_INDEXED_TOKENS_KEY)
+    }
+
+    /// Returns the maximum number of words indexed for the fields that are limited,
+    /// the words that follow are neither searchable nor highlighted.
+    pub fn max_indexed_tokens(&self, rtxn: &RoTxn) -> heed::Result<HashMap<String, usize>> {
+        Ok(self.main.get::<_, Str, SerdeJson<_>>(rtxn, FIELDS_MAX_INDEXED_TOKENS_KEY)?.unwrap_or_default())
+    }
+
+    /// Same as `max_indexed_tokens`, but returns ids instead.
+    pub fn max_indexed_tokens_ids(&self, rtxn: &RoTxn) -> heed::Result<HashMap<FieldId, usize>> {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let fields = self.max_indexed_tokens(rtxn)?
+            .into_iter()
+            .filter_map(|(name, max)| fields_ids_map.id(&name).map(|id| (id, max)))
+            .collect();
+        Ok(fields)
+    }
+
+    /* filterable features */
+
+    /// Writes the filter features enabled on the faceted fields.
+    pub fn put_filterable_features(
+        &self,
+        wtxn: &mut RwTxn,
+        features: &HashMap<String, FilterFeatures>,
+    ) -> heed::Result<()>
+    {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, FILTERABLE_FEATURES_KEY, features)
+    }
+
+    /// Deletes the filter features enabled on the faceted fields.
+    pub fn delete_filterable_features(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, FILTERABLE_FEATURES_KEY)
+    }
+
+    /// Returns the filter features enabled on the faceted fields, the fields
+    /// that are not part of this map have all their features enabled.
+    pub fn filterable_features(&self, rtxn: &RoTxn) -> heed::Result<HashMap<String, FilterFeatures>> {
+        Ok(self.main.get::<_, Str, SerdeJson<_>>(rtxn, FILTERABLE_FEATURES_KEY)?.unwrap_or_default())
+    }
+
+    /// Same as `filterable_features`, but returns ids instead, the fields
+    /// that are not part of the fields ids map are ignored.
+    pub fn filterable_features_ids(&self, rtxn: &RoTxn) -> heed::Result<HashMap<FieldId, FilterFeatures>> {
+        let filterable_features = self.filterable_features(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let filterable_features = filterable_features
+            .into_iter()
+            .filter_map(|(name, features)| fields_ids_map.id(&name).map(|id| (id, features)))
+            .collect();
+        Ok(filterable_features)
+    }
+
+    /* facet level sizes */
+
+    /// Writes the group size and the minimum size of the levels the facet numbers were computed with.
+    pub(crate) fn put_facet_level_sizes(
+        &self,
+        wtxn: &mut RwTxn,
+        level_group_size: NonZeroUsize,
+        min_level_size: NonZeroUsize,
+    ) -> heed::Result<()>
+    {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, FACET_LEVEL_SIZES_KEY, &(level_group_size, min_level_size))
+    }
+
+    /// Returns the group size and the minimum size of the levels the facet numbers were computed
+    /// with, the documents deletions keep the levels consistent with them.
+    pub fn facet_level_sizes(&self, rtxn: &RoTxn) -> heed::Result<Option<(NonZeroUsize, NonZeroUsize)>> {
+        self.main.get::<_, Str, SerdeJson<_>>(rtxn, FACET_LEVEL_SIZES_KEY)
+    }
+
+    /* facet normalizations */
+
+    /// Writes the normalizations of the values of the string faceted fields.
+    pub fn put_facet_normalizations(
+        &self,
+        wtxn: &mut RwTxn,
+        normalizations: &HashMap<String, FacetNormalization>,
+    ) -> heed::Result<()>
+    {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, FACET_NORMALIZATIONS_KEY, normalizations)
+    }
+
+    /// Deletes the normalizations of the values of the string faceted fields.
+    pub fn delete_facet_normalizations(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, FACET_NORMALIZATIONS_KEY)
+    }
+
+    /// Returns the normalizations of the values of the string faceted fields, the
+    /// fields that are not part of this map use the default normalization.
+    pub fn facet_normalizations(&self, rtxn: &RoTxn) -> heed::Result<HashMap<String, FacetNormalization>> {
+        Ok(self.main.get::<_, Str, SerdeJson<_>>(rtxn, FACET_NORMALIZATIONS_KEY)?.unwrap_or_default())
+    }
+
+    /// Same as `facet_normalizations`, but returns ids instead, the fields
+    /// that are not part of the fields ids map are ignored.
+    pub fn facet_normalizations_ids(&self, rtxn: &RoTxn) -> heed::Result<HashMap<FieldId, FacetNormalization>> {
+        let normalizations = self.facet_normalizations(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let normalizations = normalizations
+            .into_iter()
+            .filter_map(|(name, normalization)| fields_ids_map.id(&name).map(|id| (id, normalization)))
+            .collect();
+        Ok(normalizations)
+    }
+
+    /* geo rtree */
+
+    /// Writes the provided `rtree` which associates coordinates to documents ids.
+    pub fn put_geo_rtree(&self, wtxn: &mut RwTxn, rtree: &RTree<GeoPoint>) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeBincode<RTree<GeoPoint>>>(wtxn, GEO_RTREE_KEY, rtree)
+    }
+
+    /// Delete the `rtree` which associates coordinates to documents ids.
+    pub fn delete_geo_rtree(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, GEO_RTREE_KEY)
+    }
+
+    /// Returns the `rtree` which associates coordinates to documents ids.
+    pub fn geo_rtree(&self, rtxn: &RoTxn) -> heed::Result<Option<RTree<GeoPoint>>> {
+        self.main.get::<_, Str, SerdeBincode<RTree<GeoPoint>>>(rtxn, GEO_RTREE_KEY)
+    }
+
+    /* geo faceted */
+
+    /// Writes the documents ids that are faceted with a `_geo` field.
+    pub fn put_geo_faceted_documents_ids(&self, wtxn: &mut RwTxn, docids: &RoaringBitmap) -> heed::Result<()> {
+        self.main.put::<_, Str, TaggedRoaringBitmapCodec>(wtxn, GEO_FACETED_DOCUMENTS_IDS_KEY, docids)
+    }
+
+    /// Delete the documents ids that are faceted with a `_geo` field.
+    pub fn delete_geo_faceted_documents_ids(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, GEO_FACETED_DOCUMENTS_IDS_KEY)
+    }
+
+    /// Retrieve all the documents ids that are faceted with a `_geo` field.
+    pub fn geo_faceted_documents_ids(&self, rtxn: &RoTxn) -> heed::Result<RoaringBitmap> {
+        Ok(self.main.get::<_, Str, TaggedRoaringBitmapCodec>(rtxn, GEO_FACETED_DOCUMENTS_IDS_KEY)?.unwrap_or_default())
+    }
+
     /* faceted documents ids */
 
     /// Writes the documents ids that are faceted under this field id.
@@ -338,7 +678,7 @@ impl Index {
         let mut buffer = [0u8; FACETED_DOCUMENTS_IDS_PREFIX.len() + 1];
         buffer[..FACETED_DOCUMENTS_IDS_PREFIX.len()].clone_from_slice(FACETED_DOCUMENTS_IDS_PREFIX.as_bytes());
         *buffer.last_mut().unwrap() = field_id;
-        self.main.put::<_, ByteSlice, RoaringBitmapCodec>(wtxn, &buffer, docids)
+        self.main.put::<_, ByteSlice, TaggedRoaringBitmapCodec>(wtxn, &buffer, docids)
     }
 
     /// Retrieve all the documents ids that faceted under this field id.
@@ -346,12 +686,120 @@ impl Index {
         let mut buffer = [0u8; FACETED_DOCUMENTS_IDS_PREFIX.len() + 1];
         buffer[..FACETED_DOCUMENTS_IDS_PREFIX.len()].clone_from_slice(FACETED_DOCUMENTS_IDS_PREFIX.as_bytes());
         *buffer.last_mut().unwrap() = field_id;
-        match self.main.get::<_, ByteSlice, RoaringBitmapCodec>(rtxn, &buffer)? {
+        match self.main.get::<_, ByteSlice, TaggedRoaringBitmapCodec>(rtxn, &buffer)? {
             Some(docids) => Ok(docids),
             None => Ok(RoaringBitmap::new()),
         }
     }
 
+    /* facet field stats */
+
+    /// Writes the statistics of the values of this faceted field.
+    pub fn put_facet_field_stats(&self, wtxn: &mut RwTxn, field_id: FieldId, stats: &FacetFieldStats) -> heed::Result<()> {
+        let mut buffer = [0u8; FACET_FIELD_STATS_PREFIX.len() + 1];
+        buffer[..FACET_FIELD_STATS_PREFIX.len()].clone_from_slice(FACET_FIELD_STATS_PREFIX.as_bytes());
+        *buffer.last_mut().unwrap() = field_id;
+        self.main.put::<_, ByteSlice, SerdeJson<FacetFieldStats>>(wtxn, &buffer, stats)
+    }
+
+    /// Returns the statistics of the values of this faceted field, if the facets have been built.
+    pub fn facet_field_stats(&self, rtxn: &RoTxn, field_id: FieldId) -> heed::Result<Option<FacetFieldStats>> {
+        let mut buffer = [0u8; FACET_FIELD_STATS_PREFIX.len() + 1];
+        buffer[..FACET_FIELD_STATS_PREFIX.len()].clone_from_slice(FACET_FIELD_STATS_PREFIX.as_bytes());
+        *buffer.last_mut().unwrap() = field_id;
+        self.main.get::<_, ByteSlice, SerdeJson<FacetFieldStats>>(rtxn, &buffer)
+    }
+
+    /// Returns the statistics of the values of all the faceted fields that have some.
+    pub fn facet_fields_stats(&self, rtxn: &RoTxn) -> heed::Result<HashMap<FieldId, FacetFieldStats>> {
+        let mut fields_stats = HashMap::new();
+        for (field_id, _) in self.indexed_facet_fields_ids(rtxn)? {
+            if let Some(stats) = self.facet_field_stats(rtxn, field_id)? {
+                fields_stats.insert(field_id, stats);
+            }
+        }
+        Ok(fields_stats)
+    }
+
+    /* facet values count */
+
+    /// Returns the number of values of the given faceted field in the document,
+    /// the documents that do not have any value for this field are counted as zero.
+    pub fn facet_values_count(&self, rtxn: &RoTxn, field_id: FieldId, docid: DocumentId) -> heed::Result<u32> {
+        let count = self.facet_field_id_docid_count.get(rtxn, &(field_id, docid))?;
+        Ok(count.map_or(0, |count| count.get()))
+    }
+
+    /* facet values docids */
+
+    /// Returns the facet values docids database with the string facet values key codec,
+    /// the documents ids of a string are stored along with its original form.
+    pub fn facet_string_values_docids(
+        &self,
+    ) -> Database<FacetValueStringCodec, FacetStringValueDocidsCodec<CboRoaringBitmapCodec>>
+    {
+        self.facet_field_id_value_docids.remap_types()
+    }
+
+    /// Returns the original form, as written in the documents, of the given normalized facet string.
+    pub fn facet_string_original<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        field_id: FieldId,
+        value: &str,
+    ) -> heed::Result<Option<&'t str>>
+    {
+        let db = self.facet_field_id_value_docids
+            .remap_types::<FacetValueStringCodec, FacetStringValueDocidsCodec<DecodeIgnore>>();
+        Ok(db.get(rtxn, &(field_id, value))?.map(|(original, ())| original))
+    }
+
+    /// Returns the facet values docids database with the number facet levels key codec.
+    pub fn facet_number_levels_docids(&self) -> Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec> {
+        self.facet_field_id_value_docids.remap_key_type::<FacetLevelValueF64Codec>()
+    }
+
+    /// Returns the statistics of the levels of the given number faceted field, from the
+    /// lowest to the highest level, the list is empty for the fields that are not numbers or dates.
+    pub fn facet_levels(&self, rtxn: &RoTxn, field_id: FieldId) -> heed::Result<Vec<FacetLevelStats>> {
+        let faceted_fields = self.indexed_facet_fields_ids(rtxn)?;
+        if !matches!(faceted_fields.get(&field_id), Some(FacetType::Number) | Some(FacetType::Date)) {
+            return Ok(Vec::new());
+        }
+
+        let iter = self.facet_field_id_value_docids
+            .remap_types::<ByteSlice, CboRoaringBitmapLenCodec>()
+            .prefix_iter(rtxn, &[field_id])?
+            .remap_key_type::<FacetLevelValueF64Codec>();
+
+        let mut levels: Vec<FacetLevelStats> = Vec::new();
+        for result in iter {
+            let ((_fid, level, _left, _right), documents) = result?;
+            match levels.last_mut() {
+                Some(stats) if stats.level == level => {
+                    stats.entries += 1;
+                    stats.max_documents = stats.max_documents.max(documents);
+                },
+                _ => levels.push(FacetLevelStats {
+                    level,
+                    entries: 1,
+                    average_group_size: 1.0,
+                    max_documents: documents,
+                }),
+            }
+        }
+
+        let mut lower_level_entries = None;
+        for stats in &mut levels {
+            if let Some(lower_entries) = lower_level_entries {
+                stats.average_group_size = lower_entries as f64 / stats.entries as f64;
+            }
+            lower_level_entries = Some(stats.entries);
+        }
+
+        Ok(levels)
+    }
+
     /* Distinct attribute */
 
     pub(crate) fn put_distinct_attribute(&self, wtxn: &mut RwTxn, distinct_attribute: &str) -> heed::Result<()> {
@@ -366,6 +814,23 @@ impl Index {
         self.main.delete::<_, Str>(wtxn, DISTINCT_ATTRIBUTE_KEY)
     }
 
+    /* Distinct attributes */
+
+    /// Writes the fields that compose the distinct key, the documents
+    /// are distinct by the combination of the values of these fields.
+    pub(crate) fn put_distinct_attributes(&self, wtxn: &mut RwTxn, fields: &[&str]) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeBincode<&[&str]>>(wtxn, DISTINCT_ATTRIBUTES_KEY, &fields)
+    }
+
+    /// Returns the fields that compose the distinct key, in the order they were specified.
+    pub fn distinct_attributes<'t>(&self, rtxn: &'t RoTxn) -> heed::Result<Option<Vec<&'t str>>> {
+        self.main.get::<_, Str, SerdeBincode<Vec<&'t str>>>(rtxn, DISTINCT_ATTRIBUTES_KEY)
+    }
+
+    pub(crate) fn delete_distinct_attributes(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, DISTINCT_ATTRIBUTES_KEY)
+    }
+
     /* criteria */
 
     pub fn put_criteria(&self, wtxn: &mut RwTxn, criteria: &[Criterion]) -> heed::Result<()> {
@@ -415,6 +880,26 @@ impl Index {
         }
     }
 
+    /* exact words */
+
+    /// Writes the words on which the typo tolerance is disabled.
+    pub fn put_exact_words<A: AsRef<[u8]>>(&self, wtxn: &mut RwTxn, fst: &fst::Set<A>) -> heed::Result<()> {
+        self.main.put::<_, Str, ByteSlice>(wtxn, EXACT_WORDS_KEY, fst.as_fst().as_bytes())
+    }
+
+    pub fn delete_exact_words(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, EXACT_WORDS_KEY)
+    }
+
+    /// Returns the words on which the typo tolerance is disabled, these words
+    /// must be matched exactly, even when they are long enough to allow typos.
+    pub fn exact_words<'t>(&self, rtxn: &'t RoTxn) -> anyhow::Result<Option<fst::Set<&'t [u8]>>> {
+        match self.main.get::<_, Str, ByteSlice>(rtxn, EXACT_WORDS_KEY)? {
+            Some(bytes) => Ok(Some(fst::Set::new(bytes)?)),
+            None => Ok(None),
+        }
+    }
+
     /* synonyms */
 
     pub fn put_synonyms(&self, wtxn: &mut RwTxn, synonyms: &HashMap<Vec<String>, Vec<Vec<String>>>) -> heed::Result<()> {
@@ -449,6 +934,180 @@ impl Index {
         }
     }
 
+    /// Deletes the FST which is the words prefixes dictionnary of the engine.
+    pub fn delete_words_prefixes_fst(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, WORDS_PREFIXES_FST_KEY)
+    }
+
+    /* exactness databases */
+
+    /// Writes that the documents were indexed along with the databases of the exactness
+    /// criterion, the field id word count docids and the field id original word docids.
+    pub(crate) fn put_exactness_databases_built(&self, wtxn: &mut RwTxn) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<bool>>(wtxn, EXACTNESS_DATABASES_KEY, &true)
+    }
+
+    /// Returns `true` if the documents were indexed before the databases of the exactness
+    /// criterion existed, these databases are empty until the documents are reindexed.
+    pub fn exactness_databases_outdated(&self, rtxn: &RoTxn) -> anyhow::Result<bool> {
+        let built = self.main.get::<_, Str, SerdeJson<bool>>(rtxn, EXACTNESS_DATABASES_KEY)?;
+        Ok(built != Some(true) && self.number_of_documents(rtxn)? != 0)
+    }
+
+    /* facet string originals */
+
+    /// Writes that the facet string values docids are stored along with the original
+    /// form of the values, as encoded by the `FacetStringValueDocidsCodec`.
+    pub(crate) fn put_facet_string_originals_built(&self, wtxn: &mut RwTxn) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<bool>>(wtxn, FACET_STRING_ORIGINALS_KEY, &true)
+    }
+
+    /// Returns `true` if the documents were indexed before the facet string values docids
+    /// stored the original form of the values, these values can't be decoded until the
+    /// documents are reindexed.
+    pub fn facet_string_originals_outdated(&self, rtxn: &RoTxn) -> anyhow::Result<bool> {
+        let built = self.main.get::<_, Str, SerdeJson<bool>>(rtxn, FACET_STRING_ORIGINALS_KEY)?;
+        Ok(built != Some(true) && self.number_of_documents(rtxn)? != 0)
+    }
+
+    /* prefix databases enabled */
+
+    /// Writes whether the words prefixes FST and the prefix databases must be computed.
+    pub fn put_prefix_databases_enabled(&self, wtxn: &mut RwTxn, enabled: bool) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<bool>>(wtxn, PREFIX_DATABASES_ENABLED_KEY, &enabled)
+    }
+
+    pub fn delete_prefix_databases_enabled(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, PREFIX_DATABASES_ENABLED_KEY)
+    }
+
+    /// Returns whether the words prefixes FST and the prefix databases must be computed,
+    /// they are enabled by default. Disabling them speeds up the indexing process but
+    /// the prefix search then relies on the, slower, words FST derivations.
+    pub fn prefix_databases_enabled(&self, rtxn: &RoTxn) -> heed::Result<bool> {
+        let enabled = self.main.get::<_, Str, SerdeJson<bool>>(rtxn, PREFIX_DATABASES_ENABLED_KEY)?;
+        Ok(enabled.unwrap_or(true))
+    }
+
+    /* proximity enabled */
+
+    /// Writes whether the words pairs proximities must be computed and the proximity criterion used.
+    pub fn put_proximity_enabled(&self, wtxn: &mut RwTxn, enabled: bool) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<bool>>(wtxn, PROXIMITY_ENABLED_KEY, &enabled)
+    }
+
+    pub fn delete_proximity_enabled(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, PROXIMITY_ENABLED_KEY)
+    }
+
+    /// Returns whether the words pairs proximities must be computed, enabled by default.
+    /// When disabled the proximity criterion is a no-op and the phrases are approximated
+    /// by only requiring all of their words to be in the documents.
+    pub fn proximity_enabled(&self, rtxn: &RoTxn) -> heed::Result<bool> {
+        let enabled = self.main.get::<_, Str, SerdeJson<bool>>(rtxn, PROXIMITY_ENABLED_KEY)?;
+        Ok(enabled.unwrap_or(true))
+    }
+
+    /* typo tolerance */
+
+    /// Writes whether the query words can be matched with typos.
+    pub fn put_typo_tolerance_enabled(&self, wtxn: &mut RwTxn, enabled: bool) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<bool>>(wtxn, TYPO_TOLERANCE_ENABLED_KEY, &enabled)
+    }
+
+    pub fn delete_typo_tolerance_enabled(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, TYPO_TOLERANCE_ENABLED_KEY)
+    }
+
+    /// Returns whether the query words can be matched with typos, enabled by default.
+    /// When disabled the query words are only matched exactly and the typo criterion is a no-op.
+    pub fn typo_tolerance_enabled(&self, rtxn: &RoTxn) -> heed::Result<bool> {
+        let enabled = self.main.get::<_, Str, SerdeJson<bool>>(rtxn, TYPO_TOLERANCE_ENABLED_KEY)?;
+        Ok(enabled.unwrap_or(true))
+    }
+
+    /// Writes the minimum number of characters a query word must have to be matched with one typo.
+    pub fn put_min_word_size_for_one_typo(&self, wtxn: &mut RwTxn, size: u8) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<u8>>(wtxn, MIN_WORD_SIZE_FOR_ONE_TYPO_KEY, &size)
+    }
+
+    pub fn delete_min_word_size_for_one_typo(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, MIN_WORD_SIZE_FOR_ONE_TYPO_KEY)
+    }
+
+    /// Returns the minimum number of characters a query word must have to be matched with one typo.
+    pub fn min_word_size_for_one_typo(&self, rtxn: &RoTxn) -> heed::Result<u8> {
+        let size = self.main.get::<_, Str, SerdeJson<u8>>(rtxn, MIN_WORD_SIZE_FOR_ONE_TYPO_KEY)?;
+        Ok(size.unwrap_or(DEFAULT_MIN_WORD_SIZE_FOR_ONE_TYPO))
+    }
+
+    /// Writes the minimum number of characters a query word must have to be matched with two typos.
+    pub fn put_min_word_size_for_two_typos(&self, wtxn: &mut RwTxn, size: u8) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<u8>>(wtxn, MIN_WORD_SIZE_FOR_TWO_TYPOS_KEY, &size)
+    }
+
+    pub fn delete_min_word_size_for_two_typos(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, MIN_WORD_SIZE_FOR_TWO_TYPOS_KEY)
+    }
+
+    /// Returns the minimum number of characters a query word must have to be matched with two typos.
+    pub fn min_word_size_for_two_typos(&self, rtxn: &RoTxn) -> heed::Result<u8> {
+        let size = self.main.get::<_, Str, SerdeJson<u8>>(rtxn, MIN_WORD_SIZE_FOR_TWO_TYPOS_KEY)?;
+        Ok(size.unwrap_or(DEFAULT_MIN_WORD_SIZE_FOR_TWO_TYPOS))
+    }
+
+    /* attribute ranking */
+
+    /// Writes how the attribute criterion must compute the buckets of documents.
+    pub fn put_attribute_ranking(&self, wtxn: &mut RwTxn, ranking: AttributeRanking) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<AttributeRanking>>(wtxn, ATTRIBUTE_RANKING_KEY, &ranking)
+    }
+
+    pub fn delete_attribute_ranking(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, ATTRIBUTE_RANKING_KEY)
+    }
+
+    /// Returns how the attribute criterion computes the buckets of documents, `Auto` by default.
+    pub fn attribute_ranking(&self, rtxn: &RoTxn) -> heed::Result<AttributeRanking> {
+        let ranking = self.main.get::<_, Str, SerdeJson<AttributeRanking>>(rtxn, ATTRIBUTE_RANKING_KEY)?;
+        Ok(ranking.unwrap_or_default())
+    }
+
+    /* terms matching strategy */
+
+    /// Writes the order in which the query words are removed when the documents don't
+    /// contain all of them, it is used by the searches that don't define their own.
+    pub fn put_terms_matching_strategy(&self, wtxn: &mut RwTxn, strategy: TermsMatchingStrategy) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<TermsMatchingStrategy>>(wtxn, TERMS_MATCHING_STRATEGY_KEY, &strategy)
+    }
+
+    pub fn delete_terms_matching_strategy(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, TERMS_MATCHING_STRATEGY_KEY)
+    }
+
+    /// Returns the order in which the query words are removed, from the end of the query by default.
+    pub fn terms_matching_strategy(&self, rtxn: &RoTxn) -> heed::Result<TermsMatchingStrategy> {
+        let strategy = self.main.get::<_, Str, SerdeJson<TermsMatchingStrategy>>(rtxn, TERMS_MATCHING_STRATEGY_KEY)?;
+        Ok(strategy.unwrap_or_default())
+    }
+
+    /* analyzer options */
+
+    /// Writes the options applied to the texts of the documents and of the queries before they are analyzed.
+    pub fn put_analyzer_options(&self, wtxn: &mut RwTxn, options: &AnalyzerOptions) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<AnalyzerOptions>>(wtxn, ANALYZER_OPTIONS_KEY, options)
+    }
+
+    pub fn delete_analyzer_options(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, ANALYZER_OPTIONS_KEY)
+    }
+
+    /// Returns the options applied to the texts before they are analyzed, all disabled by default.
+    pub fn analyzer_options(&self, rtxn: &RoTxn) -> heed::Result<AnalyzerOptions> {
+        let options = self.main.get::<_, Str, SerdeJson<AnalyzerOptions>>(rtxn, ANALYZER_OPTIONS_KEY)?;
+        Ok(options.unwrap_or_default())
+    }
+
     /* word documents count */
 
     /// Returns the number of documents ids associated with the given word,
@@ -457,6 +1116,187 @@ impl Index {
         self.word_docids.remap_data_type::<RoaringBitmapLenCodec>().get(rtxn, word)
     }
 
+    /* word docids */
+
+    /// Returns an iterator over the words that start with the given prefix, in lexicographic
+    /// order, and their documents ids, those are only decoded when asked for.
+    pub fn word_docids_iter<'t>(&self, rtxn: &'t RoTxn, prefix: &str) -> heed::Result<WordDocidsIter<'t>> {
+        let db = self.word_docids.remap_data_type::<ByteSlice>();
+        let iter = if prefix.is_empty() {
+            Box::new(db.iter(rtxn)?) as Box<dyn Iterator<Item=_> + 't>
+        } else {
+            Box::new(db.prefix_iter(rtxn, prefix)?)
+        };
+        Ok(WordDocidsIter { iter })
+    }
+
+    /* fuzzy words */
+
+    /// Returns an iterator over the words of the dictionary that are at most `max_typos`
+    /// away from the given word, in lexicographic order, along with their number of typos
+    /// and the number of documents they appear in. A transposition counts as a single typo
+    /// and `max_typos` is capped to two.
+    ///
+    /// The word must be given as it is indexed, i.e. lowercased and normalized.
+    pub fn fuzzy_words<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        word: &str,
+        max_typos: u8,
+    ) -> anyhow::Result<FuzzyWordsIter<'t>>
+    {
+        let fst = self.words_fst(rtxn)?;
+        let dfa = build_dfa(word, max_typos, false);
+        let mut stream = fst.search_with_state(&dfa).into_stream();
+
+        let mut words = Vec::new();
+        while let Some((derived_word, state)) = stream.next() {
+            let derived_word = std::str::from_utf8(derived_word)?;
+            words.push((derived_word.to_string(), dfa.distance(state).to_u8()));
+        }
+
+        Ok(FuzzyWordsIter {
+            rtxn,
+            word_docids: self.word_docids.remap_data_type::<RoaringBitmapLenCodec>(),
+            words: words.into_iter(),
+        })
+    }
+
+    /* document word positions */
+
+    /// Returns the words of the given document along with the positions where they appear,
+    /// a position is the field id multiplied by `ONE_ATTRIBUTE` plus the word index in the field.
+    pub fn document_word_positions<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        docid: DocumentId,
+    ) -> heed::Result<BTreeMap<&'t str, RoaringBitmap>>
+    {
+        let mut words_positions = BTreeMap::new();
+        for result in self.docid_word_positions.prefix_iter(rtxn, &(docid, ""))? {
+            let ((_, word), positions) = result?;
+            words_positions.insert(word, positions);
+        }
+        Ok(words_positions)
+    }
+
+    /* word co-occurrences */
+
+    /// Returns the words that appear close to the given word, at most `limit` of them,
+    /// along with the number of documents where they appear together, the most frequent first.
+    ///
+    /// The words pairs proximities are used to find those words, it is therefore
+    /// empty when the proximity is disabled.
+    pub fn word_cooccurrences<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        word: &str,
+        limit: usize,
+    ) -> heed::Result<Vec<(&'t str, u64)>>
+    {
+        let mut prefix = Vec::with_capacity(word.len() + 1);
+        prefix.extend_from_slice(word.as_bytes());
+        prefix.push(0);
+
+        // The entries are ordered by second word and then by proximity,
+        // we merge the documents ids of the different proximities of a same word.
+        let mut cooccurrences = Vec::new();
+        let mut current: Option<(&str, RoaringBitmap)> = None;
+        let iter = self.word_pair_proximity_docids
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(rtxn, &prefix)?
+            .remap_key_type::<StrStrU8Codec>();
+
+        for result in iter {
+            let ((_, w2, _), docids) = result?;
+            if w2 == word { continue }
+            match current.as_mut() {
+                Some((cw2, cdocids)) if *cw2 == w2 => cdocids.union_with(&docids),
+                _ => {
+                    if let Some((cw2, cdocids)) = current.replace((w2, docids)) {
+                        cooccurrences.push((cw2, cdocids.len()));
+                    }
+                },
+            }
+        }
+
+        if let Some((cw2, cdocids)) = current {
+            cooccurrences.push((cw2, cdocids.len()));
+        }
+
+        cooccurrences.sort_unstable_by(|(wa, ca), (wb, cb)| cb.cmp(ca).then_with(|| wa.cmp(wb)));
+        cooccurrences.truncate(limit);
+
+        Ok(cooccurrences)
+    }
+
+    /* phrases */
+
+    /// Returns the number of documents that contain the given words one after the other,
+    /// the words must be given as they are indexed, i.e. lowercased and normalized.
+    ///
+    /// The documents where the consecutive words of the phrase appear next to each other are
+    /// found with the words pairs proximities, or with the words documents ids when the proximity
+    /// is disabled, then the positions of the words are checked in each of these documents.
+    pub fn phrase_document_count(&self, rtxn: &RoTxn, words: &[&str]) -> heed::Result<u64> {
+        match words {
+            [] => return Ok(0),
+            [word] => return Ok(self.word_documents_count(rtxn, word)?.unwrap_or(0)),
+            _ => (),
+        }
+
+        let proximity_enabled = self.proximity_enabled(rtxn)?;
+        let mut candidates: Option<RoaringBitmap> = None;
+        for pair in words.windows(2) {
+            let docids = if proximity_enabled {
+                self.word_pair_proximity_docids.get(rtxn, &(pair[0], pair[1], 1))?
+            } else {
+                let docids = self.word_docids.get(rtxn, pair[0])?.unwrap_or_default();
+                self.word_docids.get(rtxn, pair[1])?.map(|other| docids & other)
+            };
+
+            let docids = docids.unwrap_or_default();
+            let intersection = match candidates.take() {
+                Some(candidates) => candidates & docids,
+                None => docids,
+            };
+            if intersection.is_empty() {
+                return Ok(0);
+            }
+            candidates = Some(intersection);
+        }
+
+        let candidates = candidates.unwrap_or_default();
+        // Two words with a proximity of one are always next to each other.
+        if proximity_enabled && words.len() == 2 {
+            return Ok(candidates.len());
+        }
+
+        let mut count = 0;
+        for docid in candidates {
+            // The positions where the phrase could start, shifted by the index of each word.
+            let mut starts: Option<RoaringBitmap> = None;
+            for (i, &word) in words.iter().enumerate() {
+                let positions = self.docid_word_positions.get(rtxn, &(docid, word))?.unwrap_or_default();
+                let shifted: RoaringBitmap = positions.iter().filter_map(|p| p.checked_sub(i as u32)).collect();
+                let intersection = match starts.take() {
+                    Some(starts) => starts & shifted,
+                    None => shifted,
+                };
+                starts = Some(intersection);
+                if starts.as_ref().map_or(true, RoaringBitmap::is_empty) {
+                    break;
+                }
+            }
+
+            if starts.map_or(false, |starts| !starts.is_empty()) {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
     /* documents */
 
     /// Returns a [`Vec`] of the requested documents. Returns an error if a document is missing.
@@ -477,10 +1317,43 @@ impl Index {
         Ok(documents)
     }
 
+    /// Returns a random sample of at most `n` documents ordered by document id,
+    /// the same seed always returns the same sample for the same documents.
+    pub fn sample_documents<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        n: usize,
+        seed: u64,
+    ) -> anyhow::Result<Vec<(DocumentId, obkv::KvReader<'t>)>>
+    {
+        let documents_ids = self.documents_ids(rtxn)?;
+        let len = documents_ids.len() as usize;
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut positions = rand::seq::index::sample(&mut rng, len, n.min(len)).into_vec();
+        positions.sort_unstable();
+
+        let mut positions = positions.into_iter().peekable();
+        let ids = documents_ids.into_iter().enumerate().filter_map(|(i, id)| {
+            if positions.peek() == Some(&i) {
+                positions.next();
+                Some(id)
+            } else {
+                None
+            }
+        });
+
+        self.documents(rtxn, ids)
+    }
+
     pub fn facets_distribution<'a>(&'a self, rtxn: &'a RoTxn) -> FacetDistribution<'a> {
         FacetDistribution::new(rtxn, self)
     }
 
+    pub fn facets_stats<'a>(&'a self, rtxn: &'a RoTxn) -> FacetStats<'a> {
+        FacetStats::new(rtxn, self)
+    }
+
     pub fn search<'a>(&'a self, rtxn: &'a RoTxn) -> Search<'a> {
         Search::new(rtxn, self)
     }
@@ -508,6 +1381,7 @@ impl Index {
 
 #[cfg(test)]
 pub(crate) mod tests {
+    use std::iter::FromIterator;
     use std::ops::Deref;
 
     use heed::EnvOpenOptions;
@@ -515,7 +1389,9 @@ pub(crate) mod tests {
     use tempfile::TempDir;
 
     use crate::Index;
-    use crate::update::{IndexDocuments, UpdateFormat};
+    use crate::proximity::ONE_ATTRIBUTE;
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use super::*;
 
     pub(crate) struct TempIndex {
         inner: Index,
@@ -570,4 +1446,323 @@ pub(crate) mod tests {
             "age".to_string() => 1,
         });
     }
+
+    #[test]
+    fn external_to_internal_ids() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": 1, "name": "kevin" },
+            { "id": 2, "name": "bob" },
+            { "id": 3, "name": "kevina" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let external_documents_ids = index.external_documents_ids(&rtxn).unwrap();
+        let kevin = external_documents_ids.get("1").unwrap();
+        let kevina = external_documents_ids.get("3").unwrap();
+
+        assert_eq!(index.external_to_internal_id(&rtxn, "1").unwrap(), Some(kevin));
+        assert_eq!(index.external_to_internal_id(&rtxn, "4").unwrap(), None);
+
+        let docids = index.external_to_internal_ids(&rtxn, &["1", "3", "4"]).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), {
+            let mut expected = vec![kevin, kevina];
+            expected.sort_unstable();
+            expected
+        });
+    }
+
+    #[test]
+    fn word_docids_iter() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": 1, "name": "hello world" },
+            { "id": 2, "name": "hello help" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let words: Vec<_> = index.word_docids_iter(&rtxn, "hel").unwrap()
+            .map(|r| r.map(|(word, docids)| (word, docids.len().unwrap())))
+            .collect::<heed::Result<_>>()
+            .unwrap();
+        assert_eq!(words, vec![("hello", 2), ("help", 1)]);
+
+        let all_words = index.word_docids_iter(&rtxn, "").unwrap().count();
+        assert_eq!(all_words, 5);
+
+        let (_, docids) = index.word_docids_iter(&rtxn, "world").unwrap().next().unwrap().unwrap();
+        let expected: RoaringBitmap = Some(0).into_iter().collect();
+        assert_eq!(docids.decode().unwrap(), expected);
+    }
+
+    #[test]
+    fn fuzzy_words() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": 1, "name": "hello world" },
+            { "id": 2, "name": "hello help" },
+            { "id": 3, "name": "hallo yellow" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let words: Vec<_> = index.fuzzy_words(&rtxn, "hello", 1).unwrap()
+            .collect::<heed::Result<_>>()
+            .unwrap();
+        assert_eq!(words, vec![("hallo".to_string(), 1, 1), ("hello".to_string(), 0, 2)]);
+
+        let words: Vec<_> = index.fuzzy_words(&rtxn, "hello", 2).unwrap()
+            .map(|r| r.map(|(word, typos, _)| (word, typos)))
+            .collect::<heed::Result<_>>()
+            .unwrap();
+        let expected = vec![
+            ("hallo".to_string(), 1),
+            ("hello".to_string(), 0),
+            ("help".to_string(), 2),
+            ("yellow".to_string(), 2),
+        ];
+        assert_eq!(words, expected);
+    }
+
+    #[test]
+    fn word_cooccurrences() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": 1, "name": "hello world" },
+            { "id": 2, "name": "hello world again" },
+            { "id": 3, "name": "hello kitty" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let cooccurrences = index.word_cooccurrences(&rtxn, "hello", 2).unwrap();
+        assert_eq!(cooccurrences, vec![("world", 2), ("again", 1)]);
+
+        let cooccurrences = index.word_cooccurrences(&rtxn, "kitty", 10).unwrap();
+        assert_eq!(cooccurrences, vec![("hello", 1)]);
+    }
+
+    #[test]
+    fn phrase_document_count() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": 1, "name": "the quick brown fox" },
+            { "id": 2, "name": "quick brown dogs and a brown fox" },
+            { "id": 3, "name": "a fox. quick and brown" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.phrase_document_count(&rtxn, &["quick", "brown"]).unwrap(), 2);
+        assert_eq!(index.phrase_document_count(&rtxn, &["brown", "fox"]).unwrap(), 2);
+        // The pairs of words are next to each other in the second document but not the phrase.
+        assert_eq!(index.phrase_document_count(&rtxn, &["quick", "brown", "fox"]).unwrap(), 1);
+        assert_eq!(index.phrase_document_count(&rtxn, &["fox"]).unwrap(), 3);
+        // The words separated by a dot are not next to each other.
+        assert_eq!(index.phrase_document_count(&rtxn, &["fox", "quick"]).unwrap(), 0);
+        assert_eq!(index.phrase_document_count(&rtxn, &[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn document_word_positions() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": 1, "title": "hello world", "description": "hello again" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let title = fields_ids_map.id("title").unwrap() as u32 * ONE_ATTRIBUTE;
+        let description = fields_ids_map.id("description").unwrap() as u32 * ONE_ATTRIBUTE;
+
+        let positions = index.document_word_positions(&rtxn, 0).unwrap();
+        let words: Vec<_> = positions.keys().copied().collect();
+        assert_eq!(words, vec!["1", "again", "hello", "world"]);
+        assert_eq!(positions["hello"], RoaringBitmap::from_iter(vec![title, description]));
+        assert_eq!(positions["world"], RoaringBitmap::from_iter(vec![title + 1]));
+        assert_eq!(positions["again"], RoaringBitmap::from_iter(vec![description + 1]));
+    }
+
+    #[test]
+    fn facet_levels() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "age".into() => "number".into(),
+            "name".into() => "string".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+
+        let documents: Vec<_> = (0..100)
+            .map(|i| serde_json::json!({ "id": i, "name": format!("name{}", i), "age": i }))
+            .collect();
+        let content = serde_json::to_vec(&documents).unwrap();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(&content[..], |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+
+        let age = fields_ids_map.id("age").unwrap();
+        let levels = index.facet_levels(&rtxn, age).unwrap();
+        assert!(levels.len() > 1);
+        assert_eq!(levels[0].level, 0);
+        assert_eq!(levels[0].entries, 100);
+        assert_eq!(levels[0].max_documents, 1);
+        for pair in levels.windows(2) {
+            assert_eq!(pair[1].level, pair[0].level + 1);
+            assert!(pair[1].entries < pair[0].entries);
+            assert_eq!(pair[1].average_group_size, pair[0].entries as f64 / pair[1].entries as f64);
+        }
+
+        // The string facets don't have levels.
+        let name = fields_ids_map.id("name").unwrap();
+        assert!(index.facet_levels(&rtxn, name).unwrap().is_empty());
+    }
+
+    #[test]
+    fn update_committed_hooks() {
+        use std::sync::{Arc, Mutex};
+        use crate::update::{DeleteDocuments, Settings};
+
+        let index = TempIndex::new();
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let cloned_outcomes = outcomes.clone();
+        index.on_update_committed(move |outcome| cloned_outcomes.lock().unwrap().push(outcome.clone()));
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n1,kevin\n2,bob\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        index.commit_update(wtxn).unwrap();
+
+        // The outcome of an aborted transaction is never given to the hooks.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n3,kevina\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.abort().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index, 2).unwrap();
+        let kevin = builder.delete_external_id("1").unwrap();
+        builder.delete_document(42);
+        builder.execute().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.set_displayed_fields(vec!["name".into()]);
+        builder.execute(|_, _| ()).unwrap();
+        index.commit_update(wtxn).unwrap();
+
+        let outcomes = outcomes.lock().unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].update_id, 0);
+        assert_eq!(outcomes[0].added_documents, RoaringBitmap::from_iter(vec![0, 1]));
+        assert_eq!(outcomes[1].update_id, 2);
+        assert_eq!(outcomes[1].removed_documents, RoaringBitmap::from_iter(vec![kevin]));
+        assert!(outcomes[1].settings.as_ref().unwrap().displayed_fields);
+    }
+
+    #[test]
+    fn sample_documents() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut content = String::from("id,name\n");
+        for i in 0..100 {
+            content.push_str(&format!("{},name{}\n", i, i));
+        }
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let ids = |sample: Vec<(DocumentId, obkv::KvReader)>| sample.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+        let sample = ids(index.sample_documents(&rtxn, 10, 42).unwrap());
+        assert_eq!(sample.len(), 10);
+        assert!(sample.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(sample, ids(index.sample_documents(&rtxn, 10, 42).unwrap()));
+        assert_ne!(sample, ids(index.sample_documents(&rtxn, 10, 43).unwrap()));
+
+        // Asking for more documents than available returns all of them.
+        assert_eq!(ids(index.sample_documents(&rtxn, 1000, 42).unwrap()), (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn writer_lock() {
+        use std::fs;
+        use crate::{WriterLockError, WRITER_LOCK_FILE_NAME};
+
+        let index = TempIndex::new();
+        let lock_path = index.path().join(WRITER_LOCK_FILE_NAME);
+
+        // The copies of an index share the same lock.
+        let cloned_index = index.clone();
+        let mut wtxn = index.try_write_txn().unwrap();
+        let error = cloned_index.try_write_txn().err().unwrap();
+        assert!(matches!(error.downcast_ref(), Some(WriterLockError::AlreadyWriting)));
+
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_displayed_fields(vec!["name".to_owned()]);
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+        assert!(!lock_path.exists());
+
+        // Another process holds the lock file.
+        index.set_writer_lock_file(true);
+        fs::write(&lock_path, "42\n").unwrap();
+        let error = index.try_write_txn().err().unwrap();
+        match error.downcast_ref() {
+            Some(WriterLockError::LockFileExists { owner, .. }) => assert_eq!(owner.as_deref(), Some("42")),
+            _ => panic!("unexpected error {}", error),
+        }
+
+        // The lock file is held until the transaction is aborted.
+        fs::remove_file(&lock_path).unwrap();
+        let wtxn = index.try_write_txn().unwrap();
+        assert!(lock_path.exists());
+        wtxn.abort().unwrap();
+        assert!(!lock_path.exists());
+        assert!(index.try_write_txn().is_ok());
+    }
 }
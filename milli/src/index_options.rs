@@ -0,0 +1,176 @@
+use std::path::Path;
+
+use anyhow::ensure;
+use heed::EnvOpenOptions;
+use heed::flags::Flags;
+
+use crate::Index;
+
+/// The default maximum number of simultaneous read transactions of LMDB.
+const DEFAULT_MAX_READERS: u32 = 126;
+const DEFAULT_MAP_SIZE: usize = 100 * 1024 * 1024 * 1024; // 100 GiB
+
+/// The options used to open the LMDB environment of an index.
+///
+/// The durability flags trade the safety of the committed updates for a faster indexing,
+/// they are only worth it for indexes that can be rebuilt from their original documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexOptions {
+    map_size: usize,
+    max_readers: u32,
+    no_sync: bool,
+    no_meta_sync: bool,
+    read_ahead: bool,
+}
+
+impl Default for IndexOptions {
+    fn default() -> IndexOptions {
+        IndexOptions {
+            map_size: DEFAULT_MAP_SIZE,
+            max_readers: DEFAULT_MAX_READERS,
+            no_sync: false,
+            no_meta_sync: false,
+            read_ahead: true,
+        }
+    }
+}
+
+impl IndexOptions {
+    pub fn new() -> IndexOptions {
+        IndexOptions::default()
+    }
+
+    /// The maximum size the index can take on disk, it is rounded up to a multiple of the OS page size.
+    /// It is only reserved in the virtual address space, specifying the whole disk space is fine.
+    /// default value if not called: 100 GiB
+    pub fn map_size(&mut self, size: usize) -> &mut Self {
+        self.map_size = size;
+        self
+    }
+
+    /// The maximum number of read transactions that can be opened at the same time,
+    /// every thread that searches the index holds one while it reads.
+    /// default value if not called: `126`
+    pub fn max_readers(&mut self, readers: u32) -> &mut Self {
+        self.max_readers = readers;
+        self
+    }
+
+    /// When `true`, the system buffers are not flushed to disk when an update is committed,
+    /// the updates are faster but a system crash can lose the last updates or corrupt the index.
+    /// A crash of the process alone doesn't lose anything.
+    /// default value if not called: `false`
+    pub fn no_sync(&mut self, no_sync: bool) -> &mut Self {
+        self.no_sync = no_sync;
+        self
+    }
+
+    /// When `true`, the metadata are not flushed to disk when an update is committed, a system
+    /// crash can undo the last update but the index is never corrupted. It is a cheaper
+    /// alternative to `no_sync` and is useless when `no_sync` is enabled.
+    /// default value if not called: `false`
+    pub fn no_meta_sync(&mut self, no_meta_sync: bool) -> &mut Self {
+        self.no_meta_sync = no_meta_sync;
+        self
+    }
+
+    /// When `false`, the OS is asked to not read ahead the pages of the index, it can help
+    /// the random reads of the searches when the index is larger than the available memory.
+    /// default value if not called: `true`
+    pub fn read_ahead(&mut self, read_ahead: bool) -> &mut Self {
+        self.read_ahead = read_ahead;
+        self
+    }
+
+    /// Returns an error if the options can't be used to open an index.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(self.map_size != 0, "the map size of an index can't be zero");
+        ensure!(self.max_readers != 0, "the maximum number of readers of an index can't be zero");
+        Ok(())
+    }
+
+    /// Validates the options and converts them into the LMDB environment options.
+    pub fn env_options(&self) -> anyhow::Result<EnvOpenOptions> {
+        self.validate()?;
+
+        let mut options = EnvOpenOptions::new();
+        options.map_size(round_up_to_page_size(self.map_size, page_size::get()));
+        options.max_readers(self.max_readers);
+        // Safety: these flags only change the durability and the
+        // reading strategy of the environment, not the layout of the data.
+        unsafe {
+            if self.no_sync {
+                options.flag(Flags::MdbNoSync);
+            }
+            if self.no_meta_sync {
+                options.flag(Flags::MdbNoMetaSync);
+            }
+            if !self.read_ahead {
+                options.flag(Flags::MdbNoRdAhead);
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Validates the options and opens, or creates, the index at the given path.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<Index> {
+        Index::new(self.env_options()?, path)
+    }
+}
+
+/// LMDB requires the map size to be a multiple of the OS page size, the sizes
+/// too large to be rounded up are rounded down to the last multiple instead.
+fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
+    let size = size.checked_add(page_size - 1).unwrap_or(size);
+    size / page_size * page_size
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::update::{IndexDocuments, UpdateFormat};
+    use super::*;
+
+    #[test]
+    fn invalid_options() {
+        let path = tempfile::tempdir().unwrap();
+        assert!(IndexOptions::new().map_size(0).open(&path).is_err());
+        assert!(IndexOptions::new().max_readers(0).open(&path).is_err());
+    }
+
+    #[test]
+    fn map_size_rounded_up_to_page_size() {
+        assert_eq!(round_up_to_page_size(1, 4096), 4096);
+        assert_eq!(round_up_to_page_size(4096, 4096), 4096);
+        assert_eq!(round_up_to_page_size(10_000_000_000, 4096), 10_000_003_072);
+        assert_eq!(round_up_to_page_size(usize::MAX, 4096), usize::MAX / 4096 * 4096);
+
+        // 10 MB is not a multiple of the page size.
+        let path = tempfile::tempdir().unwrap();
+        let index = IndexOptions::new().map_size(10 * 1000 * 1000).open(&path).unwrap();
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 0);
+    }
+
+    #[test]
+    fn open_without_sync() {
+        let path = tempfile::tempdir().unwrap();
+        let index = IndexOptions::new()
+            .map_size(10 * 1024 * 1024) // 10 MB
+            .max_readers(10)
+            .no_sync(true)
+            .read_ahead(false)
+            .open(&path)
+            .unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,kevin\n1,kevina\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 2);
+    }
+}
@@ -1,10 +1,17 @@
 #[macro_use] extern crate pest_derive;
 
+mod analyzer_options;
 mod criterion;
+mod export;
 mod external_documents_ids;
 mod fields_ids_map;
+mod geo;
+mod index_options;
+mod locale;
+mod postings;
 mod search;
 mod update_store;
+mod writer_lock;
 pub mod facet;
 pub mod heed_codec;
 pub mod index;
@@ -20,16 +27,26 @@ use anyhow::Context;
 use fxhash::{FxHasher32, FxHasher64};
 use serde_json::{Map, Value};
 
-pub use self::criterion::{Criterion, default_criteria};
+pub use self::analyzer_options::{AnalyzerOptions, SymbolsPolicy};
+pub use self::criterion::{AscDesc, AttributeRanking, Criterion, Member, default_criteria, validate_criteria};
+pub use self::export::{DatabaseName, DatabaseSize, ExportFormat};
 pub use self::external_documents_ids::ExternalDocumentsIds;
 pub use self::fields_ids_map::FieldsIdsMap;
-pub use self::heed_codec::{BEU32StrCodec, StrStrU8Codec, StrLevelPositionCodec, ObkvCodec};
+pub use self::geo::{GeoPoint, GEO_FIELD_NAME, distance_between_two_points, lat_lng_to_xyz};
+pub use self::heed_codec::{BEU32StrCodec, FieldIdWordCodec, FieldIdWordCountCodec, StrStrU8Codec, StrLevelPositionCodec, ObkvCodec};
 pub use self::heed_codec::{RoaringBitmapCodec, BoRoaringBitmapCodec, CboRoaringBitmapCodec};
 pub use self::heed_codec::{RoaringBitmapLenCodec, BoRoaringBitmapLenCodec, CboRoaringBitmapLenCodec};
-pub use self::index::Index;
-pub use self::search::{Search, FacetDistribution, FacetCondition, SearchResult, MatchingWords};
+pub use self::heed_codec::{RoaringBitmapFormat, TaggedRoaringBitmapCodec, TaggedRoaringBitmapLenCodec};
+pub use self::index::{Index, DEFAULT_MIN_WORD_SIZE_FOR_ONE_TYPO, DEFAULT_MIN_WORD_SIZE_FOR_TWO_TYPOS};
+pub use self::index_options::IndexOptions;
+pub use self::locale::{InvalidLocale, Locale};
+pub use self::postings::{FuzzyWordsIter, LazyDocids, WordDocidsIter};
+pub use self::search::{Search, FacetDistribution, FacetCondition, FacetValuesOrder, SearchResult, SearchStream, MatchingWords};
+pub use self::search::{execute_searches, FacetNumberStats, FacetStats, QueryToken, SearchQuery, TermsMatchingStrategy};
+pub use self::search::{CriterionTrace, FacetValueHit, FilterExplanation, RankCorrelation, SearchForFacetValues, ShadowSearchResult};
 pub use self::tree_level::TreeLevel;
-pub use self::update_store::UpdateStore;
+pub use self::update_store::{Checksummed, PayloadChecksum, UpdatePriority, UpdateStore, payload_checksum};
+pub use self::writer_lock::{LockedRwTxn, WriterLockError, WRITER_LOCK_FILE_NAME};
 
 pub type FastMap4<K, V> = HashMap<K, V, BuildHasherDefault<FxHasher32>>;
 pub type FastMap8<K, V> = HashMap<K, V, BuildHasherDefault<FxHasher64>>;
@@ -114,6 +131,32 @@ pub fn json_to_string(value: &Value) -> Option<String> {
     }
 }
 
+/// The maximum size of an LMDB key.
+pub(crate) const MAX_LMDB_KEY_LENGTH: usize = 511;
+/// The maximum length of an indexed word, small enough for the keys composed of two words
+/// and some bytes, like the word pair proximity keys, to fit in an LMDB key.
+pub(crate) const MAX_WORD_LENGTH: usize = MAX_LMDB_KEY_LENGTH / 2 - 5;
+/// The length of the hexadecimal hash suffix of the truncated words.
+const WORD_HASH_SUFFIX_LENGTH: usize = 16;
+
+/// Returns the word as is if it isn't longer than `MAX_WORD_LENGTH`, otherwise truncates it
+/// and suffixes it by a hash of the whole word, this way two long words stay distinct.
+///
+/// The long query words are truncated the same way to match the truncated indexed words.
+pub(crate) fn truncate_long_word(word: &str) -> Cow<str> {
+    if word.len() <= MAX_WORD_LENGTH {
+        return Cow::Borrowed(word);
+    }
+
+    // We truncate the word on a char boundary to keep a valid UTF-8 string.
+    let mut end = MAX_WORD_LENGTH - WORD_HASH_SUFFIX_LENGTH;
+    while !word.is_char_boundary(end) {
+        end -= 1;
+    }
+    let hash = fxhash::hash64(word);
+    Cow::Owned(format!("{}{:016x}", &word[..end], hash))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,174 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+static FRENCH_ELISION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:qu|[cdjlmnst])['’](\p{L})").unwrap()
+});
+
+static ITALIAN_ELISION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:dall|dell|nell|sull|all|un|[cdlm])['’](\p{L})").unwrap()
+});
+
+// The stop words lists are sorted to be binary searched,
+// they only contain the most common function words of each language.
+const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "is", "it",
+    "of", "on", "or", "that", "the", "this", "to", "was", "with",
+];
+const FRENCH_STOP_WORDS: &[&str] = &[
+    "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "en", "est", "et", "la", "le",
+    "les", "par", "pour", "que", "qui", "sur", "un", "une",
+];
+const GERMAN_STOP_WORDS: &[&str] = &[
+    "auf", "das", "dem", "den", "der", "die", "ein", "eine", "einen", "im", "in", "ist", "mit",
+    "und", "von", "zu", "zum", "zur",
+];
+const ITALIAN_STOP_WORDS: &[&str] = &[
+    "a", "al", "alla", "con", "da", "dei", "del", "della", "di", "e", "gli", "i", "il", "in",
+    "la", "le", "lo", "per", "su", "un", "una", "uno",
+];
+const SPANISH_STOP_WORDS: &[&str] = &[
+    "a", "al", "con", "de", "del", "el", "en", "es", "la", "las", "los", "o", "para", "por",
+    "que", "se", "un", "una", "y",
+];
+const PORTUGUESE_STOP_WORDS: &[&str] = &[
+    "a", "as", "com", "da", "das", "de", "do", "dos", "e", "em", "na", "no", "o", "os", "para",
+    "por", "que", "um", "uma",
+];
+const DUTCH_STOP_WORDS: &[&str] = &[
+    "de", "een", "en", "het", "in", "is", "met", "op", "te", "van", "voor",
+];
+
+/// The language of a search query, it selects the stop words
+/// and the normalization rules that are applied to the query.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Locale {
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "fr")]
+    French,
+    #[serde(rename = "de")]
+    German,
+    #[serde(rename = "it")]
+    Italian,
+    #[serde(rename = "es")]
+    Spanish,
+    #[serde(rename = "pt")]
+    Portuguese,
+    #[serde(rename = "nl")]
+    Dutch,
+}
+
+impl Locale {
+    /// Returns the ISO 639-1 code of the language of this locale.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::French => "fr",
+            Locale::German => "de",
+            Locale::Italian => "it",
+            Locale::Spanish => "es",
+            Locale::Portuguese => "pt",
+            Locale::Dutch => "nl",
+        }
+    }
+
+    fn stop_words(&self) -> &'static [&'static str] {
+        match self {
+            Locale::English => ENGLISH_STOP_WORDS,
+            Locale::French => FRENCH_STOP_WORDS,
+            Locale::German => GERMAN_STOP_WORDS,
+            Locale::Italian => ITALIAN_STOP_WORDS,
+            Locale::Spanish => SPANISH_STOP_WORDS,
+            Locale::Portuguese => PORTUGUESE_STOP_WORDS,
+            Locale::Dutch => DUTCH_STOP_WORDS,
+        }
+    }
+
+    /// Returns `true` if the given lowercased word is a stop word of this locale.
+    pub fn is_stop_word(&self, word: &str) -> bool {
+        self.stop_words().binary_search(&word).is_ok()
+    }
+
+    /// Applies the normalization rules of this locale to the query before it is analyzed,
+    /// the elided articles and pronouns of French and Italian are removed, e.g. "l'avion".
+    pub fn normalize<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        match self {
+            Locale::French => FRENCH_ELISION_REGEX.replace_all(query, "${1}"),
+            Locale::Italian => ITALIAN_ELISION_REGEX.replace_all(query, "${1}"),
+            _ => Cow::Borrowed(query),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = InvalidLocale;
+
+    /// Parses a language code, the region of a language tag is ignored, e.g. `fr-CA` is French.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s.trim().split(|c| c == '-' || c == '_').next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "en" => Ok(Locale::English),
+            "fr" => Ok(Locale::French),
+            "de" => Ok(Locale::German),
+            "it" => Ok(Locale::Italian),
+            "es" => Ok(Locale::Spanish),
+            "pt" => Ok(Locale::Portuguese),
+            "nl" => Ok(Locale::Dutch),
+            _ => Err(InvalidLocale(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InvalidLocale(String);
+
+impl fmt::Display for InvalidLocale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid locale: {:?}, it must be one of en, fr, de, it, es, pt or nl", self.0)
+    }
+}
+
+impl Error for InvalidLocale { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop_words_are_sorted() {
+        for locale in &[
+            Locale::English, Locale::French, Locale::German, Locale::Italian,
+            Locale::Spanish, Locale::Portuguese, Locale::Dutch,
+        ] {
+            let words = locale.stop_words();
+            assert!(words.windows(2).all(|w| w[0] < w[1]), "{} stop words aren't sorted", locale);
+        }
+    }
+
+    #[test]
+    fn parse_and_normalize() {
+        assert_eq!("fr-CA".parse::<Locale>().unwrap(), Locale::French);
+        assert_eq!("EN".parse::<Locale>().unwrap(), Locale::English);
+        assert!("xx".parse::<Locale>().is_err());
+
+        assert_eq!(Locale::French.normalize("l'avion d’Air France"), "avion Air France");
+        assert_eq!(Locale::Italian.normalize("dell'arte"), "arte");
+        assert_eq!(Locale::English.normalize("don't"), "don't");
+
+        assert!(Locale::French.is_stop_word("les"));
+        assert!(!Locale::English.is_stop_word("les"));
+    }
+}
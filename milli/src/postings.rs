@@ -0,0 +1,64 @@
+use roaring::RoaringBitmap;
+use heed::{BytesDecode, Database, RoTxn};
+use heed::types::Str;
+
+use crate::{RoaringBitmapCodec, RoaringBitmapLenCodec};
+
+/// An iterator over the words of the index and their lazily decoded documents ids.
+pub struct WordDocidsIter<'t> {
+    pub(crate) iter: Box<dyn Iterator<Item=heed::Result<(&'t str, &'t [u8])>> + 't>,
+}
+
+impl<'t> Iterator for WordDocidsIter<'t> {
+    type Item = heed::Result<(&'t str, LazyDocids<'t>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|result| result.map(|(word, bytes)| (word, LazyDocids { bytes })))
+    }
+}
+
+/// The documents ids of a word that are only decoded when asked for.
+#[derive(Debug, Clone, Copy)]
+pub struct LazyDocids<'t> {
+    bytes: &'t [u8],
+}
+
+impl LazyDocids<'_> {
+    /// Decodes the documents ids.
+    pub fn decode(&self) -> heed::Result<RoaringBitmap> {
+        RoaringBitmapCodec::bytes_decode(self.bytes).ok_or(heed::Error::Decoding)
+    }
+
+    /// Returns the number of documents ids without decoding the whole bitmap.
+    pub fn len(&self) -> heed::Result<u64> {
+        RoaringBitmapLenCodec::bytes_decode(self.bytes).ok_or(heed::Error::Decoding)
+    }
+
+    pub fn is_empty(&self) -> heed::Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+}
+
+/// An iterator over the words of the dictionary that match a word with some typos,
+/// along with the number of typos and the number of documents they appear in.
+pub struct FuzzyWordsIter<'t> {
+    pub(crate) rtxn: &'t RoTxn<'t>,
+    pub(crate) word_docids: Database<Str, RoaringBitmapLenCodec>,
+    pub(crate) words: std::vec::IntoIter<(String, u8)>,
+}
+
+impl<'t> Iterator for FuzzyWordsIter<'t> {
+    type Item = heed::Result<(String, u8, u64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (word, typos) = self.words.next()?;
+        match self.word_docids.get(self.rtxn, &word) {
+            Ok(count) => Some(Ok((word, typos, count.unwrap_or(0)))),
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.words.size_hint()
+    }
+}
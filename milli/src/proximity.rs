@@ -1,7 +1,8 @@
 use std::cmp;
 use crate::{Attribute, Position};
 
-const ONE_ATTRIBUTE: u32 = 1000;
+/// The number of positions reserved for each attribute in an absolute position.
+pub const ONE_ATTRIBUTE: u32 = 1000;
 const MAX_DISTANCE: u32 = 8;
 
 pub fn index_proximity(lhs: u32, rhs: u32) -> u32 {
@@ -1,14 +1,16 @@
 use std::collections::HashMap;
 use std::mem::take;
+use std::ops::Bound;
 
-use anyhow::{bail, Context as _};
+use anyhow::Context as _;
+use heed::types::ByteSlice;
 use itertools::Itertools;
 use log::debug;
 use ordered_float::OrderedFloat;
 use roaring::RoaringBitmap;
 
 use crate::facet::FacetType;
-use crate::heed_codec::facet::FieldDocIdFacetF64Codec;
+use crate::heed_codec::facet::{FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec};
 use crate::search::criteria::{resolve_query_tree, CriteriaBuilder};
 use crate::search::facet::FacetIter;
 use crate::search::query_tree::Operation;
@@ -64,8 +66,8 @@ impl<'t> AscDesc<'t> {
     ) -> anyhow::Result<Self>
     {
         let fields_ids_map = index.fields_ids_map(rtxn)?;
-        let faceted_fields = index.faceted_fields(rtxn)?;
-        let (field_id, facet_type) = field_id_facet_type(&fields_ids_map, &faceted_fields, &field_name)?;
+        let sortable_fields = index.indexed_facet_fields(rtxn)?;
+        let (field_id, facet_type) = field_id_facet_type(&fields_ids_map, &sortable_fields, &field_name)?;
 
         Ok(AscDesc {
             index,
@@ -98,17 +100,12 @@ impl<'t> Criterion for AscDesc<'t> {
                             let candidates_is_some = candidates.is_some();
                             self.query_tree = query_tree;
                             let candidates = match (&self.query_tree, candidates) {
-                                (_, Some(mut candidates)) => {
-                                    candidates.intersect_with(&self.faceted_candidates);
-                                    candidates
-                                },
+                                (_, Some(candidates)) => candidates,
                                 (Some(qt), None) => {
                                     let context = CriteriaBuilder::new(&self.rtxn, &self.index)?;
-                                    let mut candidates = resolve_query_tree(&context, qt, &mut HashMap::new(), wdcache)?;
-                                    candidates.intersect_with(&self.faceted_candidates);
-                                    candidates
+                                    resolve_query_tree(&context, qt, &mut HashMap::new(), wdcache)?
                                 },
-                                (None, None) => take(&mut self.faceted_candidates),
+                                (None, None) => self.index.documents_ids(self.rtxn)?,
                             };
 
                             // If our parent returns candidates it means that the bucket
@@ -126,7 +123,11 @@ impl<'t> Criterion for AscDesc<'t> {
                                 continue;
                             }
 
-                            self.candidates = facet_ordered(
+                            // The documents without any value for the field are always returned
+                            // in a last bucket, whether the documents are sorted in ascending order or not.
+                            let missing = &candidates - &self.faceted_candidates;
+                            let candidates = candidates - &missing;
+                            let ordered = facet_ordered(
                                 self.index,
                                 self.rtxn,
                                 self.field_id,
@@ -134,6 +135,8 @@ impl<'t> Criterion for AscDesc<'t> {
                                 self.ascending,
                                 candidates,
                             )?;
+                            let missing = Some(missing).filter(|m| !m.is_empty()).map(Ok);
+                            self.candidates = Box::new(ordered.chain(missing));
                         },
                         None => return Ok(None),
                     }
@@ -152,15 +155,15 @@ impl<'t> Criterion for AscDesc<'t> {
 
 fn field_id_facet_type(
     fields_ids_map: &FieldsIdsMap,
-    faceted_fields: &HashMap<String, FacetType>,
+    sortable_fields: &HashMap<String, FacetType>,
     field: &str,
 ) -> anyhow::Result<(FieldId, FacetType)>
 {
     let id = fields_ids_map.id(field).with_context(|| {
         format!("field {:?} isn't registered", field)
     })?;
-    let facet_type = faceted_fields.get(field).with_context(|| {
-        format!("field {:?} isn't faceted", field)
+    let facet_type = sortable_fields.get(field).with_context(|| {
+        format!("field {:?} isn't sortable", field)
     })?;
     Ok((id, *facet_type))
 }
@@ -179,7 +182,7 @@ fn facet_ordered<'t>(
 ) -> anyhow::Result<Box<dyn Iterator<Item = heed::Result<RoaringBitmap>> + 't>>
 {
     match facet_type {
-        FacetType::Number => {
+        FacetType::Number | FacetType::Date => {
             if candidates.len() <= CANDIDATES_THRESHOLD {
                 let iter = iterative_facet_ordered_iter(
                     index, rtxn, field_id, ascending, candidates,
@@ -195,10 +198,62 @@ fn facet_ordered<'t>(
                 Ok(Box::new(iter.map(|res| res.map(|(_, docids)| docids))))
             }
         },
-        FacetType::String => bail!("criteria facet type must be a number"),
+        FacetType::String | FacetType::Hierarchy => {
+            if candidates.len() <= CANDIDATES_THRESHOLD {
+                let iter = iterative_facet_string_ordered_iter(
+                    index, rtxn, field_id, ascending, candidates,
+                )?;
+                Ok(Box::new(iter.map(Ok)))
+            } else {
+                facet_string_ordered_iter(index, rtxn, field_id, ascending, candidates)
+            }
+        },
     }
 }
 
+/// Walks the string values of the field in lexicographic order, the documents are returned
+/// with their lowest value in ascending order and with their highest one in descending order.
+///
+/// This function is fast when the amount of candidates to rank is large.
+fn facet_string_ordered_iter<'t>(
+    index: &'t Index,
+    rtxn: &'t heed::RoTxn,
+    field_id: FieldId,
+    ascending: bool,
+    candidates: RoaringBitmap,
+) -> anyhow::Result<Box<dyn Iterator<Item = heed::Result<RoaringBitmap>> + 't>>
+{
+    // The string values of a field are all the entries prefixed by its id.
+    let db = index.facet_string_values_docids().remap_key_type::<ByteSlice>();
+    let start = [field_id];
+    let end = field_id.checked_add(1).map(|id| [id]);
+    let range = (
+        Bound::Included(&start[..]),
+        end.as_ref().map_or(Bound::Unbounded, |end| Bound::Excluded(&end[..])),
+    );
+
+    let iter = if ascending {
+        Box::new(db.range(rtxn, &range)?) as Box<dyn Iterator<Item = _>>
+    } else {
+        Box::new(db.rev_range(rtxn, &range)?)
+    };
+
+    let iter = iter
+        .scan(candidates, |remaining, result| {
+            if remaining.is_empty() {
+                return None;
+            }
+            Some(result.map(|(_, (_original, mut docids))| {
+                docids.intersect_with(remaining);
+                remaining.difference_with(&docids);
+                docids
+            }))
+        })
+        .filter(|result| result.as_ref().map_or(true, |docids| !docids.is_empty()));
+
+    Ok(Box::new(iter))
+}
+
 /// Fetch the whole list of candidates facet values one by one and order them by it.
 ///
 /// This function is fast when the amount of candidates to rank is small.
@@ -239,3 +294,126 @@ fn iterative_facet_ordered_iter<'t>(
 
     Ok(vec.into_iter())
 }
+
+/// Fetch the lowest, or highest, string value of the candidates one by one and order them by it.
+///
+/// This function is fast when the amount of candidates to rank is small.
+fn iterative_facet_string_ordered_iter<'t>(
+    index: &'t Index,
+    rtxn: &'t heed::RoTxn,
+    field_id: FieldId,
+    ascending: bool,
+    candidates: RoaringBitmap,
+) -> anyhow::Result<impl Iterator<Item = RoaringBitmap> + 't>
+{
+    let db = index.field_id_docid_facet_values.remap_key_type::<FieldDocIdFacetStringCodec>();
+    let mut docids_values = Vec::with_capacity(candidates.len() as usize);
+    for docid in candidates.iter() {
+        let mut iter = db.prefix_iter(rtxn, &(field_id, docid, ""))?;
+        let entry = if ascending { iter.next() } else { iter.last() };
+        if let Some(((_, _, value), ())) = entry.transpose()? {
+            docids_values.push((docid, value));
+        }
+    }
+    docids_values.sort_unstable_by_key(|(_, v)| *v);
+    let iter = docids_values.into_iter();
+    let iter = if ascending {
+        Box::new(iter) as Box<dyn Iterator<Item = _>>
+    } else {
+        Box::new(iter.rev())
+    };
+
+    let vec: Vec<_> = iter.group_by(|(_, v)| *v)
+        .into_iter()
+        .map(|(_, ids)| ids.map(|(id, _)| id).collect())
+        .collect();
+
+    Ok(vec.into_iter())
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+    use maplit::hashmap;
+
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use crate::{AscDesc, Index, Member};
+
+    #[test]
+    fn sort_by_multiple_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "price".into() => "number".into(),
+            "rating".into() => "number".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,price,rating\n0,10,3\n1,5,1\n2,10,5\n3,5,4\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn)
+            .sort_by(vec![
+                AscDesc::Asc(Member::Field("price".to_string())),
+                AscDesc::Desc(Member::Field("rating".to_string())),
+            ])
+            .execute()
+            .unwrap();
+
+        let expected: Vec<_> = ["3", "1", "2", "0"].iter()
+            .map(|id| index.external_to_internal_id(&rtxn, id).unwrap().unwrap())
+            .collect();
+        assert_eq!(result.documents_ids, expected);
+    }
+
+    #[test]
+    fn sort_by_string_field() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "name".into() => "string".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"[
+            { \"id\": 0, \"name\": \"Charlie\" },
+            { \"id\": 1, \"name\": \"alice\" },
+            { \"id\": 2 },
+            { \"id\": 3, \"name\": \"bob\" }
+        ]"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let ids = |ids: &[&str]| -> Vec<_> {
+            ids.iter().map(|id| index.external_to_internal_id(&rtxn, id).unwrap().unwrap()).collect()
+        };
+
+        // The document without a name always comes last.
+        let result = index.search(&rtxn)
+            .sort_by(vec![AscDesc::Asc(Member::Field("name".to_string()))])
+            .execute()
+            .unwrap();
+        assert_eq!(result.documents_ids, ids(&["1", "3", "0", "2"]));
+
+        let result = index.search(&rtxn)
+            .sort_by(vec![AscDesc::Desc(Member::Field("name".to_string()))])
+            .execute()
+            .unwrap();
+        assert_eq!(result.documents_ids, ids(&["0", "3", "1", "2"]));
+    }
+}
@@ -5,7 +5,7 @@ use std::mem::take;
 
 use roaring::RoaringBitmap;
 
-use crate::{TreeLevel, search::build_dfa};
+use crate::{AttributeRanking, TreeLevel, search::{build_dfa, derivation_typos}};
 use crate::search::criteria::Query;
 use crate::search::query_tree::{Operation, QueryKind};
 use crate::search::{word_derivations, WordDerivationsCache};
@@ -28,10 +28,11 @@ pub struct Attribute<'t> {
     parent: Box<dyn Criterion + 't>,
     flattened_query_tree: Option<Vec<Vec<Vec<Query>>>>,
     current_buckets: Option<btree_map::IntoIter<u64, RoaringBitmap>>,
+    ranking: AttributeRanking,
 }
 
 impl<'t> Attribute<'t> {
-    pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>) -> Self {
+    pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>, ranking: AttributeRanking) -> Self {
         Attribute {
             ctx,
             query_tree: None,
@@ -40,6 +41,7 @@ impl<'t> Attribute<'t> {
             parent,
             flattened_query_tree: None,
             current_buckets: None,
+            ranking,
         }
     }
 }
@@ -61,11 +63,23 @@ impl<'t> Criterion for Attribute<'t> {
                         flatten_query_tree(&qt)
                     });
 
-                    let found_candidates = if candidates.len() < 1000 {
+                    let exhaustive = match self.ranking {
+                        AttributeRanking::Auto => candidates.len() < 1000,
+                        AttributeRanking::Exhaustive => true,
+                        AttributeRanking::Approximate => false,
+                    };
+
+                    let found_candidates = if exhaustive {
                         let current_buckets = match self.current_buckets.as_mut() {
                             Some(current_buckets) => current_buckets,
                             None => {
-                                let new_buckets = linear_compute_candidates(self.ctx, flattened_query_tree, candidates)?;
+                                let first_letter_typo_cost = wdcache.first_letter_typo_cost();
+                                let new_buckets = linear_compute_candidates(
+                                    self.ctx,
+                                    flattened_query_tree,
+                                    candidates,
+                                    first_letter_typo_cost,
+                                )?;
                                 self.current_buckets.get_or_insert(new_buckets.into_iter())
                             },
                         };
@@ -562,9 +576,15 @@ fn linear_compute_candidates(
     ctx: &dyn Context,
     branches: &Vec<Vec<Vec<Query>>>,
     allowed_candidates: &RoaringBitmap,
+    first_letter_typo_cost: u8,
 ) -> anyhow::Result<BTreeMap<u64, RoaringBitmap>>
 {
-    fn compute_candidate_rank(branches: &Vec<Vec<Vec<Query>>>, words_positions: HashMap<String, RoaringBitmap>) -> u64 {
+    fn compute_candidate_rank(
+        branches: &Vec<Vec<Vec<Query>>>,
+        words_positions: HashMap<String, RoaringBitmap>,
+        first_letter_typo_cost: u8,
+    ) -> u64
+    {
         let mut min_rank = u64::max_value();
         for branch in branches {
 
@@ -577,7 +597,7 @@ fn linear_compute_candidates(
                     let current_position = match kind {
                         QueryKind::Exact { word, .. } => {
                             if *prefix {
-                                word_derivations(word, true, 0, &words_positions)
+                                word_derivations(word, true, 0, first_letter_typo_cost, &words_positions)
                                     .flat_map(|positions| positions.iter().next()).min()
                             } else {
                                 words_positions.get(word)
@@ -586,7 +606,7 @@ fn linear_compute_candidates(
                             }
                         },
                         QueryKind::Tolerant { typo, word } => {
-                            word_derivations(word, *prefix, *typo, &words_positions)
+                            word_derivations(word, *prefix, *typo, first_letter_typo_cost, &words_positions)
                                 .flat_map(|positions| positions.iter().next()).min()
                         },
                     };
@@ -622,9 +642,10 @@ fn linear_compute_candidates(
     }
 
     fn word_derivations<'a>(
-        word: &str,
+        word: &'a str,
         is_prefix: bool,
         max_typo: u8,
+        first_letter_typo_cost: u8,
         words_positions: &'a HashMap<String, RoaringBitmap>,
     ) -> impl Iterator<Item = &'a RoaringBitmap>
     {
@@ -632,7 +653,10 @@ fn linear_compute_candidates(
         words_positions.iter().filter_map(move |(document_word, positions)| {
             use levenshtein_automata::Distance;
             match dfa.eval(document_word) {
-                Distance::Exact(_) => Some(positions),
+                Distance::Exact(distance) => {
+                    let typos = derivation_typos(word, document_word, distance, first_letter_typo_cost);
+                    if typos <= max_typo { Some(positions) } else { None }
+                },
                 Distance::AtLeast(_) => None,
             }
         })
@@ -641,7 +665,7 @@ fn linear_compute_candidates(
     let mut candidates = BTreeMap::new();
     for docid in allowed_candidates {
         let words_positions = ctx.docid_words_positions(docid)?;
-        let rank = compute_candidate_rank(branches, words_positions);
+        let rank = compute_candidate_rank(branches, words_positions, first_letter_typo_cost);
         candidates.entry(rank).or_insert_with(RoaringBitmap::new).insert(docid);
     }
 
@@ -690,10 +714,49 @@ fn flatten_query_tree(query_tree: &Operation) -> Vec<Vec<Vec<Query>>> {
 #[cfg(test)]
 mod tests {
     use big_s::S;
+    use heed::EnvOpenOptions;
 
     use crate::search::criteria::QueryKind;
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use crate::Index;
     use super::*;
 
+    #[test]
+    fn searchable_attributes_order() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_searchable_fields(vec![S("title"), S("description")]);
+        builder.set_criteria(vec![crate::Criterion::Words, crate::Criterion::Attribute]);
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,title,description\n0,a red car,a fast pony\n1,a fast car,a red pony\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("red").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+        drop(rtxn);
+
+        // The description becomes the most important attribute.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.set_searchable_fields(vec![S("description"), S("title")]);
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("red").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 0]);
+    }
+
     #[test]
     fn simple_flatten_query_tree() {
         let query_tree = Operation::Or(false, vec![
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::mem::take;
+use std::vec;
+
+use log::debug;
+use roaring::RoaringBitmap;
+
+use crate::proximity::ONE_ATTRIBUTE;
+use crate::search::query_tree::{Operation, OriginalWord};
+use crate::search::WordDerivationsCache;
+use crate::{FieldId, Index, TreeLevel};
+use super::{Criterion, CriterionResult, Context, resolve_query_tree};
+
+/// Ranks the documents with an attribute that is exactly the query above the documents
+/// with an attribute that starts with the query words, in order and without typos,
+/// then above the other documents.
+///
+/// The documents of each of these groups that contain the query words exactly as they
+/// were typed, without any case or diacritics normalization, are ranked first.
+///
+/// The attributes are matched with the level 0 of the word level position docids and the
+/// field id word count docids, the exact words with the field id original word docids. The
+/// documents indexed before these databases existed are reindexed by the next documents
+/// addition, until then this criterion doesn't split the buckets.
+pub struct Exactness<'t> {
+    ctx: &'t dyn Context<'t>,
+    index: &'t Index,
+    rtxn: &'t heed::RoTxn<'t>,
+    original_words: Vec<OriginalWord>,
+    /// The normalized query words that are not stop words along with their index in the query,
+    /// the stop words are not indexed but they still shift the positions of the next words.
+    query_words: Vec<(String, u32)>,
+    /// The searchable attributes that are checked for an exact match.
+    attributes: Vec<FieldId>,
+    query_tree: Option<Operation>,
+    buckets: vec::IntoIter<RoaringBitmap>,
+    bucket_candidates: RoaringBitmap,
+    parent: Box<dyn Criterion + 't>,
+}
+
+impl<'t> Exactness<'t> {
+    pub fn new(
+        ctx: &'t dyn Context<'t>,
+        index: &'t Index,
+        rtxn: &'t heed::RoTxn<'t>,
+        parent: Box<dyn Criterion + 't>,
+        original_words: Vec<OriginalWord>,
+        searchable_fields: Option<Vec<FieldId>>,
+    ) -> anyhow::Result<Self>
+    {
+        let searchable_fields = match searchable_fields {
+            Some(fids) => Some(fids),
+            None => index.searchable_fields_ids(rtxn)?,
+        };
+
+        let attributes = match searchable_fields {
+            Some(fids) => fids,
+            None => index.fields_ids_map(rtxn)?.iter().map(|(id, _)| id).collect(),
+        };
+
+        let stop_words = index.stop_words(rtxn)?;
+        let query_words = original_words.iter()
+            .enumerate()
+            .filter(|(_, word)| stop_words.as_ref().map_or(true, |sw| !sw.contains(&word.normalized)))
+            .map(|(i, word)| (word.normalized.clone(), i as u32))
+            .collect();
+
+        Ok(Exactness {
+            ctx,
+            index,
+            rtxn,
+            original_words,
+            query_words,
+            attributes,
+            query_tree: None,
+            buckets: Vec::new().into_iter(),
+            bucket_candidates: RoaringBitmap::new(),
+            parent,
+        })
+    }
+
+    /// Splits the candidates into the ones with an attribute that is exactly the query, the ones
+    /// with an attribute that starts with the query words and the other ones, the empty groups
+    /// are not returned. The words must be indexed as they are normalized in the query, the
+    /// last word of the query is not considered as a prefix here.
+    fn attribute_buckets(&self, candidates: RoaringBitmap) -> anyhow::Result<Vec<RoaringBitmap>> {
+        if self.query_words.is_empty() {
+            return Ok(vec![candidates]);
+        }
+
+        let mut exact_attribute = RoaringBitmap::new();
+        let mut starts_with = RoaringBitmap::new();
+
+        for &fid in &self.attributes {
+            let mut docids = candidates.clone();
+            for (word, index) in &self.query_words {
+                let word_docids = if *index < ONE_ATTRIBUTE {
+                    let position = fid as u32 * ONE_ATTRIBUTE + index;
+                    let key = (word.as_str(), TreeLevel::min_value(), position, position);
+                    self.index.word_level_position_docids.get(self.rtxn, &key)?
+                } else {
+                    None
+                };
+
+                match word_docids {
+                    Some(word_docids) => docids.intersect_with(&word_docids),
+                    None => docids.clear(),
+                }
+
+                if docids.is_empty() { break }
+            }
+
+            if docids.is_empty() { continue }
+
+            // The attributes with more words than the ones that are counted never match exactly.
+            if let Ok(count) = u8::try_from(self.query_words.len()) {
+                if let Some(count_docids) = self.index.field_id_word_count_docids.get(self.rtxn, &(fid, count))? {
+                    let mut exact_docids = docids.clone();
+                    exact_docids.intersect_with(&count_docids);
+                    exact_attribute.union_with(&exact_docids);
+                }
+            }
+
+            starts_with.union_with(&docids);
+        }
+
+        starts_with.difference_with(&exact_attribute);
+        let mut others = candidates;
+        others.difference_with(&exact_attribute);
+        others.difference_with(&starts_with);
+
+        let mut buckets = vec![exact_attribute, starts_with, others];
+        buckets.retain(|docids| !docids.is_empty());
+        Ok(buckets)
+    }
+
+    /// Groups the candidates by the number of query words they contain byte-exactly,
+    /// the groups are returned from the most exact to the least exact one.
+    ///
+    /// The words are looked up in the original words that were stored at indexing time,
+    /// they were tokenized with the stop words and the analyzer options of the index.
+    fn original_words_buckets(&self, candidates: RoaringBitmap) -> anyhow::Result<Vec<RoaringBitmap>> {
+        if self.original_words.is_empty() {
+            return Ok(vec![candidates]);
+        }
+
+        // The bucket at index `n` contains the candidates with `n` exact query words.
+        let mut buckets = vec![RoaringBitmap::new(); self.original_words.len() + 1];
+        buckets[0] = candidates;
+
+        for word in &self.original_words {
+            let docids = self.original_word_docids(word)?;
+            for count in (0..buckets.len() - 1).rev() {
+                let mut exact = buckets[count].clone();
+                exact.intersect_with(&docids);
+                if exact.is_empty() { continue }
+                buckets[count].difference_with(&exact);
+                buckets[count + 1].union_with(&exact);
+            }
+        }
+
+        buckets.reverse();
+        buckets.retain(|docids| !docids.is_empty());
+        Ok(buckets)
+    }
+
+    /// Returns the documents that contain the original word in one of the searchable attributes,
+    /// a prefix word matches all the original words that start with it.
+    fn original_word_docids(&self, word: &OriginalWord) -> anyhow::Result<RoaringBitmap> {
+        let db = self.index.field_id_original_word_docids;
+        let mut docids = RoaringBitmap::new();
+        if word.word.is_empty() {
+            return Ok(docids);
+        }
+
+        for &fid in &self.attributes {
+            let key = (fid, word.word.as_str());
+            if word.prefix {
+                for result in db.prefix_iter(self.rtxn, &key)? {
+                    let (_, word_docids) = result?;
+                    docids.union_with(&word_docids);
+                }
+            } else if let Some(word_docids) = db.get(self.rtxn, &key)? {
+                docids.union_with(&word_docids);
+            }
+        }
+
+        Ok(docids)
+    }
+
+    fn exactness_buckets(&self, candidates: RoaringBitmap) -> anyhow::Result<Vec<RoaringBitmap>> {
+        let mut buckets = Vec::new();
+        for candidates in self.attribute_buckets(candidates)? {
+            buckets.extend(self.original_words_buckets(candidates)?);
+        }
+        Ok(buckets)
+    }
+}
+
+impl<'t> Criterion for Exactness<'t> {
+    #[logging_timer::time("Exactness::{}")]
+    fn next(&mut self, wdcache: &mut WordDerivationsCache) -> anyhow::Result<Option<CriterionResult>> {
+        loop {
+            debug!("Exactness iteration");
+
+            if let Some(candidates) = self.buckets.next() {
+                return Ok(Some(CriterionResult {
+                    query_tree: self.query_tree.clone(),
+                    candidates: Some(candidates),
+                    bucket_candidates: take(&mut self.bucket_candidates),
+                }));
+            }
+
+            match self.parent.next(wdcache)? {
+                Some(CriterionResult { query_tree: Some(query_tree), candidates, bucket_candidates }) => {
+                    let candidates_is_some = candidates.is_some();
+                    let candidates = match candidates {
+                        Some(candidates) => candidates,
+                        None => resolve_query_tree(self.ctx, &query_tree, &mut HashMap::new(), wdcache)?,
+                    };
+
+                    // If our parent returns candidates it means that the bucket
+                    // candidates were already computed before and we can use them.
+                    if candidates_is_some {
+                        self.bucket_candidates.union_with(&bucket_candidates);
+                    } else {
+                        self.bucket_candidates.union_with(&candidates);
+                    }
+
+                    self.query_tree = Some(query_tree);
+                    if candidates.is_empty() {
+                        continue;
+                    }
+
+                    self.buckets = self.exactness_buckets(candidates)?.into_iter();
+                },
+                // Without any query word there is nothing to be exact about.
+                Some(result) => return Ok(Some(result)),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use crate::update::{IndexDocuments, UpdateFormat};
+    use crate::{Criterion, Index};
+
+    #[test]
+    fn exact_attributes_rank_first() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,title\n0,the tale of harry potter\n1,harry potter and the goblet\n2,harry potter\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let title = index.fields_ids_map(&rtxn).unwrap().id("title").unwrap();
+        let docids = index.field_id_word_count_docids.get(&rtxn, &(title, 2)).unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![2]);
+
+        let mut search = index.search(&rtxn);
+        search.query("harry potter");
+        search.override_criteria(vec![Criterion::Words, Criterion::Exactness]);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn original_words_rank_first() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &"id,name\n0,emile\n1,Émile\n2,EMILE\n".as_bytes()[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("Émile").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 0, 2]);
+
+        let result = index.search(&rtxn).query("EMILE").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn original_words_rank_first_among_many_candidates() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut content = String::from("id,name\n");
+        for id in 0..1500 {
+            content.push_str(&format!("{},emile\n", id));
+        }
+        content.push_str("1500,Émile\n");
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let name = index.fields_ids_map(&rtxn).unwrap().id("name").unwrap();
+        let docids = index.field_id_original_word_docids.get(&rtxn, &(name, "Émile")).unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1500]);
+
+        let mut search = index.search(&rtxn);
+        search.query("Émile").limit(1);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1500]);
+    }
+}
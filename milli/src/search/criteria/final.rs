@@ -25,8 +25,20 @@ pub struct Final<'t> {
 }
 
 impl<'t> Final<'t> {
-    pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>) -> Final<'t> {
-        Final { ctx, parent, wdcache: WordDerivationsCache::new() }
+    pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>, first_letter_typo_cost: u8) -> Final<'t> {
+        let wdcache = WordDerivationsCache::with_first_letter_typo_cost(first_letter_typo_cost);
+        Final { ctx, parent, wdcache }
+    }
+
+    /// Replaces the derivations cache, to reuse the derivations computed by a previous search,
+    /// the derivations must have been computed with the same first letter typo cost.
+    pub fn set_wdcache(&mut self, wdcache: WordDerivationsCache) {
+        debug_assert_eq!(self.wdcache.first_letter_typo_cost(), wdcache.first_letter_typo_cost());
+        self.wdcache = wdcache;
+    }
+
+    pub fn into_wdcache(self) -> WordDerivationsCache {
+        self.wdcache
     }
 
     #[logging_timer::time("Final::{}")]
@@ -0,0 +1,161 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::mem::take;
+use std::vec;
+
+use log::debug;
+use roaring::RoaringBitmap;
+
+use crate::search::query_tree::Operation;
+use crate::search::{MatchingWords, WordDerivationsCache};
+use super::{Criterion, CriterionResult, Context, resolve_query_tree};
+
+/// Ranks the documents in which the query words occur the most above the other documents,
+/// for the datasets where the repetition of a word is a relevancy signal.
+pub struct Frequency<'t> {
+    ctx: &'t dyn Context<'t>,
+    query_tree: Option<Operation>,
+    buckets: vec::IntoIter<RoaringBitmap>,
+    bucket_candidates: RoaringBitmap,
+    parent: Box<dyn Criterion + 't>,
+}
+
+impl<'t> Frequency<'t> {
+    pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>) -> Self {
+        Frequency {
+            ctx,
+            query_tree: None,
+            buckets: Vec::new().into_iter(),
+            bucket_candidates: RoaringBitmap::new(),
+            parent,
+        }
+    }
+}
+
+/// Groups the candidates by the number of occurrences of the query words,
+/// the groups are returned from the most to the least frequent one.
+///
+/// The words of every candidate are read, this criterion is therefore
+/// cheaper when it comes after the criteria that split the buckets the most.
+fn frequency_buckets(
+    ctx: &dyn Context,
+    query_tree: &Operation,
+    candidates: RoaringBitmap,
+) -> anyhow::Result<Vec<RoaringBitmap>>
+{
+    let matching_words = MatchingWords::from_query_tree(query_tree);
+    let mut buckets = BTreeMap::new();
+
+    for docid in candidates {
+        let occurrences: u64 = ctx.docid_words_positions(docid)?
+            .iter()
+            .filter(|(word, _)| matching_words.matches(word))
+            .map(|(_, positions)| positions.len())
+            .sum();
+
+        buckets.entry(Reverse(occurrences)).or_insert_with(RoaringBitmap::new).insert(docid);
+    }
+
+    Ok(buckets.into_iter().map(|(_, docids)| docids).collect())
+}
+
+impl<'t> Criterion for Frequency<'t> {
+    #[logging_timer::time("Frequency::{}")]
+    fn next(&mut self, wdcache: &mut WordDerivationsCache) -> anyhow::Result<Option<CriterionResult>> {
+        loop {
+            debug!("Frequency iteration");
+
+            if let Some(candidates) = self.buckets.next() {
+                return Ok(Some(CriterionResult {
+                    query_tree: self.query_tree.clone(),
+                    candidates: Some(candidates),
+                    bucket_candidates: take(&mut self.bucket_candidates),
+                }));
+            }
+
+            match self.parent.next(wdcache)? {
+                Some(CriterionResult { query_tree: Some(query_tree), candidates, bucket_candidates }) => {
+                    let candidates_is_some = candidates.is_some();
+                    let candidates = match candidates {
+                        Some(candidates) => candidates,
+                        None => resolve_query_tree(self.ctx, &query_tree, &mut HashMap::new(), wdcache)?,
+                    };
+
+                    // If our parent returns candidates it means that the bucket
+                    // candidates were already computed before and we can use them.
+                    if candidates_is_some {
+                        self.bucket_candidates.union_with(&bucket_candidates);
+                    } else {
+                        self.bucket_candidates.union_with(&candidates);
+                    }
+
+                    self.buckets = frequency_buckets(self.ctx, &query_tree, candidates)?.into_iter();
+                    self.query_tree = Some(query_tree);
+                },
+                // Without any query word there is nothing to count.
+                Some(result) => return Ok(Some(result)),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use crate::{Criterion, Index};
+
+    #[test]
+    fn frequent_words_rank_first() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_criteria(vec![Criterion::Words, Criterion::Frequency]);
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,text\n0,a cat\n1,cat cat cat\n2,cat and another cat\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("cat").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn frequent_words_rank_first_among_many_candidates() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_criteria(vec![Criterion::Words, Criterion::Frequency]);
+        builder.execute(|_, _| ()).unwrap();
+
+        let mut content = String::from("id,text\n");
+        for id in 0..1500 {
+            content.push_str(&format!("{},a cat\n", id));
+        }
+        content.push_str("1500,cat cat cat\n");
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("cat").limit(1);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1500]);
+    }
+}
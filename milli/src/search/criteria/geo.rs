@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::iter;
+use std::mem::take;
+
+use log::debug;
+use roaring::RoaringBitmap;
+use rstar::RTree;
+
+use crate::search::criteria::{resolve_query_tree, CriteriaBuilder};
+use crate::search::query_tree::Operation;
+use crate::search::WordDerivationsCache;
+use crate::{lat_lng_to_xyz, GeoPoint, Index};
+use super::{Criterion, CriterionResult};
+
+pub struct Geo<'t> {
+    index: &'t Index,
+    rtxn: &'t heed::RoTxn<'t>,
+    point: [f64; 2],
+    ascending: bool,
+    query_tree: Option<Operation>,
+    candidates: Box<dyn Iterator<Item = RoaringBitmap>>,
+    bucket_candidates: RoaringBitmap,
+    geo_candidates: RoaringBitmap,
+    rtree: Option<RTree<GeoPoint>>,
+    parent: Box<dyn Criterion + 't>,
+}
+
+impl<'t> Geo<'t> {
+    pub fn asc(
+        index: &'t Index,
+        rtxn: &'t heed::RoTxn,
+        parent: Box<dyn Criterion + 't>,
+        point: [f64; 2],
+    ) -> anyhow::Result<Self>
+    {
+        Self::new(index, rtxn, parent, point, true)
+    }
+
+    pub fn desc(
+        index: &'t Index,
+        rtxn: &'t heed::RoTxn,
+        parent: Box<dyn Criterion + 't>,
+        point: [f64; 2],
+    ) -> anyhow::Result<Self>
+    {
+        Self::new(index, rtxn, parent, point, false)
+    }
+
+    fn new(
+        index: &'t Index,
+        rtxn: &'t heed::RoTxn,
+        parent: Box<dyn Criterion + 't>,
+        point: [f64; 2],
+        ascending: bool,
+    ) -> anyhow::Result<Self>
+    {
+        Ok(Geo {
+            index,
+            rtxn,
+            point,
+            ascending,
+            query_tree: None,
+            candidates: Box::new(iter::empty()),
+            bucket_candidates: RoaringBitmap::new(),
+            geo_candidates: index.geo_faceted_documents_ids(rtxn)?,
+            rtree: index.geo_rtree(rtxn)?,
+            parent,
+        })
+    }
+}
+
+impl<'t> Criterion for Geo<'t> {
+    #[logging_timer::time("Geo::{}")]
+    fn next(&mut self, wdcache: &mut WordDerivationsCache) -> anyhow::Result<Option<CriterionResult>> {
+        loop {
+            debug!("Geo {}({:?}) iteration",
+                if self.ascending { "Asc" } else { "Desc" }, self.point
+            );
+
+            match self.candidates.next() {
+                None => {
+                    match self.parent.next(wdcache)? {
+                        Some(CriterionResult { query_tree, candidates, bucket_candidates }) => {
+                            let candidates_is_some = candidates.is_some();
+                            self.query_tree = query_tree;
+                            let candidates = match (&self.query_tree, candidates) {
+                                (_, Some(mut candidates)) => {
+                                    candidates.intersect_with(&self.geo_candidates);
+                                    candidates
+                                },
+                                (Some(qt), None) => {
+                                    let context = CriteriaBuilder::new(&self.rtxn, &self.index)?;
+                                    let mut candidates = resolve_query_tree(&context, qt, &mut HashMap::new(), wdcache)?;
+                                    candidates.intersect_with(&self.geo_candidates);
+                                    candidates
+                                },
+                                (None, None) => take(&mut self.geo_candidates),
+                            };
+
+                            // If our parent returns candidates it means that the bucket
+                            // candidates were already computed before and we can use them.
+                            //
+                            // If not, we must use the just computed candidates as our bucket
+                            // candidates.
+                            if candidates_is_some {
+                                self.bucket_candidates.union_with(&bucket_candidates);
+                            } else {
+                                self.bucket_candidates.union_with(&candidates);
+                            }
+
+                            if candidates.is_empty() {
+                                continue;
+                            }
+
+                            self.candidates = match &self.rtree {
+                                Some(rtree) => geo_ordered(rtree, candidates, self.point, self.ascending),
+                                None => Box::new(iter::empty()),
+                            };
+                        },
+                        None => return Ok(None),
+                    }
+                },
+                Some(candidates) => {
+                    return Ok(Some(CriterionResult {
+                        query_tree: self.query_tree.clone(),
+                        candidates: Some(candidates),
+                        bucket_candidates: take(&mut self.bucket_candidates),
+                    }));
+                },
+            }
+        }
+    }
+}
+
+/// Returns an iterator over groups of the given candidates ordered by their distance
+/// to the given point, the documents that share the same coordinates are grouped together.
+fn geo_ordered(
+    rtree: &RTree<GeoPoint>,
+    candidates: RoaringBitmap,
+    point: [f64; 2],
+    ascending: bool,
+) -> Box<dyn Iterator<Item = RoaringBitmap>>
+{
+    let xyz_point = lat_lng_to_xyz(&point);
+    let mut groups: Vec<(RoaringBitmap, [f64; 2])> = Vec::new();
+    let mut found = 0;
+
+    for geo_point in rtree.nearest_neighbor_iter(&xyz_point) {
+        let (docid, lat_lng) = geo_point.data;
+        if candidates.contains(docid) {
+            match groups.last_mut() {
+                Some((docids, position)) if *position == lat_lng => { docids.insert(docid); },
+                _ => groups.push((iter::once(docid).collect(), lat_lng)),
+            }
+
+            found += 1;
+            if found == candidates.len() { break }
+        }
+    }
+
+    let iter = groups.into_iter().map(|(docids, _)| docids);
+    if ascending {
+        Box::new(iter)
+    } else {
+        Box::new(iter.rev())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use crate::{AscDesc, Criterion, Index, Member};
+
+    #[test]
+    fn sort_by_geo_point() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": "paris", "_geo": { "lat": 48.8566, "lng": 2.3522 } },
+            { "id": "tokyo", "_geo": { "lat": 35.6762, "lng": 139.6503 } },
+            { "id": "london", "_geo": { "lat": "51.5074", "lng": "-0.1278" } },
+            { "id": "nowhere" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docid = |id| index.external_to_internal_id(&rtxn, id).unwrap().unwrap();
+        let brussels = [50.8503, 4.3517];
+
+        let result = index.search(&rtxn)
+            .sort_by(vec![AscDesc::Asc(Member::Geo(brussels))])
+            .execute()
+            .unwrap();
+        assert_eq!(result.documents_ids, vec![docid("paris"), docid("london"), docid("tokyo")]);
+        let paris_distance = result.geo_distances[&docid("paris")];
+        assert!((paris_distance - 264_000.0).abs() < 2_000.0, "{}", paris_distance);
+
+        let result = index.search(&rtxn)
+            .sort_by(vec![AscDesc::Desc(Member::Geo(brussels))])
+            .execute()
+            .unwrap();
+        assert_eq!(result.documents_ids, vec![docid("tokyo"), docid("london"), docid("paris")]);
+    }
+
+    #[test]
+    fn geo_asc_criterion() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": "paris", "name": "hotel", "_geo": { "lat": 48.8566, "lng": 2.3522 } },
+            { "id": "tokyo", "name": "hotel", "_geo": { "lat": 35.6762, "lng": 139.6503 } },
+            { "id": "london", "name": "hotel", "_geo": { "lat": 51.5074, "lng": -0.1278 } },
+            { "id": "nowhere", "name": "hotel" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_criteria(vec![Criterion::Words, Criterion::GeoAsc([51.5, -0.12])]);
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let london = [51.5, -0.12];
+        assert_eq!(index.criteria(&rtxn).unwrap(), vec![Criterion::Words, Criterion::GeoAsc(london)]);
+        let docid = |id| index.external_to_internal_id(&rtxn, id).unwrap().unwrap();
+
+        let result = index.search(&rtxn).query("hotel").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![docid("london"), docid("paris"), docid("tokyo")]);
+        assert!(result.geo_distances[&docid("london")] < 1_000.0);
+    }
+}
@@ -5,21 +5,32 @@ use anyhow::bail;
 use roaring::RoaringBitmap;
 
 use crate::{TreeLevel, search::{word_derivations, WordDerivationsCache}};
-use crate::{Index, DocumentId};
+use crate::{AttributeRanking, Index, DocumentId, FieldId, Member};
+use crate::proximity::ONE_ATTRIBUTE;
 
-use super::query_tree::{Operation, Query, QueryKind};
+use super::query_tree::{Operation, OriginalWord, Query, QueryKind};
 use self::asc_desc::AscDesc;
 use self::attribute::Attribute;
+use self::exactness::Exactness;
 use self::r#final::Final;
+use self::frequency::Frequency;
+use self::geo::Geo;
 use self::initial::Initial;
 use self::proximity::Proximity;
+use self::trace::{TraceRecorder, Traced};
 use self::typo::Typo;
 use self::words::Words;
 
+pub use self::trace::CriterionTrace;
+
 mod asc_desc;
 mod attribute;
+mod exactness;
+mod frequency;
+mod geo;
 mod initial;
 mod proximity;
+mod trace;
 mod typo;
 mod words;
 pub mod r#final;
@@ -67,6 +78,8 @@ impl Default for Candidates {
 pub trait Context<'c> {
     fn documents_ids(&self) -> heed::Result<RoaringBitmap>;
     fn word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
+    /// Returns the documents that contain this word in attributes where typos are allowed.
+    fn typo_word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
     fn word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
     fn word_pair_proximity_docids(&self, left: &str, right: &str, proximity: u8) -> heed::Result<Option<RoaringBitmap>>;
     fn word_prefix_pair_proximity_docids(&self, left: &str, right: &str, proximity: u8) -> heed::Result<Option<RoaringBitmap>>;
@@ -81,6 +94,13 @@ pub struct CriteriaBuilder<'t> {
     index: &'t Index,
     words_fst: fst::Set<Cow<'t, [u8]>>,
     words_prefixes_fst: fst::Set<Cow<'t, [u8]>>,
+    proximity_enabled: bool,
+    exact_attributes: Vec<FieldId>,
+    searchable_attributes: Option<Vec<FieldId>>,
+    attribute_ranking: AttributeRanking,
+    first_letter_typo_cost: u8,
+    original_words: Vec<OriginalWord>,
+    criteria: Option<Vec<crate::Criterion>>,
 }
 
 impl<'c> Context<'c> for CriteriaBuilder<'c> {
@@ -89,21 +109,93 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
     }
 
     fn word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
-        self.index.word_docids.get(self.rtxn, &word)
+        match &self.searchable_attributes {
+            Some(fids) => self.word_docids_in_attributes(word, false, fids).map(Some),
+            None => self.index.word_docids.get(self.rtxn, &word),
+        }
+    }
+
+    fn typo_word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
+        if let Some(fids) = &self.searchable_attributes {
+            let fids: Vec<_> = fids.iter().filter(|fid| !self.exact_attributes.contains(fid)).copied().collect();
+            return self.word_docids_in_attributes(word, false, &fids).map(Some);
+        }
+
+        if self.exact_attributes.is_empty() {
+            return self.word_docids(word);
+        }
+
+        // We retrieve the documents that contain the word in the positions ranges
+        // of the attributes that are between the exact attributes.
+        let mut ranges = Vec::with_capacity(self.exact_attributes.len() + 1);
+        let mut left = 0;
+        for fid in self.exact_attributes.iter().map(|fid| *fid as u32) {
+            if fid * ONE_ATTRIBUTE > left {
+                ranges.push((left, fid * ONE_ATTRIBUTE - 1));
+            }
+            left = (fid + 1) * ONE_ATTRIBUTE;
+        }
+        ranges.push((left, u32::max_value()));
+
+        let mut docids = RoaringBitmap::new();
+        for (left, right) in ranges {
+            let level = TreeLevel::min_value();
+            for result in self.word_position_iterator(word, level, false, Some(left), Some(right))? {
+                let (_, current_docids) = result?;
+                docids.union_with(&current_docids);
+            }
+        }
+
+        Ok(Some(docids))
     }
 
     fn word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
-        self.index.word_prefix_docids.get(self.rtxn, &word)
+        match &self.searchable_attributes {
+            Some(fids) => self.word_docids_in_attributes(word, true, fids).map(Some),
+            None => self.index.word_prefix_docids.get(self.rtxn, &word),
+        }
     }
 
     fn word_pair_proximity_docids(&self, left: &str, right: &str, proximity: u8) -> heed::Result<Option<RoaringBitmap>> {
+        if !self.proximity_enabled {
+            // The words pairs proximities aren't computed, we approximate
+            // them with the documents that contains both words.
+            let left = self.word_docids(left)?.unwrap_or_default();
+            let right = self.word_docids(right)?.unwrap_or_default();
+            return Ok(Some(left & right));
+        }
+
         let key = (left, right, proximity);
-        self.index.word_pair_proximity_docids.get(self.rtxn, &key)
+        let docids = self.index.word_pair_proximity_docids.get(self.rtxn, &key)?;
+        match (docids, &self.searchable_attributes) {
+            // The pairs aren't stored by attribute, we keep the documents
+            // where both words appear in the searchable attributes.
+            (Some(docids), Some(_)) => {
+                let left = self.word_docids(left)?.unwrap_or_default();
+                let right = self.word_docids(right)?.unwrap_or_default();
+                Ok(Some(docids & left & right))
+            },
+            (docids, _) => Ok(docids),
+        }
     }
 
     fn word_prefix_pair_proximity_docids(&self, left: &str, right: &str, proximity: u8) -> heed::Result<Option<RoaringBitmap>> {
+        if !self.proximity_enabled {
+            let left = self.word_docids(left)?.unwrap_or_default();
+            let right = self.word_prefix_docids(right)?.unwrap_or_default();
+            return Ok(Some(left & right));
+        }
+
         let key = (left, right, proximity);
-        self.index.word_prefix_pair_proximity_docids.get(self.rtxn, &key)
+        let docids = self.index.word_prefix_pair_proximity_docids.get(self.rtxn, &key)?;
+        match (docids, &self.searchable_attributes) {
+            (Some(docids), Some(_)) => {
+                let left = self.word_docids(left)?.unwrap_or_default();
+                let right = self.word_prefix_docids(right)?.unwrap_or_default();
+                Ok(Some(docids & left & right))
+            },
+            (docids, _) => Ok(docids),
+        }
     }
 
     fn words_fst<'t>(&self) -> &'t fst::Set<Cow<[u8]>> {
@@ -115,12 +207,8 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
     }
 
     fn docid_words_positions(&self, docid: DocumentId) -> heed::Result<HashMap<String, RoaringBitmap>> {
-        let mut words_positions = HashMap::new();
-        for result in self.index.docid_word_positions.prefix_iter(self.rtxn, &(docid, ""))? {
-            let ((_, word), positions) = result?;
-            words_positions.insert(word.to_string(), positions);
-        }
-        Ok(words_positions)
+        let words_positions = self.index.document_word_positions(self.rtxn, docid)?;
+        Ok(words_positions.into_iter().map(|(word, positions)| (word.to_string(), positions)).collect())
     }
 
     fn word_position_iterator(
@@ -170,31 +258,142 @@ impl<'t> CriteriaBuilder<'t> {
     pub fn new(rtxn: &'t heed::RoTxn<'t>, index: &'t Index) -> anyhow::Result<Self> {
         let words_fst = index.words_fst(rtxn)?;
         let words_prefixes_fst = index.words_prefixes_fst(rtxn)?;
-        Ok(Self { rtxn, index, words_fst, words_prefixes_fst })
+        let proximity_enabled = index.proximity_enabled(rtxn)?;
+        let exact_attributes = index.exact_attributes_ids(rtxn)?;
+        let attribute_ranking = index.attribute_ranking(rtxn)?;
+        Ok(Self {
+            rtxn,
+            index,
+            words_fst,
+            words_prefixes_fst,
+            proximity_enabled,
+            exact_attributes,
+            searchable_attributes: None,
+            attribute_ranking,
+            first_letter_typo_cost: 1,
+            original_words: Vec::new(),
+            criteria: None,
+        })
+    }
+
+    /// Only considers the words that appear in the given attributes.
+    pub fn restrict_searchable_attributes(&mut self, fids: Vec<FieldId>) {
+        self.searchable_attributes = Some(fids);
+    }
+
+    /// Defines the number of typos that a typo on the first letter of a word costs.
+    pub fn first_letter_typo_cost(&mut self, cost: u8) {
+        self.first_letter_typo_cost = cost;
+    }
+
+    /// Defines the query words as they were typed, used by the exactness criterion.
+    pub fn original_words(&mut self, words: Vec<OriginalWord>) {
+        self.original_words = words;
+    }
+
+    /// Replaces the ranking rules of the index by the given ones,
+    /// they must be a subset of the ranking rules of the index, in any order.
+    pub fn override_criteria(&mut self, criteria: Vec<crate::Criterion>) {
+        self.criteria = Some(criteria);
+    }
+
+    /// Returns the documents that contain the word, or prefix, in the given attributes,
+    /// it retrieves the level 0 positions that are in the positions range of these attributes.
+    fn word_docids_in_attributes(&self, word: &str, in_prefix_cache: bool, fids: &[FieldId]) -> heed::Result<RoaringBitmap> {
+        let mut docids = RoaringBitmap::new();
+        let level = TreeLevel::min_value();
+        for fid in fids.iter().map(|fid| *fid as u32) {
+            let (left, right) = (fid * ONE_ATTRIBUTE, (fid + 1) * ONE_ATTRIBUTE - 1);
+            for result in self.word_position_iterator(word, level, in_prefix_cache, Some(left), Some(right))? {
+                let (_, current_docids) = result?;
+                docids.union_with(&current_docids);
+            }
+        }
+        Ok(docids)
     }
 
     pub fn build(
         &'t self,
         query_tree: Option<Operation>,
         facet_candidates: Option<RoaringBitmap>,
+        sort_criteria: Option<Vec<crate::AscDesc>>,
+        trace: Option<TraceRecorder>,
     ) -> anyhow::Result<Final<'t>>
     {
         use crate::criterion::Criterion as Name;
 
+        let index_criteria = self.index.criteria(&self.rtxn)?;
+        let criteria = match &self.criteria {
+            Some(criteria) => {
+                for (i, name) in criteria.iter().enumerate() {
+                    if !index_criteria.contains(name) {
+                        bail!("the {} criterion is not part of the ranking rules of the index", name);
+                    }
+                    if criteria[..i].contains(name) {
+                        bail!("the {} criterion is defined more than once", name);
+                    }
+                }
+                criteria.clone()
+            },
+            None => index_criteria,
+        };
+
+        if sort_criteria.is_some() && !criteria.contains(&Name::Sort) {
+            bail!("the sort criterion must be part of the ranking rules to sort at query time");
+        }
+
         let mut criterion = Box::new(Initial::new(query_tree, facet_candidates)) as Box<dyn Criterion>;
-        for name in self.index.criteria(&self.rtxn)? {
+        for name in criteria {
+            // The disabled ranking rules are not traced, they don't split the buckets.
+            let applied = match name {
+                Name::Proximity => self.proximity_enabled,
+                Name::Sort => sort_criteria.is_some(),
+                _ => true,
+            };
+            let trace_name = trace.as_ref().filter(|_| applied).map(|_| name.to_string());
+
             criterion = match name {
                 Name::Typo => Box::new(Typo::new(self, criterion)),
                 Name::Words => Box::new(Words::new(self, criterion)),
-                Name::Proximity => Box::new(Proximity::new(self, criterion)),
-                Name::Attribute => Box::new(Attribute::new(self, criterion)),
+                Name::Proximity if self.proximity_enabled => Box::new(Proximity::new(self, criterion)),
+                Name::Attribute => Box::new(Attribute::new(self, criterion, self.attribute_ranking)),
+                Name::Exactness => Box::new(Exactness::new(
+                    self,
+                    self.index,
+                    self.rtxn,
+                    criterion,
+                    self.original_words.clone(),
+                    self.searchable_attributes.clone(),
+                )?),
+                Name::Frequency => Box::new(Frequency::new(self, criterion)),
                 Name::Asc(field) => Box::new(AscDesc::asc(&self.index, &self.rtxn, criterion, field)?),
                 Name::Desc(field) => Box::new(AscDesc::desc(&self.index, &self.rtxn, criterion, field)?),
+                Name::GeoAsc(point) => Box::new(Geo::asc(&self.index, &self.rtxn, criterion, point)?),
+                Name::Sort => match &sort_criteria {
+                    // Every sort expression splits the buckets of the previous one,
+                    // the documents are therefore sorted lexicographically.
+                    Some(sort_criteria) => {
+                        for sort in sort_criteria {
+                            criterion = match sort {
+                                crate::AscDesc::Asc(Member::Field(field)) => Box::new(AscDesc::asc(&self.index, &self.rtxn, criterion, field.clone())?),
+                                crate::AscDesc::Desc(Member::Field(field)) => Box::new(AscDesc::desc(&self.index, &self.rtxn, criterion, field.clone())?),
+                                crate::AscDesc::Asc(Member::Geo(point)) => Box::new(Geo::asc(&self.index, &self.rtxn, criterion, *point)?),
+                                crate::AscDesc::Desc(Member::Geo(point)) => Box::new(Geo::desc(&self.index, &self.rtxn, criterion, *point)?),
+                            };
+                        }
+                        criterion
+                    },
+                    None => criterion,
+                },
                 _otherwise => criterion,
             };
+
+            if let (Some(recorder), Some(name)) = (&trace, trace_name) {
+                criterion = Box::new(Traced::new(name, criterion, recorder.clone()));
+            }
         }
 
-        Ok(Final::new(self, criterion))
+        Ok(Final::new(self, criterion, self.first_letter_typo_cost))
     }
 }
 
@@ -298,7 +497,7 @@ fn query_docids(
 ) -> anyhow::Result<RoaringBitmap>
 {
     match &query.kind {
-        QueryKind::Exact { word, .. } => {
+        QueryKind::Exact { word, original_typo } => {
             if query.prefix && ctx.in_prefix_cache(&word) {
                 Ok(ctx.word_prefix_docids(&word)?.unwrap_or_default())
             } else if query.prefix {
@@ -309,6 +508,8 @@ fn query_docids(
                     docids.union_with(&current_docids);
                 }
                 Ok(docids)
+            } else if *original_typo > 0 {
+                Ok(ctx.typo_word_docids(&word)?.unwrap_or_default())
             } else {
                 Ok(ctx.word_docids(&word)?.unwrap_or_default())
             }
@@ -316,8 +517,12 @@ fn query_docids(
         QueryKind::Tolerant { typo, word } => {
             let words = word_derivations(&word, query.prefix, *typo, ctx.words_fst(), wdcache)?;
             let mut docids = RoaringBitmap::new();
-            for (word, _typo) in words {
-                let current_docids = ctx.word_docids(&word)?.unwrap_or_default();
+            for (word, typo) in words {
+                let current_docids = if *typo > 0 {
+                    ctx.typo_word_docids(&word)?.unwrap_or_default()
+                } else {
+                    ctx.word_docids(&word)?.unwrap_or_default()
+                };
                 docids.union_with(&current_docids);
             }
             Ok(docids)
@@ -407,6 +612,10 @@ pub mod test {
             Ok(self.word_docids.get(&word.to_string()).cloned())
         }
 
+        fn typo_word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
+            self.word_docids(word)
+        }
+
         fn word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
             Ok(self.word_prefix_docids.get(&word.to_string()).cloned())
         }
@@ -7,7 +7,7 @@ use log::debug;
 
 use crate::{DocumentId, Position, search::{query_tree::QueryKind}};
 use crate::search::query_tree::{maximum_proximity, Operation, Query};
-use crate::search::{build_dfa, WordDerivationsCache};
+use crate::search::{build_dfa, derivation_typos, WordDerivationsCache};
 use super::{Criterion, CriterionResult, Context, query_docids, query_pair_proximity_docids, resolve_query_tree};
 
 type Cache = HashMap<(Operation, u8), Vec<(Query, Query, RoaringBitmap)>>;
@@ -321,15 +321,19 @@ fn resolve_plane_sweep_candidates(
             let (_, (_, _, right_most_pos)) = groups.last()?;
 
             for pair in groups.windows(2) {
-                if let [(i1, (_, _, rpos1)), (i2, (lpos2, prox2, _))] = pair {
+                if let [(i1, (lpos1, _, rpos1)), (i2, (lpos2, prox2, rpos2))] = pair {
                     // if a pair overlap, meaning that they share at least a word, we return None
                     if rpos1 >= lpos2 { return None }
                     // if groups are in the good order (query order) we remove 1 to the proximity
                     // the proximity is clamped to 7
+                    //
+                    // A group of many words, like a phrase, is anchored at its last word, the
+                    // proximity is therefore measured from the end of the group which comes
+                    // first in the query, like the word pair proximity database does.
                     let pair_proximity = if i1 < i2 {
                         (*lpos2 - *rpos1 - 1).min(7)
                     } else {
-                        (*lpos2 - *rpos1).min(7)
+                        (*rpos2 - *lpos1).min(7)
                     };
 
                     proximity += pair_proximity as u8 + prox2;
@@ -447,11 +451,12 @@ fn resolve_plane_sweep_candidates(
                 result
             },
             Operation::Query(Query { prefix, kind }) => {
+                let first_letter_typo_cost = wdcache.first_letter_typo_cost();
                 let mut result = Vec::new();
                 match kind {
                     QueryKind::Exact { word, .. } => {
                         if *prefix {
-                            let iter = word_derivations(word, true, 0, &words_positions)
+                            let iter = word_derivations(word, true, 0, first_letter_typo_cost, &words_positions)
                                 .flat_map(|positions| positions.iter().map(|p| (p, 0, p)));
                             result.extend(iter);
                         } else if let Some(positions) = words_positions.get(word) {
@@ -459,7 +464,7 @@ fn resolve_plane_sweep_candidates(
                         }
                     },
                     QueryKind::Tolerant { typo, word } => {
-                        let iter = word_derivations(word, *prefix, *typo, &words_positions)
+                        let iter = word_derivations(word, *prefix, *typo, first_letter_typo_cost, &words_positions)
                             .flat_map(|positions| positions.iter().map(|p| (p, 0, p)));
                         result.extend(iter);
                     }
@@ -475,9 +480,10 @@ fn resolve_plane_sweep_candidates(
     }
 
     fn word_derivations<'a>(
-        word: &str,
+        word: &'a str,
         is_prefix: bool,
         max_typo: u8,
+        first_letter_typo_cost: u8,
         words_positions: &'a HashMap<String, RoaringBitmap>,
     ) -> impl Iterator<Item = &'a RoaringBitmap>
     {
@@ -485,7 +491,10 @@ fn resolve_plane_sweep_candidates(
         words_positions.iter().filter_map(move |(document_word, positions)| {
             use levenshtein_automata::Distance;
             match dfa.eval(document_word) {
-                Distance::Exact(_) => Some(positions),
+                Distance::Exact(distance) => {
+                    let typos = derivation_typos(word, document_word, distance, first_letter_typo_cost);
+                    if typos <= max_typo { Some(positions) } else { None }
+                },
                 Distance::AtLeast(_) => None,
             }
         })
@@ -511,3 +520,31 @@ fn resolve_plane_sweep_candidates(
 
     Ok(candidates)
 }
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use crate::update::{IndexDocuments, UpdateFormat};
+    use crate::Index;
+
+    #[test]
+    fn phrase_anchored_at_its_last_word() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,moon dark side\n1,dark side big moon\n2,dark side moon\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The "moon" word is two words away from the end of the phrase in the first document.
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("\"dark side\" moon").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2, 1, 0]);
+    }
+}
@@ -0,0 +1,56 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::search::WordDerivationsCache;
+use super::{Criterion, CriterionResult};
+
+/// What a ranking rule did during a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CriterionTrace {
+    /// The name of the ranking rule, as it is written in the settings.
+    pub name: String,
+    /// The number of buckets of documents that the ranking rule returned.
+    pub buckets: usize,
+    /// The number of documents in these buckets, when the ranking rule computed them.
+    pub candidates: u64,
+    /// The time spent computing the buckets, including the time spent in the previous ranking rules.
+    pub duration: Duration,
+}
+
+/// The traces of the ranking rules of a search, in the order of the ranking rules.
+pub type TraceRecorder = Rc<RefCell<Vec<CriterionTrace>>>;
+
+/// Records the buckets returned by a ranking rule into its trace.
+pub struct Traced<'t> {
+    position: usize,
+    recorder: TraceRecorder,
+    inner: Box<dyn Criterion + 't>,
+}
+
+impl<'t> Traced<'t> {
+    pub fn new(name: String, inner: Box<dyn Criterion + 't>, recorder: TraceRecorder) -> Traced<'t> {
+        let mut traces = recorder.borrow_mut();
+        let position = traces.len();
+        traces.push(CriterionTrace { name, buckets: 0, candidates: 0, duration: Duration::default() });
+        drop(traces);
+        Traced { position, recorder, inner }
+    }
+}
+
+impl<'t> Criterion for Traced<'t> {
+    fn next(&mut self, wdcache: &mut WordDerivationsCache) -> anyhow::Result<Option<CriterionResult>> {
+        let before = Instant::now();
+        let result = self.inner.next(wdcache)?;
+
+        let mut traces = self.recorder.borrow_mut();
+        let trace = &mut traces[self.position];
+        trace.duration += before.elapsed();
+        if let Some(CriterionResult { candidates, .. }) = &result {
+            trace.buckets += 1;
+            trace.candidates += candidates.as_ref().map_or(0, |c| c.len());
+        }
+
+        Ok(result)
+    }
+}
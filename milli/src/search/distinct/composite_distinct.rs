@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+use std::mem::size_of;
+
+use roaring::RoaringBitmap;
+
+use super::{Distinct, DocIter};
+use crate::{DocumentId, FieldId, Index};
+
+/// The maximum number of fields that can compose a distinct key.
+pub const MAX_DISTINCT_ATTRIBUTES: usize = 4;
+
+/// A distinct implementer that is backed by the per-document facet values of several fields.
+///
+/// The facet values of each of the distinct fields of a document are concatenated into a
+/// composite key, a document is filtered out and added to the excluded set when a document
+/// with the same composite key has already been seen. The documents that don't have any value
+/// for all of the distinct fields are always kept.
+pub struct CompositeDistinct<'a> {
+    distincts: Vec<FieldId>,
+    seen: HashSet<Vec<u8>>,
+    index: &'a Index,
+    txn: &'a heed::RoTxn<'a>,
+}
+
+impl<'a> CompositeDistinct<'a> {
+    pub fn new(distincts: Vec<FieldId>, index: &'a Index, txn: &'a heed::RoTxn<'a>) -> Self {
+        Self {
+            distincts,
+            seen: HashSet::new(),
+            index,
+            txn,
+        }
+    }
+}
+
+pub struct CompositeDistinctIter<'a, 'b> {
+    distincts: &'b [FieldId],
+    seen: &'b mut HashSet<Vec<u8>>,
+    index: &'a Index,
+    txn: &'a heed::RoTxn<'a>,
+    candidates: roaring::bitmap::IntoIter,
+    excluded: RoaringBitmap,
+}
+
+impl<'a, 'b> CompositeDistinctIter<'a, 'b> {
+    /// Returns the composite key of the document, the facet values of every distinct field are
+    /// prefixed by their length to avoid ambiguities, `None` if the document has no value at all.
+    fn composite_key(&self, id: DocumentId) -> anyhow::Result<Option<Vec<u8>>> {
+        const PREFIX_SIZE: usize = size_of::<FieldId>() + size_of::<DocumentId>();
+
+        let mut key = Vec::new();
+        let mut has_value = false;
+        for &distinct in self.distincts {
+            let mut prefix = [0; PREFIX_SIZE];
+            prefix[..size_of::<FieldId>()].copy_from_slice(&distinct.to_be_bytes());
+            prefix[size_of::<FieldId>()..].copy_from_slice(&id.to_be_bytes());
+
+            // The values of a field are stored in order, the key
+            // doesn't depend on the order of the values in the document.
+            let mut count: u32 = 0;
+            for result in self.index.field_id_docid_facet_values.prefix_iter(self.txn, &prefix[..])? {
+                let (bytes, ()) = result?;
+                let value = &bytes[PREFIX_SIZE..];
+                key.extend_from_slice(&(value.len() as u32).to_be_bytes());
+                key.extend_from_slice(value);
+                count += 1;
+            }
+
+            // The number of values of this field separates it from the next field.
+            key.extend_from_slice(&count.to_be_bytes());
+            has_value |= count != 0;
+        }
+
+        Ok(if has_value { Some(key) } else { None })
+    }
+
+    /// Performs the next iteration of the composite distinct. This is a convenience method that is
+    /// called by the Iterator::next implementation that transposes the result. It makes error
+    /// handling easier.
+    fn next_inner(&mut self) -> anyhow::Result<Option<DocumentId>> {
+        while let Some(id) = self.candidates.next() {
+            let accept = match self.composite_key(id)? {
+                Some(key) => self.seen.insert(key),
+                None => true,
+            };
+
+            if accept {
+                return Ok(Some(id));
+            } else {
+                self.excluded.insert(id);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for CompositeDistinctIter<'_, '_> {
+    type Item = anyhow::Result<DocumentId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_inner().transpose()
+    }
+}
+
+impl DocIter for CompositeDistinctIter<'_, '_> {
+    fn into_excluded(self) -> RoaringBitmap {
+        self.excluded
+    }
+}
+
+impl<'a, 'b> Distinct<'b> for CompositeDistinct<'a> {
+    type Iter = CompositeDistinctIter<'a, 'b>;
+
+    fn distinct(&'b mut self, candidates: RoaringBitmap, excluded: RoaringBitmap) -> Self::Iter {
+        CompositeDistinctIter {
+            distincts: &self.distincts,
+            seen: &mut self.seen,
+            index: self.index,
+            txn: self.txn,
+            candidates: candidates.into_iter(),
+            excluded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use maplit::hashmap;
+    use serde_json::Value;
+
+    use super::*;
+    use super::super::test::generate_index;
+    use crate::BEU32;
+
+    #[test]
+    fn composite_key() {
+        let facets = hashmap!{
+            "txt".to_string() => "string".to_string(),
+            "cat-int".to_string() => "number".to_string(),
+        };
+        let (index, txt, candidates) = generate_index("txt", facets);
+        let txn = index.read_txn().unwrap();
+        let cat_int = index.fields_ids_map(&txn).unwrap().id("cat-int").unwrap();
+
+        let mut distinct = CompositeDistinct::new(vec![txt, cat_int], &index, &txn);
+        let mut iter = distinct.distinct(candidates.clone(), RoaringBitmap::new());
+
+        // Every pair of values is only seen once, but a value can be seen several times.
+        let mut seen = HashSet::new();
+        let mut count = 0;
+        for id in iter.by_ref() {
+            let id = id.unwrap();
+            let document = index.documents.get(&txn, &BEU32::new(id)).unwrap().unwrap();
+            let txt_value: Value = serde_json::from_slice(document.get(txt).unwrap()).unwrap();
+            let cat_int_value: Value = serde_json::from_slice(document.get(cat_int).unwrap()).unwrap();
+            assert!(seen.insert((txt_value.to_string(), cat_int_value.to_string())));
+            count += 1;
+        }
+
+        let excluded = iter.into_excluded();
+        assert_eq!(count as u64 + excluded.len(), candidates.len());
+        assert!(count <= 3 * 3);
+    }
+}
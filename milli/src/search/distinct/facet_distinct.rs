@@ -72,7 +72,11 @@ impl<'a> FacetDistinctIter<'a> {
         for item in iter {
             let ((_, _, value), _) = item?;
             let key = (self.distinct, value);
-            let facet_docids = self.get_facet_docids::<FacetValueStringCodec>(&key)?;
+            let (_original, facet_docids) = self
+                .index
+                .facet_string_values_docids()
+                .get(self.txn, &key)?
+                .expect("Corrupted data: Facet values must exist");
             self.excluded.union_with(&facet_docids);
         }
 
@@ -112,8 +116,8 @@ impl<'a> FacetDistinctIter<'a> {
         match candidates_iter.next() {
             Some(id) => {
                 match self.facet_type {
-                    FacetType::String => self.distinct_string(id)?,
-                    FacetType::Number => self.distinct_number(id)?,
+                    FacetType::String | FacetType::Hierarchy => self.distinct_string(id)?,
+                    FacetType::Number | FacetType::Date => self.distinct_number(id)?,
                 };
 
                 // The first document of each iteration is kept, since the next call to
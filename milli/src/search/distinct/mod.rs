@@ -1,3 +1,4 @@
+mod composite_distinct;
 mod facet_distinct;
 mod map_distinct;
 mod noop_distinct;
@@ -5,6 +6,7 @@ mod noop_distinct;
 use roaring::RoaringBitmap;
 
 use crate::DocumentId;
+pub use composite_distinct::{CompositeDistinct, MAX_DISTINCT_ATTRIBUTES};
 pub use facet_distinct::FacetDistinct;
 pub use map_distinct::MapDistinct;
 pub use noop_distinct::NoopDistinct;
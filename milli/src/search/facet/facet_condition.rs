@@ -1,20 +1,21 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::ops::Bound::{self, Included, Excluded};
+use std::ops::Bound::{self, Included, Excluded, Unbounded};
 use std::str::FromStr;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use either::Either;
-use heed::types::DecodeIgnore;
+use heed::types::{ByteSlice, DecodeIgnore};
 use log::debug;
 use pest::error::{Error as PestError, ErrorVariant};
 use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 use roaring::RoaringBitmap;
 
-use crate::facet::FacetType;
-use crate::heed_codec::facet::{FacetValueStringCodec, FacetLevelValueF64Codec};
+use crate::facet::{FacetDate, FacetNormalization, FacetType, FacetValue, FilterFeatures, normalize_path};
+use crate::heed_codec::facet::{FacetStringValueDocidsCodec, FacetValueStringCodec, FacetLevelValueF64Codec, FieldDocIdCodec};
 use crate::{Index, FieldId, FieldsIdsMap, CboRoaringBitmapCodec};
+use crate::geo::{distance_between_two_points, lat_lng_envelopes, lat_lng_to_xyz, meters_to_squared_chord};
 
 use super::FacetRange;
 use super::parser::Rule;
@@ -23,6 +24,12 @@ use super::parser::{PREC_CLIMBER, FilterParser};
 use self::FacetCondition::*;
 use self::FacetNumberOperator::*;
 
+/// The maximum number of vertices that a `_geoPolygon` filter can be made of.
+const GEO_POLYGON_MAX_VERTICES: usize = 1000;
+
+/// The suffix of a faceted field name that filters on its number of values (e.g. `genres.length`).
+const VALUES_COUNT_SUFFIX: &str = ".length";
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FacetNumberOperator {
     GreaterThan(f64),
@@ -48,28 +55,69 @@ impl FacetNumberOperator {
             Between(x, y)         => (LowerThan(x), Some(GreaterThan(y))),
         }
     }
+
+    /// Formats the operator applied to the given key with the filter syntax.
+    fn clause(self, key: &str) -> String {
+        match self {
+            GreaterThan(x)        => format!("{} > {}", key, x),
+            GreaterThanOrEqual(x) => format!("{} >= {}", key, x),
+            Equal(x)              => format!("{} = {}", key, x),
+            NotEqual(x)           => format!("{} != {}", key, x),
+            LowerThan(x)          => format!("{} < {}", key, x),
+            LowerThanOrEqual(x)   => format!("{} <= {}", key, x),
+            Between(x, y)         => format!("{} {} TO {}", key, x, y),
+        }
+    }
 }
 
+/// The operators on the faceted strings, the comparisons are lexicographic.
 #[derive(Debug, Clone, PartialEq)]
 pub enum FacetStringOperator {
     Equal(String),
     NotEqual(String),
+    GreaterThan(String),
+    GreaterThanOrEqual(String),
+    LowerThan(String),
+    LowerThanOrEqual(String),
+    Between(String, String),
 }
 
 impl FacetStringOperator {
     fn equal(s: &str) -> Self {
-        FacetStringOperator::Equal(s.to_lowercase())
+        FacetStringOperator::Equal(s.to_string())
     }
 
     #[allow(dead_code)]
     fn not_equal(s: &str) -> Self {
-        FacetStringOperator::equal(s).negate()
+        FacetStringOperator::NotEqual(s.to_string())
+    }
+
+    /// This method can return two operations in case it must express
+    /// an OR operation for the between case (i.e. `TO`).
+    fn negate(self) -> (Self, Option<Self>) {
+        use FacetStringOperator as Op;
+        match self {
+            Op::Equal(x)              => (Op::NotEqual(x), None),
+            Op::NotEqual(x)           => (Op::Equal(x), None),
+            Op::GreaterThan(x)        => (Op::LowerThanOrEqual(x), None),
+            Op::GreaterThanOrEqual(x) => (Op::LowerThan(x), None),
+            Op::LowerThan(x)          => (Op::GreaterThanOrEqual(x), None),
+            Op::LowerThanOrEqual(x)   => (Op::GreaterThan(x), None),
+            Op::Between(x, y)         => (Op::LowerThan(x), Some(Op::GreaterThan(y))),
+        }
     }
 
-    fn negate(self) -> Self {
+    /// Formats the operator applied to the given key with the filter syntax.
+    fn clause(&self, key: &str) -> String {
+        use FacetStringOperator as Op;
         match self {
-            FacetStringOperator::Equal(x)    => FacetStringOperator::NotEqual(x),
-            FacetStringOperator::NotEqual(x) => FacetStringOperator::Equal(x),
+            Op::Equal(x)              => format!("{} = {:?}", key, x),
+            Op::NotEqual(x)           => format!("{} != {:?}", key, x),
+            Op::GreaterThan(x)        => format!("{} > {:?}", key, x),
+            Op::GreaterThanOrEqual(x) => format!("{} >= {:?}", key, x),
+            Op::LowerThan(x)          => format!("{} < {:?}", key, x),
+            Op::LowerThanOrEqual(x)   => format!("{} <= {:?}", key, x),
+            Op::Between(x, y)         => format!("{} {:?} TO {:?}", key, x, y),
         }
     }
 }
@@ -78,8 +126,36 @@ impl FacetStringOperator {
 pub enum FacetCondition {
     OperatorString(FieldId, FacetStringOperator),
     OperatorNumber(FieldId, FacetNumberOperator),
+    /// The documents with a number of values for this faceted field
+    /// that satisfies the operator, a missing field counts as zero values.
+    ValuesCount(FieldId, FacetNumberOperator),
+    /// The documents with any of these values for this faceted field.
+    In(FieldId, Vec<FacetValue>),
+    /// The documents with at least one value for this faceted field, a field
+    /// that is null, an empty array or that can't be converted doesn't exist.
+    Exists(FieldId),
+    /// The documents with a `_geo` point at most at this distance, in meters,
+    /// of the center point defined by its latitude and longitude.
+    GeoRadius { center: [f64; 2], radius: f64 },
+    /// The documents with a `_geo` point inside of the box defined by its
+    /// top right and bottom left corners, as latitudes and longitudes.
+    GeoBoundingBox { top_right: [f64; 2], bottom_left: [f64; 2] },
+    /// The documents with a `_geo` point inside of the polygon defined by
+    /// these vertices, as latitudes and longitudes.
+    GeoPolygon(Vec<[f64; 2]>),
     Or(Box<Self>, Box<Self>),
     And(Box<Self>, Box<Self>),
+    Not(Box<Self>),
+}
+
+/// The number of documents matched by a clause of a filter and by each of its sub-clauses,
+/// it helps understanding why a combination of clauses doesn't match any document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterExplanation {
+    /// The clause with the filter syntax, or the `AND`, `OR` and `NOT` operators.
+    pub clause: String,
+    pub matched_documents: u64,
+    pub children: Vec<FilterExplanation>,
 }
 
 fn get_field_id_facet_type<'a>(
@@ -139,6 +215,49 @@ where T: FromStr,
     }
 }
 
+/// Parses a date value, the dates are compared as their number of seconds since the epoch.
+fn pest_parse_date(pair: Pair<Rule>) -> Result<f64, pest::error::Error<Rule>> {
+    pest_parse::<FacetDate>(pair).map(f64::from)
+}
+
+/// Returns `true` if the key of this rule targets the number of values of a field,
+/// an attribute that is really named with the suffix is filtered on its values.
+fn is_values_count_key(fields_ids_map: &FieldsIdsMap, pair: &Pair<Rule>) -> bool {
+    // lexing ensures that we at least have a key
+    let key = pair.clone().into_inner().next().unwrap();
+    key.as_str().ends_with(VALUES_COUNT_SUFFIX) && fields_ids_map.id(key.as_str()).is_none()
+}
+
+/// Parses the latitudes and longitudes pairs of a geo rule and checks their ranges.
+fn geo_points(item: Pair<Rule>) -> Result<Vec<[f64; 2]>, PestError<Rule>> {
+    let item_span = item.as_span();
+    let mut items = item.into_inner();
+    let mut points = Vec::new();
+
+    while let Some(lat) = items.next() {
+        // lexing ensures that a latitude is always followed by a longitude
+        let lng = items.next().unwrap();
+        points.push(geo_point(lat, lng, item_span.clone())?);
+    }
+
+    Ok(points)
+}
+
+/// Parses a latitude and a longitude and checks their ranges.
+fn geo_point(lat: Pair<Rule>, lng: Pair<Rule>, span: pest::Span) -> Result<[f64; 2], PestError<Rule>> {
+    let point = [pest_parse(lat)?, pest_parse(lng)?];
+
+    let message = if !(-90.0..=90.0).contains(&point[0]) {
+        "latitude must be contained between -90 and 90 degrees"
+    } else if !(-180.0..=180.0).contains(&point[1]) {
+        "longitude must be contained between -180 and 180 degrees"
+    } else {
+        return Ok(point);
+    };
+
+    Err(PestError::new_from_span(ErrorVariant::CustomError { message: message.to_string() }, span))
+}
+
 impl FacetCondition {
     pub fn from_array<I, J, A, B>(
         rtxn: &heed::RoTxn,
@@ -169,8 +288,9 @@ impl FacetCondition {
             };
 
             let operator = match ftype {
-                FacetType::String => OperatorString(fid, FacetStringOperator::equal(value)),
+                FacetType::String | FacetType::Hierarchy => OperatorString(fid, FacetStringOperator::equal(value)),
                 FacetType::Number => OperatorNumber(fid, FacetNumberOperator::Equal(value.parse()?)),
+                FacetType::Date => OperatorNumber(fid, FacetNumberOperator::Equal(value.parse::<FacetDate>()?.into())),
             };
 
             if neg { Ok(operator.negate()) } else { Ok(operator) }
@@ -215,7 +335,14 @@ impl FacetCondition {
             }
         }
 
-        Ok(ands)
+        if let Some(condition) = &ands {
+            let filterable_features = index.filterable_features_ids(rtxn)?;
+            condition.check_filterable_features(&fields_ids_map, &filterable_features)?;
+        }
+
+        let faceted_fields = index.faceted_fields_ids(rtxn)?;
+        let normalizations = index.facet_normalizations_ids(rtxn)?;
+        Ok(ands.map(|condition| condition.normalize_strings(&faceted_fields, &normalizations)))
     }
 
     pub fn from_str(
@@ -226,8 +353,104 @@ impl FacetCondition {
     {
         let fields_ids_map = index.fields_ids_map(rtxn)?;
         let faceted_fields = index.faceted_fields_ids(rtxn)?;
+        let filterable_features = index.filterable_features_ids(rtxn)?;
+        let normalizations = index.facet_normalizations_ids(rtxn)?;
         let lexed = FilterParser::parse(Rule::prgm, expression)?;
-        FacetCondition::from_pairs(&fields_ids_map, &faceted_fields, lexed)
+        let condition = FacetCondition::from_pairs(&fields_ids_map, &faceted_fields, lexed)?;
+        condition.check_filterable_features(&fields_ids_map, &filterable_features)?;
+        Ok(condition.normalize_strings(&faceted_fields, &normalizations))
+    }
+
+    /// Normalizes the string values of this condition the same way the values
+    /// of their fields are normalized when the documents are indexed.
+    fn normalize_strings(
+        self,
+        faceted_fields: &HashMap<FieldId, FacetType>,
+        normalizations: &HashMap<FieldId, FacetNormalization>,
+    ) -> FacetCondition
+    {
+        use FacetStringOperator as Op;
+
+        let normalize = |fid: FieldId, s: &str| {
+            let normalization = normalizations.get(&fid).copied().unwrap_or_default();
+            match faceted_fields.get(&fid) {
+                Some(FacetType::Hierarchy) => normalize_path(s, normalization),
+                _ => normalization.normalize(s).into_owned(),
+            }
+        };
+
+        match self {
+            OperatorString(fid, op) => {
+                let normalize = |s: String| normalize(fid, &s);
+                let op = match op {
+                    Op::Equal(x)              => Op::Equal(normalize(x)),
+                    Op::NotEqual(x)           => Op::NotEqual(normalize(x)),
+                    Op::GreaterThan(x)        => Op::GreaterThan(normalize(x)),
+                    Op::GreaterThanOrEqual(x) => Op::GreaterThanOrEqual(normalize(x)),
+                    Op::LowerThan(x)          => Op::LowerThan(normalize(x)),
+                    Op::LowerThanOrEqual(x)   => Op::LowerThanOrEqual(normalize(x)),
+                    Op::Between(x, y)         => Op::Between(normalize(x), normalize(y)),
+                };
+                OperatorString(fid, op)
+            },
+            In(fid, values) => {
+                let mut values: Vec<_> = values.into_iter().map(|value| match value {
+                    FacetValue::String(s) => FacetValue::String(normalize(fid, &s)),
+                    value => value,
+                }).collect();
+                values.sort_unstable();
+                values.dedup();
+                In(fid, values)
+            },
+            Or(lhs, rhs) => {
+                let lhs = lhs.normalize_strings(faceted_fields, normalizations);
+                let rhs = rhs.normalize_strings(faceted_fields, normalizations);
+                Or(Box::new(lhs), Box::new(rhs))
+            },
+            And(lhs, rhs) => {
+                let lhs = lhs.normalize_strings(faceted_fields, normalizations);
+                let rhs = rhs.normalize_strings(faceted_fields, normalizations);
+                And(Box::new(lhs), Box::new(rhs))
+            },
+            Not(condition) => Not(Box::new(condition.normalize_strings(faceted_fields, normalizations))),
+            condition => condition,
+        }
+    }
+
+    /// Makes sure that the operators used by this condition are enabled on their fields.
+    fn check_filterable_features(
+        &self,
+        fim: &FieldsIdsMap,
+        features: &HashMap<FieldId, FilterFeatures>,
+    ) -> anyhow::Result<()>
+    {
+        let (fid, allowed, kind) = match self {
+            OperatorString(fid, FacetStringOperator::Equal(_))
+            | OperatorString(fid, FacetStringOperator::NotEqual(_))
+            | In(fid, _)
+            | OperatorNumber(fid, Equal(_))
+            | OperatorNumber(fid, NotEqual(_)) => {
+                let allowed = features.get(fid).map_or(true, |f| f.equality);
+                (*fid, allowed, "equality")
+            },
+            OperatorString(fid, _) | OperatorNumber(fid, _) => {
+                let allowed = features.get(fid).map_or(true, |f| f.comparison);
+                (*fid, allowed, "comparison")
+            },
+            ValuesCount(..) | Exists(_) | GeoRadius { .. } | GeoBoundingBox { .. } | GeoPolygon(_) => return Ok(()),
+            Or(lhs, rhs) | And(lhs, rhs) => {
+                lhs.check_filterable_features(fim, features)?;
+                return rhs.check_filterable_features(fim, features);
+            },
+            Not(condition) => return condition.check_filterable_features(fim, features),
+        };
+
+        if !allowed {
+            let name = fim.name(fid).unwrap_or_default();
+            bail!("attribute {:?} doesn't allow {} filters", name, kind);
+        }
+
+        Ok(())
     }
 
     fn from_pairs(
@@ -239,6 +462,8 @@ impl FacetCondition {
         PREC_CLIMBER.climb(
             expression,
             |pair: Pair<Rule>| match pair.as_rule() {
+                Rule::greater | Rule::geq | Rule::eq | Rule::neq | Rule::leq | Rule::less | Rule::between
+                    if is_values_count_key(fim, &pair) => Ok(Self::values_count(fim, ff, pair)?),
                 Rule::greater => Ok(Self::greater_than(fim, ff, pair)?),
                 Rule::geq => Ok(Self::greater_than_or_equal(fim, ff, pair)?),
                 Rule::eq => Ok(Self::equal(fim, ff, pair)?),
@@ -246,6 +471,13 @@ impl FacetCondition {
                 Rule::leq => Ok(Self::lower_than_or_equal(fim, ff, pair)?),
                 Rule::less => Ok(Self::lower_than(fim, ff, pair)?),
                 Rule::between => Ok(Self::between(fim, ff, pair)?),
+                Rule::in_list => Ok(Self::in_list(fim, ff, pair)?),
+                Rule::not_in_list => Ok(Self::in_list(fim, ff, pair)?.negate()),
+                Rule::exists | Rule::is_not_null => Ok(Self::exists(fim, ff, pair)?),
+                Rule::not_exists | Rule::is_null => Ok(Self::exists(fim, ff, pair)?.negate()),
+                Rule::geo_radius => Ok(Self::geo_radius(pair)?),
+                Rule::geo_bounding_box => Ok(Self::geo_bounding_box(pair)?),
+                Rule::geo_polygon => Ok(Self::geo_polygon(pair)?),
                 Rule::not => Ok(Self::from_pairs(fim, ff, pair.into_inner())?.negate()),
                 Rule::prgm => Self::from_pairs(fim, ff, pair.into_inner()),
                 Rule::term => Self::from_pairs(fim, ff, pair.into_inner()),
@@ -263,14 +495,156 @@ impl FacetCondition {
 
     fn negate(self) -> FacetCondition {
         match self {
-            OperatorString(fid, op) => OperatorString(fid, op.negate()),
+            OperatorString(fid, op) => match op.negate() {
+                (op, None) => OperatorString(fid, op),
+                (a, Some(b)) => Or(Box::new(OperatorString(fid, a)), Box::new(OperatorString(fid, b))),
+            },
             OperatorNumber(fid, op) => match op.negate() {
                 (op, None) => OperatorNumber(fid, op),
                 (a, Some(b)) => Or(Box::new(OperatorNumber(fid, a)), Box::new(OperatorNumber(fid, b))),
             },
+            ValuesCount(fid, op) => match op.negate() {
+                (op, None) => ValuesCount(fid, op),
+                (a, Some(b)) => Or(Box::new(ValuesCount(fid, a)), Box::new(ValuesCount(fid, b))),
+            },
+            In(..) | Exists(_) | GeoRadius { .. } | GeoBoundingBox { .. } | GeoPolygon(_) => Not(Box::new(self)),
             Or(a, b) => And(Box::new(a.negate()), Box::new(b.negate())),
             And(a, b) => Or(Box::new(a.negate()), Box::new(b.negate())),
+            Not(condition) => *condition,
+        }
+    }
+
+    fn geo_radius(item: Pair<Rule>) -> anyhow::Result<FacetCondition> {
+        let item_span = item.as_span();
+        let mut items = item.into_inner();
+        // lexing ensures that we have a latitude, a longitude and a radius
+        let (lat, lng, radius) = (items.next().unwrap(), items.next().unwrap(), items.next().unwrap());
+        let center = geo_point(lat, lng, item_span.clone())?;
+        let radius: f64 = pest_parse(radius)?;
+
+        if !radius.is_finite() || radius < 0.0 {
+            return Err(PestError::<Rule>::new_from_span(
+                ErrorVariant::CustomError {
+                    message: "the radius must be a positive number of meters".to_string(),
+                },
+                item_span,
+            ).into());
+        }
+
+        Ok(GeoRadius { center, radius })
+    }
+
+    fn geo_bounding_box(item: Pair<Rule>) -> anyhow::Result<FacetCondition> {
+        let item_span = item.as_span();
+        let points = geo_points(item)?;
+        let (top_right, bottom_left) = (points[0], points[1]);
+
+        if top_right[0] < bottom_left[0] {
+            return Err(PestError::<Rule>::new_from_span(
+                ErrorVariant::CustomError {
+                    message: "the top right corner must be above the bottom left corner".to_string(),
+                },
+                item_span,
+            ).into());
+        }
+
+        Ok(GeoBoundingBox { top_right, bottom_left })
+    }
+
+    fn geo_polygon(item: Pair<Rule>) -> anyhow::Result<FacetCondition> {
+        let item_span = item.as_span();
+        let vertices = geo_points(item)?;
+
+        if vertices.len() < 3 || vertices.len() > GEO_POLYGON_MAX_VERTICES {
+            return Err(PestError::<Rule>::new_from_span(
+                ErrorVariant::CustomError {
+                    message: format!(
+                        "a polygon must be made of at least 3 and at most {} vertices, found {}",
+                        GEO_POLYGON_MAX_VERTICES,
+                        vertices.len(),
+                    ),
+                },
+                item_span,
+            ).into());
+        }
+
+        Ok(GeoPolygon(vertices))
+    }
+
+    fn values_count(
+        fields_ids_map: &FieldsIdsMap,
+        faceted_fields: &HashMap<FieldId, FacetType>,
+        item: Pair<Rule>,
+    ) -> anyhow::Result<FacetCondition>
+    {
+        let rule = item.as_rule();
+        let mut items = item.into_inner();
+        // lexing ensures that we at least have a key
+        let key = items.next().unwrap();
+        let name = key.as_str().strip_suffix(VALUES_COUNT_SUFFIX).unwrap();
+        let field_id = fields_ids_map
+            .id(name)
+            .filter(|fid| faceted_fields.contains_key(fid))
+            .ok_or_else(|| {
+                PestError::<Rule>::new_from_span(
+                    ErrorVariant::CustomError {
+                        message: format!(
+                            "attribute `{}` is not faceted, available faceted attributes are: {}",
+                            name,
+                            faceted_fields.keys().flat_map(|id| fields_ids_map.name(*id)).collect::<Vec<_>>().join(", ")
+                        ),
+                    },
+                    key.as_span(),
+                )
+            })?;
+
+        let value = items.next().unwrap();
+        let operator = match rule {
+            Rule::greater => GreaterThan(pest_parse(value)?),
+            Rule::geq => GreaterThanOrEqual(pest_parse(value)?),
+            Rule::eq => Equal(pest_parse(value)?),
+            Rule::neq => NotEqual(pest_parse(value)?),
+            Rule::leq => LowerThanOrEqual(pest_parse(value)?),
+            Rule::less => LowerThan(pest_parse(value)?),
+            Rule::between => Between(pest_parse(value)?, pest_parse(items.next().unwrap())?),
+            _ => unreachable!(),
+        };
+
+        Ok(ValuesCount(field_id, operator))
+    }
+
+    fn exists(
+        fields_ids_map: &FieldsIdsMap,
+        faceted_fields: &HashMap<FieldId, FacetType>,
+        item: Pair<Rule>,
+    ) -> anyhow::Result<FacetCondition>
+    {
+        let mut items = item.into_inner();
+        let (fid, _ftype) = get_field_id_facet_type(fields_ids_map, faceted_fields, &mut items)?;
+        Ok(Exists(fid))
+    }
+
+    fn in_list(
+        fields_ids_map: &FieldsIdsMap,
+        faceted_fields: &HashMap<FieldId, FacetType>,
+        item: Pair<Rule>,
+    ) -> anyhow::Result<FacetCondition>
+    {
+        let mut items = item.into_inner();
+        let (fid, ftype) = get_field_id_facet_type(fields_ids_map, faceted_fields, &mut items)?;
+        let mut values = Vec::new();
+        for value in items {
+            let value = match ftype {
+                FacetType::String | FacetType::Hierarchy => FacetValue::from(value.as_str().to_string()),
+                FacetType::Number => FacetValue::from(pest_parse::<f64>(value)?),
+                FacetType::Date => FacetValue::from(pest_parse_date(value)?),
+            };
+            values.push(value);
         }
+
+        values.sort_unstable();
+        values.dedup();
+        Ok(In(fid, values))
     }
 
     fn between(
@@ -279,25 +653,26 @@ impl FacetCondition {
         item: Pair<Rule>,
     ) -> anyhow::Result<FacetCondition>
     {
-        let item_span = item.as_span();
         let mut items = item.into_inner();
         let (fid, ftype) = get_field_id_facet_type(fields_ids_map, faceted_fields, &mut items)?;
         let lvalue = items.next().unwrap();
         let rvalue = items.next().unwrap();
         match ftype {
-            FacetType::String => {
-                Err(PestError::<Rule>::new_from_span(
-                    ErrorVariant::CustomError {
-                        message: "invalid operator on a faceted string".to_string(),
-                    },
-                    item_span,
-                ).into())
+            FacetType::String | FacetType::Hierarchy => {
+                let lvalue = lvalue.as_str().to_string();
+                let rvalue = rvalue.as_str().to_string();
+                Ok(OperatorString(fid, FacetStringOperator::Between(lvalue, rvalue)))
             },
             FacetType::Number => {
                 let lvalue = pest_parse(lvalue)?;
                 let rvalue = pest_parse(rvalue)?;
                 Ok(OperatorNumber(fid, Between(lvalue, rvalue)))
             },
+            FacetType::Date => {
+                let lvalue = pest_parse_date(lvalue)?;
+                let rvalue = pest_parse_date(rvalue)?;
+                Ok(OperatorNumber(fid, Between(lvalue, rvalue)))
+            },
         }
     }
 
@@ -311,8 +686,9 @@ impl FacetCondition {
         let (fid, ftype) = get_field_id_facet_type(fields_ids_map, faceted_fields, &mut items)?;
         let value = items.next().unwrap();
         match ftype {
-            FacetType::String => Ok(OperatorString(fid, FacetStringOperator::equal(value.as_str()))),
+            FacetType::String | FacetType::Hierarchy => Ok(OperatorString(fid, FacetStringOperator::equal(value.as_str()))),
             FacetType::Number => Ok(OperatorNumber(fid, Equal(pest_parse(value)?))),
+            FacetType::Date => Ok(OperatorNumber(fid, Equal(pest_parse_date(value)?))),
         }
     }
 
@@ -322,20 +698,16 @@ impl FacetCondition {
         item: Pair<Rule>,
     ) -> anyhow::Result<FacetCondition>
     {
-        let item_span = item.as_span();
         let mut items = item.into_inner();
         let (fid, ftype) = get_field_id_facet_type(fields_ids_map, faceted_fields, &mut items)?;
         let value = items.next().unwrap();
         match ftype {
-            FacetType::String => {
-                Err(PestError::<Rule>::new_from_span(
-                    ErrorVariant::CustomError {
-                        message: "invalid operator on a faceted string".to_string(),
-                    },
-                    item_span,
-                ).into())
+            FacetType::String | FacetType::Hierarchy => {
+                let value = value.as_str().to_string();
+                Ok(OperatorString(fid, FacetStringOperator::GreaterThan(value)))
             },
             FacetType::Number => Ok(OperatorNumber(fid, GreaterThan(pest_parse(value)?))),
+            FacetType::Date => Ok(OperatorNumber(fid, GreaterThan(pest_parse_date(value)?))),
         }
     }
 
@@ -345,20 +717,16 @@ impl FacetCondition {
         item: Pair<Rule>,
     ) -> anyhow::Result<FacetCondition>
     {
-        let item_span = item.as_span();
         let mut items = item.into_inner();
         let (fid, ftype) = get_field_id_facet_type(fields_ids_map, faceted_fields, &mut items)?;
         let value = items.next().unwrap();
         match ftype {
-            FacetType::String => {
-                Err(PestError::<Rule>::new_from_span(
-                    ErrorVariant::CustomError {
-                        message: "invalid operator on a faceted string".to_string(),
-                    },
-                    item_span,
-                ).into())
+            FacetType::String | FacetType::Hierarchy => {
+                let value = value.as_str().to_string();
+                Ok(OperatorString(fid, FacetStringOperator::GreaterThanOrEqual(value)))
             },
             FacetType::Number => Ok(OperatorNumber(fid, GreaterThanOrEqual(pest_parse(value)?))),
+            FacetType::Date => Ok(OperatorNumber(fid, GreaterThanOrEqual(pest_parse_date(value)?))),
         }
     }
 
@@ -368,20 +736,16 @@ impl FacetCondition {
         item: Pair<Rule>,
     ) -> anyhow::Result<FacetCondition>
     {
-        let item_span = item.as_span();
         let mut items = item.into_inner();
         let (fid, ftype) = get_field_id_facet_type(fields_ids_map, faceted_fields, &mut items)?;
         let value = items.next().unwrap();
         match ftype {
-            FacetType::String => {
-                Err(PestError::<Rule>::new_from_span(
-                    ErrorVariant::CustomError {
-                        message: "invalid operator on a faceted string".to_string(),
-                    },
-                    item_span,
-                ).into())
+            FacetType::String | FacetType::Hierarchy => {
+                let value = value.as_str().to_string();
+                Ok(OperatorString(fid, FacetStringOperator::LowerThan(value)))
             },
             FacetType::Number => Ok(OperatorNumber(fid, LowerThan(pest_parse(value)?))),
+            FacetType::Date => Ok(OperatorNumber(fid, LowerThan(pest_parse_date(value)?))),
         }
     }
 
@@ -391,20 +755,16 @@ impl FacetCondition {
         item: Pair<Rule>,
     ) -> anyhow::Result<FacetCondition>
     {
-        let item_span = item.as_span();
         let mut items = item.into_inner();
         let (fid, ftype) = get_field_id_facet_type(fields_ids_map, faceted_fields, &mut items)?;
         let value = items.next().unwrap();
         match ftype {
-            FacetType::String => {
-                Err(PestError::<Rule>::new_from_span(
-                    ErrorVariant::CustomError {
-                        message: "invalid operator on a faceted string".to_string(),
-                    },
-                    item_span,
-                ).into())
+            FacetType::String | FacetType::Hierarchy => {
+                let value = value.as_str().to_string();
+                Ok(OperatorString(fid, FacetStringOperator::LowerThanOrEqual(value)))
             },
             FacetType::Number => Ok(OperatorNumber(fid, LowerThanOrEqual(pest_parse(value)?))),
+            FacetType::Date => Ok(OperatorNumber(fid, LowerThanOrEqual(pest_parse_date(value)?))),
         }
     }
 }
@@ -530,7 +890,7 @@ impl FacetCondition {
     fn evaluate_string_operator(
         rtxn: &heed::RoTxn,
         index: &Index,
-        db: heed::Database<FacetValueStringCodec, CboRoaringBitmapCodec>,
+        db: heed::Database<FacetValueStringCodec, FacetStringValueDocidsCodec<CboRoaringBitmapCodec>>,
         field_id: FieldId,
         operator: &FacetStringOperator,
     ) -> anyhow::Result<RoaringBitmap>
@@ -538,7 +898,7 @@ impl FacetCondition {
         match operator {
             FacetStringOperator::Equal(string) => {
                 match db.get(rtxn, &(field_id, string))? {
-                    Some(docids) => Ok(docids),
+                    Some((_original, docids)) => Ok(docids),
                     None => Ok(RoaringBitmap::new())
                 }
             },
@@ -548,43 +908,397 @@ impl FacetCondition {
                 let docids = Self::evaluate_string_operator(rtxn, index, db, field_id, &op)?;
                 Ok(all_documents_ids - docids)
             },
+            FacetStringOperator::GreaterThan(string) => {
+                Self::evaluate_string_range(rtxn, db, field_id, Excluded(string), Unbounded)
+            },
+            FacetStringOperator::GreaterThanOrEqual(string) => {
+                Self::evaluate_string_range(rtxn, db, field_id, Included(string), Unbounded)
+            },
+            FacetStringOperator::LowerThan(string) => {
+                Self::evaluate_string_range(rtxn, db, field_id, Unbounded, Excluded(string))
+            },
+            FacetStringOperator::LowerThanOrEqual(string) => {
+                Self::evaluate_string_range(rtxn, db, field_id, Unbounded, Included(string))
+            },
+            FacetStringOperator::Between(left, right) => {
+                Self::evaluate_string_range(rtxn, db, field_id, Included(left), Included(right))
+            },
         }
     }
 
+    /// Unions the documents ids of the string values of the field that are in the given range,
+    /// the strings are ordered lexicographically in the database.
+    fn evaluate_string_range(
+        rtxn: &heed::RoTxn,
+        db: heed::Database<FacetValueStringCodec, FacetStringValueDocidsCodec<CboRoaringBitmapCodec>>,
+        field_id: FieldId,
+        left: Bound<&String>,
+        right: Bound<&String>,
+    ) -> anyhow::Result<RoaringBitmap>
+    {
+        // Make sure we always bound the range with the field id,
+        // as the values of all the fields are in the same database.
+        let left = match left {
+            Included(string) => Included((field_id, string.as_str())),
+            Excluded(string) => Excluded((field_id, string.as_str())),
+            Unbounded => Included((field_id, "")),
+        };
+        let right = match right {
+            Included(string) => Included((field_id, string.as_str())),
+            Excluded(string) => Excluded((field_id, string.as_str())),
+            Unbounded => match field_id.checked_add(1) {
+                Some(next_field_id) => Excluded((next_field_id, "")),
+                None => Unbounded,
+            },
+        };
+
+        let mut output = RoaringBitmap::new();
+        for result in db.range(rtxn, &(left, right))? {
+            let (_, (_original, docids)) = result?;
+            output.union_with(&docids);
+        }
+
+        Ok(output)
+    }
+
+    /// Unions the documents ids of every value in a single pass, without
+    /// exploring the number levels as the values are exact.
+    fn evaluate_in(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        field_id: FieldId,
+        values: &[FacetValue],
+    ) -> anyhow::Result<RoaringBitmap>
+    {
+        let string_db = index.facet_string_values_docids();
+        let number_db = index.facet_number_levels_docids();
+
+        let mut output = RoaringBitmap::new();
+        for value in values {
+            let docids = match value {
+                FacetValue::String(string) => string_db.get(rtxn, &(field_id, string))?.map(|(_, docids)| docids),
+                FacetValue::Number(number) => number_db.get(rtxn, &(field_id, 0, **number, **number))?,
+            };
+            if let Some(docids) = docids {
+                output.union_with(&docids);
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn evaluate_geo_radius(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        center: [f64; 2],
+        radius: f64,
+    ) -> anyhow::Result<RoaringBitmap>
+    {
+        let rtree = match index.geo_rtree(rtxn)? {
+            Some(rtree) => rtree,
+            None => return Ok(RoaringBitmap::new()),
+        };
+
+        // The R-tree returns the points that are in the sphere around the center, we then
+        // check the distance on the surface of the earth to avoid the rounding errors.
+        let xyz_center = lat_lng_to_xyz(&center);
+        let max_squared_distance = meters_to_squared_chord(radius) * (1.0 + f64::EPSILON * 16.0);
+        let docids = rtree.locate_within_distance(xyz_center, max_squared_distance)
+            .filter(|point| distance_between_two_points(&center, &point.data.1) <= radius)
+            .map(|point| point.data.0)
+            .collect();
+
+        Ok(docids)
+    }
+
+    fn evaluate_geo_bounding_box(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        top_right: [f64; 2],
+        bottom_left: [f64; 2],
+    ) -> anyhow::Result<RoaringBitmap>
+    {
+        let rtree = match index.geo_rtree(rtxn)? {
+            Some(rtree) => rtree,
+            None => return Ok(RoaringBitmap::new()),
+        };
+
+        // When the left longitude is greater than the right one the box
+        // crosses the antimeridian and must wrap around the globe.
+        let crosses_antimeridian = bottom_left[1] > top_right[1];
+        let lat_range = [bottom_left[0], top_right[0]];
+        let lng_range = if crosses_antimeridian {
+            [bottom_left[1], top_right[1] + 360.0]
+        } else {
+            [bottom_left[1], top_right[1]]
+        };
+
+        // The R-tree returns the points in the envelopes of the box, we then
+        // check the coordinates to discard the points in the corners of the envelopes.
+        let mut docids = RoaringBitmap::new();
+        for envelope in lat_lng_envelopes(lat_range, lng_range) {
+            let points = rtree.locate_in_envelope_intersecting(&envelope).filter(|point| {
+                let [lat, lng] = point.data.1;
+                let lat_contained = bottom_left[0] <= lat && lat <= top_right[0];
+                let lng_contained = if crosses_antimeridian {
+                    bottom_left[1] <= lng || lng <= top_right[1]
+                } else {
+                    bottom_left[1] <= lng && lng <= top_right[1]
+                };
+                lat_contained && lng_contained
+            });
+            docids.extend(points.map(|point| point.data.0));
+        }
+
+        Ok(docids)
+    }
+
+    fn evaluate_values_count(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        field_id: FieldId,
+        operator: FacetNumberOperator,
+    ) -> anyhow::Result<RoaringBitmap>
+    {
+        let matches = |count: f64| match operator {
+            GreaterThan(x) => count > x,
+            GreaterThanOrEqual(x) => count >= x,
+            Equal(x) => count == x,
+            NotEqual(x) => count != x,
+            LowerThan(x) => count < x,
+            LowerThanOrEqual(x) => count <= x,
+            Between(x, y) => x <= count && count <= y,
+        };
+
+        let mut counted = RoaringBitmap::new();
+        let mut docids = RoaringBitmap::new();
+        let iter = index.facet_field_id_docid_count
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(rtxn, &[field_id])?
+            .remap_key_type::<FieldDocIdCodec>();
+
+        for result in iter {
+            let ((_fid, docid), count) = result?;
+            counted.insert(docid);
+            if matches(count.get() as f64) {
+                docids.insert(docid);
+            }
+        }
+
+        // The documents without any value for this field are not stored.
+        if matches(0.0) {
+            docids |= index.documents_ids(rtxn)? - counted;
+        }
+
+        Ok(docids)
+    }
+
+    fn evaluate_geo_polygon(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        vertices: &[[f64; 2]],
+    ) -> anyhow::Result<RoaringBitmap>
+    {
+        let rtree = match index.geo_rtree(rtxn)? {
+            Some(rtree) => rtree,
+            None => return Ok(RoaringBitmap::new()),
+        };
+
+        // The edges crossing the antimeridian are unwrapped, the longitudes
+        // of the polygon can therefore go past 180 or below -180 degrees.
+        let vertices = unwrap_longitudes(vertices);
+        let (min_lat, max_lat, min_lng, max_lng) = vertices.iter().fold(
+            (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+            |(min_lat, max_lat, min_lng, max_lng), [lat, lng]| {
+                (min_lat.min(*lat), max_lat.max(*lat), min_lng.min(*lng), max_lng.max(*lng))
+            },
+        );
+
+        // We only retrieve the points that are in the envelopes of the polygon bounding box,
+        // a point is checked at its longitude and at the longitudes one turn apart.
+        let mut docids = RoaringBitmap::new();
+        for envelope in lat_lng_envelopes([min_lat, max_lat], [min_lng, max_lng]) {
+            let points = rtree.locate_in_envelope_intersecting(&envelope).filter(|point| {
+                let [lat, lng] = point.data.1;
+                [lng, lng - 360.0, lng + 360.0].iter().any(|lng| {
+                    (min_lng..=max_lng).contains(lng) && point_in_polygon([lat, *lng], &vertices)
+                })
+            });
+            docids.extend(points.map(|point| point.data.0));
+        }
+
+        Ok(docids)
+    }
+
+    /// Returns the documents matching this condition, the condition is simplified first, using
+    /// the statistics of the faceted fields, and isn't evaluated when it can't match any document.
     pub fn evaluate(
         &self,
         rtxn: &heed::RoTxn,
         index: &Index,
     ) -> anyhow::Result<RoaringBitmap>
     {
-        let db = index.facet_field_id_value_docids;
+        let stats = index.facet_fields_stats(rtxn)?;
+        match self.clone().simplify_with_stats(&stats) {
+            Some(condition) => condition.evaluate_simplified(rtxn, index),
+            None => Ok(RoaringBitmap::new()),
+        }
+    }
+
+    fn evaluate_simplified(
+        &self,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+    ) -> anyhow::Result<RoaringBitmap>
+    {
         match self {
             OperatorString(fid, op) => {
-                let db = db.remap_key_type::<FacetValueStringCodec>();
+                let db = index.facet_string_values_docids();
                 Self::evaluate_string_operator(rtxn, index, db, *fid, op)
             },
             OperatorNumber(fid, op) => {
-                let db = db.remap_key_type::<FacetLevelValueF64Codec>();
+                let db = index.facet_number_levels_docids();
                 Self::evaluate_number_operator(rtxn, index, db, *fid, *op)
             },
+            ValuesCount(fid, op) => Self::evaluate_values_count(rtxn, index, *fid, *op),
+            In(fid, values) => Self::evaluate_in(rtxn, index, *fid, values),
+            Exists(fid) => Ok(index.faceted_documents_ids(rtxn, *fid)?),
+            GeoRadius { center, radius } => Self::evaluate_geo_radius(rtxn, index, *center, *radius),
+            GeoBoundingBox { top_right, bottom_left } => {
+                Self::evaluate_geo_bounding_box(rtxn, index, *top_right, *bottom_left)
+            },
+            GeoPolygon(vertices) => Self::evaluate_geo_polygon(rtxn, index, vertices),
             Or(lhs, rhs) => {
-                let lhs = lhs.evaluate(rtxn, index)?;
-                let rhs = rhs.evaluate(rtxn, index)?;
+                let lhs = lhs.evaluate_simplified(rtxn, index)?;
+                let rhs = rhs.evaluate_simplified(rtxn, index)?;
                 Ok(lhs | rhs)
             },
             And(lhs, rhs) => {
-                let lhs = lhs.evaluate(rtxn, index)?;
-                let rhs = rhs.evaluate(rtxn, index)?;
-                Ok(lhs & rhs)
+                let lhs = lhs.evaluate_simplified(rtxn, index)?;
+                // There is no need to evaluate the right hand side when no document can match.
+                if lhs.is_empty() {
+                    return Ok(lhs);
+                }
+                match rhs.as_ref() {
+                    // Removing the documents is cheaper than intersecting with their complement.
+                    Not(condition) => Ok(lhs - condition.evaluate_simplified(rtxn, index)?),
+                    rhs => Ok(lhs & rhs.evaluate_simplified(rtxn, index)?),
+                }
+            },
+            Not(condition) => {
+                let all_documents_ids = index.documents_ids(rtxn)?;
+                let docids = condition.evaluate_simplified(rtxn, index)?;
+                Ok(all_documents_ids - docids)
             },
         }
     }
 }
 
+impl FacetCondition {
+    /// Evaluates the condition and reports the number of documents matched by each of its clauses.
+    pub fn explain(&self, rtxn: &heed::RoTxn, index: &Index) -> anyhow::Result<FilterExplanation> {
+        let fields_ids_map = index.fields_ids_map(rtxn)?;
+        let (_docids, explanation) = self.explain_docids(rtxn, index, &fields_ids_map)?;
+        Ok(explanation)
+    }
+
+    fn explain_docids(
+        &self,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        fields_ids_map: &FieldsIdsMap,
+    ) -> anyhow::Result<(RoaringBitmap, FilterExplanation)>
+    {
+        let (docids, children) = match self {
+            Or(lhs, rhs) | And(lhs, rhs) => {
+                let (lhs_docids, lhs) = lhs.explain_docids(rtxn, index, fields_ids_map)?;
+                let (rhs_docids, rhs) = rhs.explain_docids(rtxn, index, fields_ids_map)?;
+                let docids = match self {
+                    Or(..) => lhs_docids | rhs_docids,
+                    _ => lhs_docids & rhs_docids,
+                };
+                (docids, vec![lhs, rhs])
+            },
+            Not(condition) => {
+                let (docids, child) = condition.explain_docids(rtxn, index, fields_ids_map)?;
+                (index.documents_ids(rtxn)? - docids, vec![child])
+            },
+            leaf => (leaf.evaluate(rtxn, index)?, Vec::new()),
+        };
+
+        let clause = self.clause(fields_ids_map);
+        let explanation = FilterExplanation { clause, matched_documents: docids.len(), children };
+        Ok((docids, explanation))
+    }
+
+    /// Formats the condition with the filter syntax, without its sub-conditions.
+    fn clause(&self, fields_ids_map: &FieldsIdsMap) -> String {
+        let name = |fid: &FieldId| fields_ids_map.name(*fid).unwrap_or_default();
+        match self {
+            OperatorString(fid, op) => op.clause(name(fid)),
+            OperatorNumber(fid, op) => op.clause(name(fid)),
+            ValuesCount(fid, op) => op.clause(&format!("{}{}", name(fid), VALUES_COUNT_SUFFIX)),
+            In(fid, values) => {
+                let values: Vec<_> = values.iter().map(|value| match value {
+                    FacetValue::String(string) => format!("{:?}", string),
+                    FacetValue::Number(number) => number.to_string(),
+                }).collect();
+                format!("{} IN [{}]", name(fid), values.join(", "))
+            },
+            Exists(fid) => format!("{} EXISTS", name(fid)),
+            GeoRadius { center: [lat, lng], radius } => format!("_geoRadius({}, {}, {})", lat, lng, radius),
+            GeoBoundingBox { top_right: [tr_lat, tr_lng], bottom_left: [bl_lat, bl_lng] } => {
+                format!("_geoBoundingBox([{}, {}], [{}, {}])", tr_lat, tr_lng, bl_lat, bl_lng)
+            },
+            GeoPolygon(vertices) => {
+                let vertices: Vec<_> = vertices.iter().map(|[lat, lng]| format!("[{}, {}]", lat, lng)).collect();
+                format!("_geoPolygon({})", vertices.join(", "))
+            },
+            Or(..) => "OR".to_string(),
+            And(..) => "AND".to_string(),
+            Not(_) => "NOT".to_string(),
+        }
+    }
+}
+
+/// Returns the vertices of the polygon with the longitudes shifted by whole turns so that
+/// two consecutive vertices are never more than half a turn apart, the edges that cross the
+/// antimeridian are then drawn the short way, as expected, instead of around the globe.
+fn unwrap_longitudes(vertices: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut unwrapped: Vec<[f64; 2]> = Vec::with_capacity(vertices.len());
+    for &[lat, mut lng] in vertices {
+        if let Some([_, previous]) = unwrapped.last() {
+            while lng - previous > 180.0 { lng -= 360.0 }
+            while previous - lng > 180.0 { lng += 360.0 }
+        }
+        unwrapped.push([lat, lng]);
+    }
+    unwrapped
+}
+
+/// Returns `true` if the point is inside of the polygon, the polygon can be concave.
+///
+/// It casts a ray from the point and counts the number of polygon edges that it crosses,
+/// latitudes and longitudes are considered as plane coordinates.
+fn point_in_polygon(point: [f64; 2], vertices: &[[f64; 2]]) -> bool {
+    let [y, x] = point;
+    let mut inside = false;
+    let mut j = vertices.len() - 1;
+    for (i, &[yi, xi]) in vertices.iter().enumerate() {
+        let [yj, xj] = vertices[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::update::Settings;
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use crate::Criterion;
     use heed::EnvOpenOptions;
     use maplit::hashmap;
 
@@ -605,7 +1319,7 @@ mod tests {
         // Test that the facet condition is correctly generated.
         let rtxn = index.read_txn().unwrap();
         let condition = FacetCondition::from_str(&rtxn, &index, "channel = ponce").unwrap();
-        let expected = OperatorString(0, FacetStringOperator::equal("Ponce"));
+        let expected = OperatorString(0, FacetStringOperator::equal("ponce"));
         assert_eq!(condition, expected);
 
         let condition = FacetCondition::from_str(&rtxn, &index, "channel != ponce").unwrap();
@@ -617,6 +1331,215 @@ mod tests {
         assert_eq!(condition, expected);
     }
 
+    #[test]
+    fn string_normalization() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // The genre values are kept as is, the tag values are lowercased and trimmed by default.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "genre".into() => "string".into(),
+            "tag".into() => "string".into(),
+        });
+        builder.set_facet_normalizations(hashmap!{ "genre".into() => FacetNormalization::none() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &br#"[
+            { "id": 0, "genre": "Rock", "tag": "Rock" },
+            { "id": 1, "genre": "rock", "tag": " ROCK " }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let evaluate = |rtxn: &heed::RoTxn, expression: &str| {
+            let condition = FacetCondition::from_str(rtxn, &index, expression).unwrap();
+            condition.evaluate(rtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+
+        assert_eq!(evaluate(&rtxn, "genre = Rock"), vec![0]);
+        assert_eq!(evaluate(&rtxn, "genre = rock"), vec![1]);
+        assert_eq!(evaluate(&rtxn, "tag = rOcK"), vec![0, 1]);
+        assert_eq!(evaluate(&rtxn, "tag IN [ROCK, pop]"), vec![0, 1]);
+        drop(rtxn);
+
+        // Changing the normalizations reindexes the documents.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.reset_facet_normalizations();
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert!(diff.facet_normalizations && diff.reindexed);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(evaluate(&rtxn, "genre = Rock"), vec![0, 1]);
+    }
+
+    #[test]
+    fn string_ranges() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "release".into() => "string".into() });
+        builder.execute(|_, _| ()).unwrap();
+        let content = &b"id,release\n0,2019-12\n1,2020-01\n2,2020-06\n3,2021-03\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let release = index.fields_ids_map(&rtxn).unwrap().id("release").unwrap();
+        let condition = FacetCondition::from_str(&rtxn, &index, "release > \"2020-01\"").unwrap();
+        assert_eq!(condition, OperatorString(release, FacetStringOperator::GreaterThan("2020-01".into())));
+
+        let evaluate = |expression: &str| {
+            let condition = FacetCondition::from_str(&rtxn, &index, expression).unwrap();
+            condition.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+        assert_eq!(evaluate("release > \"2020-01\""), vec![2, 3]);
+        assert_eq!(evaluate("release >= 2020-01"), vec![1, 2, 3]);
+        assert_eq!(evaluate("release < 2020"), vec![0]);
+        assert_eq!(evaluate("release <= 2020-06"), vec![0, 1, 2]);
+        assert_eq!(evaluate("release 2020 TO 2020-12"), vec![1, 2]);
+        assert_eq!(evaluate("NOT release 2020 TO 2020-12"), vec![0, 3]);
+    }
+
+    #[test]
+    fn in_list() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "genre".into() => "string".into(),
+            "year".into() => "number".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+        let content = &b"id,genre,year\n0,Rock,1999\n1,pop,2001\n2,jazz,2001\n3,,2010\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let genre = index.fields_ids_map(&rtxn).unwrap().id("genre").unwrap();
+        let condition = FacetCondition::from_str(&rtxn, &index, r#"genre IN [pop, "ROCK", pop]"#).unwrap();
+        assert_eq!(condition, In(genre, vec![FacetValue::from("pop"), FacetValue::from("rock")]));
+
+        let evaluate = |expression: &str| {
+            let condition = FacetCondition::from_str(&rtxn, &index, expression).unwrap();
+            condition.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+        assert_eq!(evaluate("genre IN [rock, pop, metal]"), vec![0, 1]);
+        assert_eq!(evaluate("year IN [2001, 2010]"), vec![1, 2, 3]);
+        assert_eq!(evaluate("genre IN []"), Vec::<u32>::new());
+        // NOT IN removes the matching documents from all the documents.
+        assert_eq!(evaluate("genre NOT IN [rock, pop]"), vec![2, 3]);
+        assert_eq!(evaluate("NOT year IN [1999]"), vec![1, 2, 3]);
+        assert_eq!(evaluate("year = 2001 AND genre NOT IN [pop]"), vec![2]);
+    }
+
+    #[test]
+    fn exists_and_is_null() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "price".into() => "number".into(),
+            "description".into() => "string".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+        let content = &br#"[
+            { "id": 0, "price": 10, "description": "cheap" },
+            { "id": 1, "price": null, "description": [] },
+            { "id": 2, "description": "free" },
+            { "id": 3, "price": [2, 3] }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let price = index.fields_ids_map(&rtxn).unwrap().id("price").unwrap();
+        let condition = FacetCondition::from_str(&rtxn, &index, "price IS NULL").unwrap();
+        assert_eq!(condition, Not(Box::new(Exists(price))));
+
+        let evaluate = |expression: &str| {
+            let condition = FacetCondition::from_str(&rtxn, &index, expression).unwrap();
+            condition.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+        assert_eq!(evaluate("price EXISTS"), vec![0, 3]);
+        assert_eq!(evaluate("price IS NOT NULL"), vec![0, 3]);
+        assert_eq!(evaluate("price NOT EXISTS"), vec![1, 2]);
+        assert_eq!(evaluate("description IS NULL"), vec![1, 3]);
+        assert_eq!(evaluate("price EXISTS AND description IS NULL"), vec![3]);
+    }
+
+    #[test]
+    fn date() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "created_at".into() => "date".into() });
+        builder.set_criteria(vec![Criterion::Desc("created_at".into())]);
+        builder.execute(|_, _| ()).unwrap();
+
+        // The dates are given as RFC3339 dates or as numbers of seconds since the epoch.
+        let content = &br#"[
+            { "id": 0, "created_at": "2021-02-28T23:59:59Z" },
+            { "id": 1, "created_at": "2021-03-01T00:00:00Z" },
+            { "id": 2, "created_at": 1617235200 },
+            { "id": 3, "created_at": "2021-03-15T12:00:00+02:00" },
+            { "id": 4, "created_at": "not a date" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let created_at = index.fields_ids_map(&rtxn).unwrap().id("created_at").unwrap();
+        let condition = FacetCondition::from_str(&rtxn, &index, r#"created_at > "2021-03-01T00:00:00Z""#).unwrap();
+        assert_eq!(condition, OperatorNumber(created_at, GreaterThan(1614556800.0)));
+
+        let evaluate = |expression: &str| {
+            let condition = FacetCondition::from_str(&rtxn, &index, expression).unwrap();
+            condition.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+        assert_eq!(evaluate(r#"created_at > "2021-03-01T00:00:00Z""#), vec![2, 3]);
+        assert_eq!(evaluate(r#"created_at >= "2021-03-01T00:00:00Z""#), vec![1, 2, 3]);
+        assert_eq!(evaluate(r#"created_at "2021-03-01T00:00:00Z" TO 1617235200"#), vec![1, 2, 3]);
+        assert_eq!(evaluate("created_at < 1614556800"), vec![0]);
+        assert!(FacetCondition::from_str(&rtxn, &index, "created_at > yesterday").is_err());
+
+        // The documents are sorted by their dates, the invalid date is not indexed.
+        let result = index.search(&rtxn).execute().unwrap();
+        assert_eq!(result.documents_ids[..4], [2, 3, 1, 0]);
+    }
+
     #[test]
     fn number() {
         let path = tempfile::tempdir().unwrap();
@@ -695,6 +1618,44 @@ mod tests {
         assert_eq!(condition, expected);
     }
 
+    #[test]
+    fn nested_boolean_expression() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "genre".into() => "string".into(),
+            "year".into() => "number".into(),
+            "label".into() => "string".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+        let content = &b"id,genre,year,label\n\
+            0,rock,1995,major\n\
+            1,jazz,1992,indie\n\
+            2,jazz,2001,major\n\
+            3,pop,2005,major\n\
+            4,rock,1985,major\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let expression = r#"(genre = "rock" OR genre = "jazz") AND year >= 1990 AND NOT label = "indie""#;
+        let condition = FacetCondition::from_str(&rtxn, &index, expression).unwrap();
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![0, 2]);
+
+        let expression = r#"NOT (genre = "rock" OR (year > 2000 AND label = major))"#;
+        let condition = FacetCondition::from_str(&rtxn, &index, expression).unwrap();
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![1]);
+    }
+
     #[test]
     fn from_array() {
         let path = tempfile::tempdir().unwrap();
@@ -725,4 +1686,261 @@ mod tests {
         ).unwrap();
         assert_eq!(condition, expected);
     }
+
+    #[test]
+    fn geo_radius() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": "paris", "_geo": { "lat": 48.8566, "lng": 2.3522 } },
+            { "id": "versailles", "_geo": { "lat": 48.8049, "lng": 2.1204 } },
+            { "id": "london", "_geo": { "lat": 51.5074, "lng": -0.1278 } },
+            { "id": "nowhere" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docid = |id| index.external_to_internal_id(&rtxn, id).unwrap().unwrap();
+        let evaluate = |expression| {
+            let condition = FacetCondition::from_str(&rtxn, &index, expression).unwrap();
+            condition.evaluate(&rtxn, &index).unwrap().into_iter().collect::<Vec<_>>()
+        };
+
+        let condition = FacetCondition::from_str(&rtxn, &index, "_geoRadius(48.8566, 2.3522, 1000)").unwrap();
+        assert_eq!(condition, GeoRadius { center: [48.8566, 2.3522], radius: 1000.0 });
+
+        // Versailles is at about 18 kilometers of Paris and London at about 343 kilometers.
+        assert_eq!(evaluate("_geoRadius(48.8566, 2.3522, 1000)"), vec![docid("paris")]);
+        let mut expected = vec![docid("paris"), docid("versailles")];
+        expected.sort_unstable();
+        assert_eq!(evaluate("_geoRadius(48.8566, 2.3522, 20000)"), expected);
+        assert_eq!(evaluate("_geoRadius(48.8566, 2.3522, 400000)").len(), 3);
+        assert_eq!(evaluate("NOT _geoRadius(48.8566, 2.3522, 20000)").len(), 2);
+
+        assert!(FacetCondition::from_str(&rtxn, &index, "_geoRadius(48.8566, 2.3522, -1)").is_err());
+        assert!(FacetCondition::from_str(&rtxn, &index, "_geoRadius(91, 2.3522, 1000)").is_err());
+        assert!(FacetCondition::from_str(&rtxn, &index, "_geoRadius(48.8566, 2.3522)").is_err());
+    }
+
+    #[test]
+    fn geo_bounding_box() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": "paris", "_geo": { "lat": 48.8566, "lng": 2.3522 } },
+            { "id": "london", "_geo": { "lat": 51.5074, "lng": -0.1278 } },
+            { "id": "fiji", "_geo": { "lat": -17.7134, "lng": 178.0650 } },
+            { "id": "samoa", "_geo": { "lat": -13.7590, "lng": -172.1046 } },
+            { "id": "nowhere" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docid = |id| index.external_to_internal_id(&rtxn, id).unwrap().unwrap();
+
+        let condition = FacetCondition::from_str(&rtxn, &index, "_geoBoundingBox([50, 3], [48, 2])").unwrap();
+        let expected = GeoBoundingBox { top_right: [50.0, 3.0], bottom_left: [48.0, 2.0] };
+        assert_eq!(condition, expected);
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![docid("paris")]);
+
+        // This box crosses the antimeridian.
+        let condition = FacetCondition::from_str(&rtxn, &index, "_geoBoundingBox([-10, -170], [-20, 170])").unwrap();
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        let mut expected = vec![docid("fiji"), docid("samoa")];
+        expected.sort_unstable();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), expected);
+
+        let condition = FacetCondition::from_str(&rtxn, &index, "NOT _geoBoundingBox([-10, -170], [-20, 170])").unwrap();
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        let mut expected = vec![docid("paris"), docid("london"), docid("nowhere")];
+        expected.sort_unstable();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), expected);
+
+        assert!(FacetCondition::from_str(&rtxn, &index, "_geoBoundingBox([48, 3], [50, 2])").is_err());
+        assert!(FacetCondition::from_str(&rtxn, &index, "_geoBoundingBox([91, 3], [50, 2])").is_err());
+    }
+
+    #[test]
+    fn concave_polygon() {
+        // A "C" shaped polygon, opened on the right.
+        let polygon = [
+            [0.0, 0.0], [0.0, 10.0], [2.0, 10.0], [2.0, 2.0],
+            [8.0, 2.0], [8.0, 10.0], [10.0, 10.0], [10.0, 0.0],
+        ];
+        assert!(point_in_polygon([1.0, 5.0], &polygon));
+        assert!(point_in_polygon([5.0, 1.0], &polygon));
+        assert!(point_in_polygon([9.0, 9.0], &polygon));
+        // The hole of the "C" is not part of the polygon.
+        assert!(!point_in_polygon([5.0, 5.0], &polygon));
+        assert!(!point_in_polygon([11.0, 5.0], &polygon));
+    }
+
+    #[test]
+    fn geo_polygon() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": "inside", "_geo": { "lat": 1, "lng": 5 } },
+            { "id": "hole", "_geo": { "lat": 5, "lng": 5 } },
+            { "id": "outside", "_geo": { "lat": 20, "lng": 20 } },
+            { "id": "fiji", "_geo": { "lat": -17.7134, "lng": 178.0650 } },
+            { "id": "samoa", "_geo": { "lat": -13.7590, "lng": -172.1046 } }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docid = |id| index.external_to_internal_id(&rtxn, id).unwrap().unwrap();
+
+        let condition = FacetCondition::from_str(
+            &rtxn, &index,
+            "_geoPolygon([0, 0], [0, 10], [2, 10], [2, 2], [8, 2], [8, 10], [10, 10], [10, 0])",
+        ).unwrap();
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![docid("inside")]);
+
+        // This polygon crosses the antimeridian, between Fiji and Samoa.
+        let condition = FacetCondition::from_str(
+            &rtxn, &index,
+            "_geoPolygon([-10, 170], [-10, -170], [-20, -170], [-20, 170])",
+        ).unwrap();
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        let mut expected = vec![docid("fiji"), docid("samoa")];
+        expected.sort_unstable();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), expected);
+
+        assert!(FacetCondition::from_str(&rtxn, &index, "_geoPolygon([0, 0], [0, 10])").is_err());
+        let too_many_vertices = (0..=GEO_POLYGON_MAX_VERTICES)
+            .map(|i| format!("[{}, {}]", i % 90, i % 180))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let expression = format!("_geoPolygon({})", too_many_vertices);
+        assert!(FacetCondition::from_str(&rtxn, &index, &expression).is_err());
+    }
+
+    #[test]
+    fn values_count() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "genres".into() => "string".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &br#"[
+            { "id": "alien", "genres": ["horror", "science fiction"] },
+            { "id": "jaws", "genres": "horror" },
+            { "id": "brazil", "genres": ["comedy", "drama", "science fiction"] },
+            { "id": "nothing" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docid = |id| index.external_to_internal_id(&rtxn, id).unwrap().unwrap();
+        let genres = index.fields_ids_map(&rtxn).unwrap().id("genres").unwrap();
+        assert_eq!(index.facet_values_count(&rtxn, genres, docid("brazil")).unwrap(), 3);
+        assert_eq!(index.facet_values_count(&rtxn, genres, docid("nothing")).unwrap(), 0);
+
+        let condition = FacetCondition::from_str(&rtxn, &index, "genres.length > 1").unwrap();
+        assert_eq!(condition, ValuesCount(genres, GreaterThan(1.0)));
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        let mut expected = vec![docid("alien"), docid("brazil")];
+        expected.sort_unstable();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), expected);
+
+        // The documents without any value are counted as having zero values.
+        let condition = FacetCondition::from_str(&rtxn, &index, "NOT genres.length >= 1").unwrap();
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![docid("nothing")]);
+
+        let condition = FacetCondition::from_str(&rtxn, &index, "genres.length 1 TO 2").unwrap();
+        let docids = condition.evaluate(&rtxn, &index).unwrap();
+        let mut expected = vec![docid("alien"), docid("jaws")];
+        expected.sort_unstable();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), expected);
+
+        assert!(FacetCondition::from_str(&rtxn, &index, "id.length > 1").is_err());
+    }
+
+    #[test]
+    fn explain() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "channel".into() => "string".into(),
+            "timestamp".into() => "number".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,channel,timestamp\n1,gotaga,20\n2,ponce,30\n3,gotaga,40\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let condition = FacetCondition::from_str(
+            &rtxn, &index,
+            "channel = ponce AND (timestamp > 35 OR NOT timestamp 10 TO 30)",
+        ).unwrap();
+        let explanation = condition.explain(&rtxn, &index).unwrap();
+
+        let leaf = |clause: &str, matched_documents| FilterExplanation {
+            clause: clause.to_string(),
+            matched_documents,
+            children: Vec::new(),
+        };
+        let expected = FilterExplanation {
+            clause: "AND".to_string(),
+            matched_documents: 0,
+            children: vec![
+                leaf("channel = \"ponce\"", 1),
+                FilterExplanation {
+                    clause: "OR".to_string(),
+                    matched_documents: 1,
+                    children: vec![
+                        leaf("timestamp > 35", 1),
+                        FilterExplanation {
+                            clause: "OR".to_string(),
+                            matched_documents: 1,
+                            children: vec![leaf("timestamp < 10", 0), leaf("timestamp > 30", 1)],
+                        },
+                    ],
+                },
+            ],
+        };
+        assert_eq!(explanation, expected);
+    }
 }
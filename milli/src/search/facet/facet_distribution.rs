@@ -1,16 +1,18 @@
-use std::collections::{HashSet, BTreeMap};
-use std::ops::Bound::Unbounded;
-use std::{cmp, fmt};
+use std::cmp::{self, Reverse};
+use std::collections::{BinaryHeap, HashSet, BTreeMap};
+use std::fmt;
+use std::ops::Bound::{Included, Unbounded};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use heed::BytesDecode;
+use ordered_float::OrderedFloat;
 use roaring::RoaringBitmap;
 
-use crate::facet::{FacetType, FacetValue};
-use crate::heed_codec::facet::{FacetValueStringCodec, FacetLevelValueF64Codec};
+use crate::facet::{FacetHierarchyNode, FacetType, FacetValue, HIERARCHY_SEPARATOR, normalize_path};
+use crate::heed_codec::facet::{FacetStringValueDocidsCodec, FacetValueStringCodec};
 use crate::heed_codec::facet::{FieldDocIdFacetStringCodec, FieldDocIdFacetF64Codec};
 use crate::search::facet::{FacetIter, FacetRange};
-use crate::{Index, FieldId, DocumentId};
+use crate::{Index, FieldId, DocumentId, CboRoaringBitmapCodec, CboRoaringBitmapLenCodec};
 
 /// The default number of values by facets that will
 /// be fetched from the key-value store.
@@ -20,14 +22,32 @@ const DEFAULT_VALUES_BY_FACET: usize = 100;
 /// the key-value store. Searching for more values could slow down the engine.
 const MAX_VALUES_BY_FACET: usize = 1000;
 
-/// Threshold on the number of candidates that will make
-/// the system to choose between one algorithm or another.
+/// Threshold on the number of candidates under which the facet values are always
+/// fetched from the documents, above it the number of distinct values of the field
+/// is used to choose between one algorithm or another.
 const CANDIDATES_THRESHOLD: u64 = 1000;
 
+/// Defines which values of a facet are kept when there are
+/// more of them than the maximum number of values by facet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FacetValuesOrder {
+    /// Keeps the values associated with the highest number of documents.
+    Count,
+    /// Keeps the lowest values, in lexicographic order for strings.
+    Value,
+}
+
+impl Default for FacetValuesOrder {
+    fn default() -> FacetValuesOrder {
+        FacetValuesOrder::Value
+    }
+}
+
 pub struct FacetDistribution<'a> {
     facets: Option<HashSet<String>>,
     candidates: Option<RoaringBitmap>,
     max_values_by_facet: usize,
+    order: FacetValuesOrder,
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
 }
@@ -38,6 +58,7 @@ impl<'a> FacetDistribution<'a> {
             facets: None,
             candidates: None,
             max_values_by_facet: DEFAULT_VALUES_BY_FACET,
+            order: FacetValuesOrder::default(),
             rtxn,
             index,
         }
@@ -58,8 +79,84 @@ impl<'a> FacetDistribution<'a> {
         self
     }
 
+    /// Defines which values are kept when a facet has more values than the maximum
+    /// number of values by facet, the values are always returned in value order.
+    pub fn order_by(&mut self, order: FacetValuesOrder) -> &mut Self {
+        self.order = order;
+        self
+    }
+
+    /// Only keeps the maximum number of values by facet of the given
+    /// facet values, chosen according to the facet values order.
+    fn keep_top_values(&self, facet_values: BTreeMap<FacetValue, u64>) -> BTreeMap<FacetValue, u64> {
+        if facet_values.len() <= self.max_values_by_facet {
+            return facet_values;
+        }
+
+        match self.order {
+            FacetValuesOrder::Value => facet_values.into_iter().take(self.max_values_by_facet).collect(),
+            FacetValuesOrder::Count => {
+                let mut facet_values: Vec<_> = facet_values.into_iter().collect();
+                // The sort is stable, values with the same count stay in value order.
+                facet_values.sort_by_key(|(_, count)| Reverse(*count));
+                facet_values.truncate(self.max_values_by_facet);
+                facet_values.into_iter().collect()
+            },
+        }
+    }
+
+    /// The facet levels are explored best-first, the number of documents of a group
+    /// is an upper bound of the number of documents of every value it contains, we can
+    /// therefore stop as soon as enough level 0 entries are at the top of the queue.
+    fn facet_numbers_by_count(
+        &self,
+        field_id: FieldId,
+        candidates: Option<&RoaringBitmap>,
+    ) -> heed::Result<BTreeMap<FacetValue, u64>>
+    {
+        let db = self.index.facet_number_levels_docids();
+        let highest_level = FacetIter::highest_level(self.rtxn, db, field_id)?.unwrap_or(0);
+
+        let mut queue = BinaryHeap::new();
+        let mut facet_values = BTreeMap::new();
+        let (mut level, mut left, mut right) = (highest_level, Unbounded, Unbounded);
+
+        loop {
+            for result in FacetRange::new(self.rtxn, db, field_id, level, left, right)? {
+                let ((_, group_level, group_left, group_right), mut docids) = result?;
+                if let Some(candidates) = candidates {
+                    docids.intersect_with(candidates);
+                }
+                if !docids.is_empty() {
+                    // Groups with the same count are popped by ascending values.
+                    let (group_left, group_right) = (OrderedFloat(group_left), OrderedFloat(group_right));
+                    queue.push((docids.len(), Reverse(group_left), group_level, group_right));
+                }
+            }
+
+            loop {
+                match queue.pop() {
+                    Some((count, Reverse(value), 0, _)) => {
+                        facet_values.insert(FacetValue::from(value), count);
+                        if facet_values.len() == self.max_values_by_facet {
+                            return Ok(facet_values);
+                        }
+                    },
+                    Some((_, Reverse(group_left), group_level, group_right)) => {
+                        level = group_level - 1;
+                        left = Included(group_left.into_inner());
+                        right = Included(group_right.into_inner());
+                        break;
+                    },
+                    None => return Ok(facet_values),
+                }
+            }
+        }
+    }
+
     /// There is a small amount of candidates OR we ask for facet string values so we
-    /// decide to iterate over the facet values of each one of them, one by one.
+    /// decide to iterate over the facet values of each one of them, one by one. Only
+    /// the first candidates are used for the string values, their counts are approximate.
     fn facet_values_from_documents(
         &self,
         field_id: FieldId,
@@ -72,6 +169,7 @@ impl<'a> FacetDistribution<'a> {
             rtxn: &'t heed::RoTxn,
             field_id: FieldId,
             candidates: &RoaringBitmap,
+            limit: usize,
         ) -> heed::Result<BTreeMap<FacetValue, u64>>
         where
             KC: BytesDecode<'t, DItem = (FieldId, DocumentId, K)>,
@@ -80,7 +178,7 @@ impl<'a> FacetDistribution<'a> {
             let mut facet_values = BTreeMap::new();
             let mut key_buffer = vec![field_id];
 
-            for docid in candidates.into_iter().take(CANDIDATES_THRESHOLD as usize) {
+            for docid in candidates.into_iter().take(limit) {
                 key_buffer.truncate(1);
                 key_buffer.extend_from_slice(&docid.to_be_bytes());
                 let iter = index.field_id_docid_facet_values
@@ -98,14 +196,42 @@ impl<'a> FacetDistribution<'a> {
 
         let index = self.index;
         let rtxn = self.rtxn;
-        match facet_type {
-            FacetType::String => {
-                fetch_facet_values::<FieldDocIdFacetStringCodec, _>(index, rtxn, field_id, candidates)
+        let facet_values = match facet_type {
+            FacetType::String | FacetType::Hierarchy => {
+                let limit = CANDIDATES_THRESHOLD as usize;
+                let facet_values = fetch_facet_values::<FieldDocIdFacetStringCodec, _>(
+                    index, rtxn, field_id, candidates, limit,
+                )?;
+                return self.original_facet_strings(field_id, self.keep_top_values(facet_values));
             },
-            FacetType::Number => {
-                fetch_facet_values::<FieldDocIdFacetF64Codec, _>(index, rtxn, field_id, candidates)
+            FacetType::Number | FacetType::Date => {
+                let limit = usize::MAX;
+                fetch_facet_values::<FieldDocIdFacetF64Codec, _>(index, rtxn, field_id, candidates, limit)?
             },
+        };
+
+        Ok(self.keep_top_values(facet_values))
+    }
+
+    /// Replaces the normalized facet strings by their original form, as written in the documents.
+    fn original_facet_strings(
+        &self,
+        field_id: FieldId,
+        facet_values: BTreeMap<FacetValue, u64>,
+    ) -> heed::Result<BTreeMap<FacetValue, u64>>
+    {
+        let mut originals = BTreeMap::new();
+        for (value, count) in facet_values {
+            let value = match value {
+                FacetValue::String(string) => match self.index.facet_string_original(self.rtxn, field_id, &string)? {
+                    Some(original) => FacetValue::from(original),
+                    None => FacetValue::String(string),
+                },
+                value => value,
+            };
+            originals.insert(value, count);
         }
+        Ok(originals)
     }
 
     /// There is too much documents, we use the facet levels to move throught
@@ -117,9 +243,13 @@ impl<'a> FacetDistribution<'a> {
         candidates: &RoaringBitmap,
     ) -> heed::Result<BTreeMap<FacetValue, u64>>
     {
+        if self.order == FacetValuesOrder::Count {
+            return self.facet_numbers_by_count(field_id, Some(candidates));
+        }
+
         let iter = match facet_type {
-            FacetType::String => unreachable!(),
-            FacetType::Number => {
+            FacetType::String | FacetType::Hierarchy => unreachable!(),
+            FacetType::Number | FacetType::Date => {
                 let iter = FacetIter::new_non_reducing(
                     self.rtxn, self.index, field_id, candidates.clone(),
                 )?;
@@ -150,18 +280,36 @@ impl<'a> FacetDistribution<'a> {
         facet_type: FacetType,
     ) -> heed::Result<BTreeMap<FacetValue, u64>>
     {
+        if self.order == FacetValuesOrder::Count {
+            return match facet_type {
+                FacetType::String | FacetType::Hierarchy => {
+                    // There is no facet levels for strings, we must count every value.
+                    let mut facet_values = BTreeMap::new();
+                    let iter = self.index.facet_field_id_value_docids
+                        .prefix_iter(self.rtxn, &[field_id])?
+                        .remap_types::<FacetValueStringCodec, FacetStringValueDocidsCodec<CboRoaringBitmapLenCodec>>();
+                    for result in iter {
+                        let (_, (original, count)) = result?;
+                        facet_values.insert(FacetValue::from(original), count);
+                    }
+                    Ok(self.keep_top_values(facet_values))
+                },
+                FacetType::Number | FacetType::Date => self.facet_numbers_by_count(field_id, None),
+            };
+        }
+
         let db = self.index.facet_field_id_value_docids;
         let level = 0;
         let iter = match facet_type {
-            FacetType::String => {
+            FacetType::String | FacetType::Hierarchy => {
                 let iter = db
                     .prefix_iter(self.rtxn, &[field_id])?
-                    .remap_key_type::<FacetValueStringCodec>()
-                    .map(|r| r.map(|((_, v), docids)| (FacetValue::from(v), docids)));
+                    .remap_types::<FacetValueStringCodec, FacetStringValueDocidsCodec<CboRoaringBitmapCodec>>()
+                    .map(|r| r.map(|(_, (original, docids))| (FacetValue::from(original), docids)));
                 Box::new(iter) as Box::<dyn Iterator<Item=_>>
             },
-            FacetType::Number => {
-                let db = db.remap_key_type::<FacetLevelValueF64Codec>();
+            FacetType::Number | FacetType::Date => {
+                let db = self.index.facet_number_levels_docids();
                 let range = FacetRange::new(
                     self.rtxn, db, field_id, level, Unbounded, Unbounded,
                 )?;
@@ -190,7 +338,12 @@ impl<'a> FacetDistribution<'a> {
         if let Some(candidates) = self.candidates.as_ref() {
             // Classic search, candidates were specified, we must return facet values only related
             // to those candidates. We also enter here for facet strings for performance reasons.
-            if candidates.len() <= CANDIDATES_THRESHOLD || facet_type == FacetType::String {
+            // Iterating over the candidates is cheaper than over the facet levels when there are
+            // fewer candidates than distinct values, according to the statistics of the field.
+            let distinct_values = self.index.facet_field_stats(self.rtxn, field_id)?.map(|s| s.distinct_values);
+            let fewer_candidates = distinct_values.map_or(false, |count| candidates.len() <= count);
+            let is_string = matches!(facet_type, FacetType::String | FacetType::Hierarchy);
+            if candidates.len() <= CANDIDATES_THRESHOLD || fewer_candidates || is_string {
                 self.facet_values_from_documents(field_id, facet_type, candidates)
             } else {
                 self.facet_values_from_facet_levels(field_id, facet_type, candidates)
@@ -222,6 +375,84 @@ impl<'a> FacetDistribution<'a> {
 
         Ok(facets_values)
     }
+
+    /// Returns the tree of the number of documents of the levels that are under the given
+    /// path of a hierarchical faceted field, the whole hierarchy is returned when the path is
+    /// empty. The children of a level are limited to the maximum number of values by facet,
+    /// they are chosen according to the facet values order.
+    pub fn execute_hierarchy(&self, name: &str, path: &str) -> anyhow::Result<Vec<FacetHierarchyNode>> {
+        let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        match self.index.faceted_fields(self.rtxn)?.get(name) {
+            Some(FacetType::Hierarchy) => (),
+            Some(_) => bail!("{:?} isn't a hierarchical faceted field", name),
+            None => bail!("{:?} isn't a faceted field", name),
+        }
+        let field_id = fields_ids_map.id(name).with_context(|| {
+            format!("missing field name {:?} from the fields id map", name)
+        })?;
+
+        // The paths are normalized at indexing time, we must do the same here.
+        let normalization = self.index.facet_normalizations(self.rtxn)?.get(name).copied().unwrap_or_default();
+        let path = normalize_path(path, normalization);
+        let mut prefix = vec![field_id];
+        if !path.is_empty() {
+            prefix.extend_from_slice(path.as_bytes());
+            prefix.extend_from_slice(HIERARCHY_SEPARATOR.as_bytes());
+        }
+
+        let iter = self.index.facet_field_id_value_docids
+            .prefix_iter(self.rtxn, &prefix)?
+            .remap_types::<FacetValueStringCodec, FacetStringValueDocidsCodec<CboRoaringBitmapCodec>>();
+
+        let mut counts = BTreeMap::new();
+        for result in iter {
+            let ((_, value), (original, mut docids)) = result?;
+            if let Some(candidates) = &self.candidates {
+                docids.intersect_with(candidates);
+            }
+            if !docids.is_empty() {
+                counts.insert(value.to_string(), (docids.len(), original));
+            }
+        }
+
+        Ok(self.hierarchy_children(&path, &counts))
+    }
+
+    /// Builds the nodes of the direct children of the given path, and of their own children,
+    /// from the number of documents and the original form of every path under it.
+    fn hierarchy_children(&self, path: &str, counts: &BTreeMap<String, (u64, &str)>) -> Vec<FacetHierarchyNode> {
+        let prefix = if path.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}", path, HIERARCHY_SEPARATOR)
+        };
+
+        let children: BTreeMap<_, _> = counts
+            .range::<str, _>((Included(prefix.as_str()), Unbounded))
+            .take_while(|(child, _)| child.starts_with(&prefix))
+            .filter(|(child, _)| !child[prefix.len()..].contains(HIERARCHY_SEPARATOR))
+            .map(|(child, (count, _))| (FacetValue::from(child.as_str()), *count))
+            .collect();
+
+        self.keep_top_values(children)
+            .into_iter()
+            .filter_map(|(value, count)| match value {
+                FacetValue::String(path) => Some((path, count)),
+                FacetValue::Number(_) => None,
+            })
+            .map(|(path, count)| {
+                // The last level of the original path is the one that is displayed.
+                let original = counts.get(&path).map_or("", |(_, original)| *original);
+                let value = original.rsplit(HIERARCHY_SEPARATOR).next().unwrap_or_default();
+                FacetHierarchyNode {
+                    value: value.to_string(),
+                    children: self.hierarchy_children(&path, counts),
+                    path,
+                    count,
+                }
+            })
+            .collect()
+    }
 }
 
 impl fmt::Debug for FacetDistribution<'_> {
@@ -230,6 +461,7 @@ impl fmt::Debug for FacetDistribution<'_> {
             facets,
             candidates,
             max_values_by_facet,
+            order,
             rtxn: _,
             index: _,
         } = self;
@@ -238,6 +470,167 @@ impl fmt::Debug for FacetDistribution<'_> {
             .field("facets", facets)
             .field("candidates", candidates)
             .field("max_values_by_facet", max_values_by_facet)
+            .field("order", order)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+    use maplit::{btreemap, hashmap};
+
+    use crate::FacetCondition;
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use super::*;
+
+    #[test]
+    fn top_values_by_count_and_by_value() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "genre".into() => "string".into(),
+            "age".into() => "number".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+
+        // The ages 7 and 13 are shared by many documents, the other ones are unique.
+        let mut content = String::from("id,genre,age\n");
+        for id in 0..28 {
+            let age = match id { 0..=19 => id, 20..=24 => 7, _ => 13 };
+            let genre = match id { 0..=14 => "rock", 15..=24 => "pop", _ => "jazz" };
+            content.push_str(&format!("{},{},{}\n", id, genre, age));
+        }
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let candidates = index.documents_ids(&rtxn).unwrap();
+
+        for candidates in vec![None, Some(candidates)] {
+            let mut distribution = index.facets_distribution(&rtxn);
+            if let Some(candidates) = candidates.clone() {
+                distribution.candidates(candidates);
+            }
+
+            distribution.max_values_by_facet(2).order_by(FacetValuesOrder::Count);
+            let facets = distribution.execute().unwrap();
+            assert_eq!(facets["age"], btreemap!{ FacetValue::from(7.0) => 6, FacetValue::from(13.0) => 4 });
+            assert_eq!(facets["genre"], btreemap!{ FacetValue::from("pop") => 10, FacetValue::from("rock") => 15 });
+
+            distribution.order_by(FacetValuesOrder::Value);
+            let facets = distribution.execute().unwrap();
+            assert_eq!(facets["age"], btreemap!{ FacetValue::from(0.0) => 1, FacetValue::from(1.0) => 1 });
+            assert_eq!(facets["genre"], btreemap!{ FacetValue::from("jazz") => 3, FacetValue::from("pop") => 10 });
+        }
+    }
+
+    #[test]
+    fn original_string_values() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "city".into() => "string".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,city
+0,New York
+1,new york
+2,  Paris 
+"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let candidates = index.documents_ids(&rtxn).unwrap();
+        let expected = btreemap!{ FacetValue::from("New York") => 2, FacetValue::from("Paris") => 1 };
+
+        for candidates in vec![None, Some(candidates)] {
+            let mut distribution = index.facets_distribution(&rtxn);
+            if let Some(candidates) = candidates.clone() {
+                distribution.candidates(candidates);
+            }
+
+            distribution.order_by(FacetValuesOrder::Count);
+            assert_eq!(distribution.execute().unwrap()["city"], expected);
+
+            distribution.order_by(FacetValuesOrder::Value);
+            assert_eq!(distribution.execute().unwrap()["city"], expected);
+        }
+
+        // The filters are still applied on the normalized values.
+        let condition = FacetCondition::from_str(&rtxn, &index, "city = \"NEW YORK\"").unwrap();
+        let documents_ids: Vec<_> = condition.evaluate(&rtxn, &index).unwrap().into_iter().collect();
+        assert_eq!(documents_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn hierarchy_tree_and_filters() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "categories".into() => "hierarchy".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &br#"[
+            { "id": 0, "categories": "Books > Fantasy > Epic" },
+            { "id": 1, "categories": "Books>Fantasy" },
+            { "id": 2, "categories": ["Books > Science", "Movies > Fantasy"] },
+            { "id": 3, "categories": "Movies" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let evaluate = |expression: &str| {
+            let condition = FacetCondition::from_str(&rtxn, &index, expression).unwrap();
+            condition.evaluate(&rtxn, &index).unwrap().iter().collect::<Vec<_>>()
+        };
+        assert_eq!(evaluate("categories = Books"), vec![0, 1, 2]);
+        assert_eq!(evaluate("categories = \"books > fantasy\""), vec![0, 1]);
+        assert_eq!(evaluate("categories = \"Books>Fantasy>Epic\""), vec![0]);
+        assert_eq!(evaluate("categories = Fantasy"), Vec::<u32>::new());
+
+        // The values are the original last levels of the normalized paths.
+        let node = |original: &str, count, children| FacetHierarchyNode {
+            value: original.rsplit(HIERARCHY_SEPARATOR).next().unwrap().to_string(),
+            path: original.to_lowercase(),
+            count,
+            children,
+        };
+
+        let tree = index.facets_distribution(&rtxn).execute_hierarchy("categories", "Books").unwrap();
+        let expected = vec![
+            node("Books > Fantasy", 2, vec![node("Books > Fantasy > Epic", 1, vec![])]),
+            node("Books > Science", 1, vec![]),
+        ];
+        assert_eq!(tree, expected);
+
+        let mut distribution = index.facets_distribution(&rtxn);
+        distribution.candidates((2..4).collect()).max_values_by_facet(1).order_by(FacetValuesOrder::Count);
+        let tree = distribution.execute_hierarchy("categories", "").unwrap();
+        let expected = vec![
+            node("Movies", 2, vec![node("Movies > Fantasy", 1, vec![])]),
+        ];
+        assert_eq!(tree, expected);
+    }
+}
@@ -0,0 +1,310 @@
+use std::num::NonZeroUsize;
+use std::{cmp, fmt};
+
+use anyhow::{bail, Context};
+use heed::LazyDecode;
+use levenshtein_automata::Distance;
+use roaring::RoaringBitmap;
+
+use crate::facet::FacetType;
+use crate::heed_codec::facet::{FacetStringValueDocidsCodec, FacetValueStringCodec};
+use crate::search::{build_dfa, derivation_typos, Search};
+use crate::CboRoaringBitmapCodec;
+
+/// The default number of facet values returned by a facet search.
+const DEFAULT_MAX_VALUES: usize = 10;
+
+/// The hard limit in the number of facet values returned by a facet search.
+const MAX_VALUES: usize = 100;
+
+/// A facet value that matches the facet search query and its number of documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FacetValueHit {
+    pub value: String,
+    pub count: u64,
+}
+
+/// Searches for the values of a string facet that starts with the given query,
+/// the counts are restricted to the documents matching the search query and filters.
+///
+/// When there is not enough values starting with the query, the values starting with
+/// a word at one or two typos of the query are returned after them.
+pub struct SearchForFacetValues<'a> {
+    query: Option<String>,
+    facet: String,
+    candidates: Option<RoaringBitmap>,
+    max_values: usize,
+    authorize_typos: bool,
+    search_query: Search<'a>,
+}
+
+impl<'a> SearchForFacetValues<'a> {
+    pub fn new(facet: impl Into<String>, search_query: Search<'a>) -> SearchForFacetValues<'a> {
+        SearchForFacetValues {
+            query: None,
+            facet: facet.into(),
+            candidates: None,
+            max_values: DEFAULT_MAX_VALUES,
+            authorize_typos: true,
+            search_query,
+        }
+    }
+
+    /// The prefix that the returned facet values must start with.
+    pub fn query(&mut self, query: impl Into<String>) -> &mut Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    /// Reuses the candidates of a previously executed search instead of executing it again.
+    pub fn candidates(&mut self, candidates: RoaringBitmap) -> &mut Self {
+        self.candidates = Some(candidates);
+        self
+    }
+
+    /// The maximum number of facet values returned, it can't be more than 100.
+    pub fn max_values(&mut self, max: NonZeroUsize) -> &mut Self {
+        self.max_values = cmp::min(max.get(), MAX_VALUES);
+        self
+    }
+
+    pub fn authorize_typos(&mut self, value: bool) -> &mut Self {
+        self.authorize_typos = value;
+        self
+    }
+
+    pub fn execute(&self) -> anyhow::Result<Vec<FacetValueHit>> {
+        let Search { rtxn, index, .. } = self.search_query;
+
+        let fields_ids_map = index.fields_ids_map(rtxn)?;
+        let faceted_fields = index.faceted_fields(rtxn)?;
+        let field_id = fields_ids_map.id(&self.facet).with_context(|| {
+            format!("missing field name {:?} from the fields id map", self.facet)
+        })?;
+        match faceted_fields.get(&self.facet) {
+            Some(FacetType::String) | Some(FacetType::Hierarchy) => (),
+            Some(FacetType::Number) | Some(FacetType::Date) => bail!("facet search is only available on string facets"),
+            None => bail!("{:?} isn't a faceted field", self.facet),
+        }
+        let filterable_features = index.filterable_features(rtxn)?;
+        if filterable_features.get(&self.facet).map_or(false, |f| !f.facet_search) {
+            bail!("facet search is disabled on the {:?} field", self.facet);
+        }
+
+        // We only execute the search when there is a query or a filter to narrow the
+        // candidates, the counts are computed on all the documents otherwise.
+        let candidates = match &self.candidates {
+            Some(candidates) => Some(candidates.clone()),
+            None if self.search_query.query.is_some() || self.search_query.facet_condition.is_some() => {
+                Some(self.search_query.execute()?.candidates)
+            },
+            None => None,
+        };
+
+        // Facet string values are normalized at indexing time, we must do the same here.
+        let normalization = index.facet_normalizations(rtxn)?.get(&self.facet).copied().unwrap_or_default();
+        let query = normalization.normalize(self.query.as_deref().unwrap_or_default());
+        let mut prefix = vec![field_id];
+        prefix.extend_from_slice(query.as_bytes());
+
+        let iter = index.facet_field_id_value_docids
+            .prefix_iter(rtxn, &prefix)?
+            .remap_types::<FacetValueStringCodec, FacetStringValueDocidsCodec<CboRoaringBitmapCodec>>();
+
+        let mut hits = Vec::new();
+        for result in iter {
+            let ((_, value), (_original, docids)) = result?;
+            let count = candidates_count(docids, candidates.as_ref());
+
+            if count != 0 {
+                hits.push(FacetValueHit { value: value.to_string(), count });
+                if hits.len() == self.max_values {
+                    return Ok(hits);
+                }
+            }
+        }
+
+        let query_len = query.chars().count();
+        let max_typo = if !self.authorize_typos || !index.typo_tolerance_enabled(rtxn)? {
+            0
+        } else if query_len >= index.min_word_size_for_two_typos(rtxn)? as usize {
+            2
+        } else if query_len >= index.min_word_size_for_one_typo(rtxn)? as usize {
+            1
+        } else {
+            0
+        };
+
+        if max_typo != 0 {
+            // We scan all the values of the facet with an automaton, the values
+            // that exactly start with the query were already returned above.
+            let first_letter_typo_cost = self.search_query.first_letter_typo_cost;
+            let dfa = build_dfa(&query, max_typo, true);
+            let iter = index.facet_field_id_value_docids
+                .prefix_iter(rtxn, &[field_id])?
+                .remap_types::<FacetValueStringCodec, LazyDecode<FacetStringValueDocidsCodec<CboRoaringBitmapCodec>>>();
+
+            let mut typo_hits = Vec::new();
+            for result in iter {
+                let ((_, value), lazy_docids) = result?;
+                if value.starts_with(query.as_str()) {
+                    continue;
+                }
+
+                if let Distance::Exact(distance) = dfa.eval(value) {
+                    let typos = derivation_typos(&query, value, distance, first_letter_typo_cost);
+                    if typos <= max_typo {
+                        let (_original, docids) = lazy_docids.decode()?;
+                        let count = candidates_count(docids, candidates.as_ref());
+                        if count != 0 {
+                            typo_hits.push((typos, FacetValueHit { value: value.to_string(), count }));
+                        }
+                    }
+                }
+            }
+
+            // The values with the fewest typos come first, then in lexicographic order.
+            typo_hits.sort_by_key(|(typos, _)| *typos);
+            let remaining = self.max_values.saturating_sub(hits.len());
+            hits.extend(typo_hits.into_iter().take(remaining).map(|(_, hit)| hit));
+        }
+
+        Ok(hits)
+    }
+}
+
+fn candidates_count(mut docids: RoaringBitmap, candidates: Option<&RoaringBitmap>) -> u64 {
+    if let Some(candidates) = candidates {
+        docids.intersect_with(candidates);
+    }
+    docids.len()
+}
+
+impl fmt::Debug for SearchForFacetValues<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let SearchForFacetValues {
+            query,
+            facet,
+            candidates,
+            max_values,
+            authorize_typos,
+            search_query,
+        } = self;
+
+        f.debug_struct("SearchForFacetValues")
+            .field("query", query)
+            .field("facet", facet)
+            .field("candidates", candidates)
+            .field("max_values", max_values)
+            .field("authorize_typos", authorize_typos)
+            .field("search_query", search_query)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+    use maplit::hashmap;
+
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use crate::{FacetCondition, Index};
+    use super::*;
+
+    #[test]
+    fn facet_search_narrowed_by_search() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "genre".into() => "string".into(),
+            "year".into() => "number".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,title,genre,year\n0,hello,Rock,2000\n1,world,Rockabilly,2010\n2,hello,Rock,2010\n3,hello,Rap,2010\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let hits = SearchForFacetValues::new("genre", index.search(&rtxn)).query("ro").execute().unwrap();
+        assert_eq!(hits, vec![
+            FacetValueHit { value: "rock".to_string(), count: 2 },
+            FacetValueHit { value: "rockabilly".to_string(), count: 1 },
+        ]);
+
+        let mut search = index.search(&rtxn);
+        search.query("hello");
+        search.facet_condition(FacetCondition::from_str(&rtxn, &index, "year = 2010").unwrap());
+        let hits = SearchForFacetValues::new("genre", search).execute().unwrap();
+        assert_eq!(hits, vec![
+            FacetValueHit { value: "rap".to_string(), count: 1 },
+            FacetValueHit { value: "rock".to_string(), count: 1 },
+        ]);
+
+        let candidates = index.search(&rtxn).query("world").execute().unwrap().candidates;
+        let hits = SearchForFacetValues::new("genre", index.search(&rtxn))
+            .candidates(candidates)
+            .execute()
+            .unwrap();
+        assert_eq!(hits, vec![FacetValueHit { value: "rockabilly".to_string(), count: 1 }]);
+
+        assert!(SearchForFacetValues::new("year", index.search(&rtxn)).execute().is_err());
+    }
+
+    #[test]
+    fn facet_search_with_typos() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "genre".into() => "string".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,genre\n0,Classical\n1,Classic Rock\n2,Classical\n3,Jazz\n4,Clasic Metal\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // The values that start with the query come before the ones with typos.
+        let hits = SearchForFacetValues::new("genre", index.search(&rtxn)).query("clasic").execute().unwrap();
+        assert_eq!(hits, vec![
+            FacetValueHit { value: "clasic metal".to_string(), count: 1 },
+            FacetValueHit { value: "classic rock".to_string(), count: 1 },
+            FacetValueHit { value: "classical".to_string(), count: 2 },
+        ]);
+
+        let hits = SearchForFacetValues::new("genre", index.search(&rtxn))
+            .query("clasic")
+            .max_values(NonZeroUsize::new(2).unwrap())
+            .execute()
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+
+        let hits = SearchForFacetValues::new("genre", index.search(&rtxn))
+            .query("clasic")
+            .authorize_typos(false)
+            .execute()
+            .unwrap();
+        assert_eq!(hits, vec![FacetValueHit { value: "clasic metal".to_string(), count: 1 }]);
+
+        // Short queries do not allow typos.
+        let hits = SearchForFacetValues::new("genre", index.search(&rtxn)).query("jaz").execute().unwrap();
+        assert_eq!(hits, vec![FacetValueHit { value: "jazz".to_string(), count: 1 }]);
+        let hits = SearchForFacetValues::new("genre", index.search(&rtxn)).query("jzz").execute().unwrap();
+        assert!(hits.is_empty());
+    }
+}
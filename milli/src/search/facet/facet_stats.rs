@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::ops::Bound::{self, Included, Unbounded};
+
+use anyhow::Context;
+use roaring::RoaringBitmap;
+
+use crate::facet::FacetType;
+use crate::search::facet::{FacetIter, FacetRange};
+use crate::{Index, FieldId, CboRoaringBitmapLenCodec};
+
+/// The statistics of the values of a number facet, a document
+/// with many values is counted once for each of its values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FacetNumberStats {
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+    /// The number of values, the sum of the number of documents of each value.
+    pub count: u64,
+}
+
+impl FacetNumberStats {
+    pub fn avg(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    /// Accounts for a value shared by the given number of documents.
+    fn add(stats: &mut Option<FacetNumberStats>, value: f64, count: u64) {
+        if count == 0 {
+            return;
+        }
+
+        match stats {
+            Some(stats) => {
+                stats.min = stats.min.min(value);
+                stats.max = stats.max.max(value);
+                stats.sum += value * count as f64;
+                stats.count += count;
+            },
+            None => *stats = Some(FacetNumberStats { min: value, max: value, sum: value * count as f64, count }),
+        }
+    }
+}
+
+pub struct FacetStats<'a> {
+    facets: Option<HashSet<String>>,
+    candidates: Option<RoaringBitmap>,
+    rtxn: &'a heed::RoTxn<'a>,
+    index: &'a Index,
+}
+
+impl<'a> FacetStats<'a> {
+    pub fn new(rtxn: &'a heed::RoTxn, index: &'a Index) -> FacetStats<'a> {
+        FacetStats { facets: None, candidates: None, rtxn, index }
+    }
+
+    pub fn facets<I: IntoIterator<Item=A>, A: AsRef<str>>(&mut self, names: I) -> &mut Self {
+        self.facets = Some(names.into_iter().map(|s| s.as_ref().to_string()).collect());
+        self
+    }
+
+    pub fn candidates(&mut self, candidates: RoaringBitmap) -> &mut Self {
+        self.candidates = Some(candidates);
+        self
+    }
+
+    /// Only the lengths of the documents ids of the level 0 values are read,
+    /// the bitmaps themselves are never decoded.
+    fn add_values_counts(
+        &self,
+        field_id: FieldId,
+        left: f64,
+        right: f64,
+        stats: &mut Option<FacetNumberStats>,
+    ) -> heed::Result<()>
+    {
+        let db = self.index.facet_number_levels_docids().remap_data_type::<CboRoaringBitmapLenCodec>();
+        let range = (Included((field_id, 0, left, f64::MIN)), Included((field_id, 0, right, f64::MAX)));
+        for result in db.range(self.rtxn, &range)? {
+            let ((_, _, value, _), count) = result?;
+            FacetNumberStats::add(stats, value, count);
+        }
+        Ok(())
+    }
+
+    /// The groups of the facet levels that don't contain any candidate are skipped, those
+    /// that only contain candidates are accounted for without intersecting any bitmap,
+    /// the other ones are explored at the level below.
+    fn explore_facet_levels(
+        &self,
+        field_id: FieldId,
+        candidates: &RoaringBitmap,
+        level: u8,
+        left: Bound<f64>,
+        right: Bound<f64>,
+        stats: &mut Option<FacetNumberStats>,
+    ) -> heed::Result<()>
+    {
+        let db = self.index.facet_number_levels_docids();
+        for result in FacetRange::new(self.rtxn, db, field_id, level, left, right)? {
+            let ((_, _, group_left, group_right), mut docids) = result?;
+            if docids.is_disjoint(candidates) {
+                continue;
+            }
+
+            if docids.is_subset(candidates) {
+                if level == 0 {
+                    FacetNumberStats::add(stats, group_left, docids.len());
+                } else {
+                    self.add_values_counts(field_id, group_left, group_right, stats)?;
+                }
+            } else if level == 0 {
+                docids.intersect_with(candidates);
+                FacetNumberStats::add(stats, group_left, docids.len());
+            } else {
+                let (left, right) = (Included(group_left), Included(group_right));
+                self.explore_facet_levels(field_id, candidates, level - 1, left, right, stats)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn facet_number_stats(&self, field_id: FieldId) -> heed::Result<Option<FacetNumberStats>> {
+        let mut stats = None;
+        match &self.candidates {
+            Some(candidates) => {
+                let db = self.index.facet_number_levels_docids();
+                let highest_level = FacetIter::highest_level(self.rtxn, db, field_id)?.unwrap_or(0);
+                self.explore_facet_levels(field_id, candidates, highest_level, Unbounded, Unbounded, &mut stats)?;
+            },
+            None => self.add_values_counts(field_id, f64::MIN, f64::MAX, &mut stats)?,
+        }
+        Ok(stats)
+    }
+
+    /// Returns the statistics of the number and date facets, the string facets
+    /// and the facets without any value for the candidates are not returned.
+    pub fn execute(&self) -> anyhow::Result<BTreeMap<String, FacetNumberStats>> {
+        let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        let faceted_fields = self.index.faceted_fields(self.rtxn)?;
+        let names = faceted_fields
+            .into_iter()
+            .filter(|(_, ftype)| matches!(ftype, FacetType::Number | FacetType::Date))
+            .map(|(name, _)| name)
+            .filter(|name| self.facets.as_ref().map_or(true, |names| names.contains(name)));
+
+        let mut facets_stats = BTreeMap::new();
+        for name in names {
+            let fid = fields_ids_map.id(&name).with_context(|| {
+                format!("missing field name {:?} from the fields id map", name)
+            })?;
+            if let Some(stats) = self.facet_number_stats(fid)? {
+                facets_stats.insert(name, stats);
+            }
+        }
+
+        Ok(facets_stats)
+    }
+}
+
+impl fmt::Debug for FacetStats<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let FacetStats { facets, candidates, rtxn: _, index: _ } = self;
+        f.debug_struct("FacetStats")
+            .field("facets", facets)
+            .field("candidates", candidates)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+    use maplit::hashmap;
+
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use super::*;
+
+    #[test]
+    fn number_facets_stats() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "genre".into() => "string".into(),
+            "age".into() => "number".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+
+        // There are enough values to build many facet levels.
+        let mut content = String::from("id,genre,age\n");
+        for id in 0..100 {
+            content.push_str(&format!("{},rock,{}\n", id, id));
+        }
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let expected = FacetNumberStats { min: 0.0, max: 99.0, sum: 4950.0, count: 100 };
+        let stats = index.facets_stats(&rtxn).execute().unwrap();
+        assert_eq!(stats.keys().collect::<Vec<_>>(), vec!["age"]);
+        assert_eq!(stats["age"], expected);
+        assert_eq!(stats["age"].avg(), 49.5);
+
+        // The documents ids are the ages, the candidates cover entire groups and parts of others.
+        let all = index.documents_ids(&rtxn).unwrap();
+        let stats = index.facets_stats(&rtxn).candidates(all).execute().unwrap();
+        assert_eq!(stats["age"], expected);
+
+        let candidates: RoaringBitmap = (10..60).chain(vec![3, 97]).collect();
+        let stats = index.facets_stats(&rtxn).candidates(candidates).execute().unwrap();
+        let sum = (10..60).sum::<u32>() as f64 + 100.0;
+        assert_eq!(stats["age"], FacetNumberStats { min: 3.0, max: 97.0, sum, count: 52 });
+
+        let stats = index.facets_stats(&rtxn).candidates(RoaringBitmap::new()).execute().unwrap();
+        assert!(stats.is_empty());
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+use crate::FieldId;
+use crate::facet::FacetFieldStats;
+use super::{FacetCondition, FacetNumberOperator, FacetStringOperator as Op};
+use super::FacetCondition::*;
+use super::FacetNumberOperator::*;
+
+impl FacetCondition {
+    /// Simplifies the condition without reading the index, returns `None` when
+    /// the condition can't match any document.
+    ///
+    /// A document can have many values for the same facet, two clauses on the same facet
+    /// can therefore match different values: `x > 5 AND x < 3` matches the documents
+    /// with both a value above 5 and below 3. We only remove the clauses implied by
+    /// another one (e.g. `x > 3 AND x > 5` becomes `x > 5`) and only report the
+    /// contradictions that are true whatever the values of the documents.
+    ///
+    /// The clauses of a conjunction are reordered to evaluate the most selective ones
+    /// first, the evaluation stops as soon as no document can match anymore.
+    pub fn simplify(self) -> Option<FacetCondition> {
+        self.simplify_with_stats(&HashMap::new())
+    }
+
+    /// Same as `simplify` but the clauses of a conjunction are ordered by the number of
+    /// documents they are estimated to match, according to the statistics of the faceted
+    /// fields, when all of them can be estimated. They are ordered by kind otherwise.
+    pub fn simplify_with_stats(self, stats: &HashMap<FieldId, FacetFieldStats>) -> Option<FacetCondition> {
+        match self {
+            And(..) => {
+                let mut conjuncts = Vec::new();
+                for condition in self.into_conjuncts() {
+                    match condition.simplify_with_stats(stats) {
+                        Some(condition) => conjuncts.extend(condition.into_conjuncts()),
+                        None => return None,
+                    }
+                }
+
+                if !remove_implied_conjuncts(&mut conjuncts) {
+                    return None;
+                }
+
+                // The sorts are stable, clauses with the same selectivity keep their order.
+                let estimations: Option<Vec<_>> = conjuncts.iter().map(|c| c.estimated_documents(stats)).collect();
+                match estimations {
+                    Some(estimations) => {
+                        let mut estimated: Vec<_> = estimations.into_iter().zip(conjuncts).collect();
+                        estimated.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+                        conjuncts = estimated.into_iter().map(|(_, c)| c).collect();
+                    },
+                    None => conjuncts.sort_by_key(FacetCondition::estimated_selectivity),
+                }
+
+                let mut conjuncts = conjuncts.into_iter();
+                let first = conjuncts.next()?;
+                Some(conjuncts.fold(first, |lhs, rhs| And(Box::new(lhs), Box::new(rhs))))
+            },
+            Or(lhs, rhs) => match (lhs.simplify_with_stats(stats), rhs.simplify_with_stats(stats)) {
+                (Some(lhs), Some(rhs)) if lhs == rhs || implies(&rhs, &lhs) => Some(lhs),
+                (Some(lhs), Some(rhs)) if implies(&lhs, &rhs) => Some(rhs),
+                (Some(lhs), Some(rhs)) => Some(Or(Box::new(lhs), Box::new(rhs))),
+                (Some(condition), None) | (None, Some(condition)) => Some(condition),
+                (None, None) => None,
+            },
+            Not(condition) => match *condition {
+                Not(condition) => condition.simplify_with_stats(stats),
+                // A condition that can't match any document is kept as is,
+                // its negation matches all the documents.
+                condition => match condition.clone().simplify_with_stats(stats) {
+                    Some(simplified) => Some(Not(Box::new(simplified))),
+                    None => Some(Not(Box::new(condition))),
+                },
+            },
+            OperatorNumber(_, op) | ValuesCount(_, op) if interval(op).map_or(false, is_empty_interval) => None,
+            OperatorString(_, Op::Between(left, right)) if left > right => None,
+            In(_, values) if values.is_empty() => None,
+            condition => Some(condition),
+        }
+    }
+
+    /// Returns the clauses of this conjunction, or the condition itself if it isn't one.
+    fn into_conjuncts(self) -> Vec<FacetCondition> {
+        match self {
+            And(lhs, rhs) => {
+                let mut conjuncts = lhs.into_conjuncts();
+                conjuncts.extend(rhs.into_conjuncts());
+                conjuncts
+            },
+            condition => vec![condition],
+        }
+    }
+
+    /// Estimates how selective a condition is, the lowest ones are expected to match the
+    /// fewest documents: the exact values first, then the ranges and the broad conditions.
+    /// The negations are always last as they are cheaper to evaluate as a difference.
+    fn estimated_selectivity(&self) -> u8 {
+        match self {
+            OperatorNumber(_, Equal(_)) | OperatorString(_, Op::Equal(_)) => 0,
+            In(..) => 1,
+            OperatorNumber(_, Between(..)) | OperatorString(_, Op::Between(..)) => 2,
+            GeoRadius { .. } | GeoBoundingBox { .. } | GeoPolygon(_) => 3,
+            OperatorNumber(..) | OperatorString(..) | ValuesCount(..) => 4,
+            And(..) => 5,
+            Or(..) => 6,
+            Exists(_) => 7,
+            Not(_) => 8,
+        }
+    }
+
+    /// Estimates the number of documents matched by a condition from the statistics of the
+    /// faceted fields, the values are assumed to be evenly distributed among the documents
+    /// and a range is assumed to match a fixed fraction of them. Returns `None` when a field
+    /// has no statistics or for the geo conditions, the negations are estimated to match
+    /// every document as they are cheaper to evaluate last, as a difference.
+    fn estimated_documents(&self, stats: &HashMap<FieldId, FacetFieldStats>) -> Option<f64> {
+        let documents_count = |fid: &FieldId| stats.get(fid).map(|s| s.documents_count as f64);
+        let documents_by_value = |fid: &FieldId| stats.get(fid).map(FacetFieldStats::documents_by_value);
+
+        match self {
+            OperatorNumber(fid, Equal(_)) | OperatorString(fid, Op::Equal(_)) => documents_by_value(fid),
+            OperatorNumber(fid, NotEqual(_)) | OperatorString(fid, Op::NotEqual(_)) => {
+                Some(documents_count(fid)? - documents_by_value(fid)?)
+            },
+            In(fid, values) => Some((values.len() as f64 * documents_by_value(fid)?).min(documents_count(fid)?)),
+            OperatorNumber(fid, Between(..)) | OperatorString(fid, Op::Between(..)) => Some(documents_count(fid)? / 4.0),
+            OperatorNumber(fid, _) | OperatorString(fid, _) | ValuesCount(fid, _) => Some(documents_count(fid)? / 3.0),
+            Exists(fid) => documents_count(fid),
+            GeoRadius { .. } | GeoBoundingBox { .. } | GeoPolygon(_) => None,
+            And(lhs, rhs) => Some(lhs.estimated_documents(stats)?.min(rhs.estimated_documents(stats)?)),
+            Or(lhs, rhs) => Some(lhs.estimated_documents(stats)? + rhs.estimated_documents(stats)?),
+            Not(_) => Some(f64::INFINITY),
+        }
+    }
+}
+
+/// Removes the duplicated clauses and the clauses implied by another one, returns
+/// `false` if a clause contradicts another one, the conjunction then matches nothing.
+fn remove_implied_conjuncts(conjuncts: &mut Vec<FacetCondition>) -> bool {
+    let mut i = 0;
+    while i < conjuncts.len() {
+        let current = &conjuncts[i];
+        let redundant = conjuncts.iter().enumerate().any(|(j, other)| {
+            let implied = other == current || implies(other, current);
+            let equivalent = other == current || implies(current, other);
+            // Of two equivalent clauses we keep the first one.
+            j != i && implied && (!equivalent || j < i)
+        });
+
+        if redundant {
+            conjuncts.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    for (i, lhs) in conjuncts.iter().enumerate() {
+        for rhs in &conjuncts[i + 1..] {
+            if contradicts(lhs, rhs) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Returns `true` if the documents matched by `lhs` are always matched by `rhs`.
+fn implies(lhs: &FacetCondition, rhs: &FacetCondition) -> bool {
+    match (lhs, rhs) {
+        // A document has a value in the interval of `lhs`, this value is also in the interval of `rhs`.
+        (OperatorNumber(lfid, lop), OperatorNumber(rfid, rop)) |
+        (ValuesCount(lfid, lop), ValuesCount(rfid, rop)) if lfid == rfid => {
+            match (interval(*lop), interval(*rop)) {
+                (Some(lhs), Some(rhs)) => interval_contains(rhs, lhs),
+                _ => false,
+            }
+        },
+        // The documents matched by a value of a facet have a value for this facet.
+        (OperatorNumber(fid, _), Exists(efid)) |
+        (OperatorString(fid, _), Exists(efid)) |
+        (In(fid, _), Exists(efid)) => fid == efid,
+        _ => false,
+    }
+}
+
+/// Returns `true` if no document can be matched by both conditions.
+fn contradicts(lhs: &FacetCondition, rhs: &FacetCondition) -> bool {
+    match (lhs, rhs) {
+        (Not(condition), other) | (other, Not(condition)) if **condition == *other => true,
+        (OperatorNumber(lfid, Equal(lvalue)), OperatorNumber(rfid, NotEqual(rvalue))) |
+        (OperatorNumber(lfid, NotEqual(lvalue)), OperatorNumber(rfid, Equal(rvalue))) => {
+            lfid == rfid && lvalue == rvalue
+        },
+        // A document has a single number of values, the intervals must intersect.
+        (ValuesCount(lfid, lop), ValuesCount(rfid, rop)) if lfid == rfid => {
+            match (interval(*lop), interval(*rop)) {
+                (Some((lleft, lright)), Some((rleft, rright))) => {
+                    let left = if lower_is_looser(lleft, rleft) { rleft } else { lleft };
+                    let right = if upper_is_looser(lright, rright) { rright } else { lright };
+                    is_empty_interval((left, right))
+                },
+                _ => false,
+            }
+        },
+        _ => false,
+    }
+}
+
+/// The interval of the values matched by a number operator, the
+/// `NotEqual` operator matches the values outside of an interval.
+fn interval(op: FacetNumberOperator) -> Option<(Bound<f64>, Bound<f64>)> {
+    match op {
+        GreaterThan(x)        => Some((Excluded(x), Unbounded)),
+        GreaterThanOrEqual(x) => Some((Included(x), Unbounded)),
+        Equal(x)              => Some((Included(x), Included(x))),
+        NotEqual(_)           => None,
+        LowerThan(x)          => Some((Unbounded, Excluded(x))),
+        LowerThanOrEqual(x)   => Some((Unbounded, Included(x))),
+        Between(x, y)         => Some((Included(x), Included(y))),
+    }
+}
+
+fn is_empty_interval((left, right): (Bound<f64>, Bound<f64>)) -> bool {
+    match (left, right) {
+        (Included(l), Included(r)) => l > r,
+        (Included(l), Excluded(r)) | (Excluded(l), Included(r)) | (Excluded(l), Excluded(r)) => l >= r,
+        _ => false,
+    }
+}
+
+fn interval_contains(
+    (outer_left, outer_right): (Bound<f64>, Bound<f64>),
+    (inner_left, inner_right): (Bound<f64>, Bound<f64>),
+) -> bool
+{
+    lower_is_looser(outer_left, inner_left) && upper_is_looser(outer_right, inner_right)
+}
+
+/// Returns `true` if the lower bound `a` accepts all the values accepted by `b`.
+fn lower_is_looser(a: Bound<f64>, b: Bound<f64>) -> bool {
+    match (a, b) {
+        (Unbounded, _) => true,
+        (_, Unbounded) => false,
+        (Excluded(a), Included(b)) => a < b,
+        (Included(a), Included(b)) | (Included(a), Excluded(b)) | (Excluded(a), Excluded(b)) => a <= b,
+    }
+}
+
+/// Returns `true` if the upper bound `a` accepts all the values accepted by `b`.
+fn upper_is_looser(a: Bound<f64>, b: Bound<f64>) -> bool {
+    match (a, b) {
+        (Unbounded, _) => true,
+        (_, Unbounded) => false,
+        (Excluded(a), Included(b)) => a > b,
+        (Included(a), Included(b)) | (Included(a), Excluded(b)) | (Excluded(a), Excluded(b)) => a >= b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+
+    use super::*;
+
+    fn and(lhs: FacetCondition, rhs: FacetCondition) -> FacetCondition {
+        And(Box::new(lhs), Box::new(rhs))
+    }
+
+    fn or(lhs: FacetCondition, rhs: FacetCondition) -> FacetCondition {
+        Or(Box::new(lhs), Box::new(rhs))
+    }
+
+    fn not(condition: FacetCondition) -> FacetCondition {
+        Not(Box::new(condition))
+    }
+
+    #[test]
+    fn implied_clauses() {
+        // x > 3 AND x > 5
+        let condition = and(OperatorNumber(0, GreaterThan(3.0)), OperatorNumber(0, GreaterThan(5.0)));
+        assert_eq!(condition.simplify(), Some(OperatorNumber(0, GreaterThan(5.0))));
+
+        // x >= 3 AND x 4 TO 6 AND x EXISTS
+        let condition = and(
+            and(OperatorNumber(0, GreaterThanOrEqual(3.0)), OperatorNumber(0, Between(4.0, 6.0))),
+            Exists(0),
+        );
+        assert_eq!(condition.simplify(), Some(OperatorNumber(0, Between(4.0, 6.0))));
+
+        // x > 3 OR x > 5
+        let condition = or(OperatorNumber(0, GreaterThan(3.0)), OperatorNumber(0, GreaterThan(5.0)));
+        assert_eq!(condition.simplify(), Some(OperatorNumber(0, GreaterThan(3.0))));
+
+        // The clauses on different fields are kept.
+        let condition = and(OperatorNumber(0, GreaterThan(3.0)), OperatorNumber(1, GreaterThan(5.0)));
+        assert_eq!(condition.clone().simplify(), Some(condition));
+
+        // The duplicated clauses and the double negations are removed.
+        let condition = and(Exists(2), not(not(Exists(2))));
+        assert_eq!(condition.simplify(), Some(Exists(2)));
+    }
+
+    #[test]
+    fn contradictions() {
+        // x = 3 AND x != 3
+        let condition = and(OperatorNumber(0, Equal(3.0)), OperatorNumber(0, NotEqual(3.0)));
+        assert_eq!(condition.simplify(), None);
+
+        // x EXISTS AND NOT x EXISTS
+        let condition = and(Exists(0), and(OperatorNumber(1, Equal(1.0)), not(Exists(0))));
+        assert_eq!(condition.simplify(), None);
+
+        // x.length > 3 AND x.length < 2
+        let condition = and(ValuesCount(0, GreaterThan(3.0)), ValuesCount(0, LowerThan(2.0)));
+        assert_eq!(condition.simplify(), None);
+
+        // x 5 TO 3 OR x = 1
+        let condition = or(OperatorNumber(0, Between(5.0, 3.0)), OperatorNumber(0, Equal(1.0)));
+        assert_eq!(condition.simplify(), Some(OperatorNumber(0, Equal(1.0))));
+
+        // A document can have both a value above 5 and a value below 3.
+        let condition = and(OperatorNumber(0, GreaterThan(5.0)), OperatorNumber(0, LowerThan(3.0)));
+        assert_eq!(condition.clone().simplify(), Some(condition));
+
+        // The negation of a contradiction is kept.
+        let condition = not(In(0, Vec::new()));
+        assert_eq!(condition.clone().simplify(), Some(condition));
+    }
+
+    #[test]
+    fn selectivity_order() {
+        // NOT x EXISTS AND y > 3 AND z = 1
+        let condition = and(
+            and(not(Exists(0)), OperatorNumber(1, GreaterThan(3.0))),
+            OperatorNumber(2, Equal(1.0)),
+        );
+        let expected = and(
+            and(OperatorNumber(2, Equal(1.0)), OperatorNumber(1, GreaterThan(3.0))),
+            not(Exists(0)),
+        );
+        assert_eq!(condition.simplify(), Some(expected));
+    }
+
+    #[test]
+    fn statistics_order() {
+        // x has two distinct values, each of them is shared by half of the documents,
+        // y has one distinct value by document, a range should match fewer documents.
+        let stats = hashmap!{
+            0 => FacetFieldStats { distinct_values: 2, documents_count: 1000 },
+            1 => FacetFieldStats { distinct_values: 1000, documents_count: 1000 },
+        };
+
+        // x = 1 AND y > 3
+        let condition = and(OperatorNumber(0, Equal(1.0)), OperatorNumber(1, GreaterThan(3.0)));
+        let expected = and(OperatorNumber(1, GreaterThan(3.0)), OperatorNumber(0, Equal(1.0)));
+        assert_eq!(condition.clone().simplify_with_stats(&stats), Some(expected));
+        assert_eq!(condition.clone().simplify(), Some(condition));
+
+        // A field without statistics can't be estimated, the clauses are ordered by kind.
+        let condition = and(OperatorNumber(1, GreaterThan(3.0)), OperatorNumber(2, Equal(1.0)));
+        let expected = and(OperatorNumber(2, Equal(1.0)), OperatorNumber(1, GreaterThan(3.0)));
+        assert_eq!(condition.simplify_with_stats(&stats), Some(expected));
+    }
+}
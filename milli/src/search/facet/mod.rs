@@ -9,11 +9,16 @@ use crate::heed_codec::CboRoaringBitmapCodec;
 use crate::heed_codec::facet::FacetLevelValueF64Codec;
 use crate::{Index, FieldId};
 
-pub use self::facet_condition::{FacetCondition, FacetNumberOperator, FacetStringOperator};
-pub use self::facet_distribution::FacetDistribution;
+pub use self::facet_condition::{FacetCondition, FacetNumberOperator, FacetStringOperator, FilterExplanation};
+pub use self::facet_distribution::{FacetDistribution, FacetValuesOrder};
+pub use self::facet_stats::{FacetNumberStats, FacetStats};
+pub use self::facet_search::{FacetValueHit, SearchForFacetValues};
 
 mod facet_condition;
 mod facet_distribution;
+mod facet_stats;
+mod facet_search;
+mod filter_optimizer;
 mod parser;
 
 pub struct FacetRange<'t> {
@@ -140,7 +145,7 @@ impl<'t> FacetIter<'t> {
         documents_ids: RoaringBitmap,
     ) -> heed::Result<FacetIter<'t>>
     {
-        let db = index.facet_field_id_value_docids.remap_key_type::<FacetLevelValueF64Codec>();
+        let db = index.facet_number_levels_docids();
         let highest_level = Self::highest_level(rtxn, db, field_id)?.unwrap_or(0);
         let highest_iter = FacetRange::new(rtxn, db, field_id, highest_level, Unbounded, Unbounded)?;
         let level_iters = vec![(documents_ids, Left(highest_iter))];
@@ -157,7 +162,7 @@ impl<'t> FacetIter<'t> {
         documents_ids: RoaringBitmap,
     ) -> heed::Result<FacetIter<'t>>
     {
-        let db = index.facet_field_id_value_docids.remap_key_type::<FacetLevelValueF64Codec>();
+        let db = index.facet_number_levels_docids();
         let highest_level = Self::highest_level(rtxn, db, field_id)?.unwrap_or(0);
         let highest_iter = FacetRevRange::new(rtxn, db, field_id, highest_level, Unbounded, Unbounded)?;
         let level_iters = vec![(documents_ids, Right(highest_iter))];
@@ -175,7 +180,7 @@ impl<'t> FacetIter<'t> {
         documents_ids: RoaringBitmap,
     ) -> heed::Result<FacetIter<'t>>
     {
-        let db = index.facet_field_id_value_docids.remap_key_type::<FacetLevelValueF64Codec>();
+        let db = index.facet_number_levels_docids();
         let highest_level = Self::highest_level(rtxn, db, field_id)?.unwrap_or(0);
         let highest_iter = FacetRange::new(rtxn, db, field_id, highest_level, Unbounded, Unbounded)?;
         let level_iters = vec![(documents_ids, Left(highest_iter))];
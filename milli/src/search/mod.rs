@@ -1,45 +1,80 @@
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::hash_map::{Entry, HashMap};
 use std::fmt;
 use std::mem::take;
+use std::ops::Range;
 use std::str::Utf8Error;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
+use anyhow::{bail, Context as _};
 use fst::{IntoStreamer, Streamer};
+use heed::types::ByteSlice;
 use levenshtein_automata::{LevenshteinAutomatonBuilder as LevBuilder, DFA};
+use linked_hash_map::LinkedHashMap;
 use log::debug;
 use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
 use once_cell::sync::Lazy;
 use roaring::bitmap::RoaringBitmap;
 
-use distinct::{Distinct, DocIter, FacetDistinct, MapDistinct, NoopDistinct};
+use distinct::{CompositeDistinct, Distinct, DocIter, FacetDistinct, MapDistinct, NoopDistinct};
 use crate::search::criteria::r#final::{Final, FinalResult};
-use crate::{Index, DocumentId};
+use crate::geo::extract_geo_point;
+use crate::{AscDesc, BEU32, Criterion, Index, DocumentId, Locale, FieldId, FieldsIdsMap, GEO_FIELD_NAME, Member, distance_between_two_points};
 
 pub use self::facet::{
-    FacetCondition, FacetDistribution, FacetIter, FacetNumberOperator, FacetStringOperator,
+    FacetCondition, FacetDistribution, FacetIter, FacetNumberOperator, FacetNumberStats, FacetStats,
+    FacetStringOperator, FacetValueHit, FacetValuesOrder, FilterExplanation, SearchForFacetValues,
 };
-pub use self::query_tree::MatchingWords;
-use self::query_tree::QueryTreeBuilder;
+pub use self::query_tree::{MatchingWords, QueryToken, TermsMatchingStrategy};
+pub use self::shadow::{RankCorrelation, ShadowSearchResult};
+pub use self::criteria::CriterionTrace;
+pub use self::distinct::MAX_DISTINCT_ATTRIBUTES;
+use self::criteria::CriteriaBuilder;
+use self::query_tree::{Operation, OriginalWord, QueryTreeBuilder, original_words, query_tokens};
 
 // Building these factories is not free.
 static LEVDIST0: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(0, true));
 static LEVDIST1: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(1, true));
 static LEVDIST2: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(2, true));
 
+/// The maximum number of analyzed queries kept in the query tokens cache.
+const QUERY_TOKENS_CACHE_SIZE: usize = 1000;
+
+// Autocomplete requests send the same queries over and over,
+// we keep the most recently analyzed ones to avoid analyzing them again.
+static QUERY_TOKENS_CACHE: Lazy<Mutex<LinkedHashMap<String, Arc<Vec<QueryToken>>>>> =
+    Lazy::new(|| Mutex::new(LinkedHashMap::with_capacity(QUERY_TOKENS_CACHE_SIZE)));
+
 mod criteria;
 mod distinct;
 mod facet;
 mod query_tree;
+mod shadow;
 
 pub struct Search<'a> {
     query: Option<String>,
+    tokens: Option<Arc<Vec<QueryToken>>>,
     facet_condition: Option<FacetCondition>,
+    sort_criteria: Option<Vec<AscDesc>>,
     offset: usize,
     limit: usize,
-    optional_words: bool,
+    terms_matching_strategy: Option<TermsMatchingStrategy>,
+    min_words_match: u8,
     authorize_typos: bool,
+    first_letter_typo_cost: u8,
     words_limit: usize,
+    zero_result_fallback: bool,
+    searchable_attributes: Option<Vec<String>>,
+    criteria: Option<Vec<Criterion>>,
+    locales: Vec<Locale>,
+    ignore_stop_words: bool,
+    pinned: Vec<DocumentId>,
+    excluded: RoaringBitmap,
+    demoted: RoaringBitmap,
+    ranking_trace_sample_rate: f64,
+    shared_caches: Option<&'a RefCell<SharedCaches>>,
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
 }
@@ -48,12 +83,26 @@ impl<'a> Search<'a> {
     pub fn new(rtxn: &'a heed::RoTxn, index: &'a Index) -> Search<'a> {
         Search {
             query: None,
+            tokens: None,
             facet_condition: None,
+            sort_criteria: None,
             offset: 0,
             limit: 20,
-            optional_words: true,
+            terms_matching_strategy: None,
+            min_words_match: 0,
             authorize_typos: true,
+            first_letter_typo_cost: 1,
             words_limit: 10,
+            zero_result_fallback: false,
+            searchable_attributes: None,
+            criteria: None,
+            locales: Vec::new(),
+            ignore_stop_words: true,
+            pinned: Vec::new(),
+            excluded: RoaringBitmap::new(),
+            demoted: RoaringBitmap::new(),
+            ranking_trace_sample_rate: 0.0,
+            shared_caches: None,
             rtxn,
             index,
         }
@@ -64,6 +113,14 @@ impl<'a> Search<'a> {
         self
     }
 
+    /// Searches for these already analyzed tokens instead of analyzing the query,
+    /// for the callers that tokenize, correct or expand the query themselves.
+    /// The query is ignored when tokens are given.
+    pub fn tokens(&mut self, tokens: Vec<QueryToken>) -> &mut Search<'a> {
+        self.tokens = Some(Arc::new(tokens));
+        self
+    }
+
     pub fn offset(&mut self, offset: usize) -> &mut Search<'a> {
         self.offset = offset;
         self
@@ -75,7 +132,27 @@ impl<'a> Search<'a> {
     }
 
     pub fn optional_words(&mut self, value: bool) -> &mut Search<'a> {
-        self.optional_words = value;
+        self.terms_matching_strategy = Some(if value {
+            TermsMatchingStrategy::Last
+        } else {
+            TermsMatchingStrategy::All
+        });
+        self
+    }
+
+    /// Defines which query words can be dropped, and in which order,
+    /// when the documents don't contain all of them.
+    /// The strategy of the index settings is used when none is defined.
+    pub fn terms_matching_strategy(&mut self, value: TermsMatchingStrategy) -> &mut Search<'a> {
+        self.terms_matching_strategy = Some(value);
+        self
+    }
+
+    /// Defines the percentage of the query words that the documents must contain, the query
+    /// words are no longer removed once this minimum is reached, e.g. with `75` at least three
+    /// of the four query words must be present. It is ignored when all the words are required.
+    pub fn min_words_match(&mut self, percentage: u8) -> &mut Search<'a> {
+        self.min_words_match = percentage;
         self
     }
 
@@ -84,91 +161,491 @@ impl<'a> Search<'a> {
         self
     }
 
+    /// Defines the number of typos that a typo on the first letter of a query word costs,
+    /// it is one by default, like any other typo, and can be increased to favor the
+    /// words starting like the query words.
+    pub fn first_letter_typo_cost(&mut self, value: u8) -> &mut Search<'a> {
+        self.first_letter_typo_cost = value;
+        self
+    }
+
     pub fn words_limit(&mut self, value: usize) -> &mut Search<'a> {
         self.words_limit = value;
         self
     }
 
+    /// When the search requires all the query words and doesn't find any document,
+    /// the query words are progressively removed from the end of the query until
+    /// some documents are found, the removed words are reported in the result.
+    pub fn zero_result_fallback(&mut self, value: bool) -> &mut Search<'a> {
+        self.zero_result_fallback = value;
+        self
+    }
+
+    /// Restricts the query words matching to the given searchable attributes, for this search only.
+    pub fn searchable_attributes(&mut self, attributes: &[&str]) -> &mut Search<'a> {
+        self.searchable_attributes = Some(attributes.iter().map(|s| s.to_string()).collect());
+        self
+    }
+
     pub fn facet_condition(&mut self, condition: FacetCondition) -> &mut Search<'a> {
         self.facet_condition = Some(condition);
         self
     }
 
+    /// Analyzes the query with the stop words and the normalization rules of these locales,
+    /// in addition to the ones of the index, e.g. the language of the user interface.
+    pub fn locales(&mut self, locales: &[Locale]) -> &mut Search<'a> {
+        self.locales = locales.to_vec();
+        self
+    }
+
+    /// When `false`, the stop words of the query are kept and used by the phrases and the
+    /// proximity, they remain optional and are the first words removed by the words ranking
+    /// rule, a query like "to be or not to be" is therefore not reduced to almost nothing.
+    pub fn ignore_stop_words(&mut self, value: bool) -> &mut Search<'a> {
+        self.ignore_stop_words = value;
+        self
+    }
+
+    /// Ranks the documents with these ranking rules instead of the ones of the index,
+    /// they must be a subset of the ranking rules of the index, in any order.
+    pub fn override_criteria(&mut self, criteria: Vec<Criterion>) -> &mut Search<'a> {
+        self.criteria = Some(criteria);
+        self
+    }
+
+    /// Places these documents, in this order, before the ranked documents, they are excluded
+    /// from the ranking and only returned if they match the facet condition of the search.
+    pub fn pinned(&mut self, documents_ids: Vec<DocumentId>) -> &mut Search<'a> {
+        self.pinned = documents_ids;
+        self
+    }
+
+    /// Removes these documents from the results, whatever the query and the facet condition are.
+    pub fn excluded(&mut self, documents_ids: Vec<DocumentId>) -> &mut Search<'a> {
+        self.excluded = documents_ids.into_iter().collect();
+        self
+    }
+
+    /// Returns these documents after all the other ones, when they match the search.
+    pub fn demoted(&mut self, documents_ids: Vec<DocumentId>) -> &mut Search<'a> {
+        self.demoted = documents_ids.into_iter().collect();
+        self
+    }
+
+    /// Records what the ranking rules did for this fraction of the searches, e.g. `0.01` traces
+    /// one search out of a hundred on average, `1.0` traces all of them. The traces are not
+    /// recorded by default as they slightly slow down the ranking.
+    pub fn ranking_trace_sample_rate(&mut self, rate: f64) -> &mut Search<'a> {
+        self.ranking_trace_sample_rate = rate.min(1.0).max(0.0); // clamp [0, 1]
+        self
+    }
+
+    /// Sorts the documents by the given expressions, applied lexicographically:
+    /// the documents that are equal for the first expression are sorted by the second one.
+    pub fn sort_by(&mut self, criteria: Vec<AscDesc>) -> &mut Search<'a> {
+        self.sort_criteria = Some(criteria);
+        self
+    }
+
     pub fn execute(&self) -> anyhow::Result<SearchResult> {
+        let mut result = self.execute_with_words_limit(self.words_limit)?;
+
+        let all_words_required = self.resolved_terms_matching_strategy()? == TermsMatchingStrategy::All;
+        if self.zero_result_fallback && all_words_required && result.candidates.is_empty() {
+            let parts = match self.search_tokens()? {
+                Some(tokens) => self.analyze_query(&tokens, |builder, tokens| {
+                    builder.words_limit(self.words_limit);
+                    builder.query_parts(tokens)
+                })?,
+                None => Vec::new(),
+            };
+
+            // We remove the query parts one by one from the end of the query.
+            for words_limit in (1..parts.len()).rev() {
+                let mut fallback = self.execute_with_words_limit(words_limit)?;
+                if !fallback.candidates.is_empty() {
+                    debug!("zero result fallback found documents with {} query parts", words_limit);
+                    fallback.dropped_words = parts[words_limit..].to_vec();
+                    result = fallback;
+                    break;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Reports the number of documents matched by each clause of the facet condition of this search.
+    pub fn explain_filter(&self) -> anyhow::Result<Option<FilterExplanation>> {
+        match &self.facet_condition {
+            Some(condition) => condition.explain(self.rtxn, self.index).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the terms matching strategy of this search or, if there is none, the one of the index.
+    fn resolved_terms_matching_strategy(&self) -> heed::Result<TermsMatchingStrategy> {
+        match self.terms_matching_strategy {
+            Some(strategy) => Ok(strategy),
+            None => self.index.terms_matching_strategy(self.rtxn),
+        }
+    }
+
+    /// Returns the tokens given to this search or, if there is none, analyzes
+    /// the query or retrieves its tokens from the query tokens cache.
+    fn search_tokens(&self) -> heed::Result<Option<Arc<Vec<QueryToken>>>> {
+        match (&self.tokens, &self.query) {
+            (Some(tokens), _) => Ok(Some(tokens.clone())),
+            (None, Some(query)) => {
+                // The query is normalized like the documents were before being analyzed.
+                let options = self.index.analyzer_options(self.rtxn)?;
+                let mut query = options.normalize(query);
+                for locale in &self.locales {
+                    let normalized = match locale.normalize(&query) {
+                        Cow::Owned(normalized) => Some(normalized),
+                        Cow::Borrowed(_) => None,
+                    };
+                    if let Some(normalized) = normalized {
+                        query = Cow::Owned(normalized);
+                    }
+                }
+                Ok(Some(cached_query_tokens(&query)))
+            },
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Calls the given function with a query tree builder and the query tokens.
+    fn analyze_query<F, T>(&self, tokens: &[QueryToken], f: F) -> anyhow::Result<T>
+    where F: FnOnce(&mut QueryTreeBuilder, &[QueryToken]) -> anyhow::Result<T>,
+    {
+        let mut builder = QueryTreeBuilder::new(self.rtxn, self.index);
+        builder.locales(&self.locales);
+        builder.ignore_stop_words(self.ignore_stop_words);
+        f(&mut builder, tokens)
+    }
+
+    /// Builds the query tree and retrieves the documents matching the facet condition.
+    fn query_tree_and_candidates(
+        &self,
+        words_limit: usize,
+    ) -> anyhow::Result<(Option<Operation>, Vec<OriginalWord>, Option<RoaringBitmap>)>
+    {
         // We create the query tree by spliting the query into tokens.
         let before = Instant::now();
-        let query_tree = match self.query.as_ref() {
-            Some(query) => {
-                let mut builder = QueryTreeBuilder::new(self.rtxn, self.index);
-                builder.optional_words(self.optional_words);
+        let (query_tree, original_words) = match self.search_tokens()? {
+            Some(tokens) => self.analyze_query(&tokens, |builder, tokens| {
+                builder.terms_matching_strategy(self.resolved_terms_matching_strategy()?);
+                builder.min_words_match(self.min_words_match);
                 builder.authorize_typos(self.authorize_typos);
-                builder.words_limit(self.words_limit);
-                // We make sure that the analyzer is aware of the stop words
-                // this ensures that the query builder is able to properly remove them.
-                let mut config = AnalyzerConfig::default();
-                let stop_words = self.index.stop_words(self.rtxn)?;
-                if let Some(ref stop_words) = stop_words {
-                    config.stop_words(stop_words);
-                }
-                let analyzer = Analyzer::new(config);
-                let result = analyzer.analyze(query);
-                let tokens = result.tokens();
-                builder.build(tokens)?
-            },
-            None => None,
+                builder.words_limit(words_limit);
+                Ok((builder.build_from_tokens(tokens)?, original_words(tokens)))
+            })?,
+            None => (None, Vec::new()),
         };
 
         debug!("query tree: {:?} took {:.02?}", query_tree, before.elapsed());
 
         // We create the original candidates with the facet conditions results.
         let before = Instant::now();
-        let facet_candidates = match &self.facet_condition {
-            Some(condition) => Some(condition.evaluate(self.rtxn, self.index)?),
+        let mut facet_candidates = match &self.facet_condition {
+            Some(condition) => Some(self.evaluate_facet_condition(condition)?),
             None => None,
         };
 
+        // The excluded documents are removed from the candidates universe.
+        if !self.excluded.is_empty() {
+            let candidates = match facet_candidates {
+                Some(candidates) => candidates,
+                None => self.index.documents_ids(self.rtxn)?,
+            };
+            facet_candidates = Some(candidates - &self.excluded);
+        }
+
         debug!("facet candidates: {:?} took {:.02?}", facet_candidates, before.elapsed());
 
+        Ok((query_tree, original_words, facet_candidates))
+    }
+
+    /// Evaluates the facet condition, the documents ids of the conditions
+    /// already evaluated by a previous search are retrieved from the shared caches.
+    fn evaluate_facet_condition(&self, condition: &FacetCondition) -> anyhow::Result<RoaringBitmap> {
+        let caches = match self.shared_caches {
+            Some(caches) => caches,
+            None => return condition.evaluate(self.rtxn, self.index),
+        };
+
+        let cached = caches.borrow().filters.iter().find(|(c, _)| c == condition).map(|(_, docids)| docids.clone());
+        match cached {
+            Some(docids) => Ok(docids),
+            None => {
+                let docids = condition.evaluate(self.rtxn, self.index)?;
+                caches.borrow_mut().filters.push((condition.clone(), docids.clone()));
+                Ok(docids)
+            },
+        }
+    }
+
+    /// Returns the fields ids map of the index, it is read once for all the searches sharing caches.
+    fn fields_ids_map(&self) -> anyhow::Result<FieldsIdsMap> {
+        let caches = match self.shared_caches {
+            Some(caches) => caches,
+            None => return Ok(self.index.fields_ids_map(self.rtxn)?),
+        };
+
+        let mut caches = caches.borrow_mut();
+        match &caches.fields_ids_map {
+            Some(fields_ids_map) => Ok(fields_ids_map.clone()),
+            None => {
+                let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+                caches.fields_ids_map = Some(fields_ids_map.clone());
+                Ok(fields_ids_map)
+            },
+        }
+    }
+
+    fn criteria_builder(&self, original_words: Vec<OriginalWord>) -> anyhow::Result<CriteriaBuilder<'a>> {
+        let mut criteria_builder = CriteriaBuilder::new(self.rtxn, self.index)?;
+        criteria_builder.first_letter_typo_cost(self.first_letter_typo_cost);
+        criteria_builder.original_words(original_words);
+        if let Some(criteria) = &self.criteria {
+            criteria_builder.override_criteria(criteria.clone());
+        }
+        if let Some(attributes) = &self.searchable_attributes {
+            criteria_builder.restrict_searchable_attributes(self.searchable_attributes_ids(attributes)?);
+        }
+        Ok(criteria_builder)
+    }
+
+    /// Streams the ranked documents ids to the given function, the buckets of the ranking rules
+    /// are only computed when the documents of the previous one have been consumed, callers that
+    /// only need the first documents therefore don't pay for the following buckets.
+    ///
+    /// The offset and limit are ignored, the distinct attribute, the pinned
+    /// and the demoted documents are not supported.
+    pub fn execute_stream<F, T>(&self, f: F) -> anyhow::Result<T>
+    where F: FnOnce(SearchStream) -> anyhow::Result<T>,
+    {
+        if self.index.distinct_attribute(self.rtxn)?.is_some() || self.index.distinct_attributes(self.rtxn)?.is_some() {
+            bail!("the distinct attribute is not supported when streaming the search results");
+        }
+        if !self.pinned.is_empty() {
+            bail!("the pinned documents are not supported when streaming the search results");
+        }
+        if !self.demoted.is_empty() {
+            bail!("the demoted documents are not supported when streaming the search results");
+        }
+
+        let (query_tree, original_words, facet_candidates) = self.query_tree_and_candidates(self.words_limit)?;
+        let criteria_builder = self.criteria_builder(original_words)?;
+        let criteria = criteria_builder.build(query_tree, facet_candidates, self.sort_criteria.clone(), None)?;
+        f(SearchStream { criteria, bucket: RoaringBitmap::new().into_iter() })
+    }
+
+    /// Ranks the documents with both the ranking rules of this search and the given shadow ones,
+    /// the query and the facet condition are evaluated once for both of them.
+    ///
+    /// The zero result fallback is not applied.
+    pub fn execute_shadow(&self, shadow_criteria: Vec<Criterion>) -> anyhow::Result<ShadowSearchResult> {
+        let (query_tree, original_words, facet_candidates) = self.query_tree_and_candidates(self.words_limit)?;
+        let pinned = self.pinned_documents(facet_candidates.as_ref())?;
+
+        let criteria_builder = self.criteria_builder(original_words.clone())?;
+        let primary = self.rank(&criteria_builder, query_tree.clone(), facet_candidates.clone(), &pinned)?;
+
+        let mut criteria_builder = self.criteria_builder(original_words)?;
+        criteria_builder.override_criteria(shadow_criteria);
+        let shadow = self.rank(&criteria_builder, query_tree, facet_candidates, &pinned)?;
+
+        let correlation = RankCorrelation::new(&primary.documents_ids, &shadow.documents_ids);
+        Ok(ShadowSearchResult { primary, shadow, correlation })
+    }
+
+    fn execute_with_words_limit(&self, words_limit: usize) -> anyhow::Result<SearchResult> {
+        let (query_tree, original_words, facet_candidates) = self.query_tree_and_candidates(words_limit)?;
+        let pinned = self.pinned_documents(facet_candidates.as_ref())?;
+        let criteria_builder = self.criteria_builder(original_words)?;
+        self.rank(&criteria_builder, query_tree, facet_candidates, &pinned)
+    }
+
+    /// Ranks the candidates with the ranking rules of the given criteria builder.
+    fn rank(
+        &self,
+        criteria_builder: &CriteriaBuilder,
+        query_tree: Option<Operation>,
+        facet_candidates: Option<RoaringBitmap>,
+        pinned: &[DocumentId],
+    ) -> anyhow::Result<SearchResult>
+    {
         let matching_words = match query_tree.as_ref() {
             Some(query_tree) => MatchingWords::from_query_tree(&query_tree),
             None => MatchingWords::default(),
         };
 
-        let criteria_builder = criteria::CriteriaBuilder::new(self.rtxn, self.index)?;
-        let criteria = criteria_builder.build(query_tree, facet_candidates)?;
+        // The sampled searches are decided randomly, independently of their queries.
+        let sampled = self.ranking_trace_sample_rate > 0.0 && rand::random::<f64>() < self.ranking_trace_sample_rate;
+        let recorder = if sampled { Some(Default::default()) } else { None };
+
+        let mut criteria = criteria_builder.build(query_tree, facet_candidates, self.sort_criteria.clone(), recorder.clone())?;
+        if let Some(caches) = self.shared_caches {
+            if let Some(wdcache) = caches.borrow_mut().wdcaches.remove(&self.first_letter_typo_cost) {
+                criteria.set_wdcache(wdcache);
+            }
+        }
 
-        match self.index.distinct_attribute(self.rtxn)? {
-            None => self.perform_sort(NoopDistinct, matching_words, criteria),
+        let result = match self.index.distinct_attribute(self.rtxn)? {
+            None => match self.index.distinct_attributes(self.rtxn)? {
+                Some(names) => {
+                    let field_ids_map = self.fields_ids_map()?;
+                    let ids = names.iter()
+                        .map(|name| field_ids_map.id(name).expect("distinct not present in field map"))
+                        .collect();
+                    let distinct = CompositeDistinct::new(ids, self.index, self.rtxn);
+                    self.perform_sort(distinct, matching_words, &mut criteria, pinned)
+                },
+                None => self.perform_sort(NoopDistinct, matching_words, &mut criteria, pinned),
+            },
             Some(name) => {
-                let field_ids_map = self.index.fields_ids_map(self.rtxn)?;
+                let field_ids_map = self.fields_ids_map()?;
                 let id = field_ids_map.id(name).expect("distinct not present in field map");
                 let faceted_fields = self.index.faceted_fields(self.rtxn)?;
                 match faceted_fields.get(name) {
                     Some(facet_type) => {
                         let distinct = FacetDistinct::new(id, self.index, self.rtxn, *facet_type);
-                        self.perform_sort(distinct, matching_words, criteria)
+                        self.perform_sort(distinct, matching_words, &mut criteria, pinned)
                     }
                     None => {
                         let distinct = MapDistinct::new(id, self.index, self.rtxn);
-                        self.perform_sort(distinct, matching_words, criteria)
+                        self.perform_sort(distinct, matching_words, &mut criteria, pinned)
                     }
                 }
             }
+        };
+
+        if let Some(caches) = self.shared_caches {
+            caches.borrow_mut().wdcaches.insert(self.first_letter_typo_cost, criteria.into_wdcache());
+        }
+        let mut result = result?;
+
+        result.geo_distances = self.geo_distances(&result.documents_ids)?;
+        result.ranking_trace = recorder.map(|recorder| take(&mut *recorder.borrow_mut()));
+        result.locales = self.locales.clone();
+
+        Ok(result)
+    }
+
+    /// Returns the pinned documents that exist, match the facet condition
+    /// and are not excluded, without duplicates.
+    fn pinned_documents(&self, facet_candidates: Option<&RoaringBitmap>) -> anyhow::Result<Vec<DocumentId>> {
+        if self.pinned.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let documents_ids = self.index.documents_ids(self.rtxn)?;
+        let mut seen = RoaringBitmap::new();
+        let pinned = self.pinned.iter()
+            .copied()
+            .filter(|&id| documents_ids.contains(id))
+            .filter(|&id| facet_candidates.map_or(true, |candidates| candidates.contains(id)))
+            .filter(|&id| seen.insert(id))
+            .collect();
+
+        Ok(pinned)
+    }
+
+    /// Converts the searchable attributes names into fields ids,
+    /// returns an error if one of them isn't searchable.
+    fn searchable_attributes_ids(&self, attributes: &[String]) -> anyhow::Result<Vec<FieldId>> {
+        let fields_ids_map = self.fields_ids_map()?;
+        let searchable_fields = self.index.searchable_fields(self.rtxn)?;
+
+        let mut fids = Vec::with_capacity(attributes.len());
+        for name in attributes {
+            let is_searchable = searchable_fields.as_ref().map_or(true, |fields| fields.contains(&name.as_str()));
+            match fields_ids_map.id(name) {
+                Some(fid) if is_searchable => fids.push(fid),
+                _ => bail!("attribute {:?} is not searchable", name),
+            }
         }
+
+        Ok(fids)
+    }
+
+    /// Computes the distance, in meters, between the given documents and the first geo point
+    /// used in the sort expressions or, if there is none, in the geo criterion of the ranking rules.
+    fn geo_distances(&self, documents_ids: &[DocumentId]) -> anyhow::Result<HashMap<DocumentId, f64>> {
+        let sort_point = self.sort_criteria.iter().flatten().find_map(|asc_desc| match asc_desc.member() {
+            Member::Geo(point) => Some(*point),
+            Member::Field(_) => None,
+        });
+
+        let point = match sort_point {
+            Some(point) => Some(point),
+            None => {
+                let criteria = match &self.criteria {
+                    Some(criteria) => criteria.clone(),
+                    None => self.index.criteria(self.rtxn)?,
+                };
+                criteria.into_iter().find_map(|criterion| match criterion {
+                    Criterion::GeoAsc(point) => Some(point),
+                    _ => None,
+                })
+            },
+        };
+
+        let mut geo_distances = HashMap::new();
+        if let Some(point) = point {
+            let fields_ids_map = self.fields_ids_map()?;
+            if let Some(geo_field_id) = fields_ids_map.id(GEO_FIELD_NAME) {
+                for (docid, obkv) in self.index.documents(self.rtxn, documents_ids.iter().copied())? {
+                    if let Some(lat_lng) = extract_geo_point(&obkv, geo_field_id)? {
+                        geo_distances.insert(docid, distance_between_two_points(&point, &lat_lng));
+                    }
+                }
+            }
+        }
+
+        Ok(geo_distances)
     }
 
     fn perform_sort(
         &self,
         mut distinct: impl for<'c> Distinct<'c>,
         matching_words: MatchingWords,
-        mut criteria: Final,
+        criteria: &mut Final,
+        pinned: &[DocumentId],
     ) -> anyhow::Result<SearchResult> {
         let mut offset = self.offset;
         let mut initial_candidates = RoaringBitmap::new();
         let mut excluded_documents = RoaringBitmap::new();
         let mut documents_ids = Vec::with_capacity(self.limit);
+        let mut demoted_candidates = RoaringBitmap::new();
+        let mut exhausted = false;
+
+        // The pinned documents come first and are removed from the ranked buckets.
+        let pinned_documents: RoaringBitmap = pinned.iter().copied().collect();
+        documents_ids.extend(pinned.iter().skip(offset).take(self.limit));
+        offset = offset.saturating_sub(pinned.len());
+        initial_candidates.union_with(&pinned_documents);
+
+        loop {
+            let next = if exhausted { None } else { criteria.next()? };
+            let (candidates, bucket_candidates) = match next {
+                Some(FinalResult { candidates, bucket_candidates, .. }) => {
+                    let candidates = candidates - &pinned_documents;
+                    demoted_candidates |= &candidates & &self.demoted;
+                    (candidates - &self.demoted, bucket_candidates)
+                },
+                // The demoted documents are returned in a last bucket, after the ranked ones.
+                None if !exhausted && !demoted_candidates.is_empty() => {
+                    exhausted = true;
+                    (take(&mut demoted_candidates), RoaringBitmap::new())
+                },
+                None => break,
+            };
 
-        while let Some(FinalResult { candidates, bucket_candidates, .. }) = criteria.next()? {
             debug!("Number of candidates found {}", candidates.len());
 
             let excluded = take(&mut excluded_documents);
@@ -189,7 +666,15 @@ impl<'a> Search<'a> {
             excluded_documents = candidates.into_excluded();
         }
 
-        Ok(SearchResult { matching_words, candidates: initial_candidates, documents_ids })
+        Ok(SearchResult {
+            matching_words,
+            candidates: initial_candidates,
+            documents_ids,
+            geo_distances: HashMap::new(),
+            dropped_words: Vec::new(),
+            ranking_trace: None,
+            locales: Vec::new(),
+        })
     }
 }
 
@@ -197,23 +682,50 @@ impl fmt::Debug for Search<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Search {
             query,
+            tokens,
             facet_condition,
+            sort_criteria,
             offset,
             limit,
-            optional_words,
+            terms_matching_strategy,
+            min_words_match,
             authorize_typos,
+            first_letter_typo_cost,
             words_limit,
+            zero_result_fallback,
+            searchable_attributes,
+            criteria,
+            locales,
+            ignore_stop_words,
+            pinned,
+            excluded,
+            demoted,
+            ranking_trace_sample_rate,
+            shared_caches: _,
             rtxn: _,
             index: _,
         } = self;
         f.debug_struct("Search")
             .field("query", query)
+            .field("tokens", tokens)
             .field("facet_condition", facet_condition)
+            .field("sort_criteria", sort_criteria)
             .field("offset", offset)
             .field("limit", limit)
-            .field("optional_words", optional_words)
+            .field("terms_matching_strategy", terms_matching_strategy)
+            .field("min_words_match", min_words_match)
             .field("authorize_typos", authorize_typos)
+            .field("first_letter_typo_cost", first_letter_typo_cost)
             .field("words_limit", words_limit)
+            .field("zero_result_fallback", zero_result_fallback)
+            .field("searchable_attributes", searchable_attributes)
+            .field("criteria", criteria)
+            .field("locales", locales)
+            .field("ignore_stop_words", ignore_stop_words)
+            .field("pinned", pinned)
+            .field("excluded", excluded)
+            .field("demoted", demoted)
+            .field("ranking_trace_sample_rate", ranking_trace_sample_rate)
             .finish()
     }
 }
@@ -224,9 +736,165 @@ pub struct SearchResult {
     pub candidates: RoaringBitmap,
     // TODO those documents ids should be associated with their criteria scores.
     pub documents_ids: Vec<DocumentId>,
+    /// The distance, in meters, between the documents and the geo point used to sort them.
+    pub geo_distances: HashMap<DocumentId, f64>,
+    /// The query words and phrases that were removed by the zero result fallback.
+    pub dropped_words: Vec<String>,
+    /// What the ranking rules did, when this search was sampled to be traced.
+    pub ranking_trace: Option<Vec<CriterionTrace>>,
+    /// The locales used to analyze the query.
+    pub locales: Vec<Locale>,
+}
+
+impl SearchResult {
+    /// Appends the raw stored documents of the hits, in order, to the buffer and pushes the
+    /// range of each of them in the buffer, they can be read with an `obkv::KvReader`.
+    /// The buffers can be cleared and reused between the searches to avoid allocations.
+    pub fn documents_into(
+        &self,
+        index: &Index,
+        rtxn: &heed::RoTxn,
+        buffer: &mut Vec<u8>,
+        ranges: &mut Vec<Range<usize>>,
+    ) -> anyhow::Result<()>
+    {
+        let db = index.documents.remap_data_type::<ByteSlice>();
+        ranges.reserve(self.documents_ids.len());
+        for &id in &self.documents_ids {
+            let bytes = db.get(rtxn, &BEU32::new(id))?
+                .with_context(|| format!("Could not find document {}", id))?;
+            let start = buffer.len();
+            buffer.extend_from_slice(bytes);
+            ranges.push(start..buffer.len());
+        }
+        Ok(())
+    }
 }
 
-pub type WordDerivationsCache = HashMap<(String, bool, u8), Vec<(String, u8)>>;
+/// A search to execute with `execute_searches`.
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub query: Option<String>,
+    pub facet_condition: Option<FacetCondition>,
+    pub sort_criteria: Option<Vec<AscDesc>>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for SearchQuery {
+    fn default() -> SearchQuery {
+        SearchQuery { query: None, facet_condition: None, sort_criteria: None, offset: 0, limit: 20 }
+    }
+}
+
+/// The caches shared by the searches executed together.
+#[derive(Default)]
+struct SharedCaches {
+    /// The derivations of the query words depend on the cost of a typo on the first
+    /// letter, the searches only share the derivations computed with the same cost.
+    wdcaches: HashMap<u8, WordDerivationsCache>,
+    filters: Vec<(FacetCondition, RoaringBitmap)>,
+    fields_ids_map: Option<FieldsIdsMap>,
+}
+
+/// Executes many searches against the same transaction, the derivations of the query words,
+/// the documents ids of the facet conditions and the fields ids map are shared between them.
+///
+/// The results are returned in the order of the queries.
+pub fn execute_searches(
+    rtxn: &heed::RoTxn,
+    index: &Index,
+    queries: Vec<SearchQuery>,
+) -> anyhow::Result<Vec<SearchResult>>
+{
+    let caches = RefCell::new(SharedCaches::default());
+    queries.into_iter().map(|SearchQuery { query, facet_condition, sort_criteria, offset, limit }| {
+        let mut search = Search::new(rtxn, index);
+        search.query = query;
+        search.facet_condition = facet_condition;
+        search.sort_criteria = sort_criteria;
+        search.offset(offset).limit(limit);
+        search.shared_caches = Some(&caches);
+        search.execute()
+    })
+    .collect()
+}
+
+/// The ranked documents ids of a search, see `Search::execute_stream`.
+pub struct SearchStream<'t> {
+    criteria: Final<'t>,
+    bucket: roaring::bitmap::IntoIter,
+}
+
+impl Iterator for SearchStream<'_> {
+    type Item = anyhow::Result<DocumentId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(docid) = self.bucket.next() {
+                return Some(Ok(docid));
+            }
+
+            match self.criteria.next() {
+                Ok(Some(FinalResult { candidates, .. })) => self.bucket = candidates.into_iter(),
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Returns the tokens of the analyzed query, the stop words are not given to the analyzer
+/// as they are removed when the query tree is built, the tokens only depend on the query.
+fn cached_query_tokens(query: &str) -> Arc<Vec<QueryToken>> {
+    // A poisoned cache only means that another thread panicked while holding the lock,
+    // the cached entries are always complete and can still be used.
+    let mut cache = QUERY_TOKENS_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    // if get_refresh finds the entry it is moved to the end of the linked hash map.
+    if let Some(tokens) = cache.get_refresh(query) {
+        return tokens.clone();
+    }
+    drop(cache);
+
+    let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+    let result = analyzer.analyze(query);
+    let tokens = Arc::new(query_tokens(query, result.tokens()));
+
+    let mut cache = QUERY_TOKENS_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.insert(query.to_string(), tokens.clone());
+    if cache.len() > QUERY_TOKENS_CACHE_SIZE {
+        cache.pop_front();
+    }
+
+    tokens
+}
+
+/// Caches the derivations of the query words, it also defines the number
+/// of typos that a typo on the first letter of a word costs.
+pub struct WordDerivationsCache {
+    first_letter_typo_cost: u8,
+    derivations: HashMap<(String, bool, u8), Vec<(String, u8)>>,
+}
+
+impl WordDerivationsCache {
+    pub fn new() -> WordDerivationsCache {
+        WordDerivationsCache::with_first_letter_typo_cost(1)
+    }
+
+    pub fn with_first_letter_typo_cost(first_letter_typo_cost: u8) -> WordDerivationsCache {
+        WordDerivationsCache { first_letter_typo_cost, derivations: HashMap::new() }
+    }
+
+    pub fn first_letter_typo_cost(&self) -> u8 {
+        self.first_letter_typo_cost
+    }
+}
+
+impl Default for WordDerivationsCache {
+    fn default() -> WordDerivationsCache {
+        WordDerivationsCache::new()
+    }
+}
 
 pub fn word_derivations<'c>(
     word: &str,
@@ -235,17 +903,21 @@ pub fn word_derivations<'c>(
     fst: &fst::Set<Cow<[u8]>>,
     cache: &'c mut WordDerivationsCache,
 ) -> Result<&'c [(String, u8)], Utf8Error> {
-    match cache.entry((word.to_string(), is_prefix, max_typo)) {
+    let first_letter_typo_cost = cache.first_letter_typo_cost;
+    match cache.derivations.entry((word.to_string(), is_prefix, max_typo)) {
         Entry::Occupied(entry) => Ok(entry.into_mut()),
         Entry::Vacant(entry) => {
             let mut derived_words = Vec::new();
             let dfa = build_dfa(word, max_typo, is_prefix);
             let mut stream = fst.search_with_state(&dfa).into_stream();
 
-            while let Some((word, state)) = stream.next() {
-                let word = std::str::from_utf8(word)?;
-                let distance = dfa.distance(state);
-                derived_words.push((word.to_string(), distance.to_u8()));
+            while let Some((derived_word, state)) = stream.next() {
+                let derived_word = std::str::from_utf8(derived_word)?;
+                let distance = dfa.distance(state).to_u8();
+                let typos = derivation_typos(word, derived_word, distance, first_letter_typo_cost);
+                if typos <= max_typo {
+                    derived_words.push((derived_word.to_string(), typos));
+                }
             }
 
             Ok(entry.insert(derived_words))
@@ -253,6 +925,19 @@ pub fn word_derivations<'c>(
     }
 }
 
+/// Returns the number of typos between a word and one of its derivations, given the
+/// Damerau-Levenshtein distance between them, a typo on the first letter of the word
+/// costs `first_letter_typo_cost` typos.
+pub fn derivation_typos(word: &str, derived_word: &str, distance: u8, first_letter_typo_cost: u8) -> u8 {
+    if distance != 0 && word.chars().next() != derived_word.chars().next() {
+        distance.saturating_add(first_letter_typo_cost.saturating_sub(1))
+    } else {
+        distance
+    }
+}
+
+/// Builds an automaton that matches the words at the given Damerau-Levenshtein distance,
+/// a transposition of two adjacent letters counts as a single typo.
 pub fn build_dfa(word: &str, typos: u8, is_prefix: bool) -> DFA {
     let lev = match typos {
         0 => &LEVDIST0,
@@ -266,3 +951,503 @@ pub fn build_dfa(word: &str, typos: u8, is_prefix: bool) -> DFA {
         lev.build_dfa(word)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+    use maplit::hashmap;
+
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use super::*;
+
+    #[test]
+    fn zero_result_fallback() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,hello world\n1,hello\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("hello world unknown");
+        search.terms_matching_strategy(TermsMatchingStrategy::All);
+        let result = search.execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+
+        search.zero_result_fallback(true);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        assert_eq!(result.dropped_words, vec!["unknown".to_string()]);
+
+        let result = search.query("hello unknown world").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+        assert_eq!(result.dropped_words, vec!["unknown".to_string(), "world".to_string()]);
+    }
+
+    #[test]
+    fn restrict_searchable_attributes() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,title,description\n0,hello world,nothing\n1,nothing,hello world\n2,hello,world\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("hello world");
+        search.terms_matching_strategy(TermsMatchingStrategy::All);
+        let mut documents_ids = search.execute().unwrap().documents_ids;
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 1, 2]);
+
+        search.searchable_attributes(&["title"]);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+
+        search.query("hello wor").searchable_attributes(&["title", "description"]);
+        let mut documents_ids = search.execute().unwrap().documents_ids;
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 1, 2]);
+
+        search.searchable_attributes(&["unknown"]);
+        assert!(search.execute().is_err());
+    }
+
+    #[test]
+    fn query_tokens_cache() {
+        let query = "query tokens \"cache test\"";
+        let tokens = cached_query_tokens(query);
+        let word = QueryToken::Word { word: "cache".to_string(), original: "cache".to_string() };
+        assert!(tokens.contains(&word));
+        assert!(tokens.contains(&QueryToken::Separator(1)));
+
+        let cached = cached_query_tokens(query);
+        assert!(Arc::ptr_eq(&tokens, &cached));
+    }
+
+    #[test]
+    fn first_letter_typo_cost() {
+        assert_eq!(derivation_typos("hello", "hello", 0, 2), 0);
+        assert_eq!(derivation_typos("hello", "hlelo", 1, 2), 1);
+        assert_eq!(derivation_typos("hello", "jello", 1, 2), 2);
+        assert_eq!(derivation_typos("hello", "jello", 1, 1), 1);
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,hello\n1,jello\n2,hlelo\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // A transposition counts as a single typo.
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("hello");
+        let mut documents_ids = search.execute().unwrap().documents_ids;
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 1, 2]);
+
+        // A typo on the first letter now costs two typos, more than allowed for this word.
+        search.first_letter_typo_cost(2);
+        let mut documents_ids = search.execute().unwrap().documents_ids;
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 2]);
+    }
+
+    #[test]
+    fn shared_caches_first_letter_typo_cost() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,hello\n1,jello\n2,hlelo\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let caches = RefCell::new(SharedCaches::default());
+        let mut search = index.search(&rtxn);
+        search.query("hello");
+        search.shared_caches = Some(&caches);
+        let mut documents_ids = search.execute().unwrap().documents_ids;
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 1, 2]);
+
+        // The derivations cached by the previous search must not be reused with another cost.
+        search.first_letter_typo_cost(2);
+        let mut documents_ids = search.execute().unwrap().documents_ids;
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![0, 2]);
+        assert_eq!(caches.borrow().wdcaches.len(), 2);
+    }
+
+    #[test]
+    fn execute_stream() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,hello world\n1,hello\n2,world hello\n3,bonjour\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("hello world");
+        let documents_ids = search.execute().unwrap().documents_ids;
+
+        let streamed = search.execute_stream(|stream| stream.collect::<anyhow::Result<Vec<_>>>()).unwrap();
+        assert_eq!(streamed, documents_ids);
+
+        let first = search.execute_stream(|mut stream| stream.next().transpose()).unwrap();
+        assert_eq!(first, documents_ids.first().copied());
+    }
+
+    #[test]
+    fn execute_many_searches() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,name,age\n0,hello world,20\n1,hello,30\n2,world,40\n3,bonjour,50\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let condition = FacetCondition::from_str(&rtxn, &index, "age > 25").unwrap();
+        let queries = vec![
+            SearchQuery { query: Some("hello".to_string()), ..SearchQuery::default() },
+            SearchQuery { query: Some("hello".to_string()), facet_condition: Some(condition.clone()), ..SearchQuery::default() },
+            SearchQuery { query: Some("world".to_string()), facet_condition: Some(condition.clone()), ..SearchQuery::default() },
+            SearchQuery { facet_condition: Some(condition.clone()), offset: 1, limit: 1, ..SearchQuery::default() },
+        ];
+
+        let results = execute_searches(&rtxn, &index, queries.clone()).unwrap();
+        assert_eq!(results.len(), queries.len());
+
+        for (query, result) in queries.into_iter().zip(results) {
+            let mut search = index.search(&rtxn);
+            if let Some(query) = query.query {
+                search.query(query);
+            }
+            if let Some(condition) = query.facet_condition {
+                search.facet_condition(condition);
+            }
+            search.offset(query.offset).limit(query.limit);
+            let expected = search.execute().unwrap();
+            assert_eq!(result.documents_ids, expected.documents_ids);
+            assert_eq!(result.candidates, expected.candidates);
+        }
+    }
+
+    #[test]
+    fn search_pre_analyzed_tokens() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,new york city\n1,york\n2,big apple\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The embedder expanded the "big apple" query to its entity name.
+        let rtxn = index.read_txn().unwrap();
+        let tokens = vec![QueryToken::word("new"), QueryToken::Separator(0), QueryToken::word("york")];
+        let result = index.search(&rtxn).query("big apple").tokens(tokens).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn pinned_documents_come_first() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into() });
+        builder.execute(|_, _| ()).unwrap();
+        let content = &b"id,name,age\n0,hello,20\n1,hello world,25\n2,bye,40\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // Duplicated and unknown documents are ignored.
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("hello").pinned(vec![2, 0, 2, 9]);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2, 0, 1]);
+
+        search.offset(1).limit(1);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+
+        // The pinned documents must match the filter.
+        let condition = FacetCondition::from_str(&rtxn, &index, "age < 30").unwrap();
+        search.offset(0).limit(20).facet_condition(condition);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn excluded_and_demoted_documents() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,hello world\n1,hello\n2,hello world\n3,bye\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("hello world").excluded(vec![2, 3]);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+        assert!(!result.candidates.contains(2));
+
+        // The demoted documents come last, even the best ranked ones.
+        search.excluded(Vec::new()).demoted(vec![0, 3]);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2, 1, 0]);
+
+        search.offset(2).limit(1);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+    }
+
+    #[test]
+    fn override_criteria() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into(), "rank".into() => "number".into() });
+        builder.set_criteria(vec![Criterion::Words, Criterion::Asc("age".into()), Criterion::Desc("rank".into())]);
+        builder.execute(|_, _| ()).unwrap();
+        let content = &b"id,name,age,rank\n0,hello,30,2\n1,hello,20,1\n2,hello,40,3\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("hello");
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 0, 2]);
+
+        search.override_criteria(vec![Criterion::Desc("rank".into()), Criterion::Words]);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2, 0, 1]);
+
+        // The ranking rules must be a subset of the ones of the index.
+        search.override_criteria(vec![Criterion::Typo]);
+        assert!(search.execute().is_err());
+
+        search.override_criteria(vec![Criterion::Words, Criterion::Words]);
+        assert!(search.execute().is_err());
+    }
+
+    #[test]
+    fn shadow_criteria() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into(), "rank".into() => "number".into() });
+        builder.set_criteria(vec![Criterion::Words, Criterion::Asc("age".into()), Criterion::Desc("rank".into())]);
+        builder.execute(|_, _| ()).unwrap();
+        let content = &b"id,name,age,rank\n0,hello,30,2\n1,hello,20,1\n2,hello,40,3\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("hello");
+        let result = search.execute_shadow(vec![Criterion::Words, Criterion::Desc("rank".into())]).unwrap();
+        assert_eq!(result.primary.documents_ids, vec![1, 0, 2]);
+        assert_eq!(result.shadow.documents_ids, vec![2, 0, 1]);
+        assert_eq!(result.correlation, RankCorrelation { common_documents: 3, kendall_tau: -1.0 });
+    }
+
+    #[test]
+    fn documents_into_buffer() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,hello kevin\n1,hello kevina\n2,bonjour\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("hello").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+
+        let mut buffer = Vec::new();
+        let mut ranges = Vec::new();
+        result.documents_into(&index, &rtxn, &mut buffer, &mut ranges).unwrap();
+        assert_eq!(ranges.len(), 2);
+
+        let documents = index.documents(&rtxn, result.documents_ids.iter().copied()).unwrap();
+        for ((_id, document), range) in documents.into_iter().zip(ranges) {
+            let copied = obkv::KvReader::new(&buffer[range]);
+            assert_eq!(document.iter().collect::<Vec<_>>(), copied.iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn search_locales() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,avion rouge\n1,chat noir\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The french stop words are only removed from the query when asked to.
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("les avion").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+        assert!(result.locales.is_empty());
+
+        let result = index.search(&rtxn).query("les avion").locales(&[Locale::French]).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        assert_eq!(result.locales, vec![Locale::French]);
+
+        // The elided articles are removed too.
+        let result = index.search(&rtxn).query("l'avion rouge").locales(&[Locale::French]).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+    }
+
+    #[test]
+    fn keep_stop_words() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,the who\n1,who is it\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The stop word is ignored and the document where "who" comes first ranks first.
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("the who").locales(&[Locale::English]).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 0]);
+
+        let mut search = index.search(&rtxn);
+        search.query("the who").locales(&[Locale::English]).ignore_stop_words(false);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+
+        // The stop word remains optional even when all the words are required.
+        search.terms_matching_strategy(TermsMatchingStrategy::All);
+        let result = search.execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn ranking_trace_sampling() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into(), "rank".into() => "number".into() });
+        builder.set_criteria(vec![Criterion::Words, Criterion::Proximity, Criterion::Asc("age".into()), Criterion::Desc("rank".into())]);
+        builder.set_proximity(false);
+        builder.execute(|_, _| ()).unwrap();
+        let content = &b"id,name,age,rank\n0,hello,30,2\n1,hello,20,1\n2,hello,40,3\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = index.search(&rtxn);
+        search.query("hello");
+        let result = search.execute().unwrap();
+        assert_eq!(result.ranking_trace, None);
+
+        // The disabled proximity ranking rule isn't part of the trace.
+        let result = search.ranking_trace_sample_rate(1.0).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1, 0, 2]);
+        let trace = result.ranking_trace.unwrap();
+        let names: Vec<_> = trace.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["words", "asc(age)", "desc(rank)"]);
+        assert_eq!((trace[1].buckets, trace[1].candidates), (3, 3));
+        assert_eq!((trace[2].buckets, trace[2].candidates), (3, 3));
+
+        let result = search.ranking_trace_sample_rate(0.0).execute().unwrap();
+        assert_eq!(result.ranking_trace, None);
+    }
+}
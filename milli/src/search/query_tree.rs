@@ -1,13 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::{fmt, cmp, mem};
 
 use fst::Set;
 use levenshtein_automata::{DFA, Distance};
-use meilisearch_tokenizer::{TokenKind, tokenizer::TokenStream};
+use meilisearch_tokenizer::{Analyzer, Token, TokenKind, tokenizer::TokenStream};
 use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
 use slice_group_by::GroupBy;
 
-use crate::Index;
+use crate::{DEFAULT_MIN_WORD_SIZE_FOR_ONE_TYPO, DEFAULT_MIN_WORD_SIZE_FOR_TWO_TYPOS, Index, Locale};
+use crate::truncate_long_word;
 use super::build_dfa;
 
 type IsOptionalWord = bool;
@@ -162,15 +165,46 @@ trait Context {
             None => Ok(None),
         }
     }
+
+    /// Returns `true` if the typo tolerance is disabled on this word.
+    fn is_exact_word(&self, _word: &str) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// Returns the minimum number of characters a word must have
+    /// to be matched with one typo and with two typos.
+    fn min_word_size_for_typos(&self) -> heed::Result<(u8, u8)> {
+        Ok((DEFAULT_MIN_WORD_SIZE_FOR_ONE_TYPO, DEFAULT_MIN_WORD_SIZE_FOR_TWO_TYPOS))
+    }
+}
+
+/// Defines which documents match when all the query words can't be found in them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TermsMatchingStrategy {
+    /// All the query words must be present in the documents.
+    All,
+    /// The query words are removed from the end of the query.
+    Last,
+    /// The most frequent query words are removed first.
+    Frequency,
+}
+
+impl Default for TermsMatchingStrategy {
+    fn default() -> TermsMatchingStrategy {
+        TermsMatchingStrategy::Last
+    }
 }
 
 /// The query tree builder is the interface to build a query tree.
 pub struct QueryTreeBuilder<'a> {
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
-    optional_words: bool,
+    terms_matching_strategy: TermsMatchingStrategy,
+    min_words_match: u8,
     authorize_typos: bool,
     words_limit: Option<usize>,
+    locales: Vec<Locale>,
+    ignore_stop_words: bool,
 }
 
 impl<'a> Context for QueryTreeBuilder<'a> {
@@ -185,13 +219,32 @@ impl<'a> Context for QueryTreeBuilder<'a> {
     fn word_documents_count(&self, word: &str) -> heed::Result<Option<u64>> {
         self.index.word_documents_count(self.rtxn, word)
     }
+
+    fn is_exact_word(&self, word: &str) -> anyhow::Result<bool> {
+        Ok(self.index.exact_words(self.rtxn)?.map_or(false, |words| words.contains(word)))
+    }
+
+    fn min_word_size_for_typos(&self) -> heed::Result<(u8, u8)> {
+        let one_typo = self.index.min_word_size_for_one_typo(self.rtxn)?;
+        let two_typos = self.index.min_word_size_for_two_typos(self.rtxn)?;
+        Ok((one_typo, two_typos))
+    }
 }
 
 impl<'a> QueryTreeBuilder<'a> {
     /// Create a `QueryTreeBuilder` from a heed ReadOnly transaction `rtxn`
     /// and an Index `index`.
     pub fn new(rtxn: &'a heed::RoTxn<'a>, index: &'a Index) -> Self {
-        Self { rtxn, index, optional_words: true, authorize_typos: true, words_limit: None }
+        Self {
+            rtxn,
+            index,
+            terms_matching_strategy: TermsMatchingStrategy::default(),
+            min_words_match: 0,
+            authorize_typos: true,
+            words_limit: None,
+            locales: Vec::new(),
+            ignore_stop_words: true,
+        }
     }
 
     /// if `optional_words` is set to `false` the query tree will be
@@ -200,7 +253,27 @@ impl<'a> QueryTreeBuilder<'a> {
     /// default value if not called: `true`
     #[allow(unused)]
     pub fn optional_words(&mut self, optional_words: bool) -> &mut Self {
-        self.optional_words = optional_words;
+        self.terms_matching_strategy = if optional_words {
+            TermsMatchingStrategy::Last
+        } else {
+            TermsMatchingStrategy::All
+        };
+        self
+    }
+
+    /// Defines the order in which the query words are removed to build the branches
+    /// of the query tree, the criterion `words` returns these branches one by one.
+    /// default value if not called: `TermsMatchingStrategy::Last`
+    pub fn terms_matching_strategy(&mut self, strategy: TermsMatchingStrategy) -> &mut Self {
+        self.terms_matching_strategy = strategy;
+        self
+    }
+
+    /// Defines the percentage of the query words that the documents must contain when the
+    /// query words can be removed, the branches with fewer words are not generated.
+    /// default value if not called: `0`
+    pub fn min_words_match(&mut self, percentage: u8) -> &mut Self {
+        self.min_words_match = percentage.min(100);
         self
     }
 
@@ -221,22 +294,74 @@ impl<'a> QueryTreeBuilder<'a> {
         self
     }
 
+    /// The stop words of these locales are removed from the query,
+    /// in addition to the stop words of the index.
+    pub fn locales(&mut self, locales: &[Locale]) -> &mut Self {
+        self.locales = locales.to_vec();
+        self
+    }
+
+    /// if `ignore_stop_words` is set to `false` the stop words of the query are kept,
+    /// they are matched without typos and used by the proximity and the phrases but
+    /// they are always the first words to be removed, even when all the words are required.
+    /// default value if not called: `true`
+    pub fn ignore_stop_words(&mut self, ignore_stop_words: bool) -> &mut Self {
+        self.ignore_stop_words = ignore_stop_words;
+        self
+    }
+
     /// Build the query tree:
     /// - if `optional_words` is set to `false` the query tree will be
     ///   generated forcing all query words to be present in each matching documents
     ///   (the criterion `words` will be ignored)
-    /// - if `authorize_typos` is set to `false` the query tree will be generated
-    ///   forcing all query words to match documents without any typo
-    ///   (the criterion `typo` will be ignored)
-    pub fn build(&self, query: TokenStream) -> anyhow::Result<Option<Operation>> {
+    /// - if `authorize_typos` is set to `false`, or the typo tolerance is disabled in the
+    ///   settings, the query tree will be generated forcing all query words to match
+    ///   documents without any typo (the criterion `typo` will be ignored)
+    pub fn build(&self, query: &str, tokens: TokenStream) -> anyhow::Result<Option<Operation>> {
+        self.build_from_tokens(&query_tokens(query, tokens))
+    }
+
+    /// Same as `build` but from the already analyzed query tokens.
+    pub(crate) fn build_from_tokens(&self, query: &[QueryToken]) -> anyhow::Result<Option<Operation>> {
         let stop_words = self.index.stop_words(self.rtxn)?;
-        let primitive_query = create_primitive_query(query, stop_words, self.words_limit);
+        let primitive_query = create_primitive_query(
+            query,
+            stop_words,
+            &self.locales,
+            self.ignore_stop_words,
+            self.words_limit,
+        );
         if !primitive_query.is_empty() {
-            create_query_tree(self, self.optional_words, self.authorize_typos, primitive_query).map(Some)
+            let authorize_typos = self.authorize_typos && self.index.typo_tolerance_enabled(self.rtxn)?;
+            create_query_tree(
+                self,
+                self.terms_matching_strategy,
+                self.min_words_match,
+                authorize_typos,
+                primitive_query,
+            ).map(Some)
         } else {
             Ok(None)
         }
     }
+
+    /// Returns the words and phrases, in order, that are used to build the query tree,
+    /// the `words_limit` restricts the number of parts returned.
+    pub(crate) fn query_parts(&self, query: &[QueryToken]) -> anyhow::Result<Vec<String>> {
+        let stop_words = self.index.stop_words(self.rtxn)?;
+        let primitive_query = create_primitive_query(
+            query,
+            stop_words,
+            &self.locales,
+            self.ignore_stop_words,
+            self.words_limit,
+        );
+        let parts = primitive_query.into_iter().map(|part| match part {
+            PrimitiveQueryPart::Phrase(words) => format!("\"{}\"", words.join(" ")),
+            PrimitiveQueryPart::Word(word, _) | PrimitiveQueryPart::StopWord(word) => word,
+        });
+        Ok(parts.collect())
+    }
 }
 
 /// Split the word depending on the frequency of subwords in the database documents.
@@ -264,17 +389,22 @@ fn split_best_frequency<'a>(ctx: &impl Context, word: &'a str) -> heed::Result<O
     )))
 }
 
-/// Return the `QueryKind` of a word depending on `authorize_typos`
-/// and the provided word length.
-fn typos(word: String, authorize_typos: bool) -> QueryKind {
-    if authorize_typos {
-        match word.len() {
-            0..=4 => QueryKind::exact(word),
-            5..=8 => QueryKind::tolerant(1, word),
-            _     => QueryKind::tolerant(2, word),
+/// Return the `QueryKind` of a word depending on `authorize_typos`, the provided
+/// word length compared to the minimum word sizes for typos of the context
+/// and whether typos are disabled on this word.
+fn typos(ctx: &impl Context, word: String, authorize_typos: bool) -> anyhow::Result<QueryKind> {
+    if authorize_typos && !ctx.is_exact_word(&word)? {
+        let (one_typo, two_typos) = ctx.min_word_size_for_typos()?;
+        let count = word.chars().count();
+        if count >= two_typos as usize {
+            Ok(QueryKind::tolerant(2, word))
+        } else if count >= one_typo as usize {
+            Ok(QueryKind::tolerant(1, word))
+        } else {
+            Ok(QueryKind::exact(word))
         }
     } else {
-        QueryKind::exact(word)
+        Ok(QueryKind::exact(word))
     }
 }
 
@@ -314,6 +444,69 @@ impl MatchingWords {
             Distance::AtLeast(_) => false,
         })
     }
+
+    /// Returns the byte offsets and lengths, in the original text, of the words that match,
+    /// the analyzer must be configured like the one that indexed the documents.
+    ///
+    /// The offsets are the ones of the original text and not of the normalized words,
+    /// the highlighted substrings are therefore exact even when the normalization changes their length.
+    pub fn matching_bytes<A: AsRef<[u8]>>(&self, analyzer: &Analyzer<A>, text: &str) -> Vec<(usize, usize)> {
+        analyzer.analyze(text).tokens()
+            .filter(|token| token.is_word() && self.matches(token.text()))
+            .map(|token| (token.byte_start, token.byte_end - token.byte_start))
+            .collect()
+    }
+
+    /// Returns the byte range, in the original text, of a part of the text of at most `crop_len`
+    /// characters around the first word that matches, or at the start of the text when none match.
+    ///
+    /// The text is only cut between the tokens of the analyzer and not between whitespaces,
+    /// the scripts that don't separate their words with spaces, like Chinese or Japanese, are
+    /// therefore cropped between two of their words and never in the middle of a character.
+    /// The matching word is always kept, even when it is longer than the crop length.
+    pub fn crop_bounds<A: AsRef<[u8]>>(&self, analyzer: &Analyzer<A>, text: &str, crop_len: usize) -> Range<usize> {
+        let analyzed = analyzer.analyze(text);
+        let tokens: Vec<_> = analyzed.tokens().collect();
+        if tokens.is_empty() {
+            return 0..0;
+        }
+
+        let chars_count = |token: &Token| text[token.byte_start..token.byte_end].chars().count();
+        let first_match = tokens.iter()
+            .position(|token| token.is_word() && self.matches(token.text()))
+            .unwrap_or(0);
+
+        // We grow the window of tokens on both sides of the match,
+        // one token at a time, as long as they fit in the crop length.
+        let (mut start, mut end) = (first_match, first_match + 1);
+        let mut len = chars_count(&tokens[first_match]);
+        loop {
+            let mut grown = false;
+            if let Some(token) = tokens.get(end) {
+                let token_len = chars_count(token);
+                if len + token_len <= crop_len {
+                    len += token_len;
+                    end += 1;
+                    grown = true;
+                }
+            }
+            if let Some(token) = start.checked_sub(1).map(|i| &tokens[i]) {
+                let token_len = chars_count(token);
+                if len + token_len <= crop_len {
+                    len += token_len;
+                    start -= 1;
+                    grown = true;
+                }
+            }
+            if !grown { break }
+        }
+
+        // The separators at the edges of the cropped text are not kept.
+        while start + 1 < end && !tokens[start].is_word() { start += 1 }
+        while end - 1 > start && !tokens[end - 1].is_word() { end -= 1 }
+
+        tokens[start].byte_start..tokens[end - 1].byte_end
+    }
 }
 
 /// Lists all words which can be considered as a match for the query tree.
@@ -338,7 +531,8 @@ fn fetch_queries(tree: &Operation) -> HashSet<(&str, u8, IsPrefix)> {
 /// Main function that creates the final query tree from the primitive query.
 fn create_query_tree(
     ctx: &impl Context,
-    optional_words: bool,
+    strategy: TermsMatchingStrategy,
+    min_words_match: u8,
     authorize_typos: bool,
     query: PrimitiveQuery,
 ) -> anyhow::Result<Operation>
@@ -360,13 +554,17 @@ fn create_query_tree(
                 if let Some(child) = split_best_frequency(ctx, &word)? {
                     children.push(child);
                 }
-                children.push(Operation::Query(Query { prefix, kind: typos(word, authorize_typos) }));
+                children.push(Operation::Query(Query { prefix, kind: typos(ctx, word, authorize_typos)? }));
                 Ok(Operation::or(false, children))
             },
             // create a CONSECUTIVE operation wrapping all word in the phrase
             PrimitiveQueryPart::Phrase(words) => {
                 Ok(Operation::phrase(words))
             },
+            // the stop words are too short and too frequent to be derived
+            PrimitiveQueryPart::StopWord(word) => {
+                Ok(Operation::Query(Query { prefix: false, kind: QueryKind::exact(word) }))
+            },
         }
     }
 
@@ -396,16 +594,14 @@ fn create_query_tree(
                         },
                         words => {
                             let is_prefix = words.last().map_or(false, |part| part.is_prefix());
-                            let words: Vec<_> = words.iter().filter_map(|part| {
-                                if let PrimitiveQueryPart::Word(word, _) = part {
-                                    Some(word.as_str())
-                                } else {
-                                    None
-                                }
+                            let words: Vec<_> = words.iter().filter_map(|part| match part {
+                                PrimitiveQueryPart::Word(word, _) => Some(word.as_str()),
+                                PrimitiveQueryPart::StopWord(word) => Some(word.as_str()),
+                                PrimitiveQueryPart::Phrase(_) => None,
                             }).collect();
                             let mut operations = synonyms(ctx, &words)?.unwrap_or_default();
                             let concat = words.concat();
-                            let query = Query { prefix: is_prefix, kind: typos(concat, authorize_typos) };
+                            let query = Query { prefix: is_prefix, kind: typos(ctx, concat, authorize_typos)? };
                             operations.push(Operation::Query(query));
                             and_op_children.push(Operation::or(false, operations));
                         }
@@ -424,9 +620,12 @@ fn create_query_tree(
         Ok(Operation::and(op_children))
     }
 
-    /// Create a new branch removing the last non-phrase query parts.
+    /// Create a new branch removing the non-phrase query parts one by one,
+    /// in the order defined by the terms matching strategy.
     fn optional_word(
         ctx: &impl Context,
+        strategy: TermsMatchingStrategy,
+        min_words_match: u8,
         authorize_typos: bool,
         query: PrimitiveQuery,
     ) -> anyhow::Result<Operation>
@@ -434,21 +633,33 @@ fn create_query_tree(
         let number_phrases = query.iter().filter(|p| p.is_phrase()).count();
         let mut operation_children = Vec::new();
 
-        let start = number_phrases + (number_phrases == 0) as usize;
-        for len in start..=query.len() {
-            let mut word_count = len - number_phrases;
-            let query: Vec<_> = query.iter().filter_map(|p| {
-                if p.is_phrase() {
-                    Some(p)
-                } else if word_count != 0 {
-                    word_count -= 1;
-                    Some(p)
-                } else {
-                    None
+        // The indexes of the non-phrase query parts, the first ones are the last to be removed.
+        let mut words: Vec<_> = query.iter().enumerate().filter(|(_, p)| !p.is_phrase()).map(|(i, _)| i).collect();
+        if strategy == TermsMatchingStrategy::Frequency {
+            let mut counts = HashMap::new();
+            for &i in &words {
+                if let PrimitiveQueryPart::Word(word, _) = &query[i] {
+                    counts.insert(i, ctx.word_documents_count(word)?.unwrap_or(0));
                 }
-            })
-            .cloned()
-            .collect();
+            }
+            // The sort is stable, the words with the same frequency are removed from the end.
+            words.sort_by_key(|i| counts.get(i).copied().unwrap_or(0));
+        }
+
+        // The stop words kept in the query are removed before any other word.
+        words.sort_by_key(|&i| query[i].is_stop_word());
+
+        // The branches that contain fewer than the minimum percentage of the query parts
+        // are not generated, the words criterion therefore never returns them.
+        let min_parts = (query.len() * min_words_match as usize + 99) / 100;
+        let start = cmp::max(number_phrases + (number_phrases == 0) as usize, min_parts);
+        for len in start..=query.len() {
+            let kept_words = &words[..len - number_phrases];
+            let query: Vec<_> = query.iter()
+                .enumerate()
+                .filter(|(i, p)| p.is_phrase() || kept_words.contains(i))
+                .map(|(_, p)| p.clone())
+                .collect();
 
             let ngrams = ngrams(ctx, authorize_typos, &query)?;
             operation_children.push(ngrams);
@@ -457,10 +668,19 @@ fn create_query_tree(
         Ok(Operation::or(true, operation_children))
     }
 
-    if optional_words {
-        optional_word(ctx, authorize_typos, query)
-    } else {
-        ngrams(ctx, authorize_typos, query.as_slice())
+    match strategy {
+        TermsMatchingStrategy::All if query.iter().any(PrimitiveQueryPart::is_stop_word) => {
+            // All the words are required but the stop words, the query without them is an
+            // alternative branch, the words criterion returns it after the complete query.
+            let mut children = vec![ngrams(ctx, authorize_typos, query.as_slice())?];
+            let query: Vec<_> = query.into_iter().filter(|p| !p.is_stop_word()).collect();
+            if !query.is_empty() {
+                children.push(ngrams(ctx, authorize_typos, query.as_slice())?);
+            }
+            Ok(Operation::or(true, children))
+        },
+        TermsMatchingStrategy::All => ngrams(ctx, authorize_typos, query.as_slice()),
+        strategy => optional_word(ctx, strategy, min_words_match, authorize_typos, query),
     }
 }
 
@@ -470,6 +690,8 @@ type PrimitiveQuery = Vec<PrimitiveQueryPart>;
 enum PrimitiveQueryPart {
     Phrase(Vec<String>),
     Word(String, IsPrefix),
+    /// A stop word that is kept in the query, it is never a prefix.
+    StopWord(String),
 }
 
 impl PrimitiveQueryPart {
@@ -477,42 +699,118 @@ impl PrimitiveQueryPart {
         matches!(self, Self::Phrase(_))
     }
 
+    fn is_stop_word(&self) -> bool {
+        matches!(self, Self::StopWord(_))
+    }
+
     fn is_prefix(&self) -> bool {
         matches!(self, Self::Word(_, is_prefix) if *is_prefix)
     }
 }
 
+/// A token of the analyzed query, it doesn't borrow the query
+/// and can therefore be kept in the query tokens cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryToken {
+    /// A word once normalized and as it was typed in the query.
+    Word { word: String, original: String },
+    /// A separator and the number of double quotes it contains.
+    Separator(usize),
+}
+
+impl QueryToken {
+    /// A word that is typed as it is normalized, the word must already be
+    /// normalized the way the documents are (e.g. lowercased, without diacritics).
+    pub fn word(word: impl Into<String>) -> QueryToken {
+        let word = word.into();
+        QueryToken::Word { original: word.clone(), word }
+    }
+}
+
+/// Converts the tokens of the analyzed query into owned query tokens,
+/// the tokens that are neither words nor separators are ignored.
+pub(crate) fn query_tokens(query: &str, tokens: TokenStream) -> Vec<QueryToken> {
+    tokens.filter_map(|token| match token.kind {
+        TokenKind::Word | TokenKind::StopWord => {
+            let original = query.get(token.byte_start..token.byte_end).unwrap_or(&token.word);
+            Some(QueryToken::Word { word: token.word.to_string(), original: original.to_string() })
+        },
+        TokenKind::Separator(_) => {
+            let quote_count = token.word.chars().filter(|&s| s == '"').count();
+            Some(QueryToken::Separator(quote_count))
+        },
+        _ => None,
+    })
+    .collect()
+}
+
+/// A query word as it was typed in the query, before any normalization,
+/// along with its normalized form, the one that is looked up in the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalWord {
+    pub word: String,
+    pub normalized: String,
+    pub prefix: bool,
+}
+
+/// Returns the words of the query as they were typed, the last word
+/// is a prefix when it is the last token of the query, like in the primitive query.
+pub(crate) fn original_words(query: &[QueryToken]) -> Vec<OriginalWord> {
+    let mut words = Vec::new();
+    let mut peekable = query.iter().peekable();
+    while let Some(token) = peekable.next() {
+        if let QueryToken::Word { word, original } = token {
+            let prefix = peekable.peek().is_none();
+            words.push(OriginalWord { word: original.clone(), normalized: word.clone(), prefix });
+        }
+    }
+    words
+}
+
 /// Create primitive query from tokenized query string,
 /// the primitive query is an intermediate state to build the query tree.
-fn create_primitive_query(query: TokenStream, stop_words: Option<Set<&[u8]>>, words_limit: Option<usize>) -> PrimitiveQuery {
+fn create_primitive_query(
+    query: &[QueryToken],
+    stop_words: Option<Set<&[u8]>>,
+    locales: &[Locale],
+    ignore_stop_words: bool,
+    words_limit: Option<usize>,
+) -> PrimitiveQuery
+{
     let mut primitive_query = Vec::new();
     let mut phrase = Vec::new();
     let mut quoted = false;
 
     let parts_limit = words_limit.unwrap_or(usize::MAX);
 
-    let mut peekable = query.peekable();
+    let mut peekable = query.iter().peekable();
     while let Some(token) = peekable.next() {
         // early return if word limit is exceeded
         if primitive_query.len() >= parts_limit { return primitive_query }
 
-        match token.kind {
-            TokenKind::Word | TokenKind::StopWord  => {
+        match token {
+            QueryToken::Word { word, .. } => {
+                // The long words are truncated the same way they are when indexed.
+                let word = truncate_long_word(word).into_owned();
                 // 1. if the word is quoted we push it in a phrase-buffer waiting for the ending quote,
                 // 2. if the word is not the last token of the query and is not a stop_word we push it as a non-prefix word,
+                //    the stop words are only pushed when they must not be ignored,
                 // 3. if the word is the last token of the query we push it as a prefix word.
                 if quoted {
-                    phrase.push(token.word.to_string());
+                    phrase.push(word);
                 } else if peekable.peek().is_some() {
-                     if !stop_words.as_ref().map_or(false, |swords| swords.contains(token.word.as_ref())) {
-                         primitive_query.push(PrimitiveQueryPart::Word(token.word.to_string(), false));
+                     let is_stop_word = stop_words.as_ref().map_or(false, |swords| swords.contains(&word))
+                         || locales.iter().any(|locale| locale.is_stop_word(&word));
+                     if !is_stop_word {
+                         primitive_query.push(PrimitiveQueryPart::Word(word, false));
+                     } else if !ignore_stop_words {
+                         primitive_query.push(PrimitiveQueryPart::StopWord(word));
                      }
                 } else {
-                    primitive_query.push(PrimitiveQueryPart::Word(token.word.to_string(), true));
+                    primitive_query.push(PrimitiveQueryPart::Word(word, true));
                 }
             },
-            TokenKind::Separator(_) => {
-                let quote_count = token.word.chars().filter(|&s| s == '"').count();
+            &QueryToken::Separator(quote_count) => {
                 // swap quoted state if we encounter a double quote
                 if quote_count % 2 != 0 {
                     quoted = !quoted;
@@ -521,7 +819,6 @@ fn create_primitive_query(query: TokenStream, stop_words: Option<Set<&[u8]>>, wo
                     primitive_query.push(PrimitiveQueryPart::Phrase(mem::take(&mut phrase)));
                 }
             },
-            _ => (),
         }
     }
 
@@ -578,12 +875,30 @@ mod test {
             optional_words: bool,
             authorize_typos: bool,
             words_limit: Option<usize>,
-            query: TokenStream,
+            query: &str,
+            tokens: TokenStream,
         ) -> anyhow::Result<Option<Operation>>
         {
-            let primitive_query = create_primitive_query(query, None, words_limit);
+            let strategy = if optional_words {
+                TermsMatchingStrategy::Last
+            } else {
+                TermsMatchingStrategy::All
+            };
+            self.build_with_strategy(strategy, authorize_typos, words_limit, query, tokens)
+        }
+
+        fn build_with_strategy(
+            &self,
+            strategy: TermsMatchingStrategy,
+            authorize_typos: bool,
+            words_limit: Option<usize>,
+            query: &str,
+            tokens: TokenStream,
+        ) -> anyhow::Result<Option<Operation>>
+        {
+            let primitive_query = create_primitive_query(&query_tokens(query, tokens), None, &[], true, words_limit);
             if !primitive_query.is_empty() {
-                create_query_tree(self, optional_words, authorize_typos, primitive_query).map(Some)
+                create_query_tree(self, strategy, 0, authorize_typos, primitive_query).map(Some)
             } else {
                 Ok(None)
             }
@@ -674,7 +989,7 @@ mod test {
             Operation::Query(Query { prefix: true, kind: QueryKind::tolerant(2, "heyfriends".to_string()) }),
         ]);
 
-        let query_tree = TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(false, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -694,7 +1009,7 @@ mod test {
             Operation::Query(Query { prefix: false, kind: QueryKind::tolerant(2, "heyfriends".to_string()) }),
         ]);
 
-        let query_tree = TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(false, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -725,7 +1040,7 @@ mod test {
             Operation::Query(Query { prefix: false, kind: QueryKind::tolerant(2, "helloworld".to_string()) }),
         ]);
 
-        let query_tree = TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(false, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -770,7 +1085,7 @@ mod test {
             ]),
         ]);
 
-        let query_tree = TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(false, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -790,7 +1105,7 @@ mod test {
             Operation::Query(Query { prefix: false, kind: QueryKind::tolerant(1, "ngrams".to_string()) }),
         ]);
 
-        let query_tree = TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(false, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -816,7 +1131,7 @@ mod test {
             Operation::Query(Query { prefix: false, kind: QueryKind::tolerant(2, "wordsplitfish".to_string()) }),
         ]);
 
-        let query_tree = TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(false, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -836,7 +1151,7 @@ mod test {
             Operation::Query(Query { prefix: false, kind: QueryKind::exact("wooop".to_string()) }),
         ]);
 
-        let query_tree = TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(false, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -875,11 +1190,77 @@ mod test {
                 Operation::Query(Query { prefix: false, kind: QueryKind::tolerant(2, "heymyfriend".to_string()) }),
             ]),
         ]);
-        let query_tree = TestContext::default().build(true, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(true, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
 
+    #[test]
+    fn terms_matching_strategies() {
+        fn query_words(operation: &Operation, words: &mut HashSet<String>) {
+            match operation {
+                Operation::And(ops) | Operation::Consecutive(ops) | Operation::Or(_, ops) => {
+                    ops.iter().for_each(|op| query_words(op, words));
+                },
+                Operation::Query(query) => { words.insert(query.kind.word().to_string()); },
+            }
+        }
+
+        fn first_branch_words(strategy: TermsMatchingStrategy) -> HashSet<String> {
+            let query = "this split ";
+            let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+            let result = analyzer.analyze(query);
+            let tokens = result.tokens();
+
+            let query_tree = TestContext::default()
+                .build_with_strategy(strategy, false, None, query, tokens)
+                .unwrap()
+                .unwrap();
+
+            let mut words = HashSet::new();
+            match query_tree {
+                Operation::Or(true, branches) => query_words(&branches[0], &mut words),
+                otherwise => query_words(&otherwise, &mut words),
+            }
+            words
+        }
+
+        // The last words are removed first.
+        assert_eq!(first_branch_words(TermsMatchingStrategy::Last), hashset!{ "this".to_string() });
+        // The most frequent words are removed first.
+        assert!(!first_branch_words(TermsMatchingStrategy::Frequency).contains("this"));
+        assert!(first_branch_words(TermsMatchingStrategy::Frequency).contains("split"));
+        // All the words are required.
+        let words = first_branch_words(TermsMatchingStrategy::All);
+        assert!(words.contains("this") && words.contains("split"));
+    }
+
+    #[test]
+    fn min_words_match() {
+        let query = "hey my friend ";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+        let context = TestContext::default();
+
+        let build = |min_words_match| {
+            let tokens = query_tokens(query, result.tokens());
+            let primitive_query = create_primitive_query(&tokens, None, &[], true, None);
+            create_query_tree(&context, TermsMatchingStrategy::Last, min_words_match, true, primitive_query).unwrap()
+        };
+
+        let branches = match build(0) {
+            Operation::Or(true, branches) => branches,
+            otherwise => panic!("expected the optional words branches, found {:?}", otherwise),
+        };
+        assert_eq!(branches.len(), 3);
+
+        // Two of the three words must be present, the branch with only one word is removed.
+        assert_eq!(build(60), Operation::Or(true, branches[1..].to_vec()));
+        // Every word must be present, only the last branch is kept.
+        assert_eq!(build(67), branches[2]);
+        assert_eq!(build(100), branches[2]);
+    }
+
     #[test]
     fn optional_word_phrase() {
         let query = "\"hey my\"";
@@ -891,7 +1272,7 @@ mod test {
             Operation::Query(Query { prefix: false, kind: QueryKind::exact("hey".to_string()) }),
             Operation::Query(Query { prefix: false, kind: QueryKind::exact("my".to_string()) }),
         ]);
-        let query_tree = TestContext::default().build(true, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(true, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -925,7 +1306,7 @@ mod test {
                 Operation::Query(Query { prefix: false, kind: QueryKind::exact("friend".to_string()) }),
             ]),
         ]);
-        let query_tree = TestContext::default().build(true, true, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(true, true, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -944,7 +1325,7 @@ mod test {
             ]),
             Operation::Query(Query { prefix: false, kind: QueryKind::exact("heyfriends".to_string()) }),
         ]);
-        let query_tree = TestContext::default().build(false, false, None, tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(false, false, None, query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -957,7 +1338,7 @@ mod test {
         let tokens = result.tokens();
 
         let context = TestContext::default();
-        let query_tree = context.build(false, true, None, tokens).unwrap().unwrap();
+        let query_tree = context.build(false, true, None, query, tokens).unwrap().unwrap();
 
         let expected = hashset!{
             ("word",                0, false),
@@ -997,8 +1378,90 @@ mod test {
             Operation::Query(Query { prefix: false, kind: QueryKind::exact("good".to_string()) }),
         ]);
 
-        let query_tree = TestContext::default().build(false, false, Some(2), tokens).unwrap().unwrap();
+        let query_tree = TestContext::default().build(false, false, Some(2), query, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
+
+    #[test]
+    fn matching_bytes() {
+        let query = "Émile zola";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+        let tokens = result.tokens();
+
+        let query_tree = TestContext::default().build(false, false, None, query, tokens).unwrap().unwrap();
+        let matching_words = MatchingWords::from_query_tree(&query_tree);
+
+        // The "É" takes two bytes in the original text but the normalized "e" only one.
+        let text = "Les Rougon-Macquart, ÉMILE Zola";
+        let matches = matching_words.matching_bytes(&analyzer, text);
+        assert_eq!(matches, vec![(21, 6), (28, 4)]);
+        assert_eq!(&text[21..27], "ÉMILE");
+        assert_eq!(&text[28..32], "Zola");
+    }
+
+    #[test]
+    fn crop_bounds() {
+        let query = "zola";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+        let tokens = result.tokens();
+
+        let query_tree = TestContext::default().build(false, false, None, query, tokens).unwrap().unwrap();
+        let matching_words = MatchingWords::from_query_tree(&query_tree);
+
+        let text = "Les Rougon-Macquart, written by Émile Zola in the nineteenth century";
+        let bounds = matching_words.crop_bounds(&analyzer, text, 20);
+        assert_eq!(&text[bounds], "by Émile Zola in the");
+
+        // The matching word is kept even when it is longer than the crop length.
+        let bounds = matching_words.crop_bounds(&analyzer, text, 2);
+        assert_eq!(&text[bounds], "Zola");
+
+        // The start of the text is kept when no word matches.
+        let bounds = matching_words.crop_bounds(&analyzer, "Germinal, nineteenth century", 10);
+        assert_eq!(&"Germinal, nineteenth century"[bounds], "Germinal");
+    }
+
+    #[test]
+    fn crop_bounds_japanese() {
+        let query = "東京";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+        let tokens = result.tokens();
+
+        let query_tree = TestContext::default().build(false, false, None, query, tokens).unwrap().unwrap();
+        let matching_words = MatchingWords::from_query_tree(&query_tree);
+
+        // There is no whitespace in this text, cropping it between whitespaces would keep all of it.
+        let text = "日本の首都は東京です。東京はとても大きな都市で、多くの人が住んでいます。";
+        let bounds = matching_words.crop_bounds(&analyzer, text, 8);
+        let cropped = &text[bounds.clone()];
+        assert!(cropped.contains("東京"), "{:?} doesn't contain the matching word", cropped);
+        assert!(cropped.chars().count() <= 8, "{:?} is longer than the crop length", cropped);
+
+        // The text is cut at the boundaries of the words found by the analyzer.
+        let analyzed = analyzer.analyze(text);
+        let words: Vec<_> = analyzed.tokens().filter(|t| t.is_word()).collect();
+        assert!(words.iter().any(|t| t.byte_start == bounds.start));
+        assert!(words.iter().any(|t| t.byte_end == bounds.end));
+    }
+
+    #[test]
+    fn original_query_words() {
+        let query = "Émile ZOLA";
+        let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+        let result = analyzer.analyze(query);
+        let tokens = query_tokens(query, result.tokens());
+
+        assert!(tokens.contains(&QueryToken::Word { word: "emile".to_string(), original: "Émile".to_string() }));
+        assert!(tokens.contains(&QueryToken::Word { word: "zola".to_string(), original: "ZOLA".to_string() }));
+
+        let expected = vec![
+            OriginalWord { word: "Émile".to_string(), normalized: "emile".to_string(), prefix: false },
+            OriginalWord { word: "ZOLA".to_string(), normalized: "zola".to_string(), prefix: true },
+        ];
+        assert_eq!(original_words(&tokens), expected);
+    }
 }
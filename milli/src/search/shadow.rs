@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use crate::DocumentId;
+use super::SearchResult;
+
+/// The results of a search ranked with two different ranking rules, see `Search::execute_shadow`.
+pub struct ShadowSearchResult {
+    /// The results ranked with the ranking rules of the search.
+    pub primary: SearchResult,
+    /// The results ranked with the shadow ranking rules.
+    pub shadow: SearchResult,
+    pub correlation: RankCorrelation,
+}
+
+/// Compares the orderings of the documents returned by two rankings of the same search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankCorrelation {
+    /// The number of documents returned by both rankings.
+    pub common_documents: usize,
+    /// The Kendall rank correlation coefficient of the common documents, from -1.0
+    /// when they are in the reverse order to 1.0 when they are in the same order.
+    pub kendall_tau: f64,
+}
+
+impl RankCorrelation {
+    pub fn new(primary: &[DocumentId], shadow: &[DocumentId]) -> RankCorrelation {
+        let shadow_ranks: HashMap<_, _> = shadow.iter().enumerate().map(|(i, id)| (id, i)).collect();
+        // The ranks in the shadow ranking of the common documents, in the primary ranking order.
+        let ranks: Vec<_> = primary.iter().filter_map(|id| shadow_ranks.get(id).copied()).collect();
+
+        let mut concordant = 0i64;
+        let mut discordant = 0i64;
+        for (i, a) in ranks.iter().enumerate() {
+            for b in &ranks[i + 1..] {
+                if a < b { concordant += 1 } else { discordant += 1 }
+            }
+        }
+
+        let pairs = concordant + discordant;
+        let kendall_tau = if pairs == 0 { 1.0 } else { (concordant - discordant) as f64 / pairs as f64 };
+
+        RankCorrelation { common_documents: ranks.len(), kendall_tau }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rank_correlation() {
+        let correlation = RankCorrelation::new(&[1, 2, 3, 4], &[1, 2, 3, 4]);
+        assert_eq!(correlation, RankCorrelation { common_documents: 4, kendall_tau: 1.0 });
+
+        let correlation = RankCorrelation::new(&[1, 2, 3, 4], &[4, 3, 2, 1]);
+        assert_eq!(correlation, RankCorrelation { common_documents: 4, kendall_tau: -1.0 });
+
+        // Only the documents returned by both rankings are compared.
+        let correlation = RankCorrelation::new(&[1, 2, 3, 5], &[2, 1, 3, 6]);
+        assert_eq!(correlation.common_documents, 3);
+        assert!((correlation.kendall_tau - 1.0 / 3.0).abs() < f64::EPSILON);
+
+        let correlation = RankCorrelation::new(&[1], &[2]);
+        assert_eq!(correlation, RankCorrelation { common_documents: 0, kendall_tau: 1.0 });
+    }
+}
@@ -1,6 +1,7 @@
 use chrono::Utc;
 use roaring::RoaringBitmap;
 use crate::{ExternalDocumentsIds, Index, FieldsDistribution};
+use crate::facet::FacetFieldStats;
 
 pub struct ClearDocuments<'t, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
@@ -30,14 +31,19 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
             word_prefix_pair_proximity_docids,
             word_level_position_docids,
             word_prefix_level_position_docids,
+            field_id_word_count_docids,
+            field_id_original_word_docids,
             facet_field_id_value_docids,
             field_id_docid_facet_values,
+            facet_field_id_docid_count,
             documents,
+            update_hooks: _,
+            writer_lock: _,
         } = self.index;
 
         // We retrieve the number of documents ids that we are deleting.
         let number_of_documents = self.index.number_of_documents(self.wtxn)?;
-        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        let faceted_fields = self.index.indexed_facet_fields_ids(self.wtxn)?;
 
         // We clean some of the main engine datastructures.
         self.index.put_words_fst(self.wtxn, &fst::Set::default())?;
@@ -45,10 +51,13 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
         self.index.put_external_documents_ids(self.wtxn, &ExternalDocumentsIds::default())?;
         self.index.put_documents_ids(self.wtxn, &RoaringBitmap::default())?;
         self.index.put_fields_distribution(self.wtxn, &FieldsDistribution::default())?;
+        self.index.delete_geo_rtree(self.wtxn)?;
+        self.index.delete_geo_faceted_documents_ids(self.wtxn)?;
 
-        // We clean all the faceted documents ids.
+        // We clean all the faceted documents ids and the statistics of their values.
         for (field_id, _) in faceted_fields {
             self.index.put_faceted_documents_ids(self.wtxn, field_id, &RoaringBitmap::default())?;
+            self.index.put_facet_field_stats(self.wtxn, field_id, &FacetFieldStats::default())?;
         }
 
         // Clear the other databases.
@@ -59,8 +68,11 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
         word_prefix_pair_proximity_docids.clear(self.wtxn)?;
         word_level_position_docids.clear(self.wtxn)?;
         word_prefix_level_position_docids.clear(self.wtxn)?;
+        field_id_word_count_docids.clear(self.wtxn)?;
+        field_id_original_word_docids.clear(self.wtxn)?;
         facet_field_id_value_docids.clear(self.wtxn)?;
         field_id_docid_facet_values.clear(self.wtxn)?;
+        facet_field_id_docid_count.clear(self.wtxn)?;
         documents.clear(self.wtxn)?;
 
         Ok(number_of_documents)
@@ -114,6 +126,7 @@ mod tests {
         assert!(index.word_prefix_pair_proximity_docids.is_empty(&rtxn).unwrap());
         assert!(index.facet_field_id_value_docids.is_empty(&rtxn).unwrap());
         assert!(index.field_id_docid_facet_values.is_empty(&rtxn).unwrap());
+        assert!(index.facet_field_id_docid_count.is_empty(&rtxn).unwrap());
         assert!(index.documents.is_empty(&rtxn).unwrap());
     }
 }
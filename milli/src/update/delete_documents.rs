@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
+use std::num::NonZeroUsize;
 
 use anyhow::anyhow;
 use chrono::Utc;
@@ -9,9 +10,11 @@ use roaring::RoaringBitmap;
 use serde_json::Value;
 
 use crate::facet::FacetType;
-use crate::{Index, BEU32, SmallString32, ExternalDocumentsIds};
-use crate::heed_codec::facet::{FieldDocIdFacetStringCodec, FieldDocIdFacetF64Codec};
+use crate::{Index, BEU32, SmallString32, ExternalDocumentsIds, FacetCondition};
+use crate::heed_codec::CboRoaringBitmapCodec;
+use crate::heed_codec::facet::{FacetStringValueDocidsCodec, FieldDocIdFacetStringCodec, FieldDocIdFacetF64Codec};
 use super::ClearDocuments;
+use super::facets::{clean_facet_number_levels, DEFAULT_LEVEL_GROUP_SIZE, DEFAULT_MIN_LEVEL_SIZE};
 
 pub struct DeleteDocuments<'t, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
@@ -55,7 +58,33 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         Some(docid)
     }
 
+    /// Marks all the documents matching the given filter for deletion, the filter is
+    /// evaluated against the state of the index seen by the write transaction.
+    ///
+    /// Returns the number of documents matched by the filter.
+    pub fn by_filter(&mut self, filter: &FacetCondition) -> anyhow::Result<u64> {
+        let docids = filter.evaluate(self.wtxn, self.index)?;
+        self.delete_documents(&docids);
+        Ok(docids.len())
+    }
+
+    /// Returns the ids of the documents marked for deletion that exist in the index,
+    /// those are the documents that will be deleted.
+    pub fn documents_ids(&self) -> anyhow::Result<RoaringBitmap> {
+        let documents_ids = self.index.documents_ids(self.wtxn)?;
+        Ok(&self.documents_ids & &documents_ids)
+    }
+
     pub fn execute(self) -> anyhow::Result<u64> {
+        let (index, update_id) = (self.index, self.update_id);
+        let deleted_documents = self.documents_ids()?;
+        let count = self.delete()?;
+        index.update_hooks.record_deletion(update_id, &deleted_documents);
+
+        Ok(count)
+    }
+
+    fn delete(self) -> anyhow::Result<u64> {
         self.index.set_updated_at(self.wtxn, &Utc::now())?;
         // We retrieve the current documents ids that are in the database.
         let mut documents_ids = self.index.documents_ids(self.wtxn)?;
@@ -90,9 +119,14 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             word_prefix_pair_proximity_docids,
             word_level_position_docids,
             word_prefix_level_position_docids,
+            field_id_word_count_docids,
+            field_id_original_word_docids,
             facet_field_id_value_docids,
             field_id_docid_facet_values,
+            facet_field_id_docid_count,
             documents,
+            update_hooks: _,
+            writer_lock: _,
         } = self.index;
 
         // Number of fields for each document that has been deleted.
@@ -286,16 +320,22 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         drop(iter);
 
         // Remove the documents ids from the faceted documents ids.
-        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        let faceted_fields = self.index.indexed_facet_fields_ids(self.wtxn)?;
         for (field_id, facet_type) in faceted_fields {
             let mut docids = self.index.faceted_documents_ids(self.wtxn, field_id)?;
             docids.difference_with(&self.documents_ids);
             self.index.put_faceted_documents_ids(self.wtxn, field_id, &docids)?;
 
+            // The number of distinct values is only updated when the facets are built.
+            if let Some(mut stats) = self.index.facet_field_stats(self.wtxn, field_id)? {
+                stats.documents_count = docids.len();
+                self.index.put_facet_field_stats(self.wtxn, field_id, &stats)?;
+            }
+
             // We delete the entries that are part of the documents ids.
             let iter = field_id_docid_facet_values.prefix_iter_mut(self.wtxn, &[field_id])?;
             match facet_type {
-                FacetType::String => {
+                FacetType::String | FacetType::Hierarchy => {
                     let mut iter = iter.remap_key_type::<FieldDocIdFacetStringCodec>();
                     while let Some(result) = iter.next() {
                         let ((_fid, docid, _value), ()) = result?;
@@ -304,7 +344,7 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
                         }
                     }
                 },
-                FacetType::Number => {
+                FacetType::Number | FacetType::Date => {
                     let mut iter = iter.remap_key_type::<FieldDocIdFacetF64Codec>();
                     while let Some(result) = iter.next() {
                         let ((_fid, docid, _value), ()) = result?;
@@ -314,10 +354,89 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
                     }
                 },
             }
+
+            // We delete the number of values of this field for the deleted documents.
+            for docid in &self.documents_ids {
+                facet_field_id_docid_count.delete(self.wtxn, &(field_id, docid))?;
+            }
+        }
+
+        // We remove the documents from the geo rtree.
+        let mut geo_faceted_documents_ids = self.index.geo_faceted_documents_ids(self.wtxn)?;
+        if !geo_faceted_documents_ids.is_disjoint(&self.documents_ids) {
+            let mut rtree = self.index.geo_rtree(self.wtxn)?.unwrap_or_default();
+            let points_to_remove: Vec<_> = rtree.iter()
+                .filter(|point| self.documents_ids.contains(point.data.0))
+                .cloned()
+                .collect();
+            for point in points_to_remove {
+                rtree.remove(&point);
+            }
+            geo_faceted_documents_ids.difference_with(&self.documents_ids);
+            self.index.put_geo_rtree(self.wtxn, &rtree)?;
+            self.index.put_geo_faceted_documents_ids(self.wtxn, &geo_faceted_documents_ids)?;
+        }
+
+        // We delete the documents ids that are under the facet field id values,
+        // the string values are stored along with their original form.
+        let (level_group_size, min_level_size) = match self.index.facet_level_sizes(self.wtxn)? {
+            Some(sizes) => sizes,
+            None => (
+                NonZeroUsize::new(DEFAULT_LEVEL_GROUP_SIZE).unwrap(),
+                NonZeroUsize::new(DEFAULT_MIN_LEVEL_SIZE).unwrap(),
+            ),
+        };
+
+        let faceted_fields = self.index.indexed_facet_fields_ids(self.wtxn)?;
+        for (field_id, facet_type) in faceted_fields {
+            match facet_type {
+                FacetType::String | FacetType::Hierarchy => {
+                    let mut iter = facet_field_id_value_docids
+                        .remap_data_type::<FacetStringValueDocidsCodec<CboRoaringBitmapCodec>>()
+                        .prefix_iter_mut(self.wtxn, &[field_id])?;
+                    while let Some(result) = iter.next() {
+                        let (bytes, (original, mut docids)) = result?;
+                        let previous_len = docids.len();
+                        docids.difference_with(&self.documents_ids);
+                        if docids.is_empty() {
+                            iter.del_current()?;
+                        } else if docids.len() != previous_len {
+                            let original = original.to_owned();
+                            iter.put_current(bytes, &(original.as_str(), docids))?;
+                        }
+                    }
+                },
+                FacetType::Number | FacetType::Date => {
+                    let mut iter = facet_field_id_value_docids.prefix_iter_mut(self.wtxn, &[field_id])?;
+                    while let Some(result) = iter.next() {
+                        let (bytes, mut docids) = result?;
+                        let previous_len = docids.len();
+                        docids.difference_with(&self.documents_ids);
+                        if docids.is_empty() {
+                            iter.del_current()?;
+                        } else if docids.len() != previous_len {
+                            iter.put_current(bytes, &docids)?;
+                        }
+                    }
+
+                    drop(iter);
+
+                    // The emptied groups have been removed above, the remaining ones are shrunk
+                    // so that the range iterations don't explore them for nothing, the levels
+                    // keep the sizes they were computed with.
+                    clean_facet_number_levels(
+                        self.wtxn,
+                        self.index,
+                        level_group_size,
+                        min_level_size,
+                        field_id,
+                    )?;
+                },
+            }
         }
 
-        // We delete the documents ids that are under the facet field id values.
-        let mut iter = facet_field_id_value_docids.iter_mut(self.wtxn)?;
+        // We delete the documents ids that are under the word level position docids.
+        let mut iter = word_level_position_docids.iter_mut(self.wtxn)?.remap_key_type::<ByteSlice>();
         while let Some(result) = iter.next() {
             let (bytes, mut docids) = result?;
             let previous_len = docids.len();
@@ -331,8 +450,8 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
 
         drop(iter);
 
-        // We delete the documents ids that are under the word level position docids.
-        let mut iter = word_level_position_docids.iter_mut(self.wtxn)?.remap_key_type::<ByteSlice>();
+        // We delete the documents ids that are under the word prefix level position docids.
+        let mut iter = word_prefix_level_position_docids.iter_mut(self.wtxn)?.remap_key_type::<ByteSlice>();
         while let Some(result) = iter.next() {
             let (bytes, mut docids) = result?;
             let previous_len = docids.len();
@@ -346,16 +465,31 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
 
         drop(iter);
 
-        // We delete the documents ids that are under the word prefix level position docids.
-        let mut iter = word_prefix_level_position_docids.iter_mut(self.wtxn)?.remap_key_type::<ByteSlice>();
+        // We delete the documents ids that are under the field id word count docids.
+        let mut iter = field_id_word_count_docids.iter_mut(self.wtxn)?;
         while let Some(result) = iter.next() {
-            let (bytes, mut docids) = result?;
+            let (key, mut docids) = result?;
             let previous_len = docids.len();
             docids.difference_with(&self.documents_ids);
             if docids.is_empty() {
                 iter.del_current()?;
             } else if docids.len() != previous_len {
-                iter.put_current(bytes, &docids)?;
+                iter.put_current(&key, &docids)?;
+            }
+        }
+
+        drop(iter);
+
+        // We delete the documents ids that are under the field id original word docids.
+        let mut iter = field_id_original_word_docids.iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let (key, mut docids) = result?;
+            let previous_len = docids.len();
+            docids.difference_with(&self.documents_ids);
+            if docids.is_empty() {
+                iter.del_current()?;
+            } else if docids.len() != previous_len {
+                iter.put_current(&key, &docids)?;
             }
         }
 
@@ -368,8 +502,9 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
 #[cfg(test)]
 mod tests {
     use heed::EnvOpenOptions;
+    use maplit::hashmap;
 
-    use crate::update::{IndexDocuments, UpdateFormat};
+    use crate::update::{Facets, IndexDocuments, Settings, UpdateFormat};
     use super::*;
 
     #[test]
@@ -403,4 +538,84 @@ mod tests {
 
         assert!(index.fields_distribution(&rtxn).unwrap().is_empty());
     }
+
+    #[test]
+    fn delete_documents_by_filter() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "timestamp".into() => "number".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &br#"[
+            { "id": 0, "name": "kevin", "timestamp": 10 },
+            { "id": 1, "name": "kevina", "timestamp": 20 },
+            { "id": 2, "name": "benoit", "timestamp": 30 }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+
+        // Delete the documents older than a given timestamp.
+        let filter = FacetCondition::from_str(&wtxn, &index, "timestamp < 25").unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index, 2).unwrap();
+        assert_eq!(builder.by_filter(&filter).unwrap(), 2);
+        assert_eq!(builder.execute().unwrap(), 2);
+
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let external_documents_ids = index.external_documents_ids(&rtxn).unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 1);
+        assert_eq!(external_documents_ids.get("0"), None);
+        assert_eq!(external_documents_ids.get("1"), None);
+        assert_eq!(external_documents_ids.get("2"), Some(2));
+    }
+
+    #[test]
+    fn delete_documents_keeps_the_facet_level_sizes() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "timestamp".into() => "number".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let mut content = String::from("id,timestamp\n");
+        for id in 0..100 {
+            content.push_str(&format!("{},{}\n", id, id * 10));
+        }
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+
+        // The facet levels are computed again with custom sizes.
+        let group_size = NonZeroUsize::new(2).unwrap();
+        let min_level_size = NonZeroUsize::new(3).unwrap();
+        let mut builder = Facets::new(&mut wtxn, &index, 2);
+        builder.level_group_size(group_size);
+        builder.min_level_size(min_level_size);
+        builder.execute().unwrap();
+        assert_eq!(index.facet_level_sizes(&wtxn).unwrap(), Some((group_size, min_level_size)));
+
+        let filter = FacetCondition::from_str(&wtxn, &index, "timestamp < 500").unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index, 3).unwrap();
+        assert_eq!(builder.by_filter(&filter).unwrap(), 50);
+        assert_eq!(builder.execute().unwrap(), 50);
+
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.facet_level_sizes(&rtxn).unwrap(), Some((group_size, min_level_size)));
+        let filter = FacetCondition::from_str(&rtxn, &index, "timestamp >= 700").unwrap();
+        let documents_ids = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(documents_ids.iter().collect::<Vec<_>>(), (70..100).collect::<Vec<_>>());
+    }
 }
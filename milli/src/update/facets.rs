@@ -1,21 +1,32 @@
 use std::cmp;
+use std::collections::HashSet;
 use std::fs::File;
+use std::mem;
 use std::num::NonZeroUsize;
+use std::ops::Bound::Included;
 
+use anyhow::Context;
 use chrono::Utc;
 use grenad::{CompressionType, Reader, Writer, FileFuse};
 use heed::types::{ByteSlice, DecodeIgnore};
-use heed::{BytesEncode, Error};
+use heed::{BytesDecode, BytesEncode, Error};
 use log::debug;
 use roaring::RoaringBitmap;
 
-use crate::facet::FacetType;
+use crate::facet::{FacetFieldStats, FacetType};
 use crate::heed_codec::CboRoaringBitmapCodec;
-use crate::heed_codec::facet::FacetLevelValueF64Codec;
+use crate::heed_codec::facet::{FacetLevelValueF64Codec, FacetStringValueDocidsCodec, FacetValueStringCodec, FieldDocIdFacetF64Codec};
 use crate::Index;
 use crate::update::index_documents::WriteMethod;
 use crate::update::index_documents::{create_writer, writer_into_reader, write_into_lmdb_database};
 
+/// The facet levels are entirely rebuilt when the number of new values is more than
+/// this fraction of the number of values of the field, it is cheaper than updating them.
+const INCREMENTAL_MAX_NEW_VALUES_RATIO: usize = 10;
+
+pub(crate) const DEFAULT_LEVEL_GROUP_SIZE: usize = 4;
+pub(crate) const DEFAULT_MIN_LEVEL_SIZE: usize = 5;
+
 pub struct Facets<'t, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
     index: &'i Index,
@@ -24,6 +35,7 @@ pub struct Facets<'t, 'u, 'i> {
     pub(crate) chunk_fusing_shrink_size: Option<u64>,
     level_group_size: NonZeroUsize,
     min_level_size: NonZeroUsize,
+    new_documents_ids: Option<RoaringBitmap>,
     _update_id: u64,
 }
 
@@ -40,8 +52,9 @@ impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
             chunk_compression_type: CompressionType::None,
             chunk_compression_level: None,
             chunk_fusing_shrink_size: None,
-            level_group_size: NonZeroUsize::new(4).unwrap(),
-            min_level_size: NonZeroUsize::new(5).unwrap(),
+            level_group_size: NonZeroUsize::new(DEFAULT_LEVEL_GROUP_SIZE).unwrap(),
+            min_level_size: NonZeroUsize::new(DEFAULT_MIN_LEVEL_SIZE).unwrap(),
+            new_documents_ids: None,
             _update_id: update_id,
         }
     }
@@ -56,48 +69,108 @@ impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
         self
     }
 
+    /// The documents added since the facet levels were last computed, the level 0 of the
+    /// facets must already contain their values. The values of these documents are inserted
+    /// into the existing levels instead of rebuilding them when there are only a few of them.
+    pub(crate) fn new_documents_ids(&mut self, documents_ids: RoaringBitmap) -> &mut Self {
+        self.new_documents_ids = Some(documents_ids);
+        self
+    }
+
     pub fn execute(self) -> anyhow::Result<()> {
         self.index.set_updated_at(self.wtxn, &Utc::now())?;
+        self.index.put_facet_level_sizes(self.wtxn, self.level_group_size, self.min_level_size)?;
         // We get the faceted fields to be able to create the facet levels.
-        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        let faceted_fields = self.index.indexed_facet_fields_ids(self.wtxn)?;
+        let filterable_features = self.index.filterable_features_ids(self.wtxn)?;
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let sortable_fields: HashSet<_> = self.index.sortable_fields(self.wtxn)?
+            .keys()
+            .filter_map(|name| fields_ids_map.id(name))
+            .collect();
 
         debug!("Computing and writing the facet values levels docids into LMDB on disk...");
         for (field_id, facet_type) in faceted_fields {
-            let (content, documents_ids) = match facet_type {
-                FacetType::String => {
-                    let documents_ids = compute_faceted_documents_ids(
+            let (content, documents_ids, distinct_values) = match facet_type {
+                FacetType::String | FacetType::Hierarchy => {
+                    let (documents_ids, distinct_values) = compute_faceted_string_documents_ids(
                         self.wtxn,
-                        self.index.facet_field_id_value_docids,
+                        self.index.facet_string_values_docids(),
                         field_id,
                     )?;
 
-                    (None, documents_ids)
+                    (None, documents_ids, distinct_values)
                 },
-                FacetType::Number => {
-                    clear_field_number_levels(
-                        self.wtxn,
-                        self.index.facet_field_id_value_docids.remap_key_type::<FacetLevelValueF64Codec>(),
-                        field_id,
-                    )?;
+                FacetType::Number | FacetType::Date => {
+                    // The facet levels are only useful to the comparison operators,
+                    // we don't compute them when those operators are disabled.
+                    let mut features = filterable_features.get(&field_id).copied().unwrap_or_default();
+                    // The sort uses the facet levels, they are always computed for the sortable fields.
+                    features.comparison |= sortable_fields.contains(&field_id);
+                    let incremental = match &self.new_documents_ids {
+                        Some(new_documents_ids) if features.comparison => {
+                            let (values, valued_documents_ids) = facet_number_values(
+                                self.wtxn,
+                                self.index,
+                                field_id,
+                                new_documents_ids,
+                            )?;
 
-                    let documents_ids = compute_faceted_documents_ids(
-                        self.wtxn,
-                        self.index.facet_field_id_value_docids,
-                        field_id,
-                    )?;
+                            let updated = update_facet_number_levels(
+                                self.wtxn,
+                                self.index.facet_number_levels_docids(),
+                                self.level_group_size,
+                                self.min_level_size,
+                                field_id,
+                                &values,
+                            )?;
 
-                    let content = compute_facet_number_levels(
-                        self.wtxn,
-                        self.index.facet_field_id_value_docids.remap_key_type::<FacetLevelValueF64Codec>(),
-                        self.chunk_compression_type,
-                        self.chunk_compression_level,
-                        self.chunk_fusing_shrink_size,
-                        self.level_group_size,
-                        self.min_level_size,
-                        field_id,
-                    )?;
+                            if updated {
+                                let documents_ids = self.index.faceted_documents_ids(self.wtxn, field_id)?;
+                                let db = self.index.facet_number_levels_docids();
+                                let distinct_values = level_size(self.wtxn, db, field_id, 0)? as u64;
+                                Some((documents_ids | valued_documents_ids, distinct_values))
+                            } else {
+                                None
+                            }
+                        },
+                        _ => None,
+                    };
+
+                    if let Some((documents_ids, distinct_values)) = incremental {
+                        debug!("Updated the facet levels of the field {} incrementally", field_id);
+                        (None, documents_ids, distinct_values)
+                    } else {
+                        clear_field_number_levels(
+                            self.wtxn,
+                            self.index.facet_number_levels_docids(),
+                            field_id,
+                        )?;
 
-                    (Some(content), documents_ids)
+                        let (documents_ids, distinct_values) = compute_faceted_documents_ids(
+                            self.wtxn,
+                            self.index.facet_field_id_value_docids,
+                            field_id,
+                        )?;
+
+                        if !features.comparison {
+                            debug!("Skipping the facet levels of the field {}", field_id);
+                            (None, documents_ids, distinct_values)
+                        } else {
+                            let content = compute_facet_number_levels(
+                                self.wtxn,
+                                self.index.facet_number_levels_docids(),
+                                self.chunk_compression_type,
+                                self.chunk_compression_level,
+                                self.chunk_fusing_shrink_size,
+                                self.level_group_size,
+                                self.min_level_size,
+                                field_id,
+                            )?;
+
+                            (Some(content), documents_ids, distinct_values)
+                        }
+                    }
                 },
             };
 
@@ -111,6 +184,8 @@ impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
                 )?;
             }
 
+            let stats = FacetFieldStats { distinct_values, documents_count: documents_ids.len() };
+            self.index.put_facet_field_stats(self.wtxn, field_id, &stats)?;
             self.index.put_faceted_documents_ids(self.wtxn, field_id, &documents_ids)?;
         }
 
@@ -198,18 +273,382 @@ fn compute_facet_number_levels<'t>(
     writer_into_reader(writer, shrink_size)
 }
 
+/// Returns the sorted and deduplicated number values of the given documents
+/// along with the documents that have at least one value for this field.
+fn facet_number_values(
+    rtxn: &heed::RoTxn,
+    index: &Index,
+    field_id: u8,
+    documents_ids: &RoaringBitmap,
+) -> anyhow::Result<(Vec<f64>, RoaringBitmap)>
+{
+    let db = index.field_id_docid_facet_values.remap_data_type::<DecodeIgnore>();
+    let mut values = Vec::new();
+    let mut valued_documents_ids = RoaringBitmap::new();
+
+    for docid in documents_ids {
+        let mut prefix = [0u8; 1 + 4];
+        prefix[0] = field_id;
+        prefix[1..].copy_from_slice(&docid.to_be_bytes());
+
+        for result in db.prefix_iter(rtxn, &prefix[..])? {
+            let (bytes, ()) = result?;
+            let (_, _, value) = FieldDocIdFacetF64Codec::bytes_decode(bytes).ok_or(Error::Decoding)?;
+            values.push(value);
+            valued_documents_ids.insert(docid);
+        }
+    }
+
+    values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    values.dedup();
+
+    Ok((values, valued_documents_ids))
+}
+
+/// Inserts the given values, that are already in the level 0, into the existing facet levels
+/// of the field, the groups that contain too many subgroups are split in two and the small
+/// sibling groups are merged. Returns `false` without writing anything when the levels must be
+/// rebuilt, when there are too many new values or when the number of levels changes.
+fn update_facet_number_levels(
+    wtxn: &mut heed::RwTxn,
+    db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+    level_group_size: NonZeroUsize,
+    min_level_size: NonZeroUsize,
+    field_id: u8,
+    values: &[f64],
+) -> anyhow::Result<bool>
+{
+    let first_level_size = level_size(wtxn, db, field_id, 0)?;
+    if values.len() * INCREMENTAL_MAX_NEW_VALUES_RATIO > first_level_size {
+        return Ok(false);
+    }
+
+    let expected_highest_level = expected_highest_level(first_level_size, level_group_size, min_level_size);
+    let highest_level = highest_level(wtxn, db, field_id)?;
+    if highest_level != expected_highest_level {
+        return Ok(false);
+    }
+
+    // The groups emptied by the documents deletions have been removed
+    // but the ones that became too small must be merged with their siblings.
+    for level in 1..=highest_level {
+        merge_small_groups(wtxn, db, level_group_size, field_id, level, highest_level)?;
+    }
+
+    for &value in values {
+        let docids = match db.get(wtxn, &(field_id, 0, value, value))? {
+            Some(docids) => docids,
+            None => continue,
+        };
+
+        // We choose the group of the value from the highest level to the lowest one, among
+        // the subgroups of the previously chosen group, the groups bounds are extended when
+        // the value is between two groups, this way a group always contains its subgroups.
+        let mut parent = (f64::MIN, f64::MAX);
+        let mut groups = Vec::new();
+        for level in (1..=highest_level).rev() {
+            let (left, right, mut group_docids) = find_value_group(wtxn, db, field_id, level, parent, value)?
+                .with_context(|| format!("missing facet level {} group for the field {}", level, field_id))?;
+
+            let (new_left, new_right) = (left.min(value), right.max(value));
+            if (new_left, new_right) != (left, right) {
+                db.delete(wtxn, &(field_id, level, left, right))?;
+            }
+
+            group_docids.union_with(&docids);
+            db.put(wtxn, &(field_id, level, new_left, new_right), &group_docids)?;
+
+            parent = (new_left, new_right);
+            groups.push((level, new_left, new_right));
+        }
+
+        // We split the groups from the lowest level to the highest one, this way the
+        // subgroups of a group have already been split when we count them.
+        for (level, left, right) in groups.into_iter().rev() {
+            let subgroups = subgroups(wtxn, db, field_id, level, left, right)?;
+            if subgroups.len() > 2 * level_group_size.get() {
+                let (first, second) = subgroups.split_at(subgroups.len() / 2);
+                db.delete(wtxn, &(field_id, level, left, right))?;
+                for half in &[first, second] {
+                    let (left, right) = (half[0].0, half[half.len() - 1].1);
+                    let docids = half.iter().fold(RoaringBitmap::new(), |acc, (_, _, d)| acc | d);
+                    db.put(wtxn, &(field_id, level, left, right), &docids)?;
+                }
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// Cleans the facet levels of a field once documents have been deleted, the groups without any
+/// subgroup left are removed, the bounds of the other ones are shrunk to the bounds of their
+/// subgroups and the groups that became too small are merged. The levels are rebuilt when the
+/// remaining number of values doesn't require as many levels anymore.
+pub(crate) fn clean_facet_number_levels(
+    wtxn: &mut heed::RwTxn,
+    index: &Index,
+    level_group_size: NonZeroUsize,
+    min_level_size: NonZeroUsize,
+    field_id: u8,
+) -> anyhow::Result<()>
+{
+    let db = index.facet_number_levels_docids();
+    let highest_level = highest_level(wtxn, db, field_id)?;
+    if highest_level == 0 {
+        return Ok(());
+    }
+
+    let first_level_size = level_size(wtxn, db, field_id, 0)?;
+    if highest_level > expected_highest_level(first_level_size, level_group_size, min_level_size) {
+        debug!("Rebuilding the facet levels of the field {} after deletions", field_id);
+        clear_field_number_levels(wtxn, db, field_id)?;
+        let content = compute_facet_number_levels(
+            wtxn,
+            db,
+            CompressionType::None,
+            None,
+            None,
+            level_group_size,
+            min_level_size,
+            field_id,
+        )?;
+
+        return write_into_lmdb_database(
+            wtxn,
+            *index.facet_field_id_value_docids.as_polymorph(),
+            content,
+            |_, _| anyhow::bail!("invalid facet level merging"),
+            WriteMethod::GetMergePut,
+        );
+    }
+
+    // The lowest levels are cleaned first, the bounds of the subgroups are then already shrunk.
+    for level in 1..=highest_level {
+        for (left, right) in level_bounds(wtxn, db, field_id, level)? {
+            let subgroups = subgroups(wtxn, db, field_id, level, left, right)?;
+            let (first, last) = match (subgroups.first(), subgroups.last()) {
+                (Some(first), Some(last)) => (first.0, last.1),
+                _ => {
+                    db.delete(wtxn, &(field_id, level, left, right))?;
+                    continue;
+                },
+            };
+
+            if (first, last) != (left, right) {
+                let docids = subgroups.iter().fold(RoaringBitmap::new(), |acc, (_, _, d)| acc | d);
+                db.delete(wtxn, &(field_id, level, left, right))?;
+                db.put(wtxn, &(field_id, level, first, last), &docids)?;
+            }
+        }
+
+        merge_small_groups(wtxn, db, level_group_size, field_id, level, highest_level)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the number of levels that the facet levels of a field should have.
+fn expected_highest_level(
+    first_level_size: usize,
+    level_group_size: NonZeroUsize,
+    min_level_size: NonZeroUsize,
+) -> u8
+{
+    (1u8..)
+        .take_while(|l| first_level_size / level_group_size.get().pow(*l as u32) >= min_level_size.get())
+        .last()
+        .unwrap_or(0)
+}
+
+/// Returns the highest facet level of a field, zero when there are only the values.
+fn highest_level(
+    rtxn: &heed::RoTxn,
+    db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+    field_id: u8,
+) -> heed::Result<u8>
+{
+    let range = (field_id, 0, f64::MIN, f64::MIN)..=(field_id, u8::MAX, f64::MAX, f64::MAX);
+    match db.remap_data_type::<DecodeIgnore>().rev_range(rtxn, &range)?.next() {
+        Some(result) => {
+            let ((_, level, _, _), ()) = result?;
+            Ok(level)
+        },
+        None => Ok(0),
+    }
+}
+
+/// Returns the group of the given level, among the subgroups of the given parent bounds,
+/// that contains the value or, if none does, the group that precedes the value or the first one.
+fn find_value_group(
+    rtxn: &heed::RoTxn,
+    db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+    field_id: u8,
+    level: u8,
+    (parent_left, parent_right): (f64, f64),
+    value: f64,
+) -> heed::Result<Option<(f64, f64, RoaringBitmap)>>
+{
+    let before = (field_id, level, parent_left, f64::MIN)..=(field_id, level, value, f64::MAX);
+    if let Some(result) = db.rev_range(rtxn, &before)?.next() {
+        let ((_, _, left, right), docids) = result?;
+        return Ok(Some((left, right, docids)));
+    }
+
+    let after = (Included((field_id, level, value, f64::MIN)), Included((field_id, level, parent_right, f64::MAX)));
+    match db.range(rtxn, &after)?.next() {
+        Some(result) => {
+            let ((_, _, left, right), docids) = result?;
+            Ok(Some((left, right, docids)))
+        },
+        None => Ok(None),
+    }
+}
+
+/// Returns the bounds and the documents ids of the groups of the level
+/// below the given one that are contained in the given bounds.
+fn subgroups(
+    rtxn: &heed::RoTxn,
+    db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+    field_id: u8,
+    level: u8,
+    left: f64,
+    right: f64,
+) -> heed::Result<Vec<(f64, f64, RoaringBitmap)>>
+{
+    let range = (field_id, level - 1, left, f64::MIN)..=(field_id, level - 1, right, f64::MAX);
+    db.range(rtxn, &range)?.map(|result| result.map(|((_, _, l, r), docids)| (l, r, docids))).collect()
+}
+
+/// Returns the number of entries of the given level, without decoding the documents ids.
+fn level_size(
+    rtxn: &heed::RoTxn,
+    db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+    field_id: u8,
+    level: u8,
+) -> heed::Result<usize>
+{
+    let range = (field_id, level, f64::MIN, f64::MIN)..=(field_id, level, f64::MAX, f64::MAX);
+    db.remap_data_type::<DecodeIgnore>()
+        .range(rtxn, &range)?
+        .fold(Ok(0), |count, result| result.and(count).map(|c| c + 1))
+}
+
+/// Returns the bounds of all the groups of the given level, without decoding the documents ids.
+fn level_bounds(
+    rtxn: &heed::RoTxn,
+    db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+    field_id: u8,
+    level: u8,
+) -> heed::Result<Vec<(f64, f64)>>
+{
+    let range = (field_id, level, f64::MIN, f64::MIN)..=(field_id, level, f64::MAX, f64::MAX);
+    db.remap_data_type::<DecodeIgnore>()
+        .range(rtxn, &range)?
+        .map(|result| result.map(|((_, _, left, right), ())| (left, right)))
+        .collect()
+}
+
+/// Merges the consecutive groups of the level that share the same parent group
+/// while their number of subgroups doesn't exceed the level group size.
+fn merge_small_groups(
+    wtxn: &mut heed::RwTxn,
+    db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+    level_group_size: NonZeroUsize,
+    field_id: u8,
+    level: u8,
+    highest_level: u8,
+) -> heed::Result<()>
+{
+    let groups = level_bounds(wtxn, db, field_id, level)?;
+    let children = level_bounds(wtxn, db, field_id, level - 1)?;
+    let parents = if level < highest_level {
+        level_bounds(wtxn, db, field_id, level + 1)?
+    } else {
+        Vec::new()
+    };
+
+    let mut runs = Vec::new();
+    let mut run: Vec<(f64, f64)> = Vec::new();
+    let mut run_count = 0;
+    let mut run_parent = None;
+    // The groups, their subgroups and their parents are sorted, we walk through them together.
+    let (mut child, mut parent) = (0, 0);
+    for (left, right) in groups {
+        while children.get(child).map_or(false, |c| c.0 < left) {
+            child += 1;
+        }
+        let first_child = child;
+        while children.get(child).map_or(false, |c| c.0 <= right) {
+            child += 1;
+        }
+        let count = child - first_child;
+
+        // The groups of the highest level all share the same parent.
+        while parents.get(parent).map_or(false, |p| p.1 < left) {
+            parent += 1;
+        }
+
+        if run_parent == Some(parent) && run_count + count <= level_group_size.get() {
+            run.push((left, right));
+            run_count += count;
+        } else {
+            runs.push(mem::take(&mut run));
+            run.push((left, right));
+            run_count = count;
+            run_parent = Some(parent);
+        }
+    }
+    runs.push(run);
+
+    for run in runs.into_iter().filter(|run| run.len() > 1) {
+        let mut docids = RoaringBitmap::new();
+        for (left, right) in &run {
+            if let Some(group_docids) = db.get(wtxn, &(field_id, level, *left, *right))? {
+                docids.union_with(&group_docids);
+            }
+            db.delete(wtxn, &(field_id, level, *left, *right))?;
+        }
+        let (left, right) = (run[0].0, run[run.len() - 1].1);
+        db.put(wtxn, &(field_id, level, left, right), &docids)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the documents that have a value for this field and the number of
+/// distinct values, the facet levels must have been cleared beforehand.
 fn compute_faceted_documents_ids(
     rtxn: &heed::RoTxn,
     db: heed::Database<ByteSlice, CboRoaringBitmapCodec>,
     field_id: u8,
-) -> anyhow::Result<RoaringBitmap>
+) -> anyhow::Result<(RoaringBitmap, u64)>
 {
     let mut documents_ids = RoaringBitmap::new();
+    let mut distinct_values = 0;
     for result in db.prefix_iter(rtxn, &[field_id])? {
         let (_key, docids) = result?;
         documents_ids.union_with(&docids);
+        distinct_values += 1;
+    }
+    Ok((documents_ids, distinct_values))
+}
+
+fn compute_faceted_string_documents_ids(
+    rtxn: &heed::RoTxn,
+    db: heed::Database<FacetValueStringCodec, FacetStringValueDocidsCodec<CboRoaringBitmapCodec>>,
+    field_id: u8,
+) -> anyhow::Result<(RoaringBitmap, u64)>
+{
+    let mut documents_ids = RoaringBitmap::new();
+    let mut distinct_values = 0;
+    let iter = db.remap_key_type::<ByteSlice>().prefix_iter(rtxn, &[field_id])?;
+    for result in iter {
+        let (_key, (_original, docids)) = result?;
+        documents_ids.union_with(&docids);
+        distinct_values += 1;
     }
-    Ok(documents_ids)
+    Ok((documents_ids, distinct_values))
 }
 
 fn write_number_entry(
@@ -227,3 +666,182 @@ fn write_number_entry(
     writer.insert(&key, &data)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+    use maplit::hashmap;
+
+    use crate::FacetCondition;
+    use crate::update::{DeleteDocuments, IndexDocuments, Settings, UpdateFormat};
+    use super::*;
+
+    /// Checks that every group contains exactly the documents of its subgroups
+    /// and that every group of a level is the subgroup of one group of the level above.
+    fn assert_levels_consistency(rtxn: &heed::RoTxn, index: &Index, field_id: u8) -> u8 {
+        let db = index.facet_number_levels_docids();
+        let mut level = 1;
+        while !level_bounds(rtxn, db, field_id, level).unwrap().is_empty() {
+            let groups: Vec<_> = db.range(rtxn, &((field_id, level, f64::MIN, f64::MIN)..=(field_id, level, f64::MAX, f64::MAX)))
+                .unwrap()
+                .map(Result::unwrap)
+                .collect();
+
+            let mut subgroups_count = 0;
+            for ((_, _, left, right), docids) in groups {
+                let subgroups = subgroups(rtxn, db, field_id, level, left, right).unwrap();
+                let subgroups_docids = subgroups.iter().fold(RoaringBitmap::new(), |acc, (_, _, d)| acc | d);
+                assert_eq!(docids, subgroups_docids, "level {} group {} to {}", level, left, right);
+                subgroups_count += subgroups.len();
+            }
+
+            assert_eq!(subgroups_count, level_bounds(rtxn, db, field_id, level - 1).unwrap().len());
+            level += 1;
+        }
+        level - 1
+    }
+
+    #[test]
+    fn incremental_facet_levels() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let mut content = String::from("id,age\n");
+        for id in 0..100 {
+            content.push_str(&format!("{},{}\n", id, id));
+        }
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+
+        // A few documents are added and one is replaced, the values are inserted into
+        // the existing groups and outside of the bounds of the existing levels.
+        let content = "id,age\n100,2.5\n101,150\n102,-10\n103,50.5\n10,10.5\n";
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 2);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let age = index.fields_ids_map(&rtxn).unwrap().id("age").unwrap();
+        assert_eq!(assert_levels_consistency(&rtxn, &index, age), 2);
+
+        // A rebuild would have grouped the values four by four from -10, the first
+        // group was extended to -10 instead and now also contains the value 2.5.
+        let db = index.facet_number_levels_docids();
+        let docids = db.get(&rtxn, &(age, 1, -10.0, 3.0)).unwrap().unwrap();
+        assert_eq!(docids.len(), 6);
+        assert!(db.get(&rtxn, &(age, 1, 96.0, 150.0)).unwrap().is_some());
+
+        let ages: Vec<(u32, f64)> = (0..100).filter(|&id| id != 10).map(|id| (id, id as f64))
+            .chain(vec![(100, 2.5), (101, 150.0), (102, -10.0), (103, 50.5), (10, 10.5)])
+            .collect();
+        let external_documents_ids = index.external_documents_ids(&rtxn).unwrap();
+        let expected = |filter: &dyn Fn(f64) -> bool| -> RoaringBitmap {
+            ages.iter()
+                .filter(|(_, age)| filter(*age))
+                .map(|(id, _)| external_documents_ids.get(id.to_string()).unwrap())
+                .collect()
+        };
+        let evaluate = |expression: &str| {
+            FacetCondition::from_str(&rtxn, &index, expression).unwrap().evaluate(&rtxn, &index).unwrap()
+        };
+
+        assert_eq!(evaluate("age > 49"), expected(&|age| age > 49.0));
+        assert_eq!(evaluate("age < 3"), expected(&|age| age < 3.0));
+        assert_eq!(evaluate("age 2 TO 11"), expected(&|age| (2.0..=11.0).contains(&age)));
+        assert_eq!(evaluate("age >= 150"), expected(&|age| age >= 150.0));
+        assert_eq!(evaluate("age < 0"), expected(&|age| age < 0.0));
+        assert_eq!(index.faceted_documents_ids(&rtxn, age).unwrap(), index.documents_ids(&rtxn).unwrap());
+
+        // The value 10 was removed with the replaced document and 5 values were added.
+        let stats = index.facet_field_stats(&rtxn, age).unwrap().unwrap();
+        assert_eq!(stats, FacetFieldStats { distinct_values: 104, documents_count: 104 });
+    }
+
+    #[test]
+    fn clean_facet_levels_after_deletions() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let mut content = String::from("id,age\n");
+        for id in 0..100 {
+            content.push_str(&format!("{},{}\n", id, id));
+        }
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+
+        let age = index.fields_ids_map(&wtxn).unwrap().id("age").unwrap();
+        assert_eq!(assert_levels_consistency(&wtxn, &index, age), 2);
+
+        // Every group must have documents and be bounded by its first and last subgroups.
+        let assert_tight_groups = |rtxn: &heed::RoTxn, highest_level: u8| {
+            let db = index.facet_number_levels_docids();
+            for level in 1..=highest_level {
+                for (left, right) in level_bounds(rtxn, db, age, level).unwrap() {
+                    let docids = db.get(rtxn, &(age, level, left, right)).unwrap().unwrap();
+                    assert!(!docids.is_empty(), "level {} group {} to {}", level, left, right);
+                    let subgroups = subgroups(rtxn, db, age, level, left, right).unwrap();
+                    assert_eq!(subgroups[0].0, left);
+                    assert_eq!(subgroups[subgroups.len() - 1].1, right);
+                }
+            }
+        };
+
+        // Two groups of the first level are emptied and another one is shrunk,
+        // there are still enough values to keep two levels.
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index, 2).unwrap();
+        for id in (40..48).chain(0..2) {
+            builder.delete_external_id(&id.to_string()).unwrap();
+        }
+        builder.execute().unwrap();
+        assert_eq!(assert_levels_consistency(&wtxn, &index, age), 2);
+        assert_tight_groups(&wtxn, 2);
+
+        let db = index.facet_number_levels_docids();
+        assert!(db.get(&wtxn, &(age, 1, 40.0, 43.0)).unwrap().is_none());
+        assert!(db.get(&wtxn, &(age, 1, 2.0, 3.0)).unwrap().is_some());
+        assert!(db.get(&wtxn, &(age, 2, 32.0, 39.0)).unwrap().is_some());
+
+        // There aren't enough values for two levels anymore, the levels are rebuilt.
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index, 3).unwrap();
+        for id in 50..100 {
+            builder.delete_external_id(&id.to_string()).unwrap();
+        }
+        builder.execute().unwrap();
+        assert_eq!(assert_levels_consistency(&wtxn, &index, age), 1);
+        assert_tight_groups(&wtxn, 1);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let external_documents_ids = index.external_documents_ids(&rtxn).unwrap();
+        let expected = |filter: &dyn Fn(u32) -> bool| -> RoaringBitmap {
+            (2..40).chain(48..50)
+                .filter(|age| filter(*age))
+                .map(|id| external_documents_ids.get(id.to_string()).unwrap())
+                .collect()
+        };
+        let evaluate = |expression: &str| {
+            FacetCondition::from_str(&rtxn, &index, expression).unwrap().evaluate(&rtxn, &index).unwrap()
+        };
+
+        assert_eq!(evaluate("age > 30"), expected(&|age| age > 30));
+        assert_eq!(evaluate("age 1 TO 45"), expected(&|age| (1..=45).contains(&age)));
+        assert_eq!(evaluate("age < 10"), expected(&|age| age < 10));
+    }
+}
@@ -1,11 +1,16 @@
 use std::borrow::Cow;
+use std::io::{BufWriter, Read, Seek, SeekFrom};
 
 use anyhow::{bail, ensure, Context};
 use bstr::ByteSlice as _;
 use fst::IntoStreamer;
+use heed::{BytesDecode, BytesEncode};
+use log::warn;
 use roaring::RoaringBitmap;
+use tempfile::tempfile;
 
 use crate::heed_codec::CboRoaringBitmapCodec;
+use crate::heed_codec::facet::FacetStringValueDocidsCodec;
 
 const WORDS_FST_KEY: &[u8] = crate::index::WORDS_FST_KEY.as_bytes();
 const FIELDS_IDS_MAP_KEY: &[u8] = crate::index::FIELDS_IDS_MAP_KEY.as_bytes();
@@ -14,16 +19,21 @@ const DOCUMENTS_IDS_KEY: &[u8] = crate::index::DOCUMENTS_IDS_KEY.as_bytes();
 pub fn main_merge(key: &[u8], values: &[Cow<[u8]>]) -> anyhow::Result<Vec<u8>> {
     match key {
         WORDS_FST_KEY => {
-            let fsts: Vec<_> = values.iter().map(|v| fst::Set::new(v).unwrap()).collect();
+            let fsts = values.iter().map(fst::Set::new).collect::<Result<Vec<_>, _>>()?;
 
-            // Union of the FSTs
+            // Union of the FSTs, streamed into an FST built in a temporary file.
             let mut op = fst::set::OpBuilder::new();
             fsts.iter().for_each(|fst| op.push(fst.into_stream()));
             let op = op.r#union();
 
-            let mut build = fst::SetBuilder::memory();
-            build.extend_stream(op.into_stream()).unwrap();
-            Ok(build.into_inner().unwrap())
+            let mut build = tempfile().map(BufWriter::new).map(fst::SetBuilder::new)??;
+            build.extend_stream(op.into_stream())?;
+            let mut file = build.into_inner()?.into_inner()?;
+
+            let mut bytes = Vec::new();
+            file.seek(SeekFrom::Start(0))?;
+            file.read_to_end(&mut bytes)?;
+            Ok(bytes)
         },
         FIELDS_IDS_MAP_KEY => {
             ensure!(values.windows(2).all(|vs| vs[0] == vs[1]), "fields ids map doesn't match");
@@ -60,12 +70,41 @@ pub fn word_level_position_docids_merge(_key: &[u8], values: &[Cow<[u8]>]) -> an
     cbo_roaring_bitmap_merge(values)
 }
 
+pub fn field_id_word_count_docids_merge(_key: &[u8], values: &[Cow<[u8]>]) -> anyhow::Result<Vec<u8>> {
+    cbo_roaring_bitmap_merge(values)
+}
+
+pub fn field_id_original_word_docids_merge(_key: &[u8], values: &[Cow<[u8]>]) -> anyhow::Result<Vec<u8>> {
+    cbo_roaring_bitmap_merge(values)
+}
+
 pub fn facet_field_value_docids_merge(_key: &[u8], values: &[Cow<[u8]>]) -> anyhow::Result<Vec<u8>> {
     cbo_roaring_bitmap_merge(values)
 }
 
-pub fn documents_merge(key: &[u8], _values: &[Cow<[u8]>]) -> anyhow::Result<Vec<u8>> {
-    bail!("merging documents is an error ({:?})", key.as_bstr())
+/// Unions the documents ids of a facet string and keeps the first original form of it,
+/// the one that is already in the database when merging with its content.
+pub fn facet_field_string_docids_merge(_key: &[u8], values: &[Cow<[u8]>]) -> anyhow::Result<Vec<u8>> {
+    type Codec = FacetStringValueDocidsCodec<CboRoaringBitmapCodec>;
+
+    let (first, tail) = values.split_first().context("no value to merge")?;
+    let (original, mut docids) = Codec::bytes_decode(first).context("invalid facet string docids")?;
+    for value in tail {
+        let (_, other) = Codec::bytes_decode(value).context("invalid facet string docids")?;
+        docids.union_with(&other);
+    }
+
+    let bytes = Codec::bytes_encode(&(original, docids)).context("could not serialize facet string docids")?;
+    Ok(bytes.into_owned())
+}
+
+/// Keeps the last version of the document, the values are ordered from the oldest to the newest.
+pub fn documents_merge(key: &[u8], values: &[Cow<[u8]>]) -> anyhow::Result<Vec<u8>> {
+    let last = values.last().context("no document to merge")?;
+    if values.iter().any(|v| v != last) {
+        warn!("conflicting versions of the document {:?}, keeping the last one", key.as_bstr());
+    }
+    Ok(last.to_vec())
 }
 
 pub fn merge_two_obkvs(base: obkv::KvReader, update: obkv::KvReader, buffer: &mut Vec<u8>) {
@@ -111,3 +150,14 @@ fn cbo_roaring_bitmap_merge(values: &[Cow<[u8]>]) -> anyhow::Result<Vec<u8>> {
     CboRoaringBitmapCodec::serialize_into(&head, &mut vec)?;
     Ok(vec)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_merge_keeps_the_last_version() {
+        let values = vec![Cow::Borrowed(&b"old"[..]), Cow::Borrowed(&b"new"[..])];
+        assert_eq!(documents_merge(&0u32.to_be_bytes(), &values).unwrap(), b"new".to_vec());
+    }
+}
@@ -1,7 +1,8 @@
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 use std::fs::File;
-use std::io::{self, Seek, SeekFrom};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
 use std::num::{NonZeroU32, NonZeroUsize};
 use std::str;
 use std::sync::mpsc::sync_channel;
@@ -11,16 +12,20 @@ use anyhow::Context;
 use bstr::ByteSlice as _;
 use chrono::Utc;
 use grenad::{MergerIter, Writer, Sorter, Merger, Reader, FileFuse, CompressionType};
-use heed::types::ByteSlice;
-use log::{debug, info, error};
+use heed::types::{ByteSlice, DecodeIgnore};
+use log::{debug, info, error, warn};
 use memmap::Mmap;
 use rayon::prelude::*;
 use rayon::ThreadPool;
+use roaring::RoaringBitmap;
 use serde::{Serialize, Deserialize};
 
+use crate::facet::FacetType;
+use crate::geo::extract_geo_point;
 use crate::index::Index;
+use crate::{BEU32, FieldId, FieldsIdsMap, GeoPoint, GEO_FIELD_NAME, lat_lng_to_xyz};
 use crate::update::{
-    Facets, WordsLevelPositions, WordPrefixDocids, WordsPrefixesFst, UpdateIndexingStep,
+    ClearDocuments, Facets, WordsLevelPositions, WordPrefixDocids, WordsPrefixesFst, UpdateIndexingStep,
     WordPrefixPairProximityDocids,
 };
 use self::store::{Store, Readers};
@@ -28,9 +33,11 @@ pub use self::merge_function::{
     main_merge, word_docids_merge, words_pairs_proximities_docids_merge,
     docid_word_positions_merge, documents_merge,
     word_level_position_docids_merge, word_prefix_level_positions_docids_merge,
-    facet_field_value_docids_merge, field_id_docid_facet_values_merge,
+    facet_field_value_docids_merge, facet_field_string_docids_merge,
+    field_id_docid_facet_values_merge, field_id_word_count_docids_merge,
+    field_id_original_word_docids_merge,
 };
-pub use self::transform::{Transform, TransformOutput};
+pub use self::transform::{PayloadLimitError, PayloadLimits, Transform, TransformOutput};
 
 use crate::MergeFn;
 use super::UpdateBuilder;
@@ -42,6 +49,122 @@ mod transform;
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DocumentAdditionResult {
     nb_documents: usize,
+    /// The number of words occurrences that were too long to be indexed as is,
+    /// those were skipped or truncated depending on the `LongWordsPolicy`.
+    long_words_count: usize,
+    /// The number of documents fields that contain more words than their
+    /// maximum number of indexed tokens, the following words were not indexed.
+    truncated_fields_count: usize,
+    /// The number of CSV fields that were not valid UTF-8,
+    /// the invalid sequences were replaced by the replacement character.
+    invalid_utf8_fields: usize,
+    /// The string faceted fields that have more distinct values than the threshold.
+    high_cardinality_facets: Vec<HighCardinalityFacet>,
+    /// The primary key of the documents, it is inferred from the documents when not yet known.
+    primary_key: String,
+    /// The fields that the index didn't know before this addition, sorted by name.
+    new_fields: Vec<String>,
+    /// The number of documents that replaced the existing ones with the same ids.
+    replaced_documents_count: u64,
+    /// The documents that were added or replaced.
+    #[serde(skip)]
+    documents_ids: RoaringBitmap,
+}
+
+impl DocumentAdditionResult {
+    /// The internal ids of the documents that were added or replaced.
+    pub fn documents_ids(&self) -> &RoaringBitmap {
+        &self.documents_ids
+    }
+}
+
+/// The default number of distinct values above which a string faceted field is reported.
+const DEFAULT_FACET_CARDINALITY_THRESHOLD: u64 = 100_000;
+
+/// A warning about a string faceted field that has more distinct values than the threshold,
+/// it often means that a free text field has been declared as faceted by mistake.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct HighCardinalityFacet {
+    pub field: String,
+    pub distinct_values: u64,
+    pub threshold: u64,
+}
+
+/// Returns the string faceted fields that have more distinct values than the given threshold.
+fn high_cardinality_facets(
+    rtxn: &heed::RoTxn,
+    index: &Index,
+    threshold: u64,
+) -> anyhow::Result<Vec<HighCardinalityFacet>>
+{
+    let fields_ids_map = index.fields_ids_map(rtxn)?;
+    let mut facets = Vec::new();
+
+    for (field_id, facet_type) in index.indexed_facet_fields_ids(rtxn)? {
+        if facet_type != FacetType::String && facet_type != FacetType::Hierarchy {
+            continue;
+        }
+
+        let iter = index.facet_field_id_value_docids
+            .remap_data_type::<DecodeIgnore>()
+            .prefix_iter(rtxn, &[field_id][..])?;
+
+        let mut distinct_values = 0;
+        for result in iter {
+            result?;
+            distinct_values += 1;
+        }
+
+        if distinct_values > threshold {
+            let field = fields_ids_map.name(field_id).context("unknown faceted field id")?;
+            facets.push(HighCardinalityFacet { field: field.to_string(), distinct_values, threshold });
+        }
+    }
+
+    facets.sort_unstable_by(|a, b| a.field.cmp(&b.field));
+    Ok(facets)
+}
+
+/// Returns the names of the fields of the given fields ids map that the index doesn't know yet.
+fn new_fields(rtxn: &heed::RoTxn, index: &Index, fields_ids_map: &FieldsIdsMap) -> anyhow::Result<Vec<String>> {
+    let old_fields_ids_map = index.fields_ids_map(rtxn)?;
+    let mut fields: Vec<_> = fields_ids_map.iter()
+        .filter(|(_, name)| old_fields_ids_map.id(name).is_none())
+        .map(|(_, name)| name.to_string())
+        .collect();
+    fields.sort_unstable();
+    Ok(fields)
+}
+
+/// Counts the facet values of the given documents for each faceted field and writes
+/// these counts, the documents without any value for a field are not written.
+fn write_facet_values_counts(
+    wtxn: &mut heed::RwTxn,
+    index: &Index,
+    faceted_fields: &HashMap<FieldId, FacetType>,
+    documents_ids: &RoaringBitmap,
+) -> anyhow::Result<()>
+{
+    let facet_values = index.field_id_docid_facet_values.remap_data_type::<DecodeIgnore>();
+    for &field_id in faceted_fields.keys() {
+        for docid in documents_ids {
+            let mut prefix = [0u8; 1 + 4];
+            prefix[0] = field_id;
+            prefix[1..].copy_from_slice(&docid.to_be_bytes());
+
+            let mut count = 0;
+            for result in facet_values.prefix_iter(wtxn, &prefix[..])? {
+                result?;
+                count += 1;
+            }
+
+            if count != 0 {
+                index.facet_field_id_docid_count.put(wtxn, &(field_id, docid), &BEU32::new(count))?;
+            }
+        }
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -241,6 +364,23 @@ pub enum IndexDocumentsMethod {
     UpdateDocuments,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum LongWordsPolicy {
+    /// The words longer than the maximum word length are not indexed.
+    Skip,
+
+    /// The words longer than the maximum word length are truncated and suffixed
+    /// by a hash of the whole word, this way two long words stay distinct.
+    Truncate,
+}
+
+impl Default for LongWordsPolicy {
+    fn default() -> LongWordsPolicy {
+        LongWordsPolicy::Skip
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum UpdateFormat {
@@ -250,6 +390,40 @@ pub enum UpdateFormat {
     Json,
     /// The given update is a JSON stream with a document on each line.
     JsonStream,
+    /// The given update is a Parquet file, each column of a row is a document field.
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+const GZIP_MAGIC_BYTES: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC_BYTES: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Wraps the given payload reader into a decompressor when the payload
+/// starts with the gzip or zstd magic bytes, returns it as is otherwise.
+fn decompressed_reader<'r, R: io::Read + 'r>(reader: R) -> io::Result<Box<dyn io::Read + 'r>> {
+    let mut reader = BufReader::new(reader);
+    let header = reader.fill_buf()?;
+
+    if header.starts_with(GZIP_MAGIC_BYTES) {
+        Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)))
+    } else if header.starts_with(ZSTD_MAGIC_BYTES) {
+        Ok(Box::new(zstd::stream::read::Decoder::new(reader)?))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Returns the geo points of the documents that have a valid `_geo` field.
+fn extract_geo_points(mut documents: Reader<&[u8]>, geo_field_id: FieldId) -> anyhow::Result<Vec<GeoPoint>> {
+    let mut geo_points = Vec::new();
+    while let Some((key, value)) = documents.next()? {
+        let document_id = key.try_into().map(u32::from_be_bytes).unwrap();
+        let document = obkv::KvReader::new(value);
+        if let Some(lat_lng) = extract_geo_point(&document, geo_field_id)? {
+            geo_points.push(GeoPoint::new((document_id, lat_lng), lat_lng_to_xyz(&lat_lng)));
+        }
+    }
+    Ok(geo_points)
 }
 
 pub struct IndexDocuments<'t, 'u, 'i, 'a> {
@@ -271,7 +445,12 @@ pub struct IndexDocuments<'t, 'u, 'i, 'a> {
     words_positions_min_level_size: Option<NonZeroU32>,
     update_method: IndexDocumentsMethod,
     update_format: UpdateFormat,
+    long_words_policy: LongWordsPolicy,
+    payload_limits: PayloadLimits,
+    facet_cardinality_threshold: Option<u64>,
     autogenerate_docids: bool,
+    strict_utf8: bool,
+    dry_run: bool,
     update_id: u64,
 }
 
@@ -300,7 +479,12 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
             words_positions_min_level_size: None,
             update_method: IndexDocumentsMethod::ReplaceDocuments,
             update_format: UpdateFormat::Json,
+            long_words_policy: LongWordsPolicy::default(),
+            payload_limits: PayloadLimits::default(),
+            facet_cardinality_threshold: None,
             autogenerate_docids: true,
+            strict_utf8: false,
+            dry_run: false,
             update_id,
         }
     }
@@ -313,6 +497,18 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
         self.update_format = format;
     }
 
+    pub fn long_words_policy(&mut self, policy: LongWordsPolicy) {
+        self.long_words_policy = policy;
+    }
+
+    pub fn payload_limits(&mut self, limits: PayloadLimits) {
+        self.payload_limits = limits;
+    }
+
+    pub fn facet_cardinality_threshold(&mut self, threshold: u64) {
+        self.facet_cardinality_threshold = Some(threshold);
+    }
+
     pub fn enable_autogenerate_docids(&mut self) {
         self.autogenerate_docids = true;
     }
@@ -321,16 +517,41 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
         self.autogenerate_docids = false;
     }
 
-    pub fn execute<R, F>(self, reader: R, progress_callback: F) -> anyhow::Result<DocumentAdditionResult>
+    /// Fails the update when a CSV field is not valid UTF-8, by default
+    /// the invalid sequences are replaced by the replacement character.
+    pub fn strict_utf8(&mut self, strict: bool) {
+        self.strict_utf8 = strict;
+    }
+
+    /// Only transforms and validates the documents and returns what would change,
+    /// nothing is written into the index.
+    pub fn dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    pub fn execute<R, F>(mut self, reader: R, progress_callback: F) -> anyhow::Result<DocumentAdditionResult>
     where
         R: io::Read,
         F: Fn(UpdateIndexingStep, u64) + Sync,
     {
-        self.index.set_updated_at(self.wtxn, &Utc::now())?;
         let before_transform = Instant::now();
         let update_id = self.update_id;
         let progress_callback = |step| progress_callback(step, update_id);
 
+        // The payload can be compressed, we detect it and decompress it on the fly.
+        let reader = decompressed_reader(reader)?;
+
+        // The indexes created before the exactness criterion stored the words counts and the
+        // original words must be reindexed once, these databases would otherwise stay empty.
+        // The same goes for the facet string values stored without their original form.
+        if !self.dry_run && self.index.exactness_databases_outdated(self.wtxn)? {
+            info!("Reindexing the documents to build the exactness databases...");
+            self.reindex_documents(&progress_callback)?;
+        } else if !self.dry_run && self.index.facet_string_originals_outdated(self.wtxn)? {
+            info!("Reindexing the documents to store the original facet string values...");
+            self.reindex_documents(&progress_callback)?;
+        }
+
         let transform = Transform {
             rtxn: &self.wtxn,
             index: self.index,
@@ -342,23 +563,97 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
             max_memory: self.max_memory,
             index_documents_method: self.update_method,
             autogenerate_docids: self.autogenerate_docids,
+            payload_limits: self.payload_limits,
+            strict_utf8: self.strict_utf8,
+            thread_pool: self.thread_pool,
         };
 
         let output = match self.update_format {
             UpdateFormat::Csv => transform.output_from_csv(reader, &progress_callback)?,
             UpdateFormat::Json => transform.output_from_json(reader, &progress_callback)?,
             UpdateFormat::JsonStream => transform.output_from_json_stream(reader, &progress_callback)?,
+            #[cfg(feature = "parquet")]
+            UpdateFormat::Parquet => transform.output_from_parquet(reader, &progress_callback)?,
         };
 
-        let nb_documents = output.documents_count;
-
         info!("Update transformed in {:.02?}", before_transform.elapsed());
 
-        self.execute_raw(output, progress_callback)?;
-        Ok(DocumentAdditionResult { nb_documents })
+        if self.dry_run {
+            return self.dry_run_result(output);
+        }
+
+        self.index.set_updated_at(self.wtxn, &Utc::now())?;
+        let (index, update_id) = (self.index, self.update_id);
+        let result = self.execute_raw(output, progress_callback)?;
+        index.update_hooks.record_addition(update_id, result.documents_ids());
+
+        Ok(result)
+    }
+
+    /// Reindexes all the documents of the index with the current settings.
+    fn reindex_documents<F>(&mut self, progress_callback: F) -> anyhow::Result<()>
+    where
+        F: Fn(UpdateIndexingStep) + Sync
+    {
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let primary_key = self.index.primary_key(self.wtxn)?.context("Index must have a primary key")?;
+
+        let transform = Transform {
+            rtxn: &self.wtxn,
+            index: self.index,
+            log_every_n: self.log_every_n,
+            chunk_compression_type: self.chunk_compression_type,
+            chunk_compression_level: self.chunk_compression_level,
+            chunk_fusing_shrink_size: self.chunk_fusing_shrink_size,
+            max_nb_chunks: self.max_nb_chunks,
+            max_memory: self.max_memory,
+            index_documents_method: IndexDocumentsMethod::ReplaceDocuments,
+            autogenerate_docids: false,
+            // The documents are already in the index, there is no payload to limit.
+            payload_limits: PayloadLimits::default(),
+            strict_utf8: false,
+            thread_pool: self.thread_pool,
+        };
+
+        let output = transform.remap_index_documents(
+            primary_key.to_string(),
+            fields_ids_map.clone(),
+            fields_ids_map,
+        )?;
+
+        ClearDocuments::new(self.wtxn, self.index, self.update_id).execute()?;
+
+        let mut builder = IndexDocuments::new(self.wtxn, self.index, self.update_id);
+        builder.log_every_n = self.log_every_n;
+        builder.max_nb_chunks = self.max_nb_chunks;
+        builder.max_memory = self.max_memory;
+        builder.linked_hash_map_size = self.linked_hash_map_size;
+        builder.chunk_compression_type = self.chunk_compression_type;
+        builder.chunk_compression_level = self.chunk_compression_level;
+        builder.chunk_fusing_shrink_size = self.chunk_fusing_shrink_size;
+        builder.thread_pool = self.thread_pool;
+        builder.execute_raw(output, progress_callback)?;
+
+        Ok(())
+    }
+
+    /// Describes what the transformed documents would change once indexed.
+    fn dry_run_result(&self, output: TransformOutput) -> anyhow::Result<DocumentAdditionResult> {
+        let new_fields = new_fields(&self.wtxn, self.index, &output.fields_ids_map)?;
+        Ok(DocumentAdditionResult {
+            nb_documents: output.documents_count,
+            long_words_count: 0,
+            truncated_fields_count: 0,
+            invalid_utf8_fields: output.invalid_utf8_fields,
+            high_cardinality_facets: Vec::new(),
+            primary_key: output.primary_key,
+            new_fields,
+            replaced_documents_count: output.replaced_documents_ids.len(),
+            documents_ids: output.new_documents_ids | output.replaced_documents_ids,
+        })
     }
 
-    pub fn execute_raw<F>(self, output: TransformOutput, progress_callback: F) -> anyhow::Result<()>
+    pub fn execute_raw<F>(self, output: TransformOutput, progress_callback: F) -> anyhow::Result<DocumentAdditionResult>
     where
         F: Fn(UpdateIndexingStep) + Sync
     {
@@ -373,8 +668,12 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
             replaced_documents_ids,
             documents_count,
             documents_file,
+            invalid_utf8_fields,
         } = output;
 
+        let new_fields = new_fields(&self.wtxn, self.index, &fields_ids_map)?;
+        let replaced_documents_count = replaced_documents_ids.len();
+
         // We delete the documents that this document addition replaces. This way we are
         // able to simply insert all the documents even if they already exist in the database.
         if !replaced_documents_ids.is_empty() {
@@ -406,16 +705,26 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
 
         let documents = grenad::Reader::new(bytes).unwrap();
 
+        // We extract the geo points of the documents, they are inserted into the rtree
+        // once the documents have been written, the replaced ones were already removed.
+        let geo_points = match fields_ids_map.id(GEO_FIELD_NAME) {
+            Some(geo_field_id) => extract_geo_points(documents.clone(), geo_field_id)?,
+            None => Vec::new(),
+        };
+
         // The enum which indicates the type of the readers
         // merges that are potentially done on different threads.
         enum DatabaseType {
             Main,
             WordDocids,
             WordLevel0PositionDocids,
+            FieldIdWordCountDocids,
+            FieldIdOriginalWordDocids,
             FacetLevel0ValuesDocids,
+            FacetStringsDocids,
         }
 
-        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        let faceted_fields = self.index.indexed_facet_fields_ids(self.wtxn)?;
         let searchable_fields: HashSet<_> = match self.index.searchable_fields_ids(self.wtxn)? {
             Some(fields) => fields.iter().copied().collect(),
             None => fields_ids_map.iter().map(|(id, _name)| id).collect(),
@@ -423,6 +732,11 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
 
         let stop_words = self.index.stop_words(self.wtxn)?;
         let stop_words = stop_words.as_ref();
+        let proximity_enabled = self.index.proximity_enabled(self.wtxn)?;
+        let max_indexed_tokens = self.index.max_indexed_tokens_ids(self.wtxn)?;
+        let facet_normalizations = self.index.facet_normalizations_ids(self.wtxn)?;
+        let analyzer_options = self.index.analyzer_options(self.wtxn)?;
+        let long_words_policy = self.long_words_policy;
         let linked_hash_map_size = self.linked_hash_map_size;
         let max_nb_chunks = self.max_nb_chunks;
         let max_memory = self.max_memory;
@@ -454,6 +768,7 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
                         fields_ids_map.clone(),
                         searchable_fields.clone(),
                         faceted_fields.clone(),
+                        facet_normalizations.clone(),
                         linked_hash_map_size,
                         max_nb_chunks,
                         max_memory_by_job,
@@ -461,6 +776,10 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
                         chunk_compression_level,
                         chunk_fusing_shrink_size,
                         stop_words,
+                        proximity_enabled,
+                        long_words_policy,
+                        max_indexed_tokens.clone(),
+                        analyzer_options.clone(),
                     )?;
                     store.index(
                         documents,
@@ -478,9 +797,14 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
             let mut docid_word_positions_readers = Vec::with_capacity(readers.len());
             let mut words_pairs_proximities_docids_readers = Vec::with_capacity(readers.len());
             let mut word_level_position_docids_readers = Vec::with_capacity(readers.len());
+            let mut field_id_word_count_docids_readers = Vec::with_capacity(readers.len());
+            let mut field_id_original_word_docids_readers = Vec::with_capacity(readers.len());
             let mut facet_field_value_docids_readers = Vec::with_capacity(readers.len());
+            let mut facet_field_string_docids_readers = Vec::with_capacity(readers.len());
             let mut field_id_docid_facet_values_readers = Vec::with_capacity(readers.len());
             let mut documents_readers = Vec::with_capacity(readers.len());
+            let mut long_words_count = 0;
+            let mut truncated_fields_count = 0;
             readers.into_iter().for_each(|readers| {
                 let Readers {
                     main,
@@ -488,18 +812,28 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
                     docid_word_positions,
                     words_pairs_proximities_docids,
                     word_level_position_docids,
+                    field_id_word_count_docids,
+                    field_id_original_word_docids,
                     facet_field_value_docids,
+                    facet_field_string_docids,
                     field_id_docid_facet_values,
-                    documents
+                    documents,
+                    long_words_count: store_long_words_count,
+                    truncated_fields_count: store_truncated_fields_count,
                 } = readers;
                 main_readers.push(main);
                 word_docids_readers.push(word_docids);
                 docid_word_positions_readers.push(docid_word_positions);
                 words_pairs_proximities_docids_readers.push(words_pairs_proximities_docids);
                 word_level_position_docids_readers.push(word_level_position_docids);
+                field_id_word_count_docids_readers.push(field_id_word_count_docids);
+                field_id_original_word_docids_readers.push(field_id_original_word_docids);
                 facet_field_value_docids_readers.push(facet_field_value_docids);
+                facet_field_string_docids_readers.push(facet_field_string_docids);
                 field_id_docid_facet_values_readers.push(field_id_docid_facet_values);
                 documents_readers.push(documents);
+                long_words_count += store_long_words_count;
+                truncated_fields_count += store_truncated_fields_count;
             });
 
             // This is the function that merge the readers
@@ -527,11 +861,26 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
                         facet_field_value_docids_readers,
                         facet_field_value_docids_merge,
                     ),
+                    (
+                        DatabaseType::FacetStringsDocids,
+                        facet_field_string_docids_readers,
+                        facet_field_string_docids_merge,
+                    ),
                     (
                         DatabaseType::WordLevel0PositionDocids,
                         word_level_position_docids_readers,
                         word_level_position_docids_merge,
                     ),
+                    (
+                        DatabaseType::FieldIdWordCountDocids,
+                        field_id_word_count_docids_readers,
+                        field_id_word_count_docids_merge,
+                    ),
+                    (
+                        DatabaseType::FieldIdOriginalWordDocids,
+                        field_id_original_word_docids_readers,
+                        field_id_original_word_docids_merge,
+                    ),
                 ]
                 .into_par_iter()
                 .for_each(|(dbtype, readers, merge)| {
@@ -548,6 +897,8 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
                 documents_readers,
                 words_pairs_proximities_docids_readers,
                 field_id_docid_facet_values_readers,
+                long_words_count,
+                truncated_fields_count,
             )) as anyhow::Result<_>
         })?;
 
@@ -557,8 +908,22 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
             documents_readers,
             words_pairs_proximities_docids_readers,
             field_id_docid_facet_values_readers,
+            long_words_count,
+            truncated_fields_count,
         ) = readers;
 
+        if long_words_count != 0 {
+            warn!("{} words occurrences were too long to be indexed as is ({:?})", long_words_count, long_words_policy);
+        }
+
+        if truncated_fields_count != 0 {
+            info!("{} fields contained more words than their maximum number of indexed tokens", truncated_fields_count);
+        }
+
+        if invalid_utf8_fields != 0 {
+            warn!("{} CSV fields were not valid UTF-8, the invalid sequences were replaced", invalid_utf8_fields);
+        }
+
         let mut documents_ids = self.index.documents_ids(self.wtxn)?;
         let contains_documents = !documents_ids.is_empty();
         let write_method = if contains_documents {
@@ -587,7 +952,7 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
         self.index.put_documents_ids(self.wtxn, &documents_ids)?;
 
         let mut database_count = 0;
-        let total_databases = 8;
+        let total_databases = 11;
 
         progress_callback(UpdateIndexingStep::MergeDataIntoFinalDatabase {
             databases_seen: 0,
@@ -633,6 +998,14 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
             write_method,
         )?;
 
+        debug!("Writing the number of facet values of the documents into LMDB on disk...");
+        write_facet_values_counts(
+            self.wtxn,
+            self.index,
+            &faceted_fields,
+            &(&new_documents_ids | &replaced_documents_ids),
+        )?;
+
         database_count += 1;
         progress_callback(UpdateIndexingStep::MergeDataIntoFinalDatabase {
             databases_seen: database_count,
@@ -680,13 +1053,26 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
                 },
                 DatabaseType::FacetLevel0ValuesDocids => {
                     debug!("Writing the facet level 0 values docids into LMDB on disk...");
+                    // The facet numbers and strings are written into the same database from two
+                    // different streams, their keys are interleaved and can't be appended.
                     let db = *self.index.facet_field_id_value_docids.as_polymorph();
                     write_into_lmdb_database(
                         self.wtxn,
                         db,
                         content,
                         facet_field_value_docids_merge,
-                        write_method,
+                        WriteMethod::GetMergePut,
+                    )?;
+                },
+                DatabaseType::FacetStringsDocids => {
+                    debug!("Writing the facet strings docids into LMDB on disk...");
+                    let db = *self.index.facet_field_id_value_docids.as_polymorph();
+                    write_into_lmdb_database(
+                        self.wtxn,
+                        db,
+                        content,
+                        facet_field_string_docids_merge,
+                        WriteMethod::GetMergePut,
                     )?;
                 },
                 DatabaseType::WordLevel0PositionDocids => {
@@ -699,6 +1085,28 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
                         word_level_position_docids_merge,
                         write_method,
                     )?;
+                },
+                DatabaseType::FieldIdWordCountDocids => {
+                    debug!("Writing the field id word count docids into LMDB on disk...");
+                    let db = *self.index.field_id_word_count_docids.as_polymorph();
+                    write_into_lmdb_database(
+                        self.wtxn,
+                        db,
+                        content,
+                        field_id_word_count_docids_merge,
+                        write_method,
+                    )?;
+                },
+                DatabaseType::FieldIdOriginalWordDocids => {
+                    debug!("Writing the field id original word docids into LMDB on disk...");
+                    let db = *self.index.field_id_original_word_docids.as_polymorph();
+                    write_into_lmdb_database(
+                        self.wtxn,
+                        db,
+                        content,
+                        field_id_original_word_docids_merge,
+                        write_method,
+                    )?;
                 }
             }
 
@@ -709,6 +1117,18 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
             });
         }
 
+        if !geo_points.is_empty() {
+            debug!("Writing the geo points into the rtree...");
+            let mut rtree = self.index.geo_rtree(self.wtxn)?.unwrap_or_default();
+            let mut geo_faceted_documents_ids = self.index.geo_faceted_documents_ids(self.wtxn)?;
+            for point in geo_points {
+                geo_faceted_documents_ids.insert(point.data.0);
+                rtree.insert(point);
+            }
+            self.index.put_geo_rtree(self.wtxn, &rtree)?;
+            self.index.put_geo_faceted_documents_ids(self.wtxn, &geo_faceted_documents_ids)?;
+        }
+
         // Run the facets update operation.
         let mut builder = Facets::new(self.wtxn, self.index, self.update_id);
         builder.chunk_compression_type = self.chunk_compression_type;
@@ -720,35 +1140,49 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
         if let Some(value) = self.facet_min_level_size {
             builder.min_level_size(value);
         }
+        builder.new_documents_ids(&new_documents_ids | &replaced_documents_ids);
         builder.execute()?;
 
-        // Run the words prefixes update operation.
-        let mut builder = WordsPrefixesFst::new(self.wtxn, self.index, self.update_id);
-        if let Some(value) = self.words_prefix_threshold {
-            builder.threshold(value);
+        // We warn about the string faceted fields that have too many distinct values.
+        let threshold = self.facet_cardinality_threshold.unwrap_or(DEFAULT_FACET_CARDINALITY_THRESHOLD);
+        let high_cardinality_facets = high_cardinality_facets(self.wtxn, self.index, threshold)?;
+        for facet in &high_cardinality_facets {
+            warn!(
+                "the faceted field {:?} has {} distinct values which is more than {}",
+                facet.field, facet.distinct_values, facet.threshold,
+            );
         }
-        if let Some(value) = self.max_prefix_length {
-            builder.max_prefix_length(value);
-        }
-        builder.execute()?;
-
-        // Run the word prefix docids update operation.
-        let mut builder = WordPrefixDocids::new(self.wtxn, self.index);
-        builder.chunk_compression_type = self.chunk_compression_type;
-        builder.chunk_compression_level = self.chunk_compression_level;
-        builder.chunk_fusing_shrink_size = self.chunk_fusing_shrink_size;
-        builder.max_nb_chunks = self.max_nb_chunks;
-        builder.max_memory = self.max_memory;
-        builder.execute()?;
 
-        // Run the word prefix pair proximity docids update operation.
-        let mut builder = WordPrefixPairProximityDocids::new(self.wtxn, self.index);
-        builder.chunk_compression_type = self.chunk_compression_type;
-        builder.chunk_compression_level = self.chunk_compression_level;
-        builder.chunk_fusing_shrink_size = self.chunk_fusing_shrink_size;
-        builder.max_nb_chunks = self.max_nb_chunks;
-        builder.max_memory = self.max_memory;
-        builder.execute()?;
+        // Run the prefix update operations, only if the prefix databases are enabled.
+        if self.index.prefix_databases_enabled(self.wtxn)? {
+            // Run the words prefixes update operation.
+            let mut builder = WordsPrefixesFst::new(self.wtxn, self.index, self.update_id);
+            if let Some(value) = self.words_prefix_threshold {
+                builder.threshold(value);
+            }
+            if let Some(value) = self.max_prefix_length {
+                builder.max_prefix_length(value);
+            }
+            builder.execute()?;
+
+            // Run the word prefix docids update operation.
+            let mut builder = WordPrefixDocids::new(self.wtxn, self.index);
+            builder.chunk_compression_type = self.chunk_compression_type;
+            builder.chunk_compression_level = self.chunk_compression_level;
+            builder.chunk_fusing_shrink_size = self.chunk_fusing_shrink_size;
+            builder.max_nb_chunks = self.max_nb_chunks;
+            builder.max_memory = self.max_memory;
+            builder.execute()?;
+
+            // Run the word prefix pair proximity docids update operation.
+            let mut builder = WordPrefixPairProximityDocids::new(self.wtxn, self.index);
+            builder.chunk_compression_type = self.chunk_compression_type;
+            builder.chunk_compression_level = self.chunk_compression_level;
+            builder.chunk_fusing_shrink_size = self.chunk_fusing_shrink_size;
+            builder.max_nb_chunks = self.max_nb_chunks;
+            builder.max_memory = self.max_memory;
+            builder.execute()?;
+        }
 
         // Run the words level positions update operation.
         let mut builder = WordsLevelPositions::new(self.wtxn, self.index);
@@ -765,9 +1199,22 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
 
         debug_assert_eq!(database_count, total_databases);
 
+        self.index.put_exactness_databases_built(self.wtxn)?;
+        self.index.put_facet_string_originals_built(self.wtxn)?;
+
         info!("Transform output indexed in {:.02?}", before_indexing.elapsed());
 
-        Ok(())
+        Ok(DocumentAdditionResult {
+            nb_documents: documents_count,
+            long_words_count,
+            truncated_fields_count,
+            invalid_utf8_fields,
+            high_cardinality_facets,
+            primary_key,
+            new_fields,
+            replaced_documents_count,
+            documents_ids: new_documents_ids | replaced_documents_ids,
+        })
     }
 }
 
@@ -775,6 +1222,11 @@ impl<'t, 'u, 'i, 'a> IndexDocuments<'t, 'u, 'i, 'a> {
 mod tests {
     use super::*;
     use heed::EnvOpenOptions;
+    use maplit::{btreemap, hashmap};
+
+    use crate::facet::FacetValue;
+    use crate::update::Settings;
+    use crate::FacetCondition;
 
     #[test]
     fn simple_document_replacement() {
@@ -1193,4 +1645,412 @@ mod tests {
 
         drop(rtxn);
     }
+
+    #[test]
+    fn long_words_policy() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let long_word = "a".repeat(600);
+        let content = format!("id,name\n0,hello {}\n", long_word);
+
+        // By default the long words are skipped.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        let result = builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        assert_eq!(result.long_words_count, 1);
+
+        let words: Vec<_> = index.word_docids.iter(&wtxn).unwrap().map(|r| r.unwrap().0).collect();
+        assert_eq!(words, vec!["hello"]);
+
+        // The truncated words are suffixed by a hash of the whole word.
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.long_words_policy(LongWordsPolicy::Truncate);
+        let result = builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        assert_eq!(result.long_words_count, 1);
+
+        let words: Vec<_> = index.word_docids.iter(&wtxn).unwrap().map(|r| r.unwrap().0.to_string()).collect();
+        assert_eq!(words.len(), 2);
+        assert!(words[0].starts_with(&long_word[..200]));
+        assert!(words[0].len() <= crate::MAX_WORD_LENGTH);
+        assert_eq!(words[1], "hello");
+        wtxn.commit().unwrap();
+
+        // The long query words are truncated the same way and match the truncated words.
+        let rtxn = index.read_txn().unwrap();
+        let query = format!("{} hello", long_word);
+        let result = index.search(&rtxn).query(query).execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        // The words up to the maximum word length are indexed as is.
+        let mut wtxn = index.write_txn().unwrap();
+        let medium_word = "b".repeat(crate::MAX_WORD_LENGTH);
+        let content = format!("id,name\n1,{}\n", medium_word);
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 2);
+        builder.update_format(UpdateFormat::Csv);
+        let result = builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        assert_eq!(result.long_words_count, 0);
+        assert!(index.word_docids.get(&wtxn, &medium_word).unwrap().is_some());
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn long_words_composite_keys() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // This word fits in an LMDB key by itself but not in a word pair proximity key.
+        let long_word = "a".repeat(300);
+        let content = format!("id,name\n0,{} {}\n", long_word, long_word.replace('a', "b"));
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.long_words_policy(LongWordsPolicy::Truncate);
+        let result = builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        assert_eq!(result.long_words_count, 2);
+
+        // All the entries of the truncated words are stored.
+        let words: Vec<_> = index.word_docids.iter(&wtxn).unwrap().map(|r| r.unwrap().0.to_string()).collect();
+        assert_eq!(words.len(), 2);
+        for word in &words {
+            assert!(word.len() <= crate::MAX_WORD_LENGTH);
+            assert!(index.docid_word_positions.get(&wtxn, &(0, word.as_str())).unwrap().is_some());
+            let mut iter = index.word_level_position_docids.iter(&wtxn).unwrap();
+            assert!(iter.any(|result| result.unwrap().0.0 == word.as_str()));
+        }
+        let count = index.word_pair_proximity_docids.len(&wtxn).unwrap();
+        assert_ne!(count, 0);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn invalid_utf8_csv_fields() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let content = &b"id,name\n0,kev\xffin\n1,kevina\n"[..];
+
+        // In strict mode the whole update fails.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.strict_utf8(true);
+        assert!(builder.execute(content, |_, _| ()).is_err());
+
+        // By default the invalid sequences are replaced.
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        let result = builder.execute(content, |_, _| ()).unwrap();
+        assert_eq!(result.invalid_utf8_fields, 1);
+        assert_eq!(result.nb_documents, 2);
+
+        let (_, obkv) = index.documents(&wtxn, Some(0)).unwrap().pop().unwrap();
+        let name: String = serde_json::from_slice(obkv.get(1).unwrap()).unwrap();
+        assert_eq!(name, "kev\u{FFFD}in");
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn payload_limits() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let content = &br#"[
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "kevina", "nested": { "array": [{ "key": "value" }] } },
+            { "id": 2, "name": "benoit" }
+        ]"#[..];
+
+        let limits = PayloadLimits { max_documents: Some(2), ..Default::default() };
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.payload_limits(limits);
+        let error = builder.execute(content, |_, _| ()).unwrap_err();
+        let error = error.downcast_ref::<PayloadLimitError>().unwrap();
+        assert_eq!(*error, PayloadLimitError::TooManyDocuments { limit: 2 });
+
+        let limits = PayloadLimits { max_nesting_depth: Some(3), ..Default::default() };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.payload_limits(limits);
+        let error = builder.execute(content, |_, _| ()).unwrap_err();
+        let error = error.downcast_ref::<PayloadLimitError>().unwrap();
+        assert_eq!(*error, PayloadLimitError::DocumentTooDeep { depth: 4, limit: 3 });
+
+        let limits = PayloadLimits { max_document_size: Some(20), ..Default::default() };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.payload_limits(limits);
+        let error = builder.execute(&b"id,name\n0,kevin\n1,a very long name\n"[..], |_, _| ()).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<PayloadLimitError>(),
+            Some(PayloadLimitError::DocumentTooLarge { limit: 20, .. })
+        ));
+
+        // The documents within the limits are correctly indexed.
+        let limits = PayloadLimits {
+            max_documents: Some(3),
+            max_document_size: Some(1000),
+            max_nesting_depth: Some(4),
+        };
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.payload_limits(limits);
+        builder.execute(content, |_, _| ()).unwrap();
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 3);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn compressed_payloads() {
+        use std::io::Write;
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let content = &b"id,name\n0,kevin\n1,kevina\n2,benoit\n"[..];
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(content).unwrap();
+        let gzip_content = encoder.finish().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(&gzip_content[..], |_, _| ()).unwrap();
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 3);
+
+        let content = &b"{ \"id\": 3, \"name\": \"bernard\" }\n{ \"id\": 4, \"name\": \"clementine\" }\n"[..];
+        let zstd_content = zstd::encode_all(content, 0).unwrap();
+
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::JsonStream);
+        builder.execute(&zstd_content[..], |_, _| ()).unwrap();
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 5);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn high_cardinality_facets() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "name".into() => "string".into(),
+            "color".into() => "string".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,name,color\n0,kevin,blue\n1,kevina,blue\n2,benoit,red\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.facet_cardinality_threshold(2);
+        let result = builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let expected = HighCardinalityFacet { field: "name".to_string(), distinct_values: 3, threshold: 2 };
+        assert_eq!(result.high_cardinality_facets, vec![expected]);
+    }
+
+    #[test]
+    fn array_facet_values() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{
+            "genres".into() => "string".into(),
+            "years".into() => "number".into(),
+        });
+        builder.execute(|_, _| ()).unwrap();
+
+        // The invalid elements of the arrays are ignored, not the whole arrays.
+        let content = &br#"[
+            { "id": 0, "genres": ["Rock", "pop", "rock"], "years": [1999, "2001"] },
+            { "id": 1, "genres": ["jazz", { "sub": "genre" }], "years": [2001, "unknown"] },
+            { "id": 2, "genres": "pop", "years": 1999 }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let evaluate = |expression| {
+            let condition = FacetCondition::from_str(&rtxn, &index, expression).unwrap();
+            condition.evaluate(&rtxn, &index).unwrap().into_iter().collect::<Vec<_>>()
+        };
+        assert_eq!(evaluate("genres = rock"), vec![0]);
+        assert_eq!(evaluate("genres = pop"), vec![0, 2]);
+        assert_eq!(evaluate("genres = jazz AND years = 2001"), vec![1]);
+        assert_eq!(evaluate("NOT genres = pop"), vec![1]);
+        assert_eq!(evaluate("years > 2000"), vec![0, 1]);
+
+        // Every element is counted once for each document,
+        // the first original form of a value is the one that is returned.
+        let distribution = index.facets_distribution(&rtxn).facets(&["genres"]).execute().unwrap();
+        let expected = btreemap!{
+            FacetValue::from("jazz") => 1,
+            FacetValue::from("pop") => 2,
+            FacetValue::from("Rock") => 1,
+        };
+        assert_eq!(distribution["genres"], expected);
+    }
+
+    #[test]
+    fn dry_run_documents_addition() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,kevin\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        let result = builder.execute(content, |_, _| ()).unwrap();
+        assert_eq!(result.new_fields, vec!["id", "name"]);
+        wtxn.commit().unwrap();
+
+        // The dry run describes the addition without writing anything.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name,age\n0,kevina,21\n1,benoit,34\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.dry_run(true);
+        let result = builder.execute(content, |_, _| ()).unwrap();
+        assert_eq!(result.nb_documents, 2);
+        assert_eq!(result.primary_key, "id");
+        assert_eq!(result.new_fields, vec!["age"]);
+        assert_eq!(result.replaced_documents_count, 1);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 1);
+        assert_eq!(index.fields_ids_map(&rtxn).unwrap().id("age"), None);
+        let result = index.search(&rtxn).query("kevina").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+        drop(rtxn);
+
+        // The invalid documents are still rejected.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"name\nkevina\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 2);
+        builder.update_format(UpdateFormat::Csv);
+        builder.disable_autogenerate_docids();
+        builder.dry_run(true);
+        assert!(builder.execute(content, |_, _| ()).is_err());
+    }
+
+    #[test]
+    fn reindex_outdated_exactness_databases() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,kevin\n1,Kevina\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // We simulate an index that was created before the exactness databases existed.
+        let mut wtxn = index.write_txn().unwrap();
+        index.main.delete::<_, heed::types::Str>(&mut wtxn, crate::index::EXACTNESS_DATABASES_KEY).unwrap();
+        index.field_id_word_count_docids.clear(&mut wtxn).unwrap();
+        index.field_id_original_word_docids.clear(&mut wtxn).unwrap();
+        assert!(index.exactness_databases_outdated(&wtxn).unwrap());
+        wtxn.commit().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n2,benoit\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.exactness_databases_outdated(&rtxn).unwrap());
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 3);
+        let name = index.fields_ids_map(&rtxn).unwrap().id("name").unwrap();
+        let docids = index.field_id_word_count_docids.get(&rtxn, &(name, 1)).unwrap().unwrap();
+        assert_eq!(docids.len(), 3);
+        let docids = index.field_id_original_word_docids.get(&rtxn, &(name, "Kevina")).unwrap().unwrap();
+        assert_eq!(docids.len(), 1);
+    }
+
+    #[test]
+    fn reindex_outdated_facet_string_originals() {
+        use crate::heed_codec::facet::FacetValueStringCodec;
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "color".into() => "string".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,color\n0,Blue\n1,Red\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // We simulate an index that stored the facet string values docids without the originals.
+        let mut wtxn = index.write_txn().unwrap();
+        let values: Vec<_> = index.facet_string_values_docids().iter(&wtxn).unwrap()
+            .map(|result| {
+                let ((field_id, value), (_original, docids)) = result.unwrap();
+                (field_id, value.to_string(), docids)
+            })
+            .collect();
+        let legacy_db = index.facet_field_id_value_docids.remap_key_type::<FacetValueStringCodec>();
+        for (field_id, value, docids) in values {
+            legacy_db.put(&mut wtxn, &(field_id, &value), &docids).unwrap();
+        }
+        index.main.delete::<_, heed::types::Str>(&mut wtxn, crate::index::FACET_STRING_ORIGINALS_KEY).unwrap();
+        assert!(index.facet_string_originals_outdated(&wtxn).unwrap());
+        wtxn.commit().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,color\n2,blue\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 2);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.facet_string_originals_outdated(&rtxn).unwrap());
+        let color = index.fields_ids_map(&rtxn).unwrap().id("color").unwrap();
+        assert_eq!(index.facet_string_original(&rtxn, color, "red").unwrap(), Some("Red"));
+        let (_original, docids) = index.facet_string_values_docids().get(&rtxn, &(color, "blue")).unwrap().unwrap();
+        assert_eq!(docids.len(), 2);
+    }
 }
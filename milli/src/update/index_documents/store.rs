@@ -2,6 +2,7 @@ use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::fs::File;
+use std::io::BufWriter;
 use std::iter::FromIterator;
 use std::time::Instant;
 use std::{cmp, iter};
@@ -13,30 +14,35 @@ use grenad::{Reader, FileFuse, Writer, Sorter, CompressionType};
 use heed::BytesEncode;
 use linked_hash_map::LinkedHashMap;
 use log::{debug, info, warn};
+use memmap::Mmap;
 use meilisearch_tokenizer::{Analyzer, AnalyzerConfig, Token, TokenKind, token::SeparatorKind};
 use ordered_float::OrderedFloat;
 use roaring::RoaringBitmap;
 use serde_json::Value;
 use tempfile::tempfile;
 
-use crate::facet::{FacetType, FacetValue};
-use crate::heed_codec::facet::{FacetValueStringCodec, FacetLevelValueF64Codec};
+use crate::facet::{FacetDate, FacetNormalization, FacetType, FacetValue, normalize_path, path_levels};
+use crate::heed_codec::facet::{FacetStringValueDocidsCodec, FacetValueStringCodec, FacetLevelValueF64Codec};
 use crate::heed_codec::facet::{FieldDocIdFacetStringCodec, FieldDocIdFacetF64Codec};
 use crate::heed_codec::{BoRoaringBitmapCodec, CboRoaringBitmapCodec};
 use crate::update::UpdateIndexingStep;
-use crate::{json_to_string, SmallVec8, SmallVec32, Position, DocumentId, FieldId, FieldsIdsMap};
+use crate::{json_to_string, AnalyzerOptions, SmallVec8, SmallVec32, Position, DocumentId, FieldId, FieldsIdsMap};
+use crate::{truncate_long_word, MAX_LMDB_KEY_LENGTH, MAX_WORD_LENGTH};
 
-use super::{MergeFn, create_writer, create_sorter, writer_into_reader};
+use super::{LongWordsPolicy, MergeFn, create_writer, create_sorter, writer_into_reader};
 use super::merge_function::{
     main_merge, word_docids_merge, words_pairs_proximities_docids_merge,
     word_level_position_docids_merge, facet_field_value_docids_merge,
-    field_id_docid_facet_values_merge,
+    facet_field_string_docids_merge, field_id_docid_facet_values_merge,
+    field_id_word_count_docids_merge, field_id_original_word_docids_merge,
 };
 
-const LMDB_MAX_KEY_LENGTH: usize = 511;
 const ONE_KILOBYTE: usize = 1024 * 1024;
 
 const MAX_POSITION: usize = 1000;
+/// The maximum number of words of an attribute that is stored in the
+/// field id word count docids database, longer attributes are not counted.
+const MAX_COUNTED_WORDS: usize = 30;
 const WORDS_FST_KEY: &[u8] = crate::index::WORDS_FST_KEY.as_bytes();
 
 pub struct Readers {
@@ -45,9 +51,16 @@ pub struct Readers {
     pub docid_word_positions: Reader<FileFuse>,
     pub words_pairs_proximities_docids: Reader<FileFuse>,
     pub word_level_position_docids: Reader<FileFuse>,
+    pub field_id_word_count_docids: Reader<FileFuse>,
+    pub field_id_original_word_docids: Reader<FileFuse>,
+    /// The documents ids of the level 0 of the facet numbers.
     pub facet_field_value_docids: Reader<FileFuse>,
+    /// The documents ids of the facet strings along with their original value.
+    pub facet_field_string_docids: Reader<FileFuse>,
     pub field_id_docid_facet_values: Reader<FileFuse>,
     pub documents: Reader<FileFuse>,
+    pub long_words_count: usize,
+    pub truncated_fields_count: usize,
 }
 
 pub struct Store<'s, A> {
@@ -56,12 +69,19 @@ pub struct Store<'s, A> {
     fields_ids_map: FieldsIdsMap,
     searchable_fields: HashSet<FieldId>,
     faceted_fields: HashMap<FieldId, FacetType>,
+    facet_normalizations: HashMap<FieldId, FacetNormalization>,
+    proximity_enabled: bool,
+    long_words_policy: LongWordsPolicy,
+    long_words_count: usize,
+    max_indexed_tokens: HashMap<FieldId, usize>,
+    truncated_fields_count: usize,
+    analyzer_options: AnalyzerOptions,
     // Caches
     word_docids: LinkedHashMap<SmallVec32<u8>, RoaringBitmap>,
     word_docids_limit: usize,
     words_pairs_proximities_docids: LinkedHashMap<(SmallVec32<u8>, SmallVec32<u8>, u8), RoaringBitmap>,
     words_pairs_proximities_docids_limit: usize,
-    facet_field_value_docids: LinkedHashMap<(u8, FacetValue), RoaringBitmap>,
+    facet_field_value_docids: LinkedHashMap<(u8, FacetValue), (RoaringBitmap, Option<String>)>,
     facet_field_value_docids_limit: usize,
     // MTBL parameters
     chunk_compression_type: CompressionType,
@@ -72,7 +92,10 @@ pub struct Store<'s, A> {
     word_docids_sorter: Sorter<MergeFn>,
     words_pairs_proximities_docids_sorter: Sorter<MergeFn>,
     word_level_position_docids_sorter: Sorter<MergeFn>,
+    field_id_word_count_docids_sorter: Sorter<MergeFn>,
+    field_id_original_word_docids_sorter: Sorter<MergeFn>,
     facet_field_value_docids_sorter: Sorter<MergeFn>,
+    facet_field_string_docids_sorter: Sorter<MergeFn>,
     field_id_docid_facet_values_sorter: Sorter<MergeFn>,
     // MTBL writers
     docid_word_positions_writer: Writer<File>,
@@ -87,6 +110,7 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
         fields_ids_map: FieldsIdsMap,
         searchable_fields: HashSet<FieldId>,
         faceted_fields: HashMap<FieldId, FacetType>,
+        facet_normalizations: HashMap<FieldId, FacetNormalization>,
         linked_hash_map_size: Option<usize>,
         max_nb_chunks: Option<usize>,
         max_memory: Option<usize>,
@@ -94,6 +118,10 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
         chunk_compression_level: Option<u32>,
         chunk_fusing_shrink_size: Option<u64>,
         stop_words: Option<&'s Set<A>>,
+        proximity_enabled: bool,
+        long_words_policy: LongWordsPolicy,
+        max_indexed_tokens: HashMap<FieldId, usize>,
+        analyzer_options: AnalyzerOptions,
     ) -> anyhow::Result<Self>
     {
         // We divide the max memory by the number of sorter the Store have.
@@ -132,6 +160,22 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
             max_nb_chunks,
             max_memory,
         );
+        let field_id_word_count_docids_sorter = create_sorter(
+            field_id_word_count_docids_merge,
+            chunk_compression_type,
+            chunk_compression_level,
+            chunk_fusing_shrink_size,
+            max_nb_chunks,
+            max_memory,
+        );
+        let field_id_original_word_docids_sorter = create_sorter(
+            field_id_original_word_docids_merge,
+            chunk_compression_type,
+            chunk_compression_level,
+            chunk_fusing_shrink_size,
+            max_nb_chunks,
+            max_memory,
+        );
         let facet_field_value_docids_sorter = create_sorter(
             facet_field_value_docids_merge,
             chunk_compression_type,
@@ -140,6 +184,14 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
             max_nb_chunks,
             max_memory,
         );
+        let facet_field_string_docids_sorter = create_sorter(
+            facet_field_string_docids_merge,
+            chunk_compression_type,
+            chunk_compression_level,
+            chunk_fusing_shrink_size,
+            max_nb_chunks,
+            max_memory,
+        );
         let field_id_docid_facet_values_sorter = create_sorter(
             field_id_docid_facet_values_merge,
             chunk_compression_type,
@@ -168,6 +220,13 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
             fields_ids_map,
             searchable_fields,
             faceted_fields,
+            facet_normalizations,
+            proximity_enabled,
+            long_words_policy,
+            long_words_count: 0,
+            max_indexed_tokens,
+            truncated_fields_count: 0,
+            analyzer_options,
             // Caches
             word_docids: LinkedHashMap::with_capacity(linked_hash_map_size),
             word_docids_limit: linked_hash_map_size,
@@ -184,7 +243,10 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
             word_docids_sorter,
             words_pairs_proximities_docids_sorter,
             word_level_position_docids_sorter,
+            field_id_word_count_docids_sorter,
+            field_id_original_word_docids_sorter,
             facet_field_value_docids_sorter,
+            facet_field_string_docids_sorter,
             field_id_docid_facet_values_sorter,
             // MTBL writers
             docid_word_positions_writer,
@@ -215,11 +277,13 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
         Ok(())
     }
 
-    // Save the documents ids under the facet field id and value we have seen it.
+    // Save the documents ids under the facet field id and value we have seen it,
+    // the first original form of a string value that we see is the one we keep.
     fn insert_facet_values_docid(
         &mut self,
         field_id: FieldId,
         field_value: FacetValue,
+        original: Option<String>,
         id: DocumentId,
     ) -> anyhow::Result<()>
     {
@@ -228,16 +292,17 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
         let key = (field_id, field_value);
         // if get_refresh finds the element it is assured to be at the end of the linked hash map.
         match self.facet_field_value_docids.get_refresh(&key) {
-            Some(old) => { old.insert(id); },
+            Some((old, _)) => { old.insert(id); },
             None => {
                 // A newly inserted element is append at the end of the linked hash map.
-                self.facet_field_value_docids.insert(key, RoaringBitmap::from_iter(Some(id)));
+                self.facet_field_value_docids.insert(key, (RoaringBitmap::from_iter(Some(id)), original));
                 // If the word docids just reached it's capacity we must make sure to remove
                 // one element, this way next time we insert we doesn't grow the capacity.
                 if self.facet_field_value_docids.len() == self.facet_field_value_docids_limit {
                     // Removing the front element is equivalent to removing the LRU element.
                     Self::write_facet_field_value_docids(
                         &mut self.facet_field_value_docids_sorter,
+                        &mut self.facet_field_string_docids_sorter,
                         self.facet_field_value_docids.pop_front(),
                     )?;
                 }
@@ -287,13 +352,16 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
         &mut self,
         document_id: DocumentId,
         words_positions: &mut HashMap<String, SmallVec32<Position>>,
-        facet_values: &mut HashMap<FieldId, SmallVec8<FacetValue>>,
+        facet_values: &mut HashMap<FieldId, SmallVec8<(FacetValue, Option<String>)>>,
         record: &[u8],
     ) -> anyhow::Result<()>
     {
-        // We compute the list of words pairs proximities (self-join) and write it directly to disk.
-        let words_pair_proximities = compute_words_pair_proximities(&words_positions);
-        self.insert_words_pairs_proximities_docids(words_pair_proximities, document_id)?;
+        // We compute the list of words pairs proximities (self-join) and write it directly to disk,
+        // this is the most expensive part of the indexing and is skipped when proximity is disabled.
+        if self.proximity_enabled {
+            let words_pair_proximities = compute_words_pair_proximities(&words_positions);
+            self.insert_words_pairs_proximities_docids(words_pair_proximities, document_id)?;
+        }
 
         // We store document_id associated with all the words the record contains.
         for (word, _) in words_positions.iter() {
@@ -308,8 +376,8 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
 
         // We store document_id associated with all the field id and values.
         for (field, values) in facet_values.drain() {
-            for value in values {
-                self.insert_facet_values_docid(field, value, document_id)?;
+            for (value, original) in values {
+                self.insert_facet_values_docid(field, value, original, document_id)?;
             }
         }
 
@@ -409,24 +477,82 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
         Ok(())
     }
 
-    fn write_facet_field_value_docids<I>(
+    fn write_field_id_word_count_docid(
         sorter: &mut Sorter<MergeFn>,
-        iter: I,
+        field_id: FieldId,
+        word_count: usize,
+        document_id: DocumentId,
     ) -> anyhow::Result<()>
-    where I: IntoIterator<Item=((FieldId, FacetValue), RoaringBitmap)>
     {
-        use FacetValue::*;
+        if word_count == 0 || word_count > MAX_COUNTED_WORDS {
+            return Ok(());
+        }
+
+        let key = [field_id, word_count as u8];
+        let docids = RoaringBitmap::from_iter(Some(document_id));
+        let mut buffer = Vec::new();
+        CboRoaringBitmapCodec::serialize_into(&docids, &mut buffer)
+            .with_context(|| "could not serialize the word count docids")?;
+        sorter.insert(&key, &buffer)?;
+
+        Ok(())
+    }
+
+    fn write_field_id_original_word_docids<'w>(
+        sorter: &mut Sorter<MergeFn>,
+        field_id: FieldId,
+        words: impl IntoIterator<Item = &'w str>,
+        document_id: DocumentId,
+    ) -> anyhow::Result<()>
+    {
+        let docids = RoaringBitmap::from_iter(Some(document_id));
+        let mut buffer = Vec::new();
+        CboRoaringBitmapCodec::serialize_into(&docids, &mut buffer)
+            .with_context(|| "could not serialize the original word docids")?;
 
-        for ((field_id, value), docids) in iter {
-            let result = match value {
-                String(s) => FacetValueStringCodec::bytes_encode(&(field_id, &s)).map(Cow::into_owned),
-                Number(f) => FacetLevelValueF64Codec::bytes_encode(&(field_id, 0, *f, *f)).map(Cow::into_owned),
-            };
-            let key = result.context("could not serialize facet key")?;
-            let bytes = CboRoaringBitmapCodec::bytes_encode(&docids)
-                .context("could not serialize docids")?;
+        let mut key = Vec::new();
+        for word in words {
+            key.clear();
+            key.push(field_id);
+            key.extend_from_slice(word.as_bytes());
+            // The original words that are too long to be stored are never exact.
             if lmdb_key_valid_size(&key) {
-                sorter.insert(&key, &bytes)?;
+                sorter.insert(&key, &buffer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_facet_field_value_docids<I>(
+        numbers_sorter: &mut Sorter<MergeFn>,
+        strings_sorter: &mut Sorter<MergeFn>,
+        iter: I,
+    ) -> anyhow::Result<()>
+    where I: IntoIterator<Item=((FieldId, FacetValue), (RoaringBitmap, Option<String>))>
+    {
+        for ((field_id, value), (docids, original)) in iter {
+            match value {
+                FacetValue::String(s) => {
+                    let key = FacetValueStringCodec::bytes_encode(&(field_id, &s))
+                        .context("could not serialize facet key")?;
+                    // The values without an original form are kept as they are normalized.
+                    let original = original.as_deref().unwrap_or(&s);
+                    let bytes = FacetStringValueDocidsCodec::<CboRoaringBitmapCodec>::bytes_encode(&(original, docids))
+                        .context("could not serialize the original value and docids")?;
+                    if lmdb_key_valid_size(&key) {
+                        strings_sorter.insert(&key, &bytes)?;
+                    }
+                },
+                FacetValue::Number(f) => {
+                    let key = FacetLevelValueF64Codec::bytes_encode(&(field_id, 0, *f, *f))
+                        .context("could not serialize facet key")?;
+                    let bytes = CboRoaringBitmapCodec::bytes_encode(&docids)
+                        .context("could not serialize docids")?;
+                    if lmdb_key_valid_size(&key) {
+                        numbers_sorter.insert(&key, &bytes)?;
+                    }
+                },
             }
         }
 
@@ -517,26 +643,25 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
                         let value = serde_json::from_slice(content)?;
 
                         if let Some(ftype) = self.faceted_fields.get(&attr) {
-                            let mut values = match parse_facet_value(*ftype, &value) {
-                                Ok(values) => values,
-                                Err(e) => {
-                                    // We extract the name of the attribute and the document id
-                                    // to help users debug a facet type conversion.
-                                    let attr_name = self.fields_ids_map.name(attr).unwrap();
-                                    let document_id: Value = self.fields_ids_map.id(&self.primary_key)
-                                        .and_then(|fid| document.get(fid))
-                                        .map(serde_json::from_slice)
-                                        .unwrap()?;
-
+                            let normalization = self.facet_normalizations.get(&attr).copied().unwrap_or_default();
+                            let (mut values, errors) = parse_facet_value(*ftype, normalization, &value);
+                            if !errors.is_empty() {
+                                // We extract the name of the attribute and the document id
+                                // to help users debug a facet type conversion.
+                                let attr_name = self.fields_ids_map.name(attr).unwrap();
+                                let document_id: Value = self.fields_ids_map.id(&self.primary_key)
+                                    .and_then(|fid| document.get(fid))
+                                    .map(serde_json::from_slice)
+                                    .unwrap()?;
+
+                                for e in errors {
                                     let context = format!(
                                         "while extracting facet from the {:?} attribute in the {} document",
                                         attr_name, document_id,
                                     );
                                     warn!("{}", e.context(context));
-
-                                    SmallVec8::default()
-                                },
-                            };
+                                }
+                            }
                             facet_values.entry(attr).or_insert_with(SmallVec8::new).extend(values.drain(..));
                         }
 
@@ -546,13 +671,75 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
                                 None => continue,
                             };
 
+                            let content = self.analyzer_options.normalize(&content);
                             let analyzed = self.analyzer.analyze(&content);
-                            let tokens = process_tokens(analyzed.tokens());
-
-                            for (pos, token) in tokens.take_while(|(pos, _)| *pos < MAX_POSITION) {
+                            let mut tokens = process_tokens(analyzed.tokens())
+                                .take_while(|(pos, _)| *pos < MAX_POSITION);
+                            let max_tokens = self.max_indexed_tokens.get(&attr).copied().unwrap_or(usize::MAX);
+
+                            let mut word_count = 0;
+                            let mut original_words = HashSet::new();
+                            for (pos, token) in tokens.by_ref().take(max_tokens) {
+                                word_count += 1;
                                 let position = (attr as usize * MAX_POSITION + pos) as u32;
-                                words_positions.entry(token.text().to_string()).or_insert_with(SmallVec32::new).push(position);
+                                let word = match limit_word_length(token.text(), self.long_words_policy) {
+                                    Some(Cow::Borrowed(word)) => word.to_string(),
+                                    Some(Cow::Owned(word)) => {
+                                        self.long_words_count += 1;
+                                        word
+                                    },
+                                    None => {
+                                        self.long_words_count += 1;
+                                        continue;
+                                    },
+                                };
+                                // The words are also stored as they are written in the document,
+                                // the exactness criterion ranks the documents that match the query byte-exactly.
+                                let original = content.get(token.byte_start..token.byte_end).unwrap_or_default();
+                                if !original.is_empty() {
+                                    original_words.insert(original);
+                                }
+
+                                // The identifier words are indexed at the position of the identifier.
+                                for identifier_word in self.analyzer_options.identifier_words(original) {
+                                    let identifier_word = match limit_word_length(&identifier_word, self.long_words_policy) {
+                                        Some(Cow::Borrowed(identifier_word)) => identifier_word.to_string(),
+                                        Some(Cow::Owned(identifier_word)) => {
+                                            self.long_words_count += 1;
+                                            identifier_word
+                                        },
+                                        None => {
+                                            self.long_words_count += 1;
+                                            continue;
+                                        },
+                                    };
+                                    if identifier_word != word {
+                                        let positions = words_positions.entry(identifier_word).or_insert_with(SmallVec32::new);
+                                        if positions.last() != Some(&position) {
+                                            positions.push(position);
+                                        }
+                                    }
+                                }
+                                words_positions.entry(word).or_insert_with(SmallVec32::new).push(position);
+                            }
+
+                            if tokens.next().is_some() {
+                                self.truncated_fields_count += 1;
                             }
+
+                            Self::write_field_id_word_count_docid(
+                                &mut self.field_id_word_count_docids_sorter,
+                                attr,
+                                word_count,
+                                document_id,
+                            )?;
+
+                            Self::write_field_id_original_word_docids(
+                                &mut self.field_id_original_word_docids_sorter,
+                                attr,
+                                original_words,
+                                document_id,
+                            )?;
                         }
                     }
                 }
@@ -576,6 +763,8 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
     }
 
     fn finish(mut self) -> anyhow::Result<Readers> {
+        let long_words_count = self.long_words_count;
+        let truncated_fields_count = self.truncated_fields_count;
         let comp_type = self.chunk_compression_type;
         let comp_level = self.chunk_compression_level;
         let shrink_size = self.chunk_fusing_shrink_size;
@@ -587,22 +776,25 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
         )?;
         Self::write_facet_field_value_docids(
             &mut self.facet_field_value_docids_sorter,
+            &mut self.facet_field_string_docids_sorter,
             self.facet_field_value_docids,
         )?;
 
         let mut word_docids_wtr = tempfile().and_then(|f| create_writer(comp_type, comp_level, f))?;
-        let mut builder = fst::SetBuilder::memory();
+        let mut builder = tempfile().map(BufWriter::new).map(fst::SetBuilder::new)??;
 
+        // The word docids sorter spills its entries to disk and merges them back in order,
+        // the words are therefore streamed to the FST builder already sorted and deduplicated,
+        // the FST itself is built into a temporary file.
         let mut iter = self.word_docids_sorter.into_iter()?;
         while let Some((word, val)) = iter.next()? {
-            // This is a lexicographically ordered word position
-            // we use the key to construct the words fst.
             builder.insert(word)?;
             word_docids_wtr.insert(word, val)?;
         }
 
-        let fst = builder.into_set();
-        self.main_sorter.insert(WORDS_FST_KEY, fst.as_fst().as_bytes())?;
+        let fst_file = builder.into_inner()?.into_inner()?;
+        let fst = unsafe { Mmap::map(&fst_file).context("mmaping the words fst file")? };
+        self.main_sorter.insert(WORDS_FST_KEY, &fst)?;
 
         let mut main_wtr = tempfile().and_then(|f| create_writer(comp_type, comp_level, f))?;
         self.main_sorter.write_into(&mut main_wtr)?;
@@ -613,9 +805,18 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
         let mut word_level_position_docids_wtr = tempfile().and_then(|f| create_writer(comp_type, comp_level, f))?;
         self.word_level_position_docids_sorter.write_into(&mut word_level_position_docids_wtr)?;
 
+        let mut field_id_word_count_docids_wtr = tempfile().and_then(|f| create_writer(comp_type, comp_level, f))?;
+        self.field_id_word_count_docids_sorter.write_into(&mut field_id_word_count_docids_wtr)?;
+
+        let mut field_id_original_word_docids_wtr = tempfile().and_then(|f| create_writer(comp_type, comp_level, f))?;
+        self.field_id_original_word_docids_sorter.write_into(&mut field_id_original_word_docids_wtr)?;
+
         let mut facet_field_value_docids_wtr = tempfile().and_then(|f| create_writer(comp_type, comp_level, f))?;
         self.facet_field_value_docids_sorter.write_into(&mut facet_field_value_docids_wtr)?;
 
+        let mut facet_field_string_docids_wtr = tempfile().and_then(|f| create_writer(comp_type, comp_level, f))?;
+        self.facet_field_string_docids_sorter.write_into(&mut facet_field_string_docids_wtr)?;
+
         let mut field_id_docid_facet_values_wtr = tempfile().and_then(|f| create_writer(comp_type, comp_level, f))?;
         self.field_id_docid_facet_values_sorter.write_into(&mut field_id_docid_facet_values_wtr)?;
 
@@ -623,7 +824,10 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
         let word_docids = writer_into_reader(word_docids_wtr, shrink_size)?;
         let words_pairs_proximities_docids = writer_into_reader(words_pairs_proximities_docids_wtr, shrink_size)?;
         let word_level_position_docids = writer_into_reader(word_level_position_docids_wtr, shrink_size)?;
+        let field_id_word_count_docids = writer_into_reader(field_id_word_count_docids_wtr, shrink_size)?;
+        let field_id_original_word_docids = writer_into_reader(field_id_original_word_docids_wtr, shrink_size)?;
         let facet_field_value_docids = writer_into_reader(facet_field_value_docids_wtr, shrink_size)?;
+        let facet_field_string_docids = writer_into_reader(facet_field_string_docids_wtr, shrink_size)?;
         let field_id_docid_facet_values = writer_into_reader(field_id_docid_facet_values_wtr, shrink_size)?;
         let docid_word_positions = writer_into_reader(self.docid_word_positions_writer, shrink_size)?;
         let documents = writer_into_reader(self.documents_writer, shrink_size)?;
@@ -634,9 +838,14 @@ impl<'s, A: AsRef<[u8]>> Store<'s, A> {
             docid_word_positions,
             words_pairs_proximities_docids,
             word_level_position_docids,
+            field_id_word_count_docids,
+            field_id_original_word_docids,
             facet_field_value_docids,
+            facet_field_string_docids,
             field_id_docid_facet_values,
             documents,
+            long_words_count,
+            truncated_fields_count,
         })
     }
 }
@@ -677,7 +886,20 @@ fn format_count(n: usize) -> String {
 }
 
 fn lmdb_key_valid_size(key: &[u8]) -> bool {
-    !key.is_empty() && key.len() <= LMDB_MAX_KEY_LENGTH
+    !key.is_empty() && key.len() <= MAX_LMDB_KEY_LENGTH
+}
+
+/// Returns the word as is if it isn't too long to be indexed, otherwise applies the policy:
+/// returns `None` if the word must be skipped or an owned, truncated and hash suffixed, word.
+fn limit_word_length(word: &str, policy: LongWordsPolicy) -> Option<Cow<str>> {
+    if word.len() <= MAX_WORD_LENGTH {
+        return Some(Cow::Borrowed(word));
+    }
+
+    match policy {
+        LongWordsPolicy::Skip => None,
+        LongWordsPolicy::Truncate => Some(truncate_long_word(word)),
+    }
 }
 
 /// take an iterator on tokens and compute their relative position depending on separator kinds
@@ -710,60 +932,103 @@ fn process_tokens<'a>(tokens: impl Iterator<Item = Token<'a>>) -> impl Iterator<
     .filter(|(_, t)| t.is_word())
 }
 
-fn parse_facet_value(ftype: FacetType, value: &Value) -> anyhow::Result<SmallVec8<FacetValue>> {
-    use FacetValue::*;
-
+/// Returns the facet values of a field, every element of an array and every level of a
+/// hierarchical path is an independent facet value, the elements that can't be converted
+/// are returned as errors and ignored.
+///
+/// The facet strings are returned along with their original form, trimmed but not normalized,
+/// it is not returned when it is the same as the normalized value.
+fn parse_facet_value(
+    ftype: FacetType,
+    normalization: FacetNormalization,
+    value: &Value,
+) -> (SmallVec8<(FacetValue, Option<String>)>, Vec<anyhow::Error>)
+{
     fn inner_parse_facet_value(
         ftype: FacetType,
+        normalization: FacetNormalization,
         value: &Value,
         can_recurse: bool,
-        output: &mut SmallVec8<FacetValue>,
+        output: &mut SmallVec8<(FacetValue, Option<String>)>,
+        errors: &mut Vec<anyhow::Error>,
     ) -> anyhow::Result<()>
     {
+        let number = |float: f64| (FacetValue::Number(OrderedFloat(float)), None);
+
         match value {
             Value::Null => Ok(()),
             Value::Bool(b) => match ftype {
-                FacetType::String => {
-                    output.push(String(b.to_string()));
+                FacetType::String | FacetType::Hierarchy => {
+                    output.push((FacetValue::String(b.to_string()), None));
                     Ok(())
                 },
                 FacetType::Number => {
-                    output.push(Number(OrderedFloat(if *b { 1.0 } else { 0.0 })));
+                    output.push(number(if *b { 1.0 } else { 0.0 }));
                     Ok(())
                 },
+                FacetType::Date => bail!("invalid facet type, expecting {} found boolean", ftype),
             },
-            Value::Number(number) => match ftype {
-                FacetType::String => {
-                    output.push(String(number.to_string()));
+            Value::Number(n) => match ftype {
+                FacetType::String | FacetType::Hierarchy => {
+                    output.push((FacetValue::String(n.to_string()), None));
                     Ok(())
                 },
-                FacetType::Number => match number.as_f64() {
+                FacetType::Number => match n.as_f64() {
                     Some(float) => {
-                        output.push(Number(OrderedFloat(float)));
+                        output.push(number(float));
                         Ok(())
                     },
                     None => bail!("invalid facet type, expecting {} found number", ftype),
                 },
+                FacetType::Date => match n.as_i64() {
+                    Some(timestamp) => {
+                        output.push(number(FacetDate(timestamp).into()));
+                        Ok(())
+                    },
+                    None => bail!("invalid facet type, expecting {} found number {}", ftype, n),
+                },
             },
-            Value::String(string) => {
-                // TODO must be normalized and not only lowercased.
-                let string = string.trim().to_lowercase();
+            Value::String(original) => {
                 match ftype {
                     FacetType::String => {
-                        output.push(String(string));
+                        let value = normalization.normalize(original).into_owned();
+                        let original = Some(original.trim()).filter(|o| *o != value).map(ToOwned::to_owned);
+                        output.push((FacetValue::String(value), original));
                         Ok(())
                     },
-                    FacetType::Number => match string.parse() {
+                    FacetType::Hierarchy => {
+                        let path = normalize_path(original, normalization);
+                        let original_path = normalize_path(original, FacetNormalization::none());
+                        let original_levels: Vec<_> = path_levels(&original_path).collect();
+                        for (i, level) in path_levels(&path).enumerate() {
+                            let original = original_levels.get(i).filter(|o| **o != level).map(|o| o.to_string());
+                            output.push((FacetValue::String(level.to_string()), original));
+                        }
+                        Ok(())
+                    },
+                    FacetType::Number => match original.trim().parse() {
                         Ok(float) => {
-                            output.push(Number(OrderedFloat(float)));
+                            output.push(number(float));
                             Ok(())
                         },
                         Err(_err) => bail!("invalid facet type, expecting {} found string", ftype),
                     },
+                    FacetType::Date => match original.parse::<FacetDate>() {
+                        Ok(date) => {
+                            output.push(number(date.into()));
+                            Ok(())
+                        },
+                        Err(err) => bail!("invalid facet type, expecting {}: {}", ftype, err),
+                    },
                 }
             },
             Value::Array(values) => if can_recurse {
-                values.iter().map(|v| inner_parse_facet_value(ftype, v, false, output)).collect()
+                for value in values {
+                    if let Err(e) = inner_parse_facet_value(ftype, normalization, value, false, output, errors) {
+                        errors.push(e);
+                    }
+                }
+                Ok(())
             } else {
                 bail!(
                     "invalid facet type, expecting {} found array (recursive arrays are not supported)",
@@ -775,6 +1040,15 @@ fn parse_facet_value(ftype: FacetType, value: &Value) -> anyhow::Result<SmallVec
     }
 
     let mut facet_values = SmallVec8::new();
-    inner_parse_facet_value(ftype, value, true, &mut facet_values)?;
-    Ok(facet_values)
+    let mut errors = Vec::new();
+    if let Err(e) = inner_parse_facet_value(ftype, normalization, value, true, &mut facet_values, &mut errors) {
+        errors.push(e);
+    }
+
+    // An array can contain the same value multiple times, the sort is stable
+    // and the first original form of the value is the one we keep.
+    facet_values.sort_by(|(a, _), (b, _)| a.cmp(b));
+    facet_values.dedup_by(|(a, _), (b, _)| a == b);
+
+    (facet_values, errors)
 }
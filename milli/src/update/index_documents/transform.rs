@@ -1,14 +1,20 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
 use std::iter::Peekable;
+use std::str;
 use std::time::Instant;
 
 use anyhow::{anyhow, Context};
 use grenad::CompressionType;
 use log::info;
+use rayon::ThreadPool;
+use rayon::prelude::*;
 use roaring::RoaringBitmap;
+use serde::{Serialize, Deserialize};
 use serde_json::{Map, Value};
 
 use crate::{Index, BEU32, MergeFn, FieldsIdsMap, ExternalDocumentsIds, FieldId, FieldsDistribution};
@@ -17,6 +23,86 @@ use super::merge_function::merge_two_obkvs;
 use super::{create_writer, create_sorter, IndexDocumentsMethod};
 
 const DEFAULT_PRIMARY_KEY_NAME: &str = "id";
+/// The minimum number of bytes of CSV records parsed by a single thread,
+/// a batch of one chunk per thread is read in memory at a time.
+const CSV_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The hard limits enforced on the documents payloads, `None` means unlimited.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadLimits {
+    /// The maximum number of documents in a single batch.
+    pub max_documents: Option<usize>,
+    /// The maximum size of a single document once serialized, in bytes.
+    pub max_document_size: Option<usize>,
+    /// The maximum depth of the objects and arrays of a single document,
+    /// a document with only scalar values has a depth of one.
+    pub max_nesting_depth: Option<usize>,
+}
+
+impl PayloadLimits {
+    fn check_documents_count(&self, count: usize) -> Result<(), PayloadLimitError> {
+        match self.max_documents {
+            Some(limit) if count > limit => Err(PayloadLimitError::TooManyDocuments { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_document_size(&self, size: usize) -> Result<(), PayloadLimitError> {
+        match self.max_document_size {
+            Some(limit) if size > limit => Err(PayloadLimitError::DocumentTooLarge { size, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_nesting_depth(&self, document: &Map<String, Value>) -> Result<(), PayloadLimitError> {
+        match self.max_nesting_depth {
+            Some(limit) => {
+                let depth = 1 + document.values().map(value_depth).max().unwrap_or(0);
+                if depth > limit {
+                    Err(PayloadLimitError::DocumentTooDeep { depth, limit })
+                } else {
+                    Ok(())
+                }
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the number of nested objects and arrays of the given value.
+fn value_depth(value: &Value) -> usize {
+    match value {
+        Value::Array(values) => 1 + values.iter().map(value_depth).max().unwrap_or(0),
+        Value::Object(object) => 1 + object.values().map(value_depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// The error returned when a documents payload exceeds one of the `PayloadLimits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadLimitError {
+    TooManyDocuments { limit: usize },
+    DocumentTooLarge { size: usize, limit: usize },
+    DocumentTooDeep { depth: usize, limit: usize },
+}
+
+impl fmt::Display for PayloadLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadLimitError::TooManyDocuments { limit } => {
+                write!(f, "the payload contains more than {} documents", limit)
+            },
+            PayloadLimitError::DocumentTooLarge { size, limit } => {
+                write!(f, "a document is {} bytes long which exceeds the limit of {} bytes", size, limit)
+            },
+            PayloadLimitError::DocumentTooDeep { depth, limit } => {
+                write!(f, "a document has a nesting depth of {} which exceeds the limit of {}", depth, limit)
+            },
+        }
+    }
+}
+
+impl Error for PayloadLimitError { }
 
 pub struct TransformOutput {
     pub primary_key: String,
@@ -27,6 +113,9 @@ pub struct TransformOutput {
     pub replaced_documents_ids: RoaringBitmap,
     pub documents_count: usize,
     pub documents_file: File,
+    /// The number of CSV fields that were not valid UTF-8,
+    /// the invalid sequences were replaced by the replacement character.
+    pub invalid_utf8_fields: usize,
 }
 
 /// Extract the external ids, deduplicate and compute the new internal documents ids
@@ -46,6 +135,10 @@ pub struct Transform<'t, 'i> {
     pub max_memory: Option<usize>,
     pub index_documents_method: IndexDocumentsMethod,
     pub autogenerate_docids: bool,
+    pub payload_limits: PayloadLimits,
+    /// Fails on the CSV fields that are not valid UTF-8 instead of replacing the invalid sequences.
+    pub strict_utf8: bool,
+    pub thread_pool: Option<&'t ThreadPool>,
 }
 
 impl Transform<'_, '_> {
@@ -75,13 +168,9 @@ impl Transform<'_, '_> {
         R: Read,
         F: Fn(UpdateIndexingStep) + Sync,
     {
-        let mut fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
-        let mut fields_distribution = self.index.fields_distribution(self.rtxn)?;
-        let external_documents_ids = self.index.external_documents_ids(self.rtxn).unwrap();
-
         // Deserialize the whole batch of documents in memory.
-        let mut documents: Peekable<Box<dyn Iterator<Item=serde_json::Result<Map<String, Value>>>>> = if is_stream {
-            let iter = serde_json::Deserializer::from_reader(reader).into_iter();
+        let documents: Peekable<Box<dyn Iterator<Item=anyhow::Result<Map<String, Value>>> + '_>> = if is_stream {
+            let iter = serde_json::Deserializer::from_reader(reader).into_iter().map(|r| r.map_err(Into::into));
             let iter = Box::new(iter) as Box<dyn Iterator<Item=_>>;
             iter.peekable()
         } else {
@@ -91,6 +180,42 @@ impl Transform<'_, '_> {
             iter.peekable()
         };
 
+        self.output_from_documents(documents, progress_callback)
+    }
+
+    #[cfg(feature = "parquet")]
+    pub fn output_from_parquet<R, F>(self, mut reader: R, progress_callback: F) -> anyhow::Result<TransformOutput>
+    where
+        R: Read,
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        // Parquet files must be read from their footer, we spool the payload into a temporary file.
+        let mut file = tempfile::tempfile()?;
+        std::io::copy(&mut reader, &mut file)?;
+
+        let file_reader = SerializedFileReader::new(file)?;
+        let documents = file_reader.get_row_iter(None)?.map(|row| Ok(parquet_row_to_json(&row)));
+        let documents = Box::new(documents) as Box<dyn Iterator<Item=_>>;
+
+        self.output_from_documents(documents.peekable(), progress_callback)
+    }
+
+    /// Generate the `TransformOutput` from documents represented as JSON objects,
+    /// this is used by the JSON, the JSON stream and the Parquet formats.
+    fn output_from_documents<'d, F>(
+        self,
+        mut documents: Peekable<Box<dyn Iterator<Item=anyhow::Result<Map<String, Value>>> + 'd>>,
+        progress_callback: F,
+    ) -> anyhow::Result<TransformOutput>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        let mut fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        let mut fields_distribution = self.index.fields_distribution(self.rtxn)?;
+        let external_documents_ids = self.index.external_documents_ids(self.rtxn).unwrap();
+
         // We extract the primary key from the first document in
         // the batch if it hasn't already been defined in the index
         let first = documents.peek().and_then(|r| r.as_ref().ok());
@@ -112,6 +237,7 @@ impl Transform<'_, '_> {
                 replaced_documents_ids: RoaringBitmap::new(),
                 documents_count: 0,
                 documents_file: tempfile::tempfile()?,
+                invalid_utf8_fields: 0,
             });
         }
 
@@ -141,6 +267,8 @@ impl Transform<'_, '_> {
 
         for result in documents {
             let document = result?;
+            self.payload_limits.check_documents_count(documents_count + 1)?;
+            self.payload_limits.check_nesting_depth(&document)?;
 
             if self.log_every_n.map_or(false, |len| documents_count % len == 0) {
                 progress_callback(UpdateIndexingStep::TransformFromUserIntoGenericFormat {
@@ -193,6 +321,8 @@ impl Transform<'_, '_> {
                 }
             }
 
+            self.payload_limits.check_document_size(obkv_buffer.len())?;
+
             // We use the extracted/generated user id as the key for this document.
             sorter.insert(external_id.as_bytes(), &obkv_buffer)?;
             documents_count += 1;
@@ -230,7 +360,7 @@ impl Transform<'_, '_> {
         let external_documents_ids = self.index.external_documents_ids(self.rtxn).unwrap();
 
         let mut csv = csv::Reader::from_reader(reader);
-        let headers = csv.headers()?;
+        let headers = csv.headers()?.clone();
 
         let mut fields_ids = Vec::new();
         // Generate the new fields ids based on the current fields ids and this CSV headers.
@@ -277,65 +407,70 @@ impl Transform<'_, '_> {
             self.max_memory,
         );
 
-        // We write into the sorter to merge and deduplicate the documents
-        // based on the external ids.
-        let mut json_buffer = Vec::new();
-        let mut obkv_buffer = Vec::new();
-        let mut uuid_buffer = [0; uuid::adapter::Hyphenated::LENGTH];
+        // We read the records by batches of chunks aligned on the records boundaries, parse the
+        // chunks of a batch in parallel and write them into the sorter in the input order to stay
+        // deterministic, only a single batch is kept in memory at a time.
+        let chunks_per_batch = match self.thread_pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        };
+        let payload_limits = &self.payload_limits;
+        let strict_utf8 = self.strict_utf8;
         let mut documents_count = 0;
-
-        let mut fields_ids_distribution = HashMap::new();
-
-        let mut record = csv::StringRecord::new();
-        while csv.read_record(&mut record)? {
-            obkv_buffer.clear();
-            let mut writer = obkv::KvWriter::new(&mut obkv_buffer);
-
-            if self.log_every_n.map_or(false, |len| documents_count % len == 0) {
-                progress_callback(UpdateIndexingStep::TransformFromUserIntoGenericFormat {
-                    documents_seen: documents_count,
-                });
+        let mut records_count = 0;
+        let mut invalid_utf8_fields = 0;
+        let mut batch = Vec::with_capacity(chunks_per_batch);
+
+        loop {
+            batch.clear();
+            while batch.len() < chunks_per_batch {
+                let chunk = read_csv_chunk(&mut csv, CSV_CHUNK_SIZE)?;
+                if chunk.is_empty() { break }
+                // We check the number of documents while reading, before parsing them.
+                records_count += chunk.len();
+                self.payload_limits.check_documents_count(records_count)?;
+                batch.push(chunk);
             }
 
-            // We extract the user id if we know where it is or generate an UUID V4 otherwise.
-            let external_id = match primary_key_pos {
-                Some(pos) => {
-                    let external_id = &record[pos];
-                    // We validate the document id [a-zA-Z0-9\-_].
-                    match validate_document_id(&external_id) {
-                        Some(valid) => valid,
-                        None => return Err(anyhow!("invalid document id: {:?}", external_id)),
-                    }
-                },
-                None => uuid::Uuid::new_v4().to_hyphenated().encode_lower(&mut uuid_buffer),
-            };
+            if batch.is_empty() { break }
 
-            // When the primary_key_field_id is found in the fields ids list
-            // we return the generated document id instead of the record field.
-            let iter = fields_ids.iter()
-                .map(|(fi, i)| {
-                    let field = if *fi == primary_key_id { external_id } else { &record[*i] };
-                    (fi, field)
-                });
+            let parse_chunks = || {
+                batch.par_iter()
+                    .map(|records| {
+                        parse_csv_records(records, &fields_ids, primary_key_pos, primary_key_id, payload_limits, strict_utf8)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let chunks_documents = match self.thread_pool {
+                Some(pool) => pool.install(parse_chunks),
+                None => parse_chunks(),
+            };
 
-            // We retrieve the field id based on the fields ids map fields ids order.
-            for (field_id, field) in iter {
-                // We serialize the attribute values as JSON strings.
-                json_buffer.clear();
-                serde_json::to_writer(&mut json_buffer, &field)?;
-                writer.insert(*field_id, &json_buffer)?;
+            // We write into the sorter to merge and deduplicate the documents
+            // based on the external ids.
+            for result in chunks_documents {
+                let (documents, invalid_fields) = result?;
+                invalid_utf8_fields += invalid_fields;
+                for (external_id, obkv) in documents {
+                    if self.log_every_n.map_or(false, |len| documents_count % len == 0) {
+                        progress_callback(UpdateIndexingStep::TransformFromUserIntoGenericFormat {
+                            documents_seen: documents_count,
+                        });
+                    }
 
-                *fields_ids_distribution.entry(*field_id).or_insert(0) += 1;
+                    // We use the extracted/generated user id as the key for this document.
+                    sorter.insert(external_id, &obkv)?;
+                    documents_count += 1;
+                }
             }
-
-            // We use the extracted/generated user id as the key for this document.
-            sorter.insert(external_id, &obkv_buffer)?;
-            documents_count += 1;
         }
 
-        for (field_id, count) in fields_ids_distribution {
-            let field_name = fields_ids_map.name(field_id).unwrap();
-            *fields_distribution.entry(field_name.to_string()).or_default() += count;
+        // Every record contains all the fields of the headers.
+        if documents_count != 0 {
+            for (field_id, _) in &fields_ids {
+                let field_name = fields_ids_map.name(*field_id).unwrap();
+                *fields_distribution.entry(field_name.to_string()).or_default() += documents_count as u64;
+            }
         }
 
         progress_callback(UpdateIndexingStep::TransformFromUserIntoGenericFormat {
@@ -348,7 +483,7 @@ impl Transform<'_, '_> {
             .name(primary_key_id)
             .map(String::from)
             .expect("Primary key must be present in fields id map");
-        self.output_from_sorter(
+        let output = self.output_from_sorter(
             sorter,
             primary_key_name,
             fields_ids_map,
@@ -356,7 +491,9 @@ impl Transform<'_, '_> {
             documents_count,
             external_documents_ids,
             progress_callback,
-        )
+        )?;
+
+        Ok(TransformOutput { invalid_utf8_fields, ..output })
     }
 
     /// Generate the `TransformOutput` based on the given sorter that can be generated from any
@@ -471,6 +608,7 @@ impl Transform<'_, '_> {
             replaced_documents_ids,
             documents_count,
             documents_file,
+            invalid_utf8_fields: 0,
         })
     }
 
@@ -526,6 +664,7 @@ impl Transform<'_, '_> {
             replaced_documents_ids: RoaringBitmap::default(),
             documents_count,
             documents_file,
+            invalid_utf8_fields: 0,
         })
     }
 }
@@ -566,6 +705,122 @@ fn compute_primary_key_pair(
     }
 }
 
+/// Reads the next records of the CSV, the chunk is at least `chunk_size` bytes long
+/// unless the end of the CSV is reached, it always ends at the end of a record.
+fn read_csv_chunk<R: Read>(csv: &mut csv::Reader<R>, chunk_size: usize) -> anyhow::Result<Vec<csv::ByteRecord>> {
+    let mut records = Vec::new();
+    let start = csv.position().byte();
+
+    let mut record = csv::ByteRecord::new();
+    while csv.read_byte_record(&mut record)? {
+        records.push(record.clone());
+        if (csv.position().byte() - start) as usize >= chunk_size {
+            break;
+        }
+    }
+
+    Ok(records)
+}
+
+/// Parses the given CSV records, without headers, into obkvs associated with their external id,
+/// also returns the number of fields that were not valid UTF-8 when not in strict mode.
+fn parse_csv_records(
+    records: &[csv::ByteRecord],
+    fields_ids: &[(FieldId, usize)],
+    primary_key_pos: Option<usize>,
+    primary_key_id: FieldId,
+    payload_limits: &PayloadLimits,
+    strict_utf8: bool,
+) -> anyhow::Result<(Vec<(String, Vec<u8>)>, usize)>
+{
+    let mut documents = Vec::with_capacity(records.len());
+    let mut invalid_utf8_fields = 0;
+    let mut json_buffer = Vec::new();
+    let mut uuid_buffer = [0; uuid::adapter::Hyphenated::LENGTH];
+
+    for record in records {
+        let mut fields = Vec::with_capacity(record.len());
+        for field in record.iter() {
+            match str::from_utf8(field) {
+                Ok(field) => fields.push(Cow::Borrowed(field)),
+                Err(e) if strict_utf8 => return Err(anyhow!("invalid UTF-8 in a CSV field: {}", e)),
+                Err(_) => {
+                    invalid_utf8_fields += 1;
+                    fields.push(String::from_utf8_lossy(field));
+                },
+            }
+        }
+
+        let mut obkv_buffer = Vec::new();
+        let mut writer = obkv::KvWriter::new(&mut obkv_buffer);
+
+        // We extract the user id if we know where it is or generate an UUID V4 otherwise.
+        let external_id = match primary_key_pos {
+            Some(pos) => {
+                let external_id = &*fields[pos];
+                // We validate the document id [a-zA-Z0-9\-_].
+                match validate_document_id(&external_id) {
+                    Some(valid) => valid,
+                    None => return Err(anyhow!("invalid document id: {:?}", external_id)),
+                }
+            },
+            None => uuid::Uuid::new_v4().to_hyphenated().encode_lower(&mut uuid_buffer),
+        };
+
+        // When the primary_key_field_id is found in the fields ids list
+        // we return the generated document id instead of the record field.
+        let iter = fields_ids.iter()
+            .map(|(fi, i)| {
+                let field = if *fi == primary_key_id { external_id } else { &*fields[*i] };
+                (fi, field)
+            });
+
+        // We retrieve the field id based on the fields ids map fields ids order.
+        for (field_id, field) in iter {
+            // We serialize the attribute values as JSON strings.
+            json_buffer.clear();
+            serde_json::to_writer(&mut json_buffer, &field)?;
+            writer.insert(*field_id, &json_buffer)?;
+        }
+
+        payload_limits.check_document_size(obkv_buffer.len())?;
+        documents.push((external_id.to_string(), obkv_buffer));
+    }
+
+    Ok((documents, invalid_utf8_fields))
+}
+
+/// Converts a Parquet row into a JSON object, the columns become the fields.
+#[cfg(feature = "parquet")]
+fn parquet_row_to_json(row: &parquet::record::Row) -> Map<String, Value> {
+    row.get_column_iter().map(|(name, field)| (name.clone(), parquet_field_to_json(field))).collect()
+}
+
+#[cfg(feature = "parquet")]
+fn parquet_field_to_json(field: &parquet::record::Field) -> Value {
+    use parquet::record::Field;
+
+    match field {
+        Field::Null => Value::Null,
+        Field::Bool(boolean) => Value::Bool(*boolean),
+        Field::Byte(number) => Value::from(*number),
+        Field::Short(number) => Value::from(*number),
+        Field::Int(number) => Value::from(*number),
+        Field::Long(number) => Value::from(*number),
+        Field::UByte(number) => Value::from(*number),
+        Field::UShort(number) => Value::from(*number),
+        Field::UInt(number) => Value::from(*number),
+        Field::ULong(number) => Value::from(*number),
+        Field::Float(number) => Value::from(*number),
+        Field::Double(number) => Value::from(*number),
+        Field::Str(string) => Value::String(string.clone()),
+        Field::Group(row) => Value::Object(parquet_row_to_json(row)),
+        Field::ListInternal(list) => Value::Array(list.elements().iter().map(parquet_field_to_json).collect()),
+        // The dates, timestamps, decimals, bytes and maps are kept in their displayed form.
+        other => Value::String(other.to_string()),
+    }
+}
+
 /// Only the last value associated with an id is kept.
 fn keep_latest_obkv(_key: &[u8], obkvs: &[Cow<[u8]>]) -> anyhow::Result<Vec<u8>> {
     obkvs.last().context("no last value").map(|last| last.clone().into_owned())
@@ -597,6 +852,41 @@ fn validate_document_id(document_id: &str) -> Option<&str> {
 mod test {
     use super::*;
 
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_fields_to_json() {
+        use parquet::record::Field;
+        use serde_json::json;
+
+        assert_eq!(parquet_field_to_json(&Field::Null), Value::Null);
+        assert_eq!(parquet_field_to_json(&Field::Bool(true)), json!(true));
+        assert_eq!(parquet_field_to_json(&Field::Int(-42)), json!(-42));
+        assert_eq!(parquet_field_to_json(&Field::ULong(42)), json!(42));
+        assert_eq!(parquet_field_to_json(&Field::Double(4.5)), json!(4.5));
+        assert_eq!(parquet_field_to_json(&Field::Double(f64::NAN)), Value::Null);
+        assert_eq!(parquet_field_to_json(&Field::Str("hello".into())), json!("hello"));
+    }
+
+    #[test]
+    fn read_csv_chunks_on_records_boundaries() {
+        let content = "id,name\n0,kevin\n1,\"kevina\nbenoit\"\n2,bernard\n3,clementine\n";
+        let mut csv = csv::Reader::from_reader(content.as_bytes());
+        csv.headers().unwrap();
+
+        let mut chunks = Vec::new();
+        loop {
+            let chunk = read_csv_chunk(&mut csv, 16).unwrap();
+            if chunk.is_empty() { break }
+            let ids: Vec<_> = chunk.iter().map(|record| record.get(0).unwrap().to_vec()).collect();
+            chunks.push(ids);
+        }
+
+        assert_eq!(chunks, vec![
+            vec![b"0".to_vec(), b"1".to_vec()],
+            vec![b"2".to_vec(), b"3".to_vec()],
+        ]);
+    }
+
     mod compute_primary_key {
         use super::compute_primary_key_pair;
         use super::FieldsIdsMap;
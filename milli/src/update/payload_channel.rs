@@ -0,0 +1,122 @@
+use std::io;
+
+use crossbeam_channel::{Receiver, Sender};
+
+enum Message {
+    Chunk(Vec<u8>),
+    Error(io::Error),
+    End,
+}
+
+/// Creates a bounded channel that bridges a producer of payload chunks, like an async task
+/// downloading the payload from an object store, and the `io::Read` expected by the updates.
+///
+/// The capacity is the number of chunks that can be buffered before the sender blocks.
+pub fn payload_channel(capacity: usize) -> (PayloadSender, PayloadReader) {
+    let (sender, receiver) = crossbeam_channel::bounded(capacity);
+    let reader = PayloadReader { receiver, chunk: Vec::new(), offset: 0, ended: false };
+    (PayloadSender { sender }, reader)
+}
+
+/// The sending half of a payload channel, the payload must be explicitly finished,
+/// a sender dropped before that makes the reader return an unexpected EOF error.
+pub struct PayloadSender {
+    sender: Sender<Message>,
+}
+
+impl PayloadSender {
+    /// Sends the next chunk of the payload, blocks if the channel is full.
+    pub fn send(&self, chunk: Vec<u8>) -> io::Result<()> {
+        self.send_message(Message::Chunk(chunk))
+    }
+
+    /// Makes the reader return the given error, the payload can't be read any further.
+    pub fn abort(self, error: io::Error) -> io::Result<()> {
+        self.send_message(Message::Error(error))
+    }
+
+    /// Marks the end of the payload.
+    pub fn finish(self) -> io::Result<()> {
+        self.send_message(Message::End)
+    }
+
+    fn send_message(&self, message: Message) -> io::Result<()> {
+        self.sender.send(message).map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "the payload reader has been dropped")
+        })
+    }
+}
+
+/// The receiving half of a payload channel, reads the chunks in the order they were sent.
+pub struct PayloadReader {
+    receiver: Receiver<Message>,
+    chunk: Vec<u8>,
+    offset: usize,
+    ended: bool,
+}
+
+impl io::Read for PayloadReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.chunk.len() {
+            if self.ended {
+                return Ok(0);
+            }
+
+            match self.receiver.recv() {
+                Ok(Message::Chunk(chunk)) => {
+                    self.chunk = chunk;
+                    self.offset = 0;
+                },
+                Ok(Message::Error(error)) => {
+                    self.ended = true;
+                    return Err(error);
+                },
+                Ok(Message::End) => self.ended = true,
+                Err(_) => {
+                    let message = "the payload sender has been dropped before the end of the payload";
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, message));
+                },
+            }
+        }
+
+        let len = buf.len().min(self.chunk.len() - self.offset);
+        buf[..len].copy_from_slice(&self.chunk[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn read_streamed_chunks() {
+        let (sender, mut reader) = payload_channel(1);
+        let handle = std::thread::spawn(move || {
+            sender.send(b"id,name\n".to_vec()).unwrap();
+            sender.send(Vec::new()).unwrap();
+            sender.send(b"0,kevin\n".to_vec()).unwrap();
+            sender.finish().unwrap();
+        });
+
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        handle.join().unwrap();
+        assert_eq!(content, "id,name\n0,kevin\n");
+    }
+
+    #[test]
+    fn dropped_sender() {
+        let (sender, mut reader) = payload_channel(1);
+        sender.send(b"id,name\n".to_vec()).unwrap();
+        drop(sender);
+
+        let mut content = Vec::new();
+        let error = reader.read_to_end(&mut content).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(content, b"id,name\n");
+    }
+}
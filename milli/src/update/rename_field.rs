@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context};
+use chrono::Utc;
+
+use crate::{Criterion, GEO_FIELD_NAME, Index};
+
+/// Renames a field of the documents by only rewriting the metadata of the index.
+///
+/// The documents and the postings databases only store the field ids, the field keeps
+/// its id and the settings that reference it by name are updated, no reindexing is needed.
+pub struct RenameField<'t, 'u, 'i> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+}
+
+impl<'t, 'u, 'i> RenameField<'t, 'u, 'i> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+    ) -> RenameField<'t, 'u, 'i>
+    {
+        RenameField { wtxn, index }
+    }
+
+    pub fn execute(self, old_name: &str, new_name: &str) -> anyhow::Result<()> {
+        if old_name == new_name {
+            return Ok(());
+        }
+
+        // The geo field is recognized by its name when the documents are indexed.
+        if old_name == GEO_FIELD_NAME || new_name == GEO_FIELD_NAME {
+            bail!("the {} field can't be renamed and no field can be renamed into it", GEO_FIELD_NAME);
+        }
+
+        let mut fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        if fields_ids_map.id(new_name).is_some() {
+            bail!("can't rename {:?} into {:?}, a field named {:?} already exists", old_name, new_name, new_name);
+        }
+        fields_ids_map.rename(old_name, new_name)
+            .with_context(|| format!("can't rename the unknown field {:?}", old_name))?;
+
+        self.index.set_updated_at(self.wtxn, &Utc::now())?;
+        self.index.put_fields_ids_map(self.wtxn, &fields_ids_map)?;
+
+        let mut distribution = self.index.fields_distribution(self.wtxn)?;
+        if rename_key(&mut distribution, old_name, new_name) {
+            self.index.put_fields_distribution(self.wtxn, &distribution)?;
+        }
+
+        if self.index.primary_key(self.wtxn)? == Some(old_name) {
+            self.index.put_primary_key(self.wtxn, new_name)?;
+        }
+
+        if let Some(fields) = self.index.displayed_fields(self.wtxn)? {
+            if let Some(fields) = rename_in_list(fields, old_name, new_name) {
+                let fields: Vec<_> = fields.iter().map(String::as_str).collect();
+                self.index.put_displayed_fields(self.wtxn, &fields)?;
+            }
+        }
+
+        if let Some(fields) = self.index.searchable_fields(self.wtxn)? {
+            if let Some(fields) = rename_in_list(fields, old_name, new_name) {
+                let fields: Vec<_> = fields.iter().map(String::as_str).collect();
+                self.index.put_searchable_fields(self.wtxn, &fields)?;
+            }
+        }
+
+        let fields = self.index.exact_attributes(self.wtxn)?;
+        if let Some(fields) = rename_in_list(fields, old_name, new_name) {
+            let fields: Vec<_> = fields.iter().map(String::as_str).collect();
+            self.index.put_exact_attributes(self.wtxn, &fields)?;
+        }
+
+        let mut faceted_fields = self.index.faceted_fields(self.wtxn)?;
+        if rename_key(&mut faceted_fields, old_name, new_name) {
+            self.index.put_faceted_fields(self.wtxn, &faceted_fields)?;
+        }
+
+        let mut sortable_fields = self.index.sortable_fields(self.wtxn)?;
+        if rename_key(&mut sortable_fields, old_name, new_name) {
+            self.index.put_sortable_fields(self.wtxn, &sortable_fields)?;
+        }
+
+        let mut features = self.index.filterable_features(self.wtxn)?;
+        if rename_key(&mut features, old_name, new_name) {
+            self.index.put_filterable_features(self.wtxn, &features)?;
+        }
+
+        let mut normalizations = self.index.facet_normalizations(self.wtxn)?;
+        if rename_key(&mut normalizations, old_name, new_name) {
+            self.index.put_facet_normalizations(self.wtxn, &normalizations)?;
+        }
+
+        let mut max_indexed_tokens = self.index.max_indexed_tokens(self.wtxn)?;
+        if rename_key(&mut max_indexed_tokens, old_name, new_name) {
+            self.index.put_max_indexed_tokens(self.wtxn, &max_indexed_tokens)?;
+        }
+
+        if self.index.distinct_attribute(self.wtxn)? == Some(old_name) {
+            self.index.put_distinct_attribute(self.wtxn, new_name)?;
+        }
+
+        if let Some(fields) = self.index.distinct_attributes(self.wtxn)? {
+            if let Some(fields) = rename_in_list(fields, old_name, new_name) {
+                let fields: Vec<_> = fields.iter().map(String::as_str).collect();
+                self.index.put_distinct_attributes(self.wtxn, &fields)?;
+            }
+        }
+
+        let mut criteria = self.index.criteria(self.wtxn)?;
+        let mut criteria_changed = false;
+        for criterion in &mut criteria {
+            match criterion {
+                Criterion::Asc(field) | Criterion::Desc(field) if field == old_name => {
+                    *field = new_name.to_string();
+                    criteria_changed = true;
+                },
+                _ => (),
+            }
+        }
+        if criteria_changed {
+            self.index.put_criteria(self.wtxn, &criteria)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces the old name by the new one in the list, returns `None` if the old name isn't part of it.
+fn rename_in_list(names: Vec<&str>, old_name: &str, new_name: &str) -> Option<Vec<String>> {
+    if !names.contains(&old_name) {
+        return None;
+    }
+
+    let names = names.into_iter()
+        .map(|name| if name == old_name { new_name } else { name })
+        .map(ToOwned::to_owned)
+        .collect();
+    Some(names)
+}
+
+/// Moves the value of the old name under the new one, returns `true` if the map has been modified.
+fn rename_key<V>(map: &mut HashMap<String, V>, old_name: &str, new_name: &str) -> bool {
+    match map.remove(old_name) {
+        Some(value) => {
+            map.insert(new_name.to_owned(), value);
+            true
+        },
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+    use maplit::hashmap;
+
+    use crate::facet::{FacetType, FilterFeatures};
+    use crate::update::{IndexDocuments, Settings, UpdateFormat};
+    use crate::{AscDesc, FacetCondition, Member};
+    use super::*;
+
+    #[test]
+    fn rename_field_keeps_settings_and_documents() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_displayed_fields(vec!["name".into(), "age".into()]);
+        builder.set_searchable_fields(vec!["name".into()]);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into() });
+        builder.set_criteria(vec![Criterion::Words, Criterion::Sort, Criterion::Asc("age".into())]);
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,name,age\n0,kevin,20\n1,kevina,21\n2,benoit,34\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+
+        let age_id = index.fields_ids_map(&wtxn).unwrap().id("age").unwrap();
+        RenameField::new(&mut wtxn, &index).execute("age", "years").unwrap();
+        RenameField::new(&mut wtxn, &index).execute("name", "firstname").unwrap();
+        RenameField::new(&mut wtxn, &index).execute("id", "uid").unwrap();
+
+        // Renaming into an existing field or an unknown field fails.
+        assert!(RenameField::new(&mut wtxn, &index).execute("years", "uid").is_err());
+        assert!(RenameField::new(&mut wtxn, &index).execute("age", "birthday").is_err());
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        assert_eq!(fields_ids_map.id("years"), Some(age_id));
+        assert_eq!(fields_ids_map.id("age"), None);
+        assert_eq!(index.primary_key(&rtxn).unwrap(), Some("uid"));
+        assert_eq!(index.displayed_fields(&rtxn).unwrap(), Some(vec!["firstname", "years"]));
+        assert_eq!(index.searchable_fields(&rtxn).unwrap(), Some(vec!["firstname"]));
+        assert_eq!(index.faceted_fields(&rtxn).unwrap(), hashmap!{ "years".to_string() => FacetType::Number });
+        assert_eq!(index.criteria(&rtxn).unwrap(), vec![Criterion::Words, Criterion::Sort, Criterion::Asc("years".to_string())]);
+        assert_eq!(index.fields_distribution(&rtxn).unwrap().get("years"), Some(&3));
+
+        // The facet and words postings are still used under the new names.
+        let condition = FacetCondition::from_str(&rtxn, &index, "years > 20").unwrap();
+        let result = index.search(&rtxn).facet_condition(condition).execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+
+        let result = index.search(&rtxn)
+            .query("kevin")
+            .sort_by(vec![AscDesc::Desc(Member::Field("years".to_string()))])
+            .execute()
+            .unwrap();
+        let kevina = index.external_documents_ids(&rtxn).unwrap().get("1").unwrap();
+        assert_eq!(result.documents_ids.first(), Some(&kevina));
+        drop(rtxn);
+
+        // The new documents are indexed with the renamed fields.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"uid,firstname,years\n3,kevinoo,40\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 6);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.fields_ids_map(&rtxn).unwrap().len(), 3);
+        let condition = FacetCondition::from_str(&rtxn, &index, "years > 20").unwrap();
+        let result = index.search(&rtxn).facet_condition(condition).execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 3);
+    }
+
+    #[test]
+    fn rename_filterable_field() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "genre".into() => "string".into() });
+        builder.set_filterable_features(hashmap!{ "genre".into() => FilterFeatures::equality_only() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,name,genre\n0,kevin,rock\n1,kevina,jazz\n2,benoit,rock\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+
+        RenameField::new(&mut wtxn, &index).execute("genre", "category").unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(
+            index.filterable_features(&rtxn).unwrap(),
+            hashmap!{ "category".to_string() => FilterFeatures::equality_only() },
+        );
+
+        let condition = FacetCondition::from_str(&rtxn, &index, "category = rock").unwrap();
+        let result = index.search(&rtxn).facet_condition(condition).execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+
+        // The old name is unknown and the filter features follow the field.
+        assert!(FacetCondition::from_str(&rtxn, &index, "genre = rock").is_err());
+        assert!(FacetCondition::from_str(&rtxn, &index, "category > rock").is_err());
+    }
+}
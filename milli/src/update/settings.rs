@@ -1,7 +1,7 @@
 use std::collections::{BTreeSet, HashMap};
 use std::str::FromStr;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use chrono::Utc;
 use grenad::CompressionType;
 use itertools::Itertools;
@@ -9,11 +9,13 @@ use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
 use rayon::ThreadPool;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{FieldsIdsMap, Index};
-use crate::criterion::Criterion;
-use crate::facet::FacetType;
-use crate::update::{ClearDocuments, IndexDocuments, UpdateIndexingStep};
-use crate::update::index_documents::{IndexDocumentsMethod, Transform};
+use crate::{AnalyzerOptions, FieldsIdsMap, Index, TermsMatchingStrategy};
+use crate::criterion::{AttributeRanking, Criterion, default_criteria, validate_criteria};
+use crate::facet::{FacetNormalization, FacetType, FilterFeatures};
+use crate::search::MAX_DISTINCT_ATTRIBUTES;
+use crate::update::{ClearDocuments, Facets, IndexDocuments, UpdateIndexingStep};
+use crate::update::{WordPrefixDocids, WordPrefixPairProximityDocids, WordsPrefixesFst};
+use crate::update::index_documents::{IndexDocumentsMethod, PayloadLimits, Transform};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Setting<T> {
@@ -53,6 +55,161 @@ impl<'de, T: Deserialize<'de>> Deserialize<'de> for Setting<T> {
     }
 }
 
+/// Describes the settings that were changed by a settings update
+/// and the re-processing of the documents that these changes triggered.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SettingsDiff {
+    pub displayed_fields: bool,
+    pub searchable_fields: bool,
+    pub faceted_fields: bool,
+    pub sortable_fields: bool,
+    pub filterable_features: bool,
+    pub facet_normalizations: bool,
+    pub criteria: bool,
+    pub stop_words: bool,
+    pub exact_words: bool,
+    pub exact_attributes: bool,
+    pub distinct_attribute: bool,
+    pub distinct_attributes: bool,
+    pub synonyms: bool,
+    pub prefix_databases: bool,
+    pub proximity: bool,
+    pub typo_tolerance: bool,
+    pub min_word_size_for_typos: bool,
+    pub attribute_ranking: bool,
+    pub terms_matching_strategy: bool,
+    pub max_indexed_tokens: bool,
+    pub analyzer_options: bool,
+    /// The warnings about the new settings, like the ranking rules placed in an order
+    /// that makes the ranking slower, the settings are applied anyway.
+    pub warnings: Vec<String>,
+    /// All the documents were reindexed, this is the most expensive re-processing.
+    pub reindexed: bool,
+    /// The facet levels were recomputed without reindexing the documents.
+    pub facet_levels_recomputed: bool,
+    /// The prefix databases were either computed or cleared.
+    pub prefix_databases_recomputed: bool,
+}
+
+impl SettingsDiff {
+    /// Returns `true` if none of the settings were changed.
+    pub fn is_empty(&self) -> bool {
+        let SettingsDiff {
+            displayed_fields,
+            searchable_fields,
+            faceted_fields,
+            sortable_fields,
+            filterable_features,
+            facet_normalizations,
+            criteria,
+            stop_words,
+            exact_words,
+            exact_attributes,
+            distinct_attribute,
+            distinct_attributes,
+            synonyms,
+            prefix_databases,
+            proximity,
+            typo_tolerance,
+            min_word_size_for_typos,
+            attribute_ranking,
+            terms_matching_strategy,
+            max_indexed_tokens,
+            analyzer_options,
+            warnings: _,
+            reindexed: _,
+            facet_levels_recomputed: _,
+            prefix_databases_recomputed: _,
+        } = *self;
+
+        !(displayed_fields
+            || searchable_fields
+            || faceted_fields
+            || sortable_fields
+            || filterable_features
+            || facet_normalizations
+            || criteria
+            || stop_words
+            || exact_words
+            || exact_attributes
+            || distinct_attribute
+            || distinct_attributes
+            || synonyms
+            || prefix_databases
+            || proximity
+            || typo_tolerance
+            || min_word_size_for_typos
+            || attribute_ranking
+            || terms_matching_strategy
+            || max_indexed_tokens
+            || analyzer_options)
+    }
+
+    /// Marks as changed the settings and re-processings of the other diff,
+    /// it describes the changes of two settings updates applied one after the other.
+    pub fn union_with(&mut self, other: &SettingsDiff) {
+        let SettingsDiff {
+            displayed_fields,
+            searchable_fields,
+            faceted_fields,
+            sortable_fields,
+            filterable_features,
+            facet_normalizations,
+            criteria,
+            stop_words,
+            exact_words,
+            exact_attributes,
+            distinct_attribute,
+            distinct_attributes,
+            synonyms,
+            prefix_databases,
+            proximity,
+            typo_tolerance,
+            min_word_size_for_typos,
+            attribute_ranking,
+            terms_matching_strategy,
+            max_indexed_tokens,
+            analyzer_options,
+            ref warnings,
+            reindexed,
+            facet_levels_recomputed,
+            prefix_databases_recomputed,
+        } = *other;
+
+        self.displayed_fields |= displayed_fields;
+        self.searchable_fields |= searchable_fields;
+        self.faceted_fields |= faceted_fields;
+        self.sortable_fields |= sortable_fields;
+        self.filterable_features |= filterable_features;
+        self.facet_normalizations |= facet_normalizations;
+        self.criteria |= criteria;
+        self.stop_words |= stop_words;
+        self.exact_words |= exact_words;
+        self.exact_attributes |= exact_attributes;
+        self.distinct_attribute |= distinct_attribute;
+        self.distinct_attributes |= distinct_attributes;
+        self.synonyms |= synonyms;
+        self.prefix_databases |= prefix_databases;
+        self.proximity |= proximity;
+        self.typo_tolerance |= typo_tolerance;
+        self.min_word_size_for_typos |= min_word_size_for_typos;
+        self.attribute_ranking |= attribute_ranking;
+        self.terms_matching_strategy |= terms_matching_strategy;
+        self.max_indexed_tokens |= max_indexed_tokens;
+        self.analyzer_options |= analyzer_options;
+        self.warnings.extend(warnings.iter().cloned());
+        self.reindexed |= reindexed;
+        self.facet_levels_recomputed |= facet_levels_recomputed;
+        self.prefix_databases_recomputed |= prefix_databases_recomputed;
+    }
+
+    /// Returns `true` if these changes require the documents to be reindexed.
+    fn requires_reindexing(&self) -> bool {
+        self.stop_words || self.faceted_fields || self.sortable_fields || self.synonyms || self.searchable_fields
+            || self.proximity || self.max_indexed_tokens || self.analyzer_options || self.facet_normalizations
+    }
+}
+
 pub struct Settings<'a, 't, 'u, 'i> {
     wtxn: &'t mut heed::RwTxn<'i, 'u>,
     index: &'i Index,
@@ -69,10 +226,26 @@ pub struct Settings<'a, 't, 'u, 'i> {
     searchable_fields: Setting<Vec<String>>,
     displayed_fields: Setting<Vec<String>>,
     faceted_fields: Setting<HashMap<String, String>>,
-    criteria: Setting<Vec<String>>,
+    sortable_fields: Setting<HashMap<String, String>>,
+    filterable_features: Setting<HashMap<String, FilterFeatures>>,
+    facet_normalizations: Setting<HashMap<String, FacetNormalization>>,
+    criteria: Setting<Vec<Criterion>>,
     stop_words: Setting<BTreeSet<String>>,
+    exact_words: Setting<BTreeSet<String>>,
+    exact_attributes: Setting<Vec<String>>,
     distinct_attribute: Setting<String>,
+    distinct_attributes: Setting<Vec<String>>,
     synonyms: Setting<HashMap<String, Vec<String>>>,
+    prefix_databases: Setting<bool>,
+    proximity: Setting<bool>,
+    typo_tolerance: Setting<bool>,
+    min_word_size_for_one_typo: Setting<u8>,
+    min_word_size_for_two_typos: Setting<u8>,
+    attribute_ranking: Setting<AttributeRanking>,
+    terms_matching_strategy: Setting<TermsMatchingStrategy>,
+    max_indexed_tokens: Setting<HashMap<String, usize>>,
+    analyzer_options: Setting<AnalyzerOptions>,
+    dry_run: bool,
 }
 
 impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
@@ -95,18 +268,42 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
             searchable_fields: Setting::NotSet,
             displayed_fields: Setting::NotSet,
             faceted_fields: Setting::NotSet,
+            sortable_fields: Setting::NotSet,
+            filterable_features: Setting::NotSet,
+            facet_normalizations: Setting::NotSet,
             criteria: Setting::NotSet,
             stop_words: Setting::NotSet,
+            exact_words: Setting::NotSet,
+            exact_attributes: Setting::NotSet,
             distinct_attribute: Setting::NotSet,
+            distinct_attributes: Setting::NotSet,
             synonyms: Setting::NotSet,
+            prefix_databases: Setting::NotSet,
+            proximity: Setting::NotSet,
+            typo_tolerance: Setting::NotSet,
+            min_word_size_for_one_typo: Setting::NotSet,
+            min_word_size_for_two_typos: Setting::NotSet,
+            attribute_ranking: Setting::NotSet,
+            terms_matching_strategy: Setting::NotSet,
+            max_indexed_tokens: Setting::NotSet,
+            analyzer_options: Setting::NotSet,
+            dry_run: false,
             update_id,
         }
     }
 
+    /// Only validates the settings and returns what would change, nothing
+    /// is written into the index and the documents are not re-processed.
+    pub fn dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
     pub fn reset_searchable_fields(&mut self) {
         self.searchable_fields = Setting::Reset;
     }
 
+    /// The order of the searchable fields defines their importance for the attribute criterion,
+    /// the fields ids are reassigned in this order and the documents are reindexed.
     pub fn set_searchable_fields(&mut self, names: Vec<String>) {
         self.searchable_fields = Setting::Set(names);
     }
@@ -127,11 +324,41 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.faceted_fields = Setting::Set(names_facet_types);
     }
 
+    pub fn reset_sortable_fields(&mut self) {
+        self.sortable_fields = Setting::Reset;
+    }
+
+    /// Sets the fields that can be used by the asc and desc criteria and sorts, along
+    /// with their facet type, these fields don't need to be faceted to be sorted on.
+    pub fn set_sortable_fields(&mut self, names_facet_types: HashMap<String, String>) {
+        self.sortable_fields = Setting::Set(names_facet_types);
+    }
+
+    pub fn reset_filterable_features(&mut self) {
+        self.filterable_features = Setting::Reset;
+    }
+
+    /// Sets the filter features enabled on the given faceted fields,
+    /// the fields that aren't specified have all the features enabled.
+    pub fn set_filterable_features(&mut self, features: HashMap<String, FilterFeatures>) {
+        self.filterable_features = Setting::Set(features);
+    }
+
+    pub fn reset_facet_normalizations(&mut self) {
+        self.facet_normalizations = Setting::Reset;
+    }
+
+    /// Sets how the values of the given string faceted fields are normalized, the fields
+    /// that aren't specified are lowercased and trimmed. The documents are reindexed.
+    pub fn set_facet_normalizations(&mut self, normalizations: HashMap<String, FacetNormalization>) {
+        self.facet_normalizations = Setting::Set(normalizations);
+    }
+
     pub fn reset_criteria(&mut self) {
         self.criteria = Setting::Reset;
     }
 
-    pub fn set_criteria(&mut self, criteria: Vec<String>) {
+    pub fn set_criteria(&mut self, criteria: Vec<Criterion>) {
         self.criteria = Setting::Set(criteria);
     }
 
@@ -147,6 +374,40 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         }
     }
 
+    pub fn reset_exact_words(&mut self) {
+        self.exact_words = Setting::Reset;
+    }
+
+    /// Disables the typo tolerance on these words, useful for
+    /// brand names that are one typo away from common words.
+    pub fn set_exact_words(&mut self, exact_words: BTreeSet<String>) {
+        self.exact_words = if exact_words.is_empty() {
+            Setting::Reset
+        } else {
+            Setting::Set(exact_words)
+        }
+    }
+
+    pub fn reset_exact_attributes(&mut self) {
+        self.exact_attributes = Setting::Reset;
+    }
+
+    /// Disables the typo tolerance on these attributes, useful for
+    /// attributes that contain codes or part numbers.
+    pub fn set_exact_attributes(&mut self, names: Vec<String>) {
+        self.exact_attributes = Setting::Set(names);
+    }
+
+    pub fn reset_max_indexed_tokens(&mut self) {
+        self.max_indexed_tokens = Setting::Reset;
+    }
+
+    /// Only indexes the first words of these fields, useful to exclude the boilerplate
+    /// at the end of long texts, the fields that aren't specified are fully indexed.
+    pub fn set_max_indexed_tokens(&mut self, fields: HashMap<String, usize>) {
+        self.max_indexed_tokens = Setting::Set(fields);
+    }
+
     pub fn reset_distinct_attribute(&mut self) {
         self.distinct_attribute = Setting::Reset;
     }
@@ -155,6 +416,16 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.distinct_attribute = Setting::Set(distinct_attribute);
     }
 
+    pub fn reset_distinct_attributes(&mut self) {
+        self.distinct_attributes = Setting::Reset;
+    }
+
+    /// Sets the faceted fields that compose the distinct key, the documents are distinct
+    /// by the combination of the values of these fields, e.g. the `brand` and the `model`.
+    pub fn set_distinct_attributes(&mut self, names: Vec<String>) {
+        self.distinct_attributes = Setting::Set(names);
+    }
+
     pub fn reset_synonyms(&mut self) {
         self.synonyms = Setting::Reset;
     }
@@ -167,7 +438,84 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         }
     }
 
-    fn reindex<F>(&mut self, cb: &F, old_fields_ids_map: FieldsIdsMap) -> anyhow::Result<()>
+    pub fn reset_prefix_databases(&mut self) {
+        self.prefix_databases = Setting::Reset;
+    }
+
+    /// Disabling the prefix databases speeds up the indexing process,
+    /// it is useful when the as-you-type search isn't used.
+    pub fn set_prefix_databases(&mut self, enabled: bool) {
+        self.prefix_databases = Setting::Set(enabled);
+    }
+
+    pub fn reset_proximity(&mut self) {
+        self.proximity = Setting::Reset;
+    }
+
+    /// Disabling the proximity skips the computation of the words pairs proximities,
+    /// the proximity criterion then becomes a no-op.
+    pub fn set_proximity(&mut self, enabled: bool) {
+        self.proximity = Setting::Set(enabled);
+    }
+
+    pub fn reset_typo_tolerance(&mut self) {
+        self.typo_tolerance = Setting::Reset;
+    }
+
+    /// Disabling the typo tolerance only matches the query words exactly,
+    /// the typo criterion then becomes a no-op.
+    pub fn set_typo_tolerance(&mut self, enabled: bool) {
+        self.typo_tolerance = Setting::Set(enabled);
+    }
+
+    pub fn reset_min_word_size_for_one_typo(&mut self) {
+        self.min_word_size_for_one_typo = Setting::Reset;
+    }
+
+    /// Sets the number of characters from which the query words can be matched with one typo,
+    /// it must not be greater than the minimum word size for two typos.
+    pub fn set_min_word_size_for_one_typo(&mut self, size: u8) {
+        self.min_word_size_for_one_typo = Setting::Set(size);
+    }
+
+    pub fn reset_min_word_size_for_two_typos(&mut self) {
+        self.min_word_size_for_two_typos = Setting::Reset;
+    }
+
+    /// Sets the number of characters from which the query words can be matched with two typos.
+    pub fn set_min_word_size_for_two_typos(&mut self, size: u8) {
+        self.min_word_size_for_two_typos = Setting::Set(size);
+    }
+
+    pub fn reset_attribute_ranking(&mut self) {
+        self.attribute_ranking = Setting::Reset;
+    }
+
+    pub fn set_attribute_ranking(&mut self, ranking: AttributeRanking) {
+        self.attribute_ranking = Setting::Set(ranking);
+    }
+
+    pub fn reset_terms_matching_strategy(&mut self) {
+        self.terms_matching_strategy = Setting::Reset;
+    }
+
+    /// Defines whether the query words are removed from the end of the query or the
+    /// most frequent ones first, for the searches that don't specify a strategy.
+    pub fn set_terms_matching_strategy(&mut self, strategy: TermsMatchingStrategy) {
+        self.terms_matching_strategy = Setting::Set(strategy);
+    }
+
+    pub fn reset_analyzer_options(&mut self) {
+        self.analyzer_options = Setting::Reset;
+    }
+
+    /// Changing the analyzer options reindexes all the documents,
+    /// the queries are analyzed with the same options.
+    pub fn set_analyzer_options(&mut self, options: AnalyzerOptions) {
+        self.analyzer_options = Setting::Set(options);
+    }
+
+    fn reindex<F>(&mut self, cb: &F, old_fields_ids_map: FieldsIdsMap) -> anyhow::Result<bool>
         where
             F: Fn(UpdateIndexingStep, u64) + Sync
     {
@@ -177,7 +525,7 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         // if the settings are set before any document update, we don't need to do anything, and
         // will set the primary key during the first document addition.
         if self.index.number_of_documents(&self.wtxn)? == 0 {
-            return Ok(());
+            return Ok(false);
         }
 
         let transform = Transform {
@@ -191,6 +539,10 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
             max_memory: self.max_memory,
             index_documents_method: IndexDocumentsMethod::ReplaceDocuments,
             autogenerate_docids: false,
+            // The documents are already in the index, there is no payload to limit.
+            payload_limits: PayloadLimits::default(),
+            strict_utf8: false,
+            thread_pool: self.thread_pool,
         };
 
         // There already has been a document addition, the primary key should be set by now.
@@ -217,7 +569,7 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         indexing_builder.chunk_fusing_shrink_size = self.chunk_fusing_shrink_size;
         indexing_builder.thread_pool = self.thread_pool;
         indexing_builder.execute_raw(output, &cb)?;
-        Ok(())
+        Ok(true)
     }
 
     fn update_displayed(&mut self) -> anyhow::Result<bool> {
@@ -262,6 +614,29 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         Ok(true)
     }
 
+    fn update_distinct_attributes(&mut self) -> anyhow::Result<bool> {
+        match self.distinct_attributes {
+            Setting::Set(ref fields) => {
+                // fields are deduplicated, only the first occurrence is taken into account
+                let names: Vec<_> = fields.iter().unique().map(String::as_str).collect();
+                if names.is_empty() || names.len() > MAX_DISTINCT_ATTRIBUTES {
+                    bail!("the distinct key must be composed of 1 to {} fields", MAX_DISTINCT_ATTRIBUTES);
+                }
+
+                // The composite key is built from the values stored in the facet databases.
+                let facet_fields = self.index.indexed_facet_fields(self.wtxn)?;
+                if let Some(name) = names.iter().find(|name| !facet_fields.contains_key(**name)) {
+                    bail!("Can't use {:?} in the distinct attributes as it isn't a faceted field.", name);
+                }
+
+                self.index.put_distinct_attributes(self.wtxn, &names)?;
+            }
+            Setting::Reset => { self.index.delete_distinct_attributes(self.wtxn)?; },
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(true)
+    }
+
     /// Updates the index's searchable attributes. This causes the field map to be recomputed to
     /// reflect the order of the searchable attributes.
     fn update_searchable(&mut self) -> anyhow::Result<bool> {
@@ -325,6 +700,70 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         }
     }
 
+    fn update_exact_words(&mut self) -> anyhow::Result<bool> {
+        match self.exact_words {
+            Setting::Set(ref exact_words) => {
+                // The words are normalized the same way the query words are.
+                let analyzer = Analyzer::new(AnalyzerConfig::<Vec<u8>>::default());
+                let mut words = BTreeSet::new();
+                for word in exact_words {
+                    let analyzed = analyzer.analyze(word);
+                    words.extend(analyzed.tokens().filter(|t| t.is_word()).map(|t| t.text().to_string()));
+                }
+
+                let current = self.index.exact_words(self.wtxn)?;
+                let fst = fst::Set::from_iter(words)?;
+
+                // Does the new FST differ from the previous one?
+                if current.map_or(true, |current| current.as_fst().as_bytes() != fst.as_fst().as_bytes()) {
+                    self.index.put_exact_words(self.wtxn, &fst)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => Ok(self.index.delete_exact_words(self.wtxn)?),
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    fn update_exact_attributes(&mut self) -> anyhow::Result<bool> {
+        match self.exact_attributes {
+            Setting::Set(ref fields) => {
+                let mut fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+                let names: Vec<_> = fields.iter().unique().map(String::as_str).collect();
+                for name in names.iter() {
+                    fields_ids_map
+                        .insert(name)
+                        .context("field id limit exceeded")?;
+                }
+                self.index.put_exact_attributes(self.wtxn, &names)?;
+                self.index.put_fields_ids_map(self.wtxn, &fields_ids_map)?;
+            }
+            Setting::Reset => { self.index.delete_exact_attributes(self.wtxn)?; }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn update_max_indexed_tokens(&mut self) -> anyhow::Result<bool> {
+        match self.max_indexed_tokens {
+            Setting::Set(ref fields) => {
+                let mut fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+                for name in fields.keys() {
+                    fields_ids_map
+                        .insert(name)
+                        .context("field id limit exceeded")?;
+                }
+                self.index.put_max_indexed_tokens(self.wtxn, fields)?;
+                self.index.put_fields_ids_map(self.wtxn, &fields_ids_map)?;
+            }
+            Setting::Reset => { self.index.delete_max_indexed_tokens(self.wtxn)?; }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(true)
+    }
+
     fn update_synonyms(&mut self) -> anyhow::Result<bool> {
         match self.synonyms {
             Setting::Set(ref synonyms) => {
@@ -402,52 +841,415 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         Ok(true)
     }
 
-    fn update_criteria(&mut self) -> anyhow::Result<()> {
-        match self.criteria {
+    fn update_sortable_fields(&mut self) -> anyhow::Result<bool> {
+        match self.sortable_fields {
             Setting::Set(ref fields) => {
-                let faceted_fields = self.index.faceted_fields(&self.wtxn)?;
-                let mut new_criteria = Vec::new();
-                for name in fields {
-                    let criterion = Criterion::from_str(&faceted_fields, &name)?;
-                    new_criteria.push(criterion);
+                let mut fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+                let mut new_sortables = HashMap::new();
+                for (name, ty) in fields {
+                    fields_ids_map.insert(name).context("field id limit exceeded")?;
+                    let ty = FacetType::from_str(&ty)?;
+                    new_sortables.insert(name.clone(), ty);
+                }
+                self.index.put_sortable_fields(self.wtxn, &new_sortables)?;
+                self.index.put_fields_ids_map(self.wtxn, &fields_ids_map)?;
+            }
+            Setting::Reset => { self.index.delete_sortable_fields(self.wtxn)?; }
+            Setting::NotSet => return Ok(false)
+        }
+        Ok(true)
+    }
+
+    /// Checks that the fields that are both faceted and sortable have the same facet type,
+    /// their values are stored only once in the facet databases.
+    fn check_sortable_fields_types(&mut self) -> anyhow::Result<()> {
+        let faceted_fields = self.index.faceted_fields(self.wtxn)?;
+        for (name, ty) in self.index.sortable_fields(self.wtxn)? {
+            match faceted_fields.get(&name) {
+                Some(faceted_ty) if *faceted_ty != ty => bail!(
+                    "the field {:?} can't be sortable as a {} and faceted as a {}", name, ty, faceted_ty,
+                ),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn update_filterable_features(&mut self) -> anyhow::Result<bool> {
+        match self.filterable_features {
+            Setting::Set(ref features) => {
+                let old_features = self.index.filterable_features(self.wtxn)?;
+                if old_features == *features {
+                    return Ok(false);
+                }
+                self.index.put_filterable_features(self.wtxn, features)?;
+            }
+            Setting::Reset => {
+                if !self.index.delete_filterable_features(self.wtxn)? {
+                    return Ok(false);
+                }
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn update_facet_normalizations(&mut self) -> anyhow::Result<bool> {
+        match self.facet_normalizations {
+            Setting::Set(ref normalizations) => {
+                let old_normalizations = self.index.facet_normalizations(self.wtxn)?;
+                if old_normalizations == *normalizations {
+                    return Ok(false);
                 }
-                self.index.put_criteria(self.wtxn, &new_criteria)?;
+                self.index.put_facet_normalizations(self.wtxn, normalizations)?;
             }
-            Setting::Reset => { self.index.delete_criteria(self.wtxn)?; }
+            Setting::Reset => {
+                if !self.index.delete_facet_normalizations(self.wtxn)? {
+                    return Ok(false);
+                }
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn update_prefix_databases(&mut self) -> anyhow::Result<bool> {
+        let old_enabled = self.index.prefix_databases_enabled(self.wtxn)?;
+        match self.prefix_databases {
+            Setting::Set(enabled) => self.index.put_prefix_databases_enabled(self.wtxn, enabled)?,
+            Setting::Reset => { self.index.delete_prefix_databases_enabled(self.wtxn)?; },
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(old_enabled != self.index.prefix_databases_enabled(self.wtxn)?)
+    }
+
+    fn update_proximity(&mut self) -> anyhow::Result<bool> {
+        let old_enabled = self.index.proximity_enabled(self.wtxn)?;
+        match self.proximity {
+            Setting::Set(enabled) => self.index.put_proximity_enabled(self.wtxn, enabled)?,
+            Setting::Reset => { self.index.delete_proximity_enabled(self.wtxn)?; },
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(old_enabled != self.index.proximity_enabled(self.wtxn)?)
+    }
+
+    fn update_typo_tolerance(&mut self) -> anyhow::Result<bool> {
+        let old_enabled = self.index.typo_tolerance_enabled(self.wtxn)?;
+        match self.typo_tolerance {
+            Setting::Set(enabled) => self.index.put_typo_tolerance_enabled(self.wtxn, enabled)?,
+            Setting::Reset => { self.index.delete_typo_tolerance_enabled(self.wtxn)?; },
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(old_enabled != self.index.typo_tolerance_enabled(self.wtxn)?)
+    }
+
+    fn update_min_word_size_for_typos(&mut self) -> anyhow::Result<bool> {
+        let old_one_typo = self.index.min_word_size_for_one_typo(self.wtxn)?;
+        let old_two_typos = self.index.min_word_size_for_two_typos(self.wtxn)?;
+
+        match self.min_word_size_for_one_typo {
+            Setting::Set(size) => self.index.put_min_word_size_for_one_typo(self.wtxn, size)?,
+            Setting::Reset => { self.index.delete_min_word_size_for_one_typo(self.wtxn)?; },
             Setting::NotSet => (),
         }
+        match self.min_word_size_for_two_typos {
+            Setting::Set(size) => self.index.put_min_word_size_for_two_typos(self.wtxn, size)?,
+            Setting::Reset => { self.index.delete_min_word_size_for_two_typos(self.wtxn)?; },
+            Setting::NotSet => (),
+        }
+
+        let one_typo = self.index.min_word_size_for_one_typo(self.wtxn)?;
+        let two_typos = self.index.min_word_size_for_two_typos(self.wtxn)?;
+        if one_typo > two_typos {
+            bail!(
+                "the minimum word size for one typo ({}) can't be greater than the one for two typos ({})",
+                one_typo, two_typos,
+            );
+        }
+
+        Ok(old_one_typo != one_typo || old_two_typos != two_typos)
+    }
+
+    fn update_attribute_ranking(&mut self) -> anyhow::Result<bool> {
+        let old_ranking = self.index.attribute_ranking(self.wtxn)?;
+        match self.attribute_ranking {
+            Setting::Set(ranking) => self.index.put_attribute_ranking(self.wtxn, ranking)?,
+            Setting::Reset => { self.index.delete_attribute_ranking(self.wtxn)?; },
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(old_ranking != self.index.attribute_ranking(self.wtxn)?)
+    }
+
+    fn update_terms_matching_strategy(&mut self) -> anyhow::Result<bool> {
+        let old_strategy = self.index.terms_matching_strategy(self.wtxn)?;
+        match self.terms_matching_strategy {
+            Setting::Set(strategy) => self.index.put_terms_matching_strategy(self.wtxn, strategy)?,
+            Setting::Reset => { self.index.delete_terms_matching_strategy(self.wtxn)?; },
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(old_strategy != self.index.terms_matching_strategy(self.wtxn)?)
+    }
+
+    fn update_analyzer_options(&mut self) -> anyhow::Result<bool> {
+        let old_options = self.index.analyzer_options(self.wtxn)?;
+        match self.analyzer_options {
+            Setting::Set(ref options) => self.index.put_analyzer_options(self.wtxn, options)?,
+            Setting::Reset => { self.index.delete_analyzer_options(self.wtxn)?; },
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(old_options != self.index.analyzer_options(self.wtxn)?)
+    }
+
+    fn compute_prefix_databases(&mut self) -> anyhow::Result<()> {
+        WordsPrefixesFst::new(self.wtxn, self.index, self.update_id).execute()?;
+
+        let mut builder = WordPrefixDocids::new(self.wtxn, self.index);
+        builder.chunk_compression_type = self.chunk_compression_type;
+        builder.chunk_compression_level = self.chunk_compression_level;
+        builder.chunk_fusing_shrink_size = self.chunk_fusing_shrink_size;
+        builder.max_nb_chunks = self.max_nb_chunks;
+        builder.max_memory = self.max_memory;
+        builder.execute()?;
+
+        let mut builder = WordPrefixPairProximityDocids::new(self.wtxn, self.index);
+        builder.chunk_compression_type = self.chunk_compression_type;
+        builder.chunk_compression_level = self.chunk_compression_level;
+        builder.chunk_fusing_shrink_size = self.chunk_fusing_shrink_size;
+        builder.max_nb_chunks = self.max_nb_chunks;
+        builder.max_memory = self.max_memory;
+        builder.execute()
+    }
+
+    fn clear_prefix_databases(&mut self) -> anyhow::Result<()> {
+        self.index.delete_words_prefixes_fst(self.wtxn)?;
+        self.index.word_prefix_docids.clear(self.wtxn)?;
+        self.index.word_prefix_pair_proximity_docids.clear(self.wtxn)?;
         Ok(())
     }
 
-    pub fn execute<F>(mut self, progress_callback: F) -> anyhow::Result<()>
+    fn update_criteria(&mut self, warnings: &mut Vec<String>) -> anyhow::Result<bool> {
+        match self.criteria {
+            Setting::Set(ref criteria) => {
+                let sortable_fields = self.index.indexed_facet_fields(&self.wtxn)?;
+                for field_name in criteria.iter().filter_map(Criterion::field_name) {
+                    if !sortable_fields.contains_key(field_name) {
+                        bail!("Can't use {:?} as a criterion as it isn't a sortable or faceted field.", field_name);
+                    }
+                }
+                warnings.extend(validate_criteria(criteria)?);
+                // Does the new criteria differ from the current ones?
+                if self.index.criteria(self.wtxn)? != *criteria {
+                    self.index.put_criteria(self.wtxn, criteria)?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Setting::Reset => {
+                let changed = self.index.criteria(self.wtxn)? != default_criteria();
+                self.index.delete_criteria(self.wtxn)?;
+                Ok(changed)
+            }
+            Setting::NotSet => Ok(false),
+        }
+    }
+
+    /// Writes the new settings and returns which ones were changed, without re-processing.
+    fn update_settings(&mut self) -> anyhow::Result<SettingsDiff> {
+        self.index.set_updated_at(self.wtxn, &Utc::now())?;
+        let displayed_fields = self.update_displayed()?;
+        let stop_words = self.update_stop_words()?;
+        let exact_words = self.update_exact_words()?;
+        let exact_attributes = self.update_exact_attributes()?;
+        let faceted_fields = self.update_facets()?;
+        let sortable_fields = self.update_sortable_fields()?;
+        if faceted_fields || sortable_fields {
+            self.check_sortable_fields_types()?;
+        }
+        let filterable_features = self.update_filterable_features()?;
+        let facet_normalizations = self.update_facet_normalizations()?;
+        let distinct_attribute = self.update_distinct_attribute()?;
+        // update_distinct_attributes MUST be called after update_facets and update_sortable_fields,
+        // since the distinct fields must be stored in the facet databases.
+        let distinct_attributes = self.update_distinct_attributes()?;
+        if (distinct_attribute || distinct_attributes)
+            && self.index.distinct_attribute(self.wtxn)?.is_some()
+            && self.index.distinct_attributes(self.wtxn)?.is_some()
+        {
+            bail!("the distinct attribute and the distinct attributes can't be both set");
+        }
+        // update_criteria MUST be called after update_facets and update_sortable_fields,
+        // since criterion fields must be set as facets or sortables.
+        let mut warnings = Vec::new();
+        let criteria = self.update_criteria(&mut warnings)?;
+        let synonyms = self.update_synonyms()?;
+        let searchable_fields = self.update_searchable()?;
+        let prefix_databases = self.update_prefix_databases()?;
+        let proximity = self.update_proximity()?;
+        let typo_tolerance = self.update_typo_tolerance()?;
+        let min_word_size_for_typos = self.update_min_word_size_for_typos()?;
+        let attribute_ranking = self.update_attribute_ranking()?;
+        let terms_matching_strategy = self.update_terms_matching_strategy()?;
+        let max_indexed_tokens = self.update_max_indexed_tokens()?;
+        let analyzer_options = self.update_analyzer_options()?;
+
+        Ok(SettingsDiff {
+            displayed_fields,
+            searchable_fields,
+            faceted_fields,
+            sortable_fields,
+            filterable_features,
+            facet_normalizations,
+            criteria,
+            stop_words,
+            exact_words,
+            exact_attributes,
+            distinct_attribute,
+            distinct_attributes,
+            synonyms,
+            prefix_databases,
+            proximity,
+            typo_tolerance,
+            min_word_size_for_typos,
+            attribute_ranking,
+            terms_matching_strategy,
+            max_indexed_tokens,
+            analyzer_options,
+            warnings,
+            reindexed: false,
+            facet_levels_recomputed: false,
+            prefix_databases_recomputed: false,
+        })
+    }
+
+    /// Writes the new settings in a nested transaction that is aborted, this way the settings
+    /// are validated and compared to the current ones without modifying the index.
+    fn execute_dry_run(self) -> anyhow::Result<SettingsDiff> {
+        let number_of_documents = self.index.number_of_documents(self.wtxn)?;
+
+        let Settings {
+            wtxn,
+            index,
+            // The dry run doesn't index anything, the indexing parameters are not used.
+            log_every_n: _,
+            max_nb_chunks: _,
+            max_memory: _,
+            linked_hash_map_size: _,
+            chunk_compression_type: _,
+            chunk_compression_level: _,
+            chunk_fusing_shrink_size: _,
+            thread_pool: _,
+            update_id,
+            searchable_fields,
+            displayed_fields,
+            faceted_fields,
+            sortable_fields,
+            filterable_features,
+            facet_normalizations,
+            criteria,
+            stop_words,
+            exact_words,
+            exact_attributes,
+            distinct_attribute,
+            distinct_attributes,
+            synonyms,
+            prefix_databases,
+            proximity,
+            typo_tolerance,
+            min_word_size_for_one_typo,
+            min_word_size_for_two_typos,
+            attribute_ranking,
+            terms_matching_strategy,
+            max_indexed_tokens,
+            analyzer_options,
+            dry_run: _,
+        } = self;
+
+        let mut nested_wtxn = index.env.nested_write_txn(wtxn)?;
+        let mut settings = Settings::new(&mut nested_wtxn, index, update_id);
+        settings.searchable_fields = searchable_fields;
+        settings.displayed_fields = displayed_fields;
+        settings.faceted_fields = faceted_fields;
+        settings.sortable_fields = sortable_fields;
+        settings.filterable_features = filterable_features;
+        settings.facet_normalizations = facet_normalizations;
+        settings.criteria = criteria;
+        settings.stop_words = stop_words;
+        settings.exact_words = exact_words;
+        settings.exact_attributes = exact_attributes;
+        settings.distinct_attribute = distinct_attribute;
+        settings.distinct_attributes = distinct_attributes;
+        settings.synonyms = synonyms;
+        settings.prefix_databases = prefix_databases;
+        settings.proximity = proximity;
+        settings.typo_tolerance = typo_tolerance;
+        settings.min_word_size_for_one_typo = min_word_size_for_one_typo;
+        settings.min_word_size_for_two_typos = min_word_size_for_two_typos;
+        settings.attribute_ranking = attribute_ranking;
+        settings.terms_matching_strategy = terms_matching_strategy;
+        settings.max_indexed_tokens = max_indexed_tokens;
+        settings.analyzer_options = analyzer_options;
+        let mut diff = settings.update_settings()?;
+
+        let prefix_databases_enabled = index.prefix_databases_enabled(&nested_wtxn)?;
+        nested_wtxn.abort()?;
+
+        // We describe the re-processing that these changes would trigger.
+        diff.reindexed = diff.requires_reindexing() && number_of_documents != 0;
+        diff.facet_levels_recomputed = !diff.requires_reindexing() && diff.filterable_features;
+        diff.prefix_databases_recomputed = diff.prefix_databases && (!prefix_databases_enabled || !diff.reindexed);
+
+        Ok(diff)
+    }
+
+    /// Applies the settings changes and returns a description of what changed
+    /// and of the re-processing that was required to apply these changes.
+    pub fn execute<F>(mut self, progress_callback: F) -> anyhow::Result<SettingsDiff>
         where
             F: Fn(UpdateIndexingStep, u64) + Sync
     {
-        self.index.set_updated_at(self.wtxn, &Utc::now())?;
+        if self.dry_run {
+            return self.execute_dry_run();
+        }
+
         let old_fields_ids_map = self.index.fields_ids_map(&self.wtxn)?;
-        self.update_displayed()?;
-        let stop_words_updated = self.update_stop_words()?;
-        let facets_updated = self.update_facets()?;
-        self.update_distinct_attribute()?;
-        // update_criteria MUST be called after update_facets, since criterion fields must be set
-        // as facets.
-        self.update_criteria()?;
-        let synonyms_updated = self.update_synonyms()?;
-        let searchable_updated = self.update_searchable()?;
-
-        if stop_words_updated || facets_updated || synonyms_updated || searchable_updated {
-            self.reindex(&progress_callback, old_fields_ids_map)?;
+        let mut diff = self.update_settings()?;
+
+        if diff.requires_reindexing() {
+            diff.reindexed = self.reindex(&progress_callback, old_fields_ids_map)?;
+        } else if diff.filterable_features {
+            // The facet levels must only be recomputed when the features change.
+            let mut builder = Facets::new(self.wtxn, self.index, self.update_id);
+            builder.chunk_compression_type = self.chunk_compression_type;
+            builder.chunk_compression_level = self.chunk_compression_level;
+            builder.chunk_fusing_shrink_size = self.chunk_fusing_shrink_size;
+            builder.execute()?;
+            diff.facet_levels_recomputed = true;
         }
-        Ok(())
+
+        if diff.prefix_databases {
+            if !self.index.prefix_databases_enabled(self.wtxn)? {
+                self.clear_prefix_databases()?;
+                diff.prefix_databases_recomputed = true;
+            } else if !diff.reindexed {
+                self.compute_prefix_databases()?;
+                diff.prefix_databases_recomputed = true;
+            }
+        }
+
+        self.index.update_hooks.record_settings(self.update_id, &diff);
+
+        Ok(diff)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use heed::EnvOpenOptions;
-    use maplit::{btreeset, hashmap};
+    use maplit::{btreemap, btreeset, hashmap};
 
     use crate::facet::FacetType;
+    use crate::{DEFAULT_MIN_WORD_SIZE_FOR_ONE_TYPO, SymbolsPolicy};
     use crate::update::{IndexDocuments, UpdateFormat};
 
     use super::*;
@@ -653,28 +1455,52 @@ mod tests {
     }
 
     #[test]
-    fn default_stop_words() {
+    fn set_sortable_fields() {
+        use crate::FacetCondition;
+
         let path = tempfile::tempdir().unwrap();
         let mut options = EnvOpenOptions::new();
         options.map_size(10 * 1024 * 1024); // 10 MB
         let index = Index::new(options, &path).unwrap();
 
-        // First we send 3 documents with ids from 1 to 3.
+        // The age is sortable but it isn't faceted.
         let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_sortable_fields(hashmap!{ "age".into() => "number".into() });
+        builder.set_criteria(vec![Criterion::Words, Criterion::Desc("age".into())]);
+        builder.execute(|_, _| ()).unwrap();
+
         let content = &b"name,age\nkevin,23\nkevina,21\nbenoit,34\n"[..];
-        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
         builder.update_format(UpdateFormat::Csv);
         builder.execute(content, |_, _| ()).unwrap();
         wtxn.commit().unwrap();
 
-        // Ensure there is no stop_words by default
         let rtxn = index.read_txn().unwrap();
-        let stop_words = index.stop_words(&rtxn).unwrap();
-        assert!(stop_words.is_none());
+        assert!(index.faceted_fields(&rtxn).unwrap().is_empty());
+        assert_eq!(index.sortable_fields(&rtxn).unwrap(), hashmap!{ "age".to_string() => FacetType::Number });
+        assert_eq!(index.criteria(&rtxn).unwrap()[1], Criterion::Desc("age".to_string()));
+
+        // The documents are sorted by the age.
+        let result = index.search(&rtxn).execute().unwrap();
+        let age = index.fields_ids_map(&rtxn).unwrap().id("age").unwrap();
+        let documents = index.documents(&rtxn, result.documents_ids).unwrap();
+        let ages: Vec<_> = documents.iter().map(|(_, doc)| doc.get(age).unwrap()).collect();
+        assert_eq!(ages, vec![&br#""34""#[..], &br#""23""#[..], &br#""21""#[..]]);
+
+        // But they can't be filtered on it.
+        assert!(FacetCondition::from_str(&rtxn, &index, "age > 22").is_err());
+        drop(rtxn);
+
+        // A field can't be faceted and sortable with different types.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "string".into() });
+        assert!(builder.execute(|_, _| ()).is_err());
     }
 
     #[test]
-    fn set_and_reset_stop_words() {
+    fn default_stop_words() {
         let path = tempfile::tempdir().unwrap();
         let mut options = EnvOpenOptions::new();
         options.map_size(10 * 1024 * 1024); // 10 MB
@@ -682,14 +1508,35 @@ mod tests {
 
         // First we send 3 documents with ids from 1 to 3.
         let mut wtxn = index.write_txn().unwrap();
-        let content = &b"name,age,maxim\nkevin,23,I love dogs\nkevina,21,Doggos are the best\nbenoit,34,The crepes are really good\n"[..];
+        let content = &b"name,age\nkevin,23\nkevina,21\nbenoit,34\n"[..];
         let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
         builder.update_format(UpdateFormat::Csv);
         builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
 
-        // In the same transaction we provide some stop_words
-        let mut builder = Settings::new(&mut wtxn, &index, 0);
-        let set = btreeset! { "i".to_string(), "the".to_string(), "are".to_string() };
+        // Ensure there is no stop_words by default
+        let rtxn = index.read_txn().unwrap();
+        let stop_words = index.stop_words(&rtxn).unwrap();
+        assert!(stop_words.is_none());
+    }
+
+    #[test]
+    fn set_and_reset_stop_words() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // First we send 3 documents with ids from 1 to 3.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"name,age,maxim\nkevin,23,I love dogs\nkevina,21,Doggos are the best\nbenoit,34,The crepes are really good\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+
+        // In the same transaction we provide some stop_words
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        let set = btreeset! { "i".to_string(), "the".to_string(), "are".to_string() };
         builder.set_stop_words(set.clone());
         builder.execute(|_, _| ()).unwrap();
         wtxn.commit().unwrap();
@@ -816,7 +1663,7 @@ mod tests {
             "age".into() => "number".into(),
             "toto".into() => "number".into(),
         });
-        builder.set_criteria(vec!["asc(toto)".to_string()]);
+        builder.set_criteria(vec![Criterion::Asc("toto".into())]);
         builder.execute(|_, _| ()).unwrap();
         wtxn.commit().unwrap();
 
@@ -840,4 +1687,744 @@ mod tests {
         assert!(index.primary_key(&rtxn).unwrap().is_none());
         assert_eq!(vec![Criterion::Asc("toto".to_string())], index.criteria(&rtxn).unwrap());
     }
+
+    #[test]
+    fn set_criteria_in_order() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_criteria(vec![Criterion::Typo, Criterion::Words, Criterion::Words]);
+        assert!(builder.execute(|_, _| ()).is_err());
+
+        // The proximity before the words is only a warning.
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_criteria(vec![Criterion::Proximity, Criterion::Typo, Criterion::Words]);
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert_eq!(diff.warnings.len(), 1);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let criteria = index.criteria(&rtxn).unwrap();
+        assert_eq!(criteria, vec![Criterion::Proximity, Criterion::Typo, Criterion::Words]);
+        assert_eq!(validate_criteria(&criteria).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn set_criteria_opposite_orders() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_sortable_fields(hashmap!{ "age".into() => "number".into() });
+        builder.set_criteria(vec![Criterion::Asc("age".into()), Criterion::Desc("age".into())]);
+        assert!(builder.execute(|_, _| ()).is_err());
+    }
+
+    #[test]
+    fn filterable_features_skip_facet_levels() {
+        use heed::types::{ByteSlice, DecodeIgnore};
+        use crate::FacetCondition;
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content: String = (0..500).map(|i| format!("{},kevin,{}\n", i, i)).collect();
+        let content = format!("id,name,age\n{}", content);
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let count_levels = |index: &Index| {
+            let rtxn = index.read_txn().unwrap();
+            let fid = index.fields_ids_map(&rtxn).unwrap().id("age").unwrap();
+            index.facet_field_id_value_docids
+                .remap_types::<ByteSlice, DecodeIgnore>()
+                .prefix_iter(&rtxn, &[fid][..])
+                .unwrap()
+                .filter(|result| result.as_ref().unwrap().0[1] != 0)
+                .count()
+        };
+        assert_ne!(count_levels(&index), 0);
+
+        // Only allow equality filters on the age field.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.set_filterable_features(hashmap!{ "age".into() => FilterFeatures::equality_only() });
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        assert_eq!(count_levels(&index), 0);
+
+        let rtxn = index.read_txn().unwrap();
+        let condition = FacetCondition::from_str(&rtxn, &index, "age = 23").unwrap();
+        let result = index.search(&rtxn).facet_condition(condition).execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 1);
+        assert!(FacetCondition::from_str(&rtxn, &index, "age > 23").is_err());
+        assert!(FacetCondition::from_str(&rtxn, &index, "NOT age = 23 OR age < 12").is_err());
+        drop(rtxn);
+
+        // Resetting the features computes the levels again.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 3);
+        builder.reset_filterable_features();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        assert_ne!(count_levels(&index), 0);
+    }
+
+    #[test]
+    fn disable_prefix_databases() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // We need enough words for the prefixes to be part of the words prefixes FST.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut content = String::from("id,name\n0,kevin\n1,kevina\n");
+        for i in 2..1100u32 {
+            let letter = |n: u32| char::from(b'a' + (n % 26) as u8);
+            let word = format!("{}{}{}", letter(i / 676), letter(i / 26), letter(i));
+            content.push_str(&format!("{},{}\n", i, word));
+        }
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content.as_bytes(), |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.words_prefixes_fst(&rtxn).unwrap().is_empty());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_prefix_databases(false);
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The prefix databases are cleared but the prefix search still works.
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.words_prefixes_fst(&rtxn).unwrap().is_empty());
+        assert!(index.word_prefix_docids.is_empty(&rtxn).unwrap());
+        assert!(index.word_prefix_pair_proximity_docids.is_empty(&rtxn).unwrap());
+        let result = index.search(&rtxn).query("kev").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+        drop(rtxn);
+
+        // The new documents don't fill them either.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n1100,kevinou\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 2);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.words_prefixes_fst(&rtxn).unwrap().is_empty());
+        assert!(index.word_prefix_docids.is_empty(&rtxn).unwrap());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 3);
+        builder.reset_prefix_databases();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.words_prefixes_fst(&rtxn).unwrap().is_empty());
+        assert!(!index.word_prefix_docids.is_empty(&rtxn).unwrap());
+    }
+
+    #[test]
+    fn set_attribute_ranking() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,title,description\n0,nothing,hello\n1,hello,nothing\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_criteria(vec![Criterion::Attribute]);
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        for (update_id, ranking) in vec![AttributeRanking::Exhaustive, AttributeRanking::Approximate].into_iter().enumerate() {
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = Settings::new(&mut wtxn, &index, update_id as u64 + 2);
+            builder.set_attribute_ranking(ranking);
+            let diff = builder.execute(|_, _| ()).unwrap();
+            wtxn.commit().unwrap();
+            assert!(diff.attribute_ranking);
+            assert!(!diff.reindexed);
+
+            // The documents that contain the word in the first attribute are ranked first.
+            let rtxn = index.read_txn().unwrap();
+            assert_eq!(index.attribute_ranking(&rtxn).unwrap(), ranking);
+            let result = index.search(&rtxn).query("hello").execute().unwrap();
+            assert_eq!(result.documents_ids, vec![1, 0]);
+        }
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 4);
+        builder.reset_attribute_ranking();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.attribute_ranking(&rtxn).unwrap(), AttributeRanking::Auto);
+    }
+
+    #[test]
+    fn set_terms_matching_strategy() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,kevin\n1,kevin\n2,benoit\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The last query word is removed first by default.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.terms_matching_strategy(&rtxn).unwrap(), TermsMatchingStrategy::Last);
+        let result = index.search(&rtxn).query("kevin benoit").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_terms_matching_strategy(TermsMatchingStrategy::Frequency);
+        let diff = builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+        assert!(diff.terms_matching_strategy);
+        assert!(!diff.reindexed);
+
+        // The most frequent query word is removed first, unless the search defines its own strategy.
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("kevin benoit").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![2]);
+        let mut search = index.search(&rtxn);
+        search.query("kevin benoit").terms_matching_strategy(TermsMatchingStrategy::Last);
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0, 1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.reset_terms_matching_strategy();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.terms_matching_strategy(&rtxn).unwrap(), TermsMatchingStrategy::Last);
+    }
+
+    #[test]
+    fn disable_proximity() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,hello world\n1,world hello\n2,hello\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.word_pair_proximity_docids.is_empty(&rtxn).unwrap());
+        let result = index.search(&rtxn).query("\"hello world\"").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_proximity(false);
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The words pairs proximities are no more computed and phrases are approximated.
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.proximity_enabled(&rtxn).unwrap());
+        assert!(index.word_pair_proximity_docids.is_empty(&rtxn).unwrap());
+        let mut result = index.search(&rtxn).query("\"hello world\"").execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.reset_proximity();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.word_pair_proximity_docids.is_empty(&rtxn).unwrap());
+    }
+
+    #[test]
+    fn normalize_numbers() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &br#"[
+            { "id": 0, "name": "agent 007" },
+            { "id": 1, "name": "it costs 1,000 dollars" }
+        ]"#[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Json);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.search(&rtxn).query("7").execute().unwrap().documents_ids.is_empty());
+        assert!(index.search(&rtxn).query("1000").execute().unwrap().documents_ids.is_empty());
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_analyzer_options(AnalyzerOptions { normalize_numbers: true, ..AnalyzerOptions::default() });
+        let diff = builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+        assert!(diff.analyzer_options && diff.reindexed);
+
+        // The numbers are normalized in the documents and in the queries.
+        let rtxn = index.read_txn().unwrap();
+        for query in &["7", "007", "agent 0007"] {
+            let result = index.search(&rtxn).query(*query).execute().unwrap();
+            assert_eq!(result.documents_ids, vec![0], "{:?}", query);
+        }
+        for query in &["1000", "1,000", "costs 1_000"] {
+            let result = index.search(&rtxn).query(*query).execute().unwrap();
+            assert_eq!(result.documents_ids, vec![1], "{:?}", query);
+        }
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.reset_analyzer_options();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.analyzer_options(&rtxn).unwrap(), AnalyzerOptions::default());
+        assert!(index.search(&rtxn).query("1000").execute().unwrap().documents_ids.is_empty());
+    }
+
+    #[test]
+    fn split_identifiers() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_analyzer_options(AnalyzerOptions { split_identifiers: true, ..AnalyzerOptions::default() });
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,code\n0,call getUserName first\n1,the user name\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        // The identifier is indexed as a whole along with its words.
+        let result = index.search(&rtxn).query("getusername").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        let mut result = index.search(&rtxn).query("user").execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn symbols_policy() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = "id,name\n0,I \u{2764} rust\n1,rust \u{1F44D}\n".as_bytes();
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        let symbols = SymbolsPolicy::Index;
+        builder.set_analyzer_options(AnalyzerOptions { symbols, ..AnalyzerOptions::default() });
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("\u{1F44D}").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        let symbols = SymbolsPolicy::Alias(btreemap!{ "\u{2764}".to_string() => "love".to_string() });
+        builder.set_analyzer_options(AnalyzerOptions { symbols, ..AnalyzerOptions::default() });
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The symbols without an alias are stripped.
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("love").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        let result = index.search(&rtxn).query("\u{2764}").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        let result = index.search(&rtxn).query("u1f44d").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+    }
+
+    #[test]
+    fn settings_diff() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name,age\n0,kevin,23\n1,kevina,21\n2,benoit,34\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+
+        let builder = Settings::new(&mut wtxn, &index, 1);
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert!(diff.is_empty());
+        assert!(!diff.reindexed);
+
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.set_displayed_fields(vec!["name".into()]);
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert_eq!(diff, SettingsDiff { displayed_fields: true, ..SettingsDiff::default() });
+
+        let mut builder = Settings::new(&mut wtxn, &index, 3);
+        builder.set_stop_words(btreeset!{ "kevin".to_string() });
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert_eq!(diff, SettingsDiff { stop_words: true, reindexed: true, ..SettingsDiff::default() });
+
+        // Setting the same stop words doesn't change anything.
+        let mut builder = Settings::new(&mut wtxn, &index, 4);
+        builder.set_stop_words(btreeset!{ "kevin".to_string() });
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert!(diff.is_empty());
+
+        // Setting the criteria that are already in use doesn't change anything either.
+        let mut builder = Settings::new(&mut wtxn, &index, 5);
+        builder.set_criteria(crate::default_criteria());
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert!(!diff.criteria);
+        assert!(diff.is_empty());
+
+        let mut builder = Settings::new(&mut wtxn, &index, 6);
+        builder.set_filterable_features(hashmap!{ "age".into() => FilterFeatures::equality_only() });
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert!(diff.filterable_features && diff.facet_levels_recomputed && !diff.reindexed);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn set_and_reset_exact_words() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,kitkat\n1,kitcat\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("KitKat").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_exact_words(btreeset!{ "KitKat".to_string() });
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The word is normalized and no more matched with typos.
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.exact_words(&rtxn).unwrap().unwrap().contains("kitkat"));
+        let result = index.search(&rtxn).query("KitKat").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.reset_exact_words();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.exact_words(&rtxn).unwrap().is_none());
+        let result = index.search(&rtxn).query("KitKat").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+    }
+
+    #[test]
+    fn set_and_reset_typo_tolerance() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,kitkat\n1,kitcat\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The minimum word size for one typo can't be greater than the one for two typos.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_min_word_size_for_one_typo(10);
+        assert!(builder.execute(|_, _| ()).is_err());
+        wtxn.abort().unwrap();
+
+        // A six characters word is no more matched with a typo.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_min_word_size_for_one_typo(7);
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert_eq!(diff, SettingsDiff { min_word_size_for_typos: true, ..SettingsDiff::default() });
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.min_word_size_for_one_typo(&rtxn).unwrap(), 7);
+        let result = index.search(&rtxn).query("kitkat").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.reset_min_word_size_for_one_typo();
+        builder.set_typo_tolerance(false);
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.min_word_size_for_one_typo(&rtxn).unwrap(), DEFAULT_MIN_WORD_SIZE_FOR_ONE_TYPO);
+        let result = index.search(&rtxn).query("kitkat").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 3);
+        builder.reset_typo_tolerance();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.typo_tolerance_enabled(&rtxn).unwrap());
+        let result = index.search(&rtxn).query("kitkat").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+    }
+
+    #[test]
+    fn set_and_reset_exact_attributes() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name,code\n0,kitkat,zzz\n1,zzz,kitcat\n2,kitcat,zzz\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_exact_attributes(vec!["code".into()]);
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The kitcat word of the code attribute is not matched with a typo.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.exact_attributes(&rtxn).unwrap(), vec!["code"]);
+        let mut result = index.search(&rtxn).query("kitkat").execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![0, 2]);
+        let mut result = index.search(&rtxn).query("kitcat").execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![0, 1, 2]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.reset_exact_attributes();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("kitkat").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 3);
+    }
+
+    #[test]
+    fn set_and_reset_distinct_attributes() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 0);
+        builder.set_faceted_fields(hashmap!{ "brand".into() => "string".into(), "model".into() => "string".into() });
+        builder.set_distinct_attributes(vec!["brand".into(), "model".into()]);
+        builder.execute(|_, _| ()).unwrap();
+
+        let content = &b"id,brand,model\n0,acme,x1\n1,acme,x1\n2,acme,x2\n3,globex,x1\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 1);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // Only one of the acme x1 is returned.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.distinct_attributes(&rtxn).unwrap(), Some(vec!["brand", "model"]));
+        let mut result = index.search(&rtxn).execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![0, 2, 3]);
+        drop(rtxn);
+
+        // The distinct fields must be faceted and can't be combined with the distinct attribute.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.set_distinct_attributes(vec!["brand".into(), "id".into()]);
+        assert!(builder.execute(|_, _| ()).is_err());
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.set_distinct_attribute("brand".into());
+        assert!(builder.execute(|_, _| ()).is_err());
+        wtxn.abort().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.reset_distinct_attributes();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 4);
+    }
+
+    #[test]
+    fn set_and_reset_max_indexed_tokens() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,body\n0,hello world footer\n1,footer\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_max_indexed_tokens(hashmap!{ "body".into() => 2 });
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert!(diff.max_indexed_tokens && diff.reindexed);
+        wtxn.commit().unwrap();
+
+        // The words after the first two words of the body are not indexed.
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.max_indexed_tokens(&rtxn).unwrap(), hashmap!{ "body".to_string() => 2 });
+        let result = index.search(&rtxn).query("footer").execute().unwrap();
+        assert_eq!(result.documents_ids, vec![1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.reset_max_indexed_tokens();
+        builder.execute(|_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.max_indexed_tokens(&rtxn).unwrap().is_empty());
+        let mut result = index.search(&rtxn).query("footer").execute().unwrap();
+        result.documents_ids.sort_unstable();
+        assert_eq!(result.documents_ids, vec![0, 1]);
+    }
+
+    #[test]
+    fn dry_run_settings() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name,age\n0,kevin,23\n1,kevina,21\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // The dry run returns what would change without modifying the index.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 1);
+        builder.set_faceted_fields(hashmap!{ "age".into() => "number".into() });
+        builder.set_criteria(vec![Criterion::Asc("age".into())]);
+        builder.set_displayed_fields(vec!["name".into()]);
+        builder.dry_run(true);
+        let diff = builder.execute(|_, _| ()).unwrap();
+        assert!(diff.faceted_fields && diff.criteria && diff.displayed_fields && diff.reindexed);
+        assert!(!diff.stop_words);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.faceted_fields(&rtxn).unwrap().is_empty());
+        assert_eq!(index.displayed_fields(&rtxn).unwrap(), None);
+        assert_eq!(index.criteria(&rtxn).unwrap(), crate::default_criteria());
+        drop(rtxn);
+
+        // The invalid settings are still rejected.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, 2);
+        builder.set_criteria(vec![Criterion::Asc("name".into())]);
+        builder.dry_run(true);
+        assert!(builder.execute(|_, _| ()).is_err());
+    }
 }
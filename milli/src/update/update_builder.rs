@@ -2,7 +2,7 @@ use grenad::CompressionType;
 use rayon::ThreadPool;
 
 use crate::Index;
-use super::{ClearDocuments, DeleteDocuments, IndexDocuments, Settings, Facets};
+use super::{ClearDocuments, DeleteDocuments, IndexDocuments, RenameField, Settings, Facets, WordsCompaction};
 
 pub struct UpdateBuilder<'a> {
     pub(crate) log_every_n: Option<usize>,
@@ -135,4 +135,22 @@ impl<'a> UpdateBuilder<'a> {
 
         builder
     }
+
+    pub fn words_compaction<'t, 'u, 'i>(
+        self,
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+    ) -> WordsCompaction<'t, 'u, 'i>
+    {
+        WordsCompaction::new(wtxn, index, self.update_id)
+    }
+
+    pub fn rename_field<'t, 'u, 'i>(
+        self,
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+    ) -> RenameField<'t, 'u, 'i>
+    {
+        RenameField::new(wtxn, index)
+    }
 }
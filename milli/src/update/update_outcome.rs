@@ -0,0 +1,82 @@
+use std::sync::{Arc, Mutex, RwLock};
+
+use roaring::RoaringBitmap;
+
+use super::SettingsDiff;
+
+/// Describes what an update changed in the index, it is given to the hooks
+/// registered with `Index::on_update_committed` once the update is committed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UpdateOutcome {
+    pub update_id: u64,
+    /// The documents that were added or replaced by the update.
+    pub added_documents: RoaringBitmap,
+    /// The documents that were deleted by the update.
+    pub removed_documents: RoaringBitmap,
+    /// The settings that were changed, if the update was a settings update.
+    pub settings: Option<SettingsDiff>,
+}
+
+type UpdateHook = Box<dyn Fn(&UpdateOutcome) + Send + Sync>;
+
+/// The functions called with the outcome of the committed updates,
+/// they are shared by all the copies of an index.
+///
+/// The update builders record what they changed in the pending outcome, it is given to
+/// the hooks when the write transaction is committed with `Index::commit_update`.
+#[derive(Clone, Default)]
+pub struct UpdateHooks {
+    hooks: Arc<RwLock<Vec<UpdateHook>>>,
+    pending: Arc<Mutex<Option<UpdateOutcome>>>,
+}
+
+impl UpdateHooks {
+    pub fn register<F>(&self, hook: F)
+    where F: Fn(&UpdateOutcome) + Send + Sync + 'static,
+    {
+        // A poisoned lock only means that a hook panicked, the list of hooks is still valid.
+        let mut hooks = self.hooks.write().unwrap_or_else(|e| e.into_inner());
+        hooks.push(Box::new(hook));
+    }
+
+    /// Calls the registered hooks, in the order they were registered.
+    pub fn notify(&self, outcome: &UpdateOutcome) {
+        let hooks = self.hooks.read().unwrap_or_else(|e| e.into_inner());
+        hooks.iter().for_each(|hook| hook(outcome));
+    }
+
+    /// Records that the given documents were added or replaced by the update.
+    pub(crate) fn record_addition(&self, update_id: u64, documents_ids: &RoaringBitmap) {
+        self.record(update_id, |outcome| {
+            outcome.added_documents.union_with(documents_ids);
+            outcome.removed_documents.difference_with(documents_ids);
+        });
+    }
+
+    /// Records that the given documents were deleted by the update.
+    pub(crate) fn record_deletion(&self, update_id: u64, documents_ids: &RoaringBitmap) {
+        self.record(update_id, |outcome| {
+            outcome.added_documents.difference_with(documents_ids);
+            outcome.removed_documents.union_with(documents_ids);
+        });
+    }
+
+    /// Records that the given settings were changed by the update.
+    pub(crate) fn record_settings(&self, update_id: u64, diff: &SettingsDiff) {
+        self.record(update_id, |outcome| match &mut outcome.settings {
+            Some(settings) => settings.union_with(diff),
+            None => outcome.settings = Some(diff.clone()),
+        });
+    }
+
+    /// Returns and forgets the outcome recorded since the write transaction was created.
+    pub(crate) fn take_pending(&self) -> Option<UpdateOutcome> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    fn record<F: FnOnce(&mut UpdateOutcome)>(&self, update_id: u64, f: F) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let outcome = pending.get_or_insert_with(|| UpdateOutcome { update_id, ..UpdateOutcome::default() });
+        f(outcome);
+    }
+}
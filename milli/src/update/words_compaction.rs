@@ -0,0 +1,172 @@
+use chrono::Utc;
+use heed::types::{ByteSlice, DecodeIgnore};
+use heed::{BytesDecode, BytesEncode};
+use roaring::RoaringBitmap;
+
+use crate::Index;
+
+/// The number of entries that were removed by a words compaction.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WordsCompactionResult {
+    /// The number of words removed from the words FST and the word docids database.
+    pub removed_words: u64,
+    /// The number of prefixes removed from the words prefixes FST and the word prefix docids database.
+    pub removed_prefixes: u64,
+    /// The number of entries removed from the other words postings databases.
+    pub removed_postings: u64,
+}
+
+/// Removes the words, prefixes and postings entries that no longer
+/// reference any of the documents of the index, and rebuilds the FSTs.
+///
+/// The deletions already remove most of these entries but some can be left behind,
+/// by a failed update or an older version of the engine, and slow down the typo
+/// automatons scans of the words FST.
+pub struct WordsCompaction<'t, 'u, 'i> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    _update_id: u64,
+}
+
+impl<'t, 'u, 'i> WordsCompaction<'t, 'u, 'i> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+        update_id: u64,
+    ) -> WordsCompaction<'t, 'u, 'i>
+    {
+        WordsCompaction { wtxn, index, _update_id: update_id }
+    }
+
+    pub fn execute(self) -> anyhow::Result<WordsCompactionResult> {
+        self.index.set_updated_at(self.wtxn, &Utc::now())?;
+        let documents_ids = self.index.documents_ids(self.wtxn)?;
+
+        let word_docids = self.index.word_docids.remap_key_type::<ByteSlice>();
+        let removed_words = compact_database(self.wtxn, word_docids, &documents_ids)?;
+
+        // The words FST is rebuilt from the remaining words, LMDB stores them in
+        // the lexicographic order of their bytes, as the FST builder requires.
+        let mut builder = fst::SetBuilder::memory();
+        for result in self.index.word_docids.remap_data_type::<DecodeIgnore>().iter(self.wtxn)? {
+            let (word, ()) = result?;
+            builder.insert(word)?;
+        }
+        let words_fst = builder.into_set();
+        if words_fst.as_fst().as_bytes() != self.index.words_fst(self.wtxn)?.as_fst().as_bytes() {
+            self.index.put_words_fst(self.wtxn, &words_fst)?;
+        }
+
+        let word_prefix_docids = self.index.word_prefix_docids.remap_key_type::<ByteSlice>();
+        let removed_prefixes = compact_database(self.wtxn, word_prefix_docids, &documents_ids)?;
+
+        let mut builder = fst::SetBuilder::memory();
+        for result in self.index.word_prefix_docids.remap_data_type::<DecodeIgnore>().iter(self.wtxn)? {
+            let (prefix, ()) = result?;
+            builder.insert(prefix)?;
+        }
+        let words_prefixes_fst = builder.into_set();
+        if words_prefixes_fst.as_fst().as_bytes() != self.index.words_prefixes_fst(self.wtxn)?.as_fst().as_bytes() {
+            self.index.put_words_prefixes_fst(self.wtxn, &words_prefixes_fst)?;
+        }
+
+        let mut removed_postings = 0;
+        let databases = [
+            self.index.word_pair_proximity_docids.remap_key_type::<ByteSlice>(),
+            self.index.word_prefix_pair_proximity_docids.remap_key_type::<ByteSlice>(),
+            self.index.word_level_position_docids.remap_key_type::<ByteSlice>(),
+            self.index.word_prefix_level_position_docids.remap_key_type::<ByteSlice>(),
+            self.index.field_id_word_count_docids.remap_key_type::<ByteSlice>(),
+            self.index.field_id_original_word_docids.remap_key_type::<ByteSlice>(),
+        ];
+        for db in databases.iter() {
+            removed_postings += compact_database(self.wtxn, *db, &documents_ids)?;
+        }
+
+        // The positions of the words in the documents that do not exist anymore.
+        let mut iter = self.index.docid_word_positions.iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let ((docid, _word), _positions) = result?;
+            if !documents_ids.contains(docid) {
+                iter.del_current()?;
+                removed_postings += 1;
+            }
+        }
+
+        drop(iter);
+
+        Ok(WordsCompactionResult { removed_words, removed_prefixes, removed_postings })
+    }
+}
+
+/// Removes the documents ids that are not part of the given ones from every entry
+/// of the database, deletes the emptied entries and returns the number of them.
+fn compact_database<C>(
+    wtxn: &mut heed::RwTxn,
+    db: heed::Database<ByteSlice, C>,
+    documents_ids: &RoaringBitmap,
+) -> anyhow::Result<u64>
+where
+    C: for<'a> BytesDecode<'a, DItem = RoaringBitmap> + for<'a> BytesEncode<'a, EItem = RoaringBitmap>,
+{
+    let mut removed = 0;
+    let mut iter = db.iter_mut(wtxn)?;
+    while let Some(result) = iter.next() {
+        let (key, mut docids) = result?;
+        let previous_len = docids.len();
+        docids.intersect_with(documents_ids);
+        if docids.is_empty() {
+            iter.del_current()?;
+            removed += 1;
+        } else if docids.len() != previous_len {
+            iter.put_current(key, &docids)?;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use heed::EnvOpenOptions;
+
+    use crate::update::{IndexDocuments, UpdateFormat};
+    use super::*;
+
+    #[test]
+    fn compact_leftover_words() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = &b"id,name\n0,kevin\n1,kevina\n2,benoit\n"[..];
+        let mut builder = IndexDocuments::new(&mut wtxn, &index, 0);
+        builder.update_format(UpdateFormat::Csv);
+        builder.execute(content, |_, _| ()).unwrap();
+
+        // Nothing is left behind by the indexing.
+        let result = WordsCompaction::new(&mut wtxn, &index, 1).execute().unwrap();
+        assert_eq!(result, WordsCompactionResult::default());
+
+        // We simulate the words left behind by a document that was removed without cleaning them.
+        let mut documents_ids = index.documents_ids(&wtxn).unwrap();
+        let benoit = index.external_documents_ids(&wtxn).unwrap().get("2").unwrap();
+        documents_ids.remove(benoit);
+        index.put_documents_ids(&mut wtxn, &documents_ids).unwrap();
+
+        let result = WordsCompaction::new(&mut wtxn, &index, 2).execute().unwrap();
+        assert!(result.removed_words != 0);
+        assert!(result.removed_postings != 0);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let words_fst = index.words_fst(&rtxn).unwrap();
+        assert!(!words_fst.contains("benoit"));
+        assert!(words_fst.contains("kevina"));
+        assert!(index.word_docids.get(&rtxn, "benoit").unwrap().is_none());
+        assert!(index.docid_word_positions.prefix_iter(&rtxn, &(benoit, "")).unwrap().next().is_none());
+        let docids = index.word_docids.get(&rtxn, "kevin").unwrap().unwrap();
+        assert!(!docids.contains(benoit));
+    }
+}
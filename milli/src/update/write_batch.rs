@@ -0,0 +1,152 @@
+use std::io;
+
+use anyhow::bail;
+
+use crate::Index;
+use super::{DeleteDocuments, DocumentAdditionResult, IndexDocuments, IndexDocumentsMethod};
+use super::{Settings, SettingsDiff, UpdateFormat, UpdateOutcome};
+
+/// Applies many update operations, settings, documents additions and deletions,
+/// in a single write transaction that is committed only if all of them succeeded.
+///
+/// Dropping the batch without committing it rolls back all of its operations,
+/// once an operation failed, the batch refuses to apply new operations and to be committed.
+pub struct WriteBatch<'i> {
+    wtxn: heed::RwTxn<'i, 'i>,
+    index: &'i Index,
+    update_id: u64,
+    failed: bool,
+}
+
+impl<'i> WriteBatch<'i> {
+    pub fn new(index: &'i Index, update_id: u64) -> heed::Result<WriteBatch<'i>> {
+        let wtxn = index.write_txn()?;
+        Ok(WriteBatch { wtxn, index, update_id, failed: false })
+    }
+
+    /// Updates the settings with the ones defined by the given function.
+    pub fn settings<F>(&mut self, f: F) -> anyhow::Result<SettingsDiff>
+    where F: FnOnce(&mut Settings),
+    {
+        self.apply(|wtxn, index, update_id| {
+            let mut builder = Settings::new(wtxn, index, update_id);
+            f(&mut builder);
+            builder.execute(|_, _| ())
+        })
+    }
+
+    pub fn index_documents<R: io::Read>(
+        &mut self,
+        content: R,
+        format: UpdateFormat,
+        method: IndexDocumentsMethod,
+    ) -> anyhow::Result<DocumentAdditionResult>
+    {
+        self.apply(|wtxn, index, update_id| {
+            let mut builder = IndexDocuments::new(wtxn, index, update_id);
+            builder.update_format(format);
+            builder.index_documents_method(method);
+            builder.execute(content, |_, _| ())
+        })
+    }
+
+    /// Deletes the documents with the given external ids, the unknown ids are ignored.
+    pub fn delete_documents(&mut self, external_ids: &[&str]) -> anyhow::Result<u64> {
+        self.apply(|wtxn, index, update_id| {
+            let mut builder = DeleteDocuments::new(wtxn, index, update_id)?;
+            for external_id in external_ids {
+                builder.delete_external_id(external_id);
+            }
+            builder.execute()
+        })
+    }
+
+    /// Commits all the operations of the batch, the hooks registered on the index
+    /// are called with the outcome of the whole batch.
+    pub fn commit(self) -> anyhow::Result<UpdateOutcome> {
+        if self.failed {
+            bail!("an operation of this write batch failed, the batch has been rolled back");
+        }
+
+        let outcome = self.index.commit_update(self.wtxn)?;
+        Ok(outcome.unwrap_or_else(|| UpdateOutcome { update_id: self.update_id, ..UpdateOutcome::default() }))
+    }
+
+    fn apply<T, F>(&mut self, f: F) -> anyhow::Result<T>
+    where F: FnOnce(&mut heed::RwTxn<'i, 'i>, &'i Index, u64) -> anyhow::Result<T>,
+    {
+        if self.failed {
+            bail!("an operation of this write batch failed, no other operation can be applied");
+        }
+
+        let result = f(&mut self.wtxn, self.index, self.update_id);
+        self.failed = result.is_err();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::FromIterator;
+
+    use maplit::hashmap;
+    use roaring::RoaringBitmap;
+
+    use crate::index::tests::TempIndex;
+    use super::*;
+
+    #[test]
+    fn commit_all_operations() {
+        let index = TempIndex::new();
+
+        let mut batch = WriteBatch::new(&index, 0).unwrap();
+        batch.settings(|settings| {
+            settings.set_faceted_fields(hashmap!{ "age".into() => "number".into() });
+        }).unwrap();
+        let content = &b"id,name,age\n1,kevin,20\n2,bob,30\n3,kevina,40\n"[..];
+        batch.index_documents(content, UpdateFormat::Csv, IndexDocumentsMethod::ReplaceDocuments).unwrap();
+        assert_eq!(batch.delete_documents(&["2", "4"]).unwrap(), 1);
+        let outcome = batch.commit().unwrap();
+
+        assert_eq!(outcome.added_documents, RoaringBitmap::from_iter(vec![0, 2]));
+        assert_eq!(outcome.removed_documents, RoaringBitmap::from_iter(vec![1]));
+        assert!(outcome.settings.unwrap().faceted_fields);
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 2);
+        assert!(index.faceted_fields(&rtxn).unwrap().contains_key("age"));
+    }
+
+    #[test]
+    fn partial_failure_rolls_back() {
+        let index = TempIndex::new();
+
+        let mut batch = WriteBatch::new(&index, 0).unwrap();
+        batch.settings(|settings| settings.set_displayed_fields(vec!["name".into()])).unwrap();
+        let content = &br#"[{ "id": 1, "name": "kevin" }]"#[..];
+        batch.index_documents(content, UpdateFormat::Json, IndexDocumentsMethod::ReplaceDocuments).unwrap();
+        let content = &br#"[{ "id": 2, "name": "#[..];
+        assert!(batch.index_documents(content, UpdateFormat::Json, IndexDocumentsMethod::ReplaceDocuments).is_err());
+
+        // The batch refuses any other operation and to be committed.
+        assert!(batch.delete_documents(&["1"]).is_err());
+        assert!(batch.commit().is_err());
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 0);
+        assert_eq!(index.displayed_fields(&rtxn).unwrap(), None);
+    }
+
+    #[test]
+    fn dropped_batch_rolls_back() {
+        let index = TempIndex::new();
+
+        let mut batch = WriteBatch::new(&index, 0).unwrap();
+        let content = &b"id,name\n1,kevin\n"[..];
+        batch.index_documents(content, UpdateFormat::Csv, IndexDocumentsMethod::ReplaceDocuments).unwrap();
+        drop(batch);
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 0);
+    }
+}
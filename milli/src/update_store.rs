@@ -1,23 +1,131 @@
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::hash::Hasher;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use crossbeam_channel::Sender;
-use heed::types::{OwnedType, DecodeIgnore, SerdeJson, ByteSlice};
+use heed::types::{OwnedType, DecodeIgnore, SerdeJson, ByteSlice, Unit};
 use heed::{EnvOpenOptions, Env, Database};
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, IgnoredAny};
+use siphasher::sip128::{Hasher128, SipHasher};
 
 use crate::BEU64;
 
+/// The maximum number of updates with a higher priority that can be processed
+/// before the oldest pending update, it ensures that no update waits forever.
+const MAX_OVERTAKES: usize = 4;
+
+/// The lane in which an update waits to be processed, the updates of the lanes with
+/// a higher priority are processed first and the updates of a lane are processed in order.
+///
+/// An update can't be overtaken more than a few times, the oldest pending update is
+/// processed once enough updates with a higher priority have been processed before it.
+///
+/// The updates of different lanes are not processed in the order they were registered,
+/// only the updates whose result doesn't depend on this order, like the settings updates,
+/// must be given a different priority than the documents additions and deletions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum UpdatePriority {
+    Low,
+    Normal,
+    High,
+}
+
+impl UpdatePriority {
+    /// The priorities from the lowest to the highest.
+    const ALL: [UpdatePriority; 3] = [UpdatePriority::Low, UpdatePriority::Normal, UpdatePriority::High];
+}
+
+impl Default for UpdatePriority {
+    fn default() -> UpdatePriority {
+        UpdatePriority::Normal
+    }
+}
+
+/// The key of a pending update in the lanes database, the priority followed by the update id,
+/// the updates of a lane are therefore stored next to each other and in order.
+fn lane_key(priority: UpdatePriority, update_id: u64) -> [u8; 9] {
+    let mut key = [0; 9];
+    key[0] = priority as u8;
+    key[1..].copy_from_slice(&update_id.to_be_bytes());
+    key
+}
+
+/// The 128-bit SipHash of an update payload, serialized as an hexadecimal string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PayloadChecksum(pub u128);
+
+impl fmt::Display for PayloadChecksum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+impl Serialize for PayloadChecksum {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PayloadChecksum {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        u128::from_str_radix(&string, 16).map(PayloadChecksum).map_err(de::Error::custom)
+    }
+}
+
+/// The metadata of an update along with the checksum of its payload,
+/// the checksum is kept once the update has been processed or aborted.
+///
+/// The metadata stored before the checksums existed are decoded without a checksum.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "ChecksummedRepr<T>")]
+pub struct Checksummed<T> {
+    pub checksum: Option<PayloadChecksum>,
+    pub meta: T,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChecksummedRepr<T> {
+    Checksummed { checksum: Option<PayloadChecksum>, meta: T },
+    Legacy(T),
+}
+
+impl<T> From<ChecksummedRepr<T>> for Checksummed<T> {
+    fn from(repr: ChecksummedRepr<T>) -> Checksummed<T> {
+        match repr {
+            ChecksummedRepr::Checksummed { checksum, meta } => Checksummed { checksum, meta },
+            ChecksummedRepr::Legacy(meta) => Checksummed { checksum: None, meta },
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct UpdateStore<M, N> {
     env: Env,
-    pending_meta: Database<OwnedType<BEU64>, SerdeJson<M>>,
+    pending_meta: Database<OwnedType<BEU64>, SerdeJson<Checksummed<M>>>,
     pending: Database<OwnedType<BEU64>, ByteSlice>,
-    processed_meta: Database<OwnedType<BEU64>, SerdeJson<N>>,
-    aborted_meta: Database<OwnedType<BEU64>, SerdeJson<M>>,
+    /// The pending updates ids ordered by priority lane, see `lane_key`.
+    pending_lanes: Database<ByteSlice, Unit>,
+    processed_meta: Database<OwnedType<BEU64>, SerdeJson<Checksummed<N>>>,
+    aborted_meta: Database<OwnedType<BEU64>, SerdeJson<Checksummed<M>>>,
+    /// The id of the update that is currently being processed.
+    processing: Arc<RwLock<Option<u64>>>,
     notification_sender: Sender<()>,
 }
 
+/// Computes the checksum of an update payload, it only depends on the payload
+/// bytes and can be used to detect duplicate submissions or compare replicas.
+pub fn payload_checksum(content: &[u8]) -> PayloadChecksum {
+    let mut hasher = SipHasher::new();
+    hasher.write(content);
+    PayloadChecksum(hasher.finish128().as_u128())
+}
+
 pub trait UpdateHandler<M, N> {
     fn handle_update(&mut self, update_id: u64, meta: M, content: &[u8]) -> heed::Result<N>;
 }
@@ -41,10 +149,11 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
         M: for<'a> Deserialize<'a>,
         N: Serialize,
     {
-        options.max_dbs(4);
+        options.max_dbs(5);
         let env = options.open(path)?;
         let pending_meta = env.create_database(Some("pending-meta"))?;
         let pending = env.create_database(Some("pending"))?;
+        let pending_lanes = env.create_database(Some("pending-lanes"))?;
         let processed_meta = env.create_database(Some("processed-meta"))?;
         let aborted_meta = env.create_database(Some("aborted-meta"))?;
 
@@ -56,17 +165,23 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
             env,
             pending,
             pending_meta,
+            pending_lanes,
             processed_meta,
             aborted_meta,
+            processing: Arc::new(RwLock::new(None)),
             notification_sender,
         });
 
+        update_store.backfill_lanes()?;
+
         let update_store_cloned = update_store.clone();
         std::thread::spawn(move || {
+            // The number of updates processed before the oldest pending one.
+            let mut overtakes = 0;
             // Block and wait for something to process.
             for () in notification_receiver {
                 loop {
-                    match update_store_cloned.process_pending_update(&mut update_handler) {
+                    match update_store_cloned.process_pending_update(&mut update_handler, &mut overtakes) {
                         Ok(Some(_)) => (),
                         Ok(None) => break,
                         Err(e) => eprintln!("error while processing update: {}", e),
@@ -78,6 +193,36 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
         Ok(update_store)
     }
 
+    /// Puts the pending updates that are in no lane, the ones registered before
+    /// the priority lanes existed, in the lane of the default priority.
+    fn backfill_lanes(&self) -> heed::Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+
+        let mut update_ids = Vec::new();
+        for result in self.pending_meta.remap_data_type::<DecodeIgnore>().iter(&wtxn)? {
+            let (key, ()) = result?;
+            if self.pending_priority(&wtxn, key.get())?.is_none() {
+                update_ids.push(key.get());
+            }
+        }
+
+        for update_id in update_ids {
+            self.pending_lanes.put(&mut wtxn, &lane_key(UpdatePriority::default(), update_id), &())?;
+        }
+
+        wtxn.commit()
+    }
+
+    /// Returns the lane in which the given update waits, `None` if it is in no lane.
+    fn pending_priority(&self, txn: &heed::RoTxn, update_id: u64) -> heed::Result<Option<UpdatePriority>> {
+        for &priority in &UpdatePriority::ALL {
+            if self.pending_lanes.get(txn, &lane_key(priority, update_id))?.is_some() {
+                return Ok(Some(priority));
+            }
+        }
+        Ok(None)
+    }
+
     /// Returns the new biggest id to use to store the new update.
     fn new_update_id(&self, txn: &heed::RoTxn) -> heed::Result<u64> {
         let last_pending = self.pending_meta
@@ -111,6 +256,18 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
     /// into the pending-meta store. Returns the new unique update id.
     pub fn register_update(&self, meta: &M, content: &[u8]) -> heed::Result<u64>
     where M: Serialize,
+    {
+        self.register_update_with_priority(meta, content, UpdatePriority::default())
+    }
+
+    /// Registers an update like `register_update` does, in the lane of the given priority.
+    pub fn register_update_with_priority(
+        &self,
+        meta: &M,
+        content: &[u8],
+        priority: UpdatePriority,
+    ) -> heed::Result<u64>
+    where M: Serialize,
     {
         let mut wtxn = self.env.write_txn()?;
 
@@ -121,8 +278,11 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
         let update_id = self.new_update_id(&wtxn)?;
         let update_key = BEU64::new(update_id);
 
-        self.pending_meta.put(&mut wtxn, &update_key, meta)?;
+        let checksum = payload_checksum(content);
+        let pending_meta = self.pending_meta.remap_data_type::<SerdeJson<Checksummed<&M>>>();
+        pending_meta.put(&mut wtxn, &update_key, &Checksummed { checksum: Some(checksum), meta })?;
         self.pending.put(&mut wtxn, &update_key, content)?;
+        self.pending_lanes.put(&mut wtxn, &lane_key(priority, update_id), &())?;
 
         wtxn.commit()?;
 
@@ -133,44 +293,105 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
         Ok(update_id)
     }
 
-    /// Executes the user provided function on the next pending update (the one with the lowest id).
-    /// This is asynchronous as it let the user process the update with a read-only txn and
-    /// only writing the result meta to the processed-meta store *after* it has been processed.
-    fn process_pending_update<U>(&self, handler: &mut U) -> heed::Result<Option<(u64, N)>>
+    /// Returns the id of the next update to process, the first one of the lane with the
+    /// highest priority, unless the oldest pending update has been overtaken too many times.
+    fn next_update_id(&self, rtxn: &heed::RoTxn, overtakes: &mut usize) -> heed::Result<Option<u64>> {
+        // The first pending update of each lane, the lanes are ordered by priority.
+        let mut lanes = BTreeMap::new();
+        for &priority in &UpdatePriority::ALL {
+            if let Some(result) = self.pending_lanes.prefix_iter(rtxn, &[priority as u8])?.next() {
+                let (key, ()) = result?;
+                let update_id = key[1..].try_into().map(u64::from_be_bytes).unwrap();
+                lanes.insert(priority, update_id);
+            }
+        }
+
+        let oldest = match lanes.values().min() {
+            Some(oldest) => *oldest,
+            None => return Ok(None),
+        };
+
+        // The lanes are ordered by priority, the last one has the highest.
+        let highest = lanes.values().next_back().copied().unwrap_or(oldest);
+        if highest == oldest || *overtakes >= MAX_OVERTAKES {
+            *overtakes = 0;
+            Ok(Some(oldest))
+        } else {
+            *overtakes += 1;
+            Ok(Some(highest))
+        }
+    }
+
+    /// Executes the user provided function on the next pending update, the oldest one of the lane
+    /// with the highest priority. This is asynchronous as it let the user process the update with
+    /// a read-only txn and only writing the result meta to the processed-meta store *after* it
+    /// has been processed.
+    fn process_pending_update<U>(&self, handler: &mut U, overtakes: &mut usize) -> heed::Result<Option<(u64, N)>>
     where
         U: UpdateHandler<M, N>,
         M: for<'a> Deserialize<'a>,
         N: Serialize,
     {
+        // We choose the next update while holding the lock to make
+        // sure that it is not aborted before being marked as processing.
+        let mut processing = self.processing.write().unwrap_or_else(|e| e.into_inner());
+
         // Create a read transaction to be able to retrieve the pending update in order.
         let rtxn = self.env.read_txn()?;
-        let first_meta = self.pending_meta.first(&rtxn)?;
-
-        // If there is a pending update we process and only keep
-        // a reader while processing it, not a writer.
-        match first_meta {
-            Some((first_id, first_meta)) => {
-                let first_content = self.pending
-                    .get(&rtxn, &first_id)?
-                    .expect("associated update content");
-
-                // Process the pending update using the provided user function.
-                let new_meta = handler.handle_update(first_id.get(), first_meta, first_content)?;
-                drop(rtxn);
-
-                // Once the pending update have been successfully processed
-                // we must remove the content from the pending stores and
-                // write the *new* meta to the processed-meta store and commit.
-                let mut wtxn = self.env.write_txn()?;
-                self.pending_meta.delete(&mut wtxn, &first_id)?;
-                self.pending.delete(&mut wtxn, &first_id)?;
-                self.processed_meta.put(&mut wtxn, &first_id, &new_meta)?;
-                wtxn.commit()?;
-
-                Ok(Some((first_id.get(), new_meta)))
-            },
-            None => Ok(None)
+        let next_id = match self.next_update_id(&rtxn, overtakes)? {
+            Some(id) => BEU64::new(id),
+            None => return Ok(None),
+        };
+
+        *processing = Some(next_id.get());
+        drop(processing);
+
+        let result = self.process_update(rtxn, next_id, handler);
+        *self.processing.write().unwrap_or_else(|e| e.into_inner()) = None;
+
+        result.map(|new_meta| Some((next_id.get(), new_meta)))
+    }
+
+    /// Processes a pending update and only keeps a reader while processing it, not a writer.
+    fn process_update<U>(&self, rtxn: heed::RoTxn, update_id: BEU64, handler: &mut U) -> heed::Result<N>
+    where
+        U: UpdateHandler<M, N>,
+        M: for<'a> Deserialize<'a>,
+        N: Serialize,
+    {
+        let Checksummed { checksum, meta } = self.pending_meta
+            .get(&rtxn, &update_id)?
+            .expect("associated update meta");
+        let content = self.pending
+            .get(&rtxn, &update_id)?
+            .expect("associated update content");
+
+        // Process the pending update using the provided user function.
+        let new_meta = handler.handle_update(update_id.get(), meta, content)?;
+        drop(rtxn);
+
+        // Once the pending update have been successfully processed
+        // we must remove the content from the pending stores and
+        // write the *new* meta to the processed-meta store and commit.
+        let mut wtxn = self.env.write_txn()?;
+        self.pending_meta.delete(&mut wtxn, &update_id)?;
+        self.pending.delete(&mut wtxn, &update_id)?;
+        self.delete_from_lanes(&mut wtxn, update_id.get())?;
+        let processed_meta = self.processed_meta.remap_data_type::<SerdeJson<Checksummed<&N>>>();
+        processed_meta.put(&mut wtxn, &update_id, &Checksummed { checksum, meta: &new_meta })?;
+        wtxn.commit()?;
+
+        Ok(new_meta)
+    }
+
+    /// Removes a pending update from its priority lane.
+    fn delete_from_lanes(&self, wtxn: &mut heed::RwTxn, update_id: u64) -> heed::Result<()> {
+        for &priority in &UpdatePriority::ALL {
+            if self.pending_lanes.delete(wtxn, &lane_key(priority, update_id))? {
+                break;
+            }
         }
+        Ok(())
     }
 
     /// The id and metadata of the update that is currently being processed,
@@ -178,11 +399,71 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
     pub fn processing_update(&self) -> heed::Result<Option<(u64, M)>>
     where M: for<'a> Deserialize<'a>,
     {
+        let processing = *self.processing.read().unwrap_or_else(|e| e.into_inner());
+        let update_id = match processing {
+            Some(update_id) => update_id,
+            None => return Ok(None),
+        };
+
         let rtxn = self.env.read_txn()?;
-        match self.pending_meta.first(&rtxn)? {
-            Some((key, meta)) => Ok(Some((key.get(), meta))),
-            None => Ok(None),
+        let meta = self.pending_meta.get(&rtxn, &BEU64::new(update_id))?;
+        Ok(meta.map(|Checksummed { meta, .. }| (update_id, meta)))
+    }
+
+    /// Returns the priority of a pending update, `None` if the update isn't pending.
+    pub fn priority(&self, update_id: u64) -> heed::Result<Option<UpdatePriority>> {
+        let rtxn = self.env.read_txn()?;
+        let key = BEU64::new(update_id);
+        if self.pending_meta.remap_data_type::<DecodeIgnore>().get(&rtxn, &key)?.is_none() {
+            return Ok(None);
+        }
+
+        self.pending_priority(&rtxn, update_id)
+    }
+
+    /// Returns the checksum of the payload of the given update, it is kept
+    /// once the update has been processed or aborted.
+    ///
+    /// Returns `None` if the update doesn't exist or was registered before the checksums existed.
+    pub fn checksum(&self, update_id: u64) -> heed::Result<Option<PayloadChecksum>> {
+        let rtxn = self.env.read_txn()?;
+        let key = BEU64::new(update_id);
+
+        for db in &self.checksums_databases() {
+            if let Some(Checksummed { checksum, .. }) = db.get(&rtxn, &key)? {
+                return Ok(checksum);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the ids of the pending, processed and aborted updates whose payload
+    /// has the given checksum, in order, it can be used to detect duplicate submissions.
+    pub fn updates_with_checksum(&self, checksum: PayloadChecksum) -> heed::Result<Vec<u64>> {
+        let rtxn = self.env.read_txn()?;
+
+        let mut update_ids = Vec::new();
+        for db in &self.checksums_databases() {
+            for result in db.iter(&rtxn)? {
+                let (key, Checksummed { checksum: update_checksum, .. }) = result?;
+                if update_checksum == Some(checksum) {
+                    update_ids.push(key.get());
+                }
+            }
         }
+
+        update_ids.sort_unstable();
+        Ok(update_ids)
+    }
+
+    /// The pending, processed and aborted metadata databases that only decode the checksums.
+    fn checksums_databases(&self) -> [Database<OwnedType<BEU64>, SerdeJson<Checksummed<IgnoredAny>>>; 3] {
+        [
+            self.pending_meta.remap_data_type(),
+            self.processed_meta.remap_data_type(),
+            self.aborted_meta.remap_data_type(),
+        ]
     }
 
     /// Execute the user defined function with the meta-store iterators, the first
@@ -193,9 +474,9 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
         M: for<'a> Deserialize<'a>,
         N: for<'a> Deserialize<'a>,
         F: for<'a> FnMut(
-            heed::RoIter<'a, OwnedType<BEU64>, SerdeJson<N>>,
-            heed::RoIter<'a, OwnedType<BEU64>, SerdeJson<M>>,
-            heed::RoIter<'a, OwnedType<BEU64>, SerdeJson<M>>,
+            heed::RoIter<'a, OwnedType<BEU64>, SerdeJson<Checksummed<N>>>,
+            heed::RoIter<'a, OwnedType<BEU64>, SerdeJson<Checksummed<M>>>,
+            heed::RoIter<'a, OwnedType<BEU64>, SerdeJson<Checksummed<M>>>,
         ) -> heed::Result<T>,
     {
         let rtxn = self.env.read_txn()?;
@@ -218,15 +499,15 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
         let rtxn = self.env.read_txn()?;
         let key = BEU64::new(update_id);
 
-        if let Some(meta) = self.pending_meta.get(&rtxn, &key)? {
+        if let Some(Checksummed { meta, .. }) = self.pending_meta.get(&rtxn, &key)? {
             return Ok(Some(UpdateStatusMeta::Pending(meta)));
         }
 
-        if let Some(meta) = self.processed_meta.get(&rtxn, &key)? {
+        if let Some(Checksummed { meta, .. }) = self.processed_meta.get(&rtxn, &key)? {
             return Ok(Some(UpdateStatusMeta::Processed(meta)));
         }
 
-        if let Some(meta) = self.aborted_meta.get(&rtxn, &key)? {
+        if let Some(Checksummed { meta, .. }) = self.aborted_meta.get(&rtxn, &key)? {
             return Ok(Some(UpdateStatusMeta::Aborted(meta)));
         }
 
@@ -242,11 +523,13 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
     pub fn abort_update(&self, update_id: u64) -> heed::Result<Option<M>>
     where M: Serialize + for<'a> Deserialize<'a>,
     {
+        // The lock is kept until the commit to make sure the update is not chosen to be processed.
+        let processing = self.processing.read().unwrap_or_else(|e| e.into_inner());
         let mut wtxn = self.env.write_txn()?;
         let key = BEU64::new(update_id);
 
         // We cannot abort an update that is currently being processed.
-        if self.pending_meta.first(&wtxn)?.map(|(key, _)| key.get()) == Some(update_id) {
+        if *processing == Some(update_id) {
             return Ok(None);
         }
 
@@ -258,10 +541,12 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
         self.aborted_meta.put(&mut wtxn, &key, &meta)?;
         self.pending_meta.delete(&mut wtxn, &key)?;
         self.pending.delete(&mut wtxn, &key)?;
+        self.delete_from_lanes(&mut wtxn, update_id)?;
 
         wtxn.commit()?;
+        drop(processing);
 
-        Ok(Some(meta))
+        Ok(Some(meta.meta))
     }
 
     /// Aborts all the pending updates, and not the one being currently processed.
@@ -269,14 +554,17 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
     pub fn abort_pendings(&self) -> heed::Result<Vec<(u64, M)>>
     where M: Serialize + for<'a> Deserialize<'a>,
     {
+        let processing = self.processing.read().unwrap_or_else(|e| e.into_inner());
         let mut wtxn = self.env.write_txn()?;
         let mut aborted_updates = Vec::new();
 
-        // We skip the first pending update as it is currently being processed.
-        for result in self.pending_meta.iter(&wtxn)?.skip(1) {
+        // We skip the pending update that is currently being processed.
+        for result in self.pending_meta.iter(&wtxn)? {
             let (key, meta) = result?;
             let id = key.get();
-            aborted_updates.push((id, meta));
+            if *processing != Some(id) {
+                aborted_updates.push((id, meta));
+            }
         }
 
         for (id, meta) in &aborted_updates {
@@ -284,11 +572,13 @@ impl<M: 'static, N: 'static> UpdateStore<M, N> {
             self.aborted_meta.put(&mut wtxn, &key, &meta)?;
             self.pending_meta.delete(&mut wtxn, &key)?;
             self.pending.delete(&mut wtxn, &key)?;
+            self.delete_from_lanes(&mut wtxn, *id)?;
         }
 
         wtxn.commit()?;
+        drop(processing);
 
-        Ok(aborted_updates)
+        Ok(aborted_updates.into_iter().map(|(id, Checksummed { meta, .. })| (id, meta)).collect())
     }
 }
 
@@ -322,6 +612,132 @@ mod tests {
         assert_eq!(meta, UpdateStatusMeta::Processed(format!("kiki processed")));
     }
 
+    #[test]
+    fn payload_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = EnvOpenOptions::new();
+        let update_store = UpdateStore::open(options, dir, |_id, meta: String, _content:&_| {
+            Ok(meta + " processed")
+        }).unwrap();
+
+        let kiki_id = update_store.register_update(&String::from("kiki"), b"id,name\n0,kiki\n").unwrap();
+        let coco_id = update_store.register_update(&String::from("coco"), b"id,name\n1,coco\n").unwrap();
+        let kiki_again_id = update_store.register_update(&String::from("kiki"), b"id,name\n0,kiki\n").unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+
+        let checksum = payload_checksum(b"id,name\n0,kiki\n");
+        assert_eq!(update_store.checksum(kiki_id).unwrap(), Some(checksum));
+        assert_eq!(update_store.checksum(kiki_again_id).unwrap(), Some(checksum));
+        assert_ne!(update_store.checksum(coco_id).unwrap(), Some(checksum));
+        assert_eq!(update_store.checksum(42).unwrap(), None);
+
+        // The checksum is stored along with the processed metadata.
+        assert_eq!(update_store.updates_with_checksum(checksum).unwrap(), vec![kiki_id, kiki_again_id]);
+        let meta = update_store.meta(kiki_again_id).unwrap().unwrap();
+        assert_eq!(meta, UpdateStatusMeta::Processed(String::from("kiki processed")));
+
+        // The checksums are written as 128-bit hexadecimal strings.
+        let json = serde_json::to_string(&Checksummed { checksum: Some(PayloadChecksum(255)), meta: "kiki" }).unwrap();
+        assert_eq!(json, r#"{"checksum":"000000000000000000000000000000ff","meta":"kiki"}"#);
+    }
+
+    #[test]
+    fn legacy_metas() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // We register an update like the stores did before the checksums existed.
+        let mut options = EnvOpenOptions::new();
+        options.max_dbs(5);
+        let env = options.open(&dir).unwrap();
+        let aborted_meta: Database<OwnedType<BEU64>, SerdeJson<String>> =
+            env.create_database(Some("aborted-meta")).unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        aborted_meta.put(&mut wtxn, &BEU64::new(0), &String::from("kiki")).unwrap();
+        wtxn.commit().unwrap();
+        env.prepare_for_closing().wait();
+
+        let options = EnvOpenOptions::new();
+        let update_store = UpdateStore::open(options, &dir, |_id, meta: String, _content:&_| {
+            Ok(meta + " processed")
+        }).unwrap();
+
+        let meta = update_store.meta(0).unwrap().unwrap();
+        assert_eq!(meta, UpdateStatusMeta::Aborted(String::from("kiki")));
+        assert_eq!(update_store.checksum(0).unwrap(), None);
+
+        // The new updates ids follow the legacy ones.
+        let update_id = update_store.register_update(&String::from("coco"), &[]).unwrap();
+        assert_eq!(update_id, 1);
+    }
+
+    #[test]
+    fn priority_lanes() {
+        use std::sync::Mutex;
+
+        let dir = tempfile::tempdir().unwrap();
+        let options = EnvOpenOptions::new();
+        let processed = Arc::new(Mutex::new(Vec::new()));
+        let cloned_processed = processed.clone();
+        let update_store = UpdateStore::open(options, dir, move |_id, meta: String, _content:&_| {
+            // We keep the first update busy while the other ones are registered.
+            if meta == "a0" {
+                thread::sleep(Duration::from_millis(200));
+            }
+            cloned_processed.lock().unwrap().push(meta.clone());
+            Ok(meta + " processed")
+        }).unwrap();
+
+        let register = |meta: &str, priority| {
+            update_store.register_update_with_priority(&meta.to_string(), &[], priority).unwrap()
+        };
+
+        register("a0", UpdatePriority::Low);
+        thread::sleep(Duration::from_millis(50));
+        let a1_id = register("a1", UpdatePriority::Low);
+        for i in 1..=6 {
+            register(&format!("h{}", i), UpdatePriority::High);
+        }
+        update_store.register_update(&String::from("d"), &[]).unwrap();
+        assert_eq!(update_store.priority(a1_id).unwrap(), Some(UpdatePriority::Low));
+
+        thread::sleep(Duration::from_millis(400));
+
+        // The low priority update is processed once it has been overtaken enough times.
+        let expected = vec!["a0", "h1", "h2", "h3", "h4", "a1", "h5", "h6", "d"];
+        assert_eq!(*processed.lock().unwrap(), expected);
+        assert_eq!(update_store.priority(a1_id).unwrap(), None);
+    }
+
+    #[test]
+    fn backfill_pending_lanes() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // We register an update like the stores did before the priority lanes existed.
+        let mut options = EnvOpenOptions::new();
+        options.max_dbs(5);
+        let env = options.open(&dir).unwrap();
+        let pending_meta: Database<OwnedType<BEU64>, SerdeJson<Checksummed<String>>> =
+            env.create_database(Some("pending-meta")).unwrap();
+        let pending: Database<OwnedType<BEU64>, ByteSlice> = env.create_database(Some("pending")).unwrap();
+        let mut wtxn = env.write_txn().unwrap();
+        let meta = Checksummed { checksum: Some(payload_checksum(&[])), meta: String::from("kiki") };
+        pending_meta.put(&mut wtxn, &BEU64::new(0), &meta).unwrap();
+        pending.put(&mut wtxn, &BEU64::new(0), &[]).unwrap();
+        wtxn.commit().unwrap();
+        env.prepare_for_closing().wait();
+
+        let options = EnvOpenOptions::new();
+        let update_store = UpdateStore::open(options, &dir, |_id, meta: String, _content:&_| {
+            Ok(meta + " processed")
+        }).unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+
+        let meta = update_store.meta(0).unwrap().unwrap();
+        assert_eq!(meta, UpdateStatusMeta::Processed(format!("kiki processed")));
+    }
+
     #[test]
     #[ignore]
     fn long_running_update() {
@@ -0,0 +1,153 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use heed::RwTxn;
+
+/// The name of the advisory lock file that is created in the directory of an index.
+pub const WRITER_LOCK_FILE_NAME: &str = "writer.lock";
+
+/// The error returned when the writer lock of an index is already held.
+#[derive(Debug)]
+pub enum WriterLockError {
+    /// Another thread of this process is already writing into the index.
+    AlreadyWriting,
+    /// The lock file exists, another process is writing into the index or crashed while writing.
+    LockFileExists { path: PathBuf, owner: Option<String> },
+    /// The lock file could not be created.
+    Io(io::Error),
+}
+
+impl fmt::Display for WriterLockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriterLockError::AlreadyWriting => {
+                f.write_str("a write transaction is already in progress on this index")
+            },
+            WriterLockError::LockFileExists { path, owner: Some(owner) } => {
+                write!(f, "the index is locked by the process {} (lock file {})", owner, path.display())
+            },
+            WriterLockError::LockFileExists { path, owner: None } => {
+                write!(f, "the index is locked by another process (lock file {})", path.display())
+            },
+            WriterLockError::Io(error) => write!(f, "could not create the writer lock file: {}", error),
+        }
+    }
+}
+
+impl Error for WriterLockError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WriterLockError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Ensures that a single writer works on an index at a time,
+/// it is shared by all the copies of an index.
+#[derive(Clone, Default)]
+pub(crate) struct WriterLock {
+    inner: Arc<WriterLockState>,
+}
+
+#[derive(Default)]
+struct WriterLockState {
+    writing: AtomicBool,
+    lock_file: AtomicBool,
+}
+
+impl WriterLock {
+    pub fn set_lock_file(&self, enabled: bool) {
+        self.inner.lock_file.store(enabled, Ordering::Release);
+    }
+
+    /// Takes the lock without waiting, the lock file is created in the given
+    /// directory when enabled, the lock is released when the guard is dropped.
+    pub fn try_lock(&self, directory: &Path) -> Result<WriterGuard, WriterLockError> {
+        let already_writing = self.inner.writing
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err();
+        if already_writing {
+            return Err(WriterLockError::AlreadyWriting);
+        }
+
+        let mut guard = WriterGuard { state: self.inner.clone(), lock_file: None };
+        if self.inner.lock_file.load(Ordering::Acquire) {
+            let path = directory.join(WRITER_LOCK_FILE_NAME);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    // The guard removes the file even if we fail to write the process id.
+                    guard.lock_file = Some(path);
+                    writeln!(file, "{}", std::process::id()).map_err(WriterLockError::Io)?;
+                },
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let owner = fs::read_to_string(&path).ok()
+                        .map(|s| s.trim().to_owned())
+                        .filter(|s| !s.is_empty());
+                    return Err(WriterLockError::LockFileExists { path, owner });
+                },
+                Err(e) => return Err(WriterLockError::Io(e)),
+            }
+        }
+
+        Ok(guard)
+    }
+}
+
+pub(crate) struct WriterGuard {
+    state: Arc<WriterLockState>,
+    lock_file: Option<PathBuf>,
+}
+
+impl Drop for WriterGuard {
+    fn drop(&mut self) {
+        if let Some(path) = self.lock_file.take() {
+            let _ = fs::remove_file(path);
+        }
+        self.state.writing.store(false, Ordering::Release);
+    }
+}
+
+/// A write transaction that holds the writer lock of the index,
+/// the lock is released once the transaction is committed or aborted.
+pub struct LockedRwTxn<'e> {
+    // The transaction must be dropped before the guard.
+    txn: RwTxn<'e, 'e>,
+    _guard: WriterGuard,
+}
+
+impl<'e> LockedRwTxn<'e> {
+    pub(crate) fn new(txn: RwTxn<'e, 'e>, guard: WriterGuard) -> LockedRwTxn<'e> {
+        LockedRwTxn { txn, _guard: guard }
+    }
+
+    pub fn commit(self) -> heed::Result<()> {
+        let LockedRwTxn { txn, _guard } = self;
+        txn.commit()
+    }
+
+    pub fn abort(self) -> heed::Result<()> {
+        let LockedRwTxn { txn, _guard } = self;
+        txn.abort()
+    }
+}
+
+impl<'e> Deref for LockedRwTxn<'e> {
+    type Target = RwTxn<'e, 'e>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.txn
+    }
+}
+
+impl<'e> DerefMut for LockedRwTxn<'e> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.txn
+    }
+}
@@ -4,11 +4,10 @@ use std::path::PathBuf;
 use std::time::Instant;
 
 use byte_unit::Byte;
-use heed::EnvOpenOptions;
 use log::debug;
 use structopt::StructOpt;
 
-use milli::{Index, obkv_to_json};
+use milli::{IndexOptions, obkv_to_json};
 
 #[cfg(target_os = "linux")]
 #[global_allocator]
@@ -23,7 +22,7 @@ pub struct Opt {
     database: PathBuf,
 
     /// The maximum size the database can take on disk. It is recommended to specify
-    /// the whole disk space (value must be a multiple of a page size).
+    /// the whole disk space (value is rounded up to a multiple of a page size).
     #[structopt(long = "db-size", default_value = "100 GiB")]
     database_size: Byte,
 
@@ -53,11 +52,11 @@ fn main() -> anyhow::Result<()> {
         anyhow::bail!("The database ({}) does not exist.", opt.database.display());
     }
 
-    let mut options = EnvOpenOptions::new();
+    let mut options = IndexOptions::new();
     options.map_size(opt.database_size.get_bytes() as usize);
 
     // Open the LMDB database.
-    let index = Index::new(options, &opt.database)?;
+    let index = options.open(&opt.database)?;
     let rtxn = index.read_txn()?;
     let fields_ids_map = index.fields_ids_map(&rtxn)?;
     let displayed_fields = match index.displayed_fields_ids(&rtxn)? {